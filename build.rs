@@ -0,0 +1,14 @@
+fn main() {
+    #[cfg(feature = "node")]
+    napi_build::setup();
+
+    #[cfg(feature = "grpc")]
+    {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc not available");
+        // SAFETY: build scripts are single-threaded at this point
+        unsafe { std::env::set_var("PROTOC", protoc) };
+
+        println!("cargo:rerun-if-changed=proto/emotion.proto");
+        tonic_prost_build::compile_protos("proto/emotion.proto").expect("failed to compile protos");
+    }
+}
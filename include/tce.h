@@ -0,0 +1,47 @@
+/* C interface to the emotion-aware chat pipeline.
+ *
+ * Build with `cargo build --release --features ffi` and link against
+ * target/release/libtext_classifier_extractor.{so,dylib,a}.
+ *
+ * Strings passed in are NUL-terminated UTF-8. Strings returned by
+ * tce_session_turn are owned by the caller and freed with tce_string_free.
+ * A session must not be used from two threads at once. A panic inside the
+ * library is reported as a failure through tce_last_error rather than
+ * unwinding into the caller.
+ */
+#ifndef TCE_H
+#define TCE_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct TceSession TceSession;
+
+/* Starts a session from a JSON config: {"api_key": "...", "base_url": "...",
+ * "model": "...", "dry_run": false}. Only api_key is required.
+ * Returns NULL on failure. */
+TceSession *tce_session_new(const char *config_json);
+
+/* Runs one turn, blocking until the reply is ready, and returns it as JSON:
+ * {"emotion": {"sentiment": "Negative", "confidence": 0.8}, "trend": "Stable",
+ *  "strategy": "Empathetic", "response": "..."}, plus "handoff",
+ * "blocked_topic" and "break_suggested" when they apply, and "injection",
+ * the text that made the message look like an attempt to instruct the model,
+ * when it did.
+ * Returns NULL on failure. */
+char *tce_session_turn(TceSession *session, const char *text);
+
+/* The last error on the calling thread, or NULL. Valid until the next
+ * failing call on that thread; do not free. */
+const char *tce_last_error(void);
+
+void tce_string_free(char *s);
+
+void tce_session_free(TceSession *session);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TCE_H */
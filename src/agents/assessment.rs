@@ -0,0 +1,86 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use crate::SentimentClassification;
+use crate::state::EmotionTrend;
+use crate::strategy::{ResponseStrategy, StrategyBlend};
+
+/// The assistant's own reading of a turn, kept apart from the reply so none
+/// of it reaches the user unasked
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, utoipa::ToSchema)]
+pub struct Assessment {
+    /// Why the message reads as the detected emotion
+    pub emotion: String,
+    /// Why the chosen strategy suits this message
+    pub strategy: String,
+}
+
+/// What a turn decided, for the assessment to explain
+#[derive(Debug, Clone, Copy)]
+pub struct Decision<'a> {
+    pub emotion: &'a SentimentClassification,
+    pub trend: EmotionTrend,
+    pub blend: &'a StrategyBlend,
+    /// The strategy selection wanted when hysteresis kept the previous one
+    pub suppressed: Option<ResponseStrategy>,
+}
+
+impl Decision<'_> {
+    /// The detected emotion with its confidence, and any secondary labels
+    pub fn describe_emotion(&self) -> String {
+        let mut emotion = format!("{:?} ({:.0}% confident)", self.emotion.sentiment, self.emotion.confidence * 100.0);
+        let labels: Vec<String> = self
+            .emotion
+            .labels
+            .iter()
+            .filter(|label| label.sentiment != self.emotion.sentiment)
+            .map(|label| format!("{:?} {:.0}%", label.sentiment, label.confidence * 100.0))
+            .collect();
+        if !labels.is_empty() {
+            emotion.push_str(&format!(", also {}", labels.join(", ")));
+        }
+        emotion
+    }
+
+    /// The strategy with the trend it answered and what hysteresis held back
+    pub fn describe_strategy(&self) -> String {
+        let mut strategy = format!("{} for a {:?} trend", self.blend, self.trend);
+        if let Some(suppressed) = self.suppressed {
+            strategy.push_str(&format!("; {:?} was wanted but the previous strategy was kept to avoid switching too often", suppressed));
+        }
+        strategy
+    }
+}
+
+pub fn assessment_prompt(user_input: &str, decision: &Decision) -> String {
+    format!(
+        "The user wrote:\n{}\n\nDetected emotion: {}\nChosen strategy: {}",
+        user_input,
+        decision.describe_emotion(),
+        decision.describe_strategy()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EmotionLabel, Sentiment};
+
+    #[test]
+    fn test_assessment_prompt_states_the_decision() {
+        let emotion = SentimentClassification {
+            sentiment: Sentiment::Negative,
+            confidence: 0.8,
+            labels: vec![
+                EmotionLabel { sentiment: Sentiment::Negative, confidence: 0.8 },
+                EmotionLabel { sentiment: Sentiment::Positive, confidence: 0.3 },
+            ],
+            is_fallback: false,
+        };
+        let blend = StrategyBlend::single(ResponseStrategy::Empathetic);
+        let decision = Decision { emotion: &emotion, trend: EmotionTrend::Declining, blend: &blend, suppressed: Some(ResponseStrategy::Encouraging) };
+
+        let prompt = assessment_prompt("I failed my exam", &decision);
+        assert!(prompt.contains("Detected emotion: Negative (80% confident), also Positive 30%"));
+        assert!(prompt.contains("Chosen strategy: Empathetic for a Declining trend; Encouraging was wanted"));
+    }
+}
@@ -0,0 +1,183 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use super::error::{AgentError, ErrorAction};
+
+/// Reply sent instead of a model response while the provider circuit is open
+pub const FALLBACK_RESPONSE: &str =
+    "I'm having trouble thinking clearly right now, but I'm still here and listening. \
+     Could you tell me a little more while I catch up?";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    HalfOpen,
+    Open,
+}
+
+#[derive(Debug)]
+enum Inner {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    /// One probe call is in flight; everything else still gets the fallback.
+    /// A probe that never reports back (e.g. a cancelled request) is replaced
+    /// after another `open_for`.
+    HalfOpen { since: Instant },
+}
+
+/// Stops calling a provider after repeated transient failures. While open,
+/// callers serve local fallbacks; after `open_for` a single probe is let
+/// through, and its outcome closes or re-opens the circuit.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_for: Duration,
+    inner: Mutex<Inner>,
+    opened: AtomicU64,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(5, Duration::from_secs(30))
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_for: Duration) -> Self {
+        Self {
+            failure_threshold,
+            open_for,
+            inner: Mutex::new(Inner::Closed { failures: 0 }),
+            opened: AtomicU64::new(0),
+        }
+    }
+
+    /// Runs `call` unless the circuit is open, in which case `None` tells the
+    /// caller to fall back. Only transient errors count as provider failures.
+    pub async fn call<T>(&self, call: impl Future<Output = Result<T, AgentError>>) -> Option<Result<T, AgentError>> {
+        if !self.allow(Instant::now()) {
+            return None;
+        }
+
+        let result = call.await;
+        match &result {
+            Err(e) if e.action() == ErrorAction::Retry => self.record_failure(Instant::now()),
+            _ => self.record_success(),
+        }
+        Some(result)
+    }
+
+    pub fn allow(&self, now: Instant) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match *inner {
+            Inner::Closed { .. } => true,
+            Inner::Open { until } if now >= until => {
+                *inner = Inner::HalfOpen { since: now };
+                true
+            }
+            Inner::HalfOpen { since } if now >= since + self.open_for => {
+                *inner = Inner::HalfOpen { since: now };
+                true
+            }
+            Inner::Open { .. } | Inner::HalfOpen { .. } => false,
+        }
+    }
+
+    pub fn record_success(&self) {
+        *self.inner.lock().unwrap() = Inner::Closed { failures: 0 };
+    }
+
+    pub fn record_failure(&self, now: Instant) {
+        let mut inner = self.inner.lock().unwrap();
+        let open = match *inner {
+            Inner::Closed { failures } if failures + 1 >= self.failure_threshold => true,
+            Inner::Closed { failures } => {
+                *inner = Inner::Closed { failures: failures + 1 };
+                false
+            }
+            Inner::HalfOpen { .. } => true,
+            // A call admitted before the circuit opened; the timer keeps running
+            Inner::Open { .. } => false,
+        };
+
+        if open {
+            *inner = Inner::Open { until: now + self.open_for };
+            self.opened.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(open_for = ?self.open_for, "provider circuit opened");
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        match *self.inner.lock().unwrap() {
+            Inner::Closed { .. } => CircuitState::Closed,
+            Inner::HalfOpen { .. } => CircuitState::HalfOpen,
+            Inner::Open { .. } => CircuitState::Open,
+        }
+    }
+
+    /// How many times the circuit has opened since startup
+    pub fn opened_count(&self) -> u64 {
+        self.opened.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(10));
+        let now = Instant::now();
+
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+        breaker.record_success();
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.allow(now + Duration::from_secs(5)));
+        assert_eq!(breaker.opened_count(), 1);
+    }
+
+    #[test]
+    fn test_probe_closes_or_reopens() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(10));
+        let now = Instant::now();
+        breaker.record_failure(now);
+
+        // Only one probe is admitted once the open period ends
+        let later = now + Duration::from_secs(10);
+        assert!(breaker.allow(later));
+        assert!(!breaker.allow(later));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+        breaker.record_failure(later);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert_eq!(breaker.opened_count(), 2);
+
+        let much_later = later + Duration::from_secs(10);
+        assert!(breaker.allow(much_later));
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.allow(much_later));
+    }
+
+    #[tokio::test]
+    async fn test_call_skips_provider_while_open() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+
+        let result = breaker.call(async { Err::<(), _>(AgentError::Timeout) }).await;
+        assert!(matches!(result, Some(Err(AgentError::Timeout))));
+        assert!(breaker.call(async { Ok(()) }).await.is_none());
+
+        // Non-transient errors mean the provider is reachable
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+        let _ = breaker.call(async { Err::<(), _>(AgentError::Rejected("bad key".to_string())) }).await;
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
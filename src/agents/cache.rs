@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::Duration;
+use crate::SentimentClassification;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    created_at: i64,
+    classification: SentimentClassification,
+}
+
+/// Classification results kept on disk, one small JSON file per text, so
+/// repeated runs over the same data don't pay for the same call twice.
+/// Entries are keyed by tenant, provider, model and prompt version as well as
+/// the text, so tenants never see each other's results and changing any of
+/// them starts from a cold cache.
+#[derive(Debug)]
+pub struct ClassificationCache {
+    dir: PathBuf,
+    ttl: Duration,
+    tenant: String,
+    base_url: String,
+}
+
+impl ClassificationCache {
+    /// `tenant` is `None` outside the server
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration, tenant: Option<&str>, base_url: &str) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+            tenant: tenant.unwrap_or_default().to_string(),
+            base_url: base_url.to_string(),
+        }
+    }
+
+    pub async fn get(&self, model: &str, prompt_version: &str, text: &str) -> Option<SentimentClassification> {
+        let path = self.path(model, prompt_version, text);
+        let json = tokio::fs::read_to_string(&path).await.ok()?;
+        let entry: Entry = serde_json::from_str(&json).ok()?;
+
+        let age = chrono::Utc::now().timestamp() - entry.created_at;
+        if age < 0 || age as u64 > self.ttl.as_secs() {
+            let _ = tokio::fs::remove_file(&path).await;
+            return None;
+        }
+        Some(entry.classification)
+    }
+
+    /// Failures are only logged; a cache that can't be written just misses
+    pub async fn put(&self, model: &str, prompt_version: &str, text: &str, classification: &SentimentClassification) {
+        let entry = Entry {
+            created_at: chrono::Utc::now().timestamp(),
+            classification: classification.clone(),
+        };
+        let written = async {
+            let json = serde_json::to_string(&entry)?;
+            tokio::fs::create_dir_all(&self.dir).await?;
+            tokio::fs::write(self.path(model, prompt_version, text), json).await
+        };
+
+        if let Err(e) = written.await {
+            tracing::warn!(error = %e, dir = %self.dir.display(), "failed to write classification cache");
+        }
+    }
+
+    fn path(&self, model: &str, prompt_version: &str, text: &str) -> PathBuf {
+        let mut hasher = Sha256::new();
+        for part in [&self.tenant, &self.base_url, model, prompt_version, text] {
+            // Length prefixes keep ("ab", "c") and ("a", "bc") apart
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        let key: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sentiment;
+
+    fn classification() -> SentimentClassification {
+        SentimentClassification { sentiment: Sentiment::Positive, confidence: 0.9, labels: Vec::new(), is_fallback: false }
+    }
+
+    const URL: &str = "https://api.example.com/v1";
+
+    #[tokio::test]
+    async fn test_round_trip_keyed_by_model_and_version() {
+        let dir = std::env::temp_dir().join(format!("tce-cache-{}", std::process::id()));
+        let cache = ClassificationCache::new(&dir, Duration::from_secs(60), None, URL);
+
+        assert!(cache.get("glm", "v1", "great day").await.is_none());
+        cache.put("glm", "v1", "great day", &classification()).await;
+
+        assert_eq!(cache.get("glm", "v1", "great day").await.unwrap().sentiment, Sentiment::Positive);
+        assert!(cache.get("glm", "v2", "great day").await.is_none());
+        assert!(cache.get("other", "v1", "great day").await.is_none());
+
+        // Another tenant, or the same model name at another provider, starts cold
+        let tenant = ClassificationCache::new(&dir, Duration::from_secs(60), Some("acme"), URL);
+        assert!(tenant.get("glm", "v1", "great day").await.is_none());
+        let provider = ClassificationCache::new(&dir, Duration::from_secs(60), None, "http://localhost:11434/v1");
+        assert!(provider.get("glm", "v1", "great day").await.is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_miss() {
+        let dir = std::env::temp_dir().join(format!("tce-cache-ttl-{}", std::process::id()));
+        let cache = ClassificationCache::new(&dir, Duration::from_secs(60), None, URL);
+        cache.put("glm", "v1", "old", &classification()).await;
+
+        // Backdate the entry past the TTL
+        let path = cache.path("glm", "v1", "old");
+        let stale = Entry { created_at: chrono::Utc::now().timestamp() - 120, classification: classification() };
+        std::fs::write(&path, serde_json::to_string(&stale).unwrap()).unwrap();
+
+        assert!(cache.get("glm", "v1", "old").await.is_none());
+        assert!(!path.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
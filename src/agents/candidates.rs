@@ -0,0 +1,76 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use crate::models::ReplyScore;
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct CandidateScore {
+    /// Number of the candidate as listed, starting at 1
+    pub candidate: usize,
+    /// How closely the reply follows the requested strategy (0-1)
+    pub strategy_adherence: f32,
+    /// How well the reply acknowledges the user's feelings (0-1)
+    pub empathy: f32,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct CandidateScores {
+    pub scores: Vec<CandidateScore>,
+}
+
+pub fn scoring_prompt(strategy_prompt: &str, user_input: &str, candidates: &[String]) -> String {
+    let mut prompt = format!(
+        "The assistant was asked to follow this strategy:\n{}\n\nThe user wrote:\n{}\n\nCandidate replies:\n",
+        strategy_prompt, user_input
+    );
+    for (i, candidate) in candidates.iter().enumerate() {
+        prompt.push_str(&format!("\n[{}]\n{}\n", i + 1, candidate.trim()));
+    }
+    prompt
+}
+
+/// Candidate indices, best first. Unscored candidates rank last and ties keep
+/// the original order.
+pub fn rank(count: usize, scores: &[CandidateScore]) -> Vec<usize> {
+    let total = |index: usize| {
+        scores
+            .iter()
+            .find(|s| s.candidate == index + 1)
+            .map_or(f32::NEG_INFINITY, |s| s.strategy_adherence.clamp(0.0, 1.0) + s.empathy.clamp(0.0, 1.0))
+    };
+    let mut order: Vec<usize> = (0..count).collect();
+    order.sort_by(|&a, &b| total(b).total_cmp(&total(a)));
+    order
+}
+
+/// The score of the candidate at `index`, clamped to 0-1
+pub fn score_of(scores: &[CandidateScore], index: usize) -> Option<ReplyScore> {
+    scores.iter().find(|s| s.candidate == index + 1).map(|s| ReplyScore {
+        strategy_adherence: s.strategy_adherence.clamp(0.0, 1.0),
+        empathy: s.empathy.clamp(0.0, 1.0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(candidate: usize, strategy_adherence: f32, empathy: f32) -> CandidateScore {
+        CandidateScore { candidate, strategy_adherence, empathy }
+    }
+
+    #[test]
+    fn test_rank_by_total_score() {
+        let scores = [score(1, 0.4, 0.5), score(2, 0.9, 0.8), score(3, 0.9, 0.0), score(9, 1.0, 1.0)];
+        assert_eq!(rank(4, &scores), [1, 0, 2, 3]);
+        assert_eq!(rank(2, &[]), [0, 1]);
+        assert_eq!(score_of(&scores, 1), Some(ReplyScore { strategy_adherence: 0.9, empathy: 0.8 }));
+        assert_eq!(score_of(&scores, 4), None);
+    }
+
+    #[test]
+    fn test_scoring_prompt_numbers_candidates() {
+        let prompt = scoring_prompt("Be warm.", "I failed my exam", &["Oh no.".to_string(), "You'll do better.".to_string()]);
+        assert!(prompt.contains("[1]\nOh no.\n"));
+        assert!(prompt.contains("[2]\nYou'll do better.\n"));
+    }
+}
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// Collapses concurrent calls with the same key into one: the first caller
+/// runs the call, later ones wait for and share its result. Nothing is kept
+/// once the call finishes, so this only saves work during bursts.
+#[derive(Debug)]
+pub struct Coalescer<T> {
+    in_flight: Mutex<HashMap<String, Arc<OnceCell<T>>>>,
+}
+
+impl<T> Default for Coalescer<T> {
+    fn default() -> Self {
+        Self { in_flight: Mutex::new(HashMap::new()) }
+    }
+}
+
+impl<T: Clone> Coalescer<T> {
+    pub async fn run<F, Fut>(&self, key: &str, call: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let slot = self.in_flight.lock().unwrap().entry(key.to_string()).or_default().clone();
+
+        // If the caller running the call is cancelled, a waiting one takes over
+        let result = slot.get_or_init(call).await.clone();
+
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if in_flight.get(key).is_some_and(|current| Arc::ptr_eq(current, &slot)) {
+            in_flight.remove(key);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_calls_share_one_result() {
+        let coalescer = Coalescer::default();
+        let calls = AtomicUsize::new(0);
+        let call = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            42
+        };
+
+        let (a, b, c) = tokio::join!(
+            coalescer.run("same", call),
+            coalescer.run("same", call),
+            coalescer.run("other", call),
+        );
+        assert_eq!((a, b, c), (42, 42, 42));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Finished calls are forgotten
+        coalescer.run("same", call).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(coalescer.in_flight.lock().unwrap().is_empty());
+    }
+}
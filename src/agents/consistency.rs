@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use crate::{Sentiment, SentimentClassification};
+
+/// Answers below this confidence are sampled again when no threshold is set
+pub const DEFAULT_BELOW: f32 = 0.6;
+
+/// Fewer samples can't outvote a single odd answer; more rarely change the vote
+pub const SAMPLES: std::ops::RangeInclusive<usize> = 3..=5;
+
+/// Classifies hard texts several times and keeps the majority answer. Only a
+/// first answer less confident than `below` costs the extra `samples - 1` calls.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfConsistency {
+    pub samples: usize,
+    pub below: f32,
+}
+
+impl SelfConsistency {
+    pub fn wants_more(&self, first: &SentimentClassification) -> bool {
+        first.confidence < self.below
+    }
+}
+
+/// The sentiment most samples agree on, with their averaged confidence; a tie
+/// goes to the more confident side
+pub fn aggregate(samples: &[SentimentClassification]) -> Option<SentimentClassification> {
+    let mut votes: HashMap<Sentiment, (usize, f32)> = HashMap::new();
+    for sample in samples {
+        let (count, confidence) = votes.entry(sample.sentiment).or_default();
+        *count += 1;
+        *confidence += sample.confidence;
+    }
+    let (sentiment, (count, confidence)) = votes
+        .into_iter()
+        .max_by(|(_, a), (_, b)| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)))?;
+    // The labels of the surest sample that voted for the winner
+    let labels = samples
+        .iter()
+        .filter(|s| s.sentiment == sentiment)
+        .max_by(|a, b| a.confidence.total_cmp(&b.confidence))
+        .map(|s| s.labels.clone())
+        .unwrap_or_default();
+    Some(SentimentClassification { sentiment, confidence: confidence / count as f32, labels, is_fallback: false })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(sentiment: Sentiment, confidence: f32) -> SentimentClassification {
+        SentimentClassification { sentiment, confidence, labels: Vec::new(), is_fallback: false }
+    }
+
+    #[test]
+    fn test_majority_with_averaged_confidence() {
+        let result = aggregate(&[
+            sample(Sentiment::Neutral, 0.5),
+            sample(Sentiment::Negative, 0.8),
+            sample(Sentiment::Negative, 0.6),
+        ]).unwrap();
+        assert_eq!(result.sentiment, Sentiment::Negative);
+        assert!((result.confidence - 0.7).abs() < 1e-6);
+
+        let tie = aggregate(&[sample(Sentiment::Positive, 0.9), sample(Sentiment::Negative, 0.55)]).unwrap();
+        assert_eq!(tie.sentiment, Sentiment::Positive);
+        assert!(aggregate(&[]).is_none());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::models::{Message, MessageRole};
+
+/// Assumed for models the table below does not know
+pub const DEFAULT_CONTEXT_TOKENS: usize = 8_192;
+
+/// Kept free for the reply when its length is not capped
+const DEFAULT_REPLY_TOKENS: usize = 1_024;
+
+/// More history than this rarely helps the reply and only adds cost
+pub(super) const MAX_HISTORY_MESSAGES: usize = 40;
+
+/// Context windows of common models, by name prefix; the first match wins
+const KNOWN_WINDOWS: [(&str, usize); 9] = [
+    ("gpt-4o", 128_000),
+    ("gpt-4.1", 1_000_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("glm-4-long", 1_000_000),
+    ("glm-4", 128_000),
+    ("deepseek", 64_000),
+    ("qwen", 32_768),
+];
+
+/// Rough token count, at four characters a token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// How many tokens a model reads per request, which bounds the history sent with each turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextWindow {
+    pub tokens: usize,
+}
+
+impl ContextWindow {
+    pub fn for_model(model: &str) -> Self {
+        let model = model.to_lowercase();
+        let tokens = KNOWN_WINDOWS
+            .iter()
+            .find(|(prefix, _)| model.starts_with(prefix))
+            .map_or(DEFAULT_CONTEXT_TOKENS, |(_, tokens)| *tokens);
+        Self { tokens }
+    }
+
+    /// Tokens left for history once `fixed` (the preamble and the message) and
+    /// the reply are accounted for
+    pub fn history_budget(&self, fixed: &[&str], reply_tokens: Option<u64>) -> usize {
+        let reply = reply_tokens.map_or(DEFAULT_REPLY_TOKENS, |tokens| tokens as usize);
+        let fixed: usize = fixed.iter().map(|text| estimate_tokens(text)).sum();
+        self.tokens.saturating_sub(fixed + reply)
+    }
+}
+
+/// Tokens each message costs besides its text, for the role and separators
+const MESSAGE_OVERHEAD: usize = 4;
+
+/// The part of the history sent with a turn, as chat messages
+#[derive(Debug, Clone, Default)]
+pub struct HistoryWindow {
+    pub messages: Vec<Message>,
+    /// Earlier messages that were left out
+    pub omitted: usize,
+}
+
+impl HistoryWindow {
+    /// What the system message says about the history, if anything
+    pub fn note(&self) -> Option<String> {
+        if self.omitted > 0 {
+            Some(format!("{} earlier messages of this conversation are not shown.", self.omitted))
+        } else if self.messages.is_empty() {
+            Some("This is a new conversation.".to_string())
+        } else {
+            None
+        }
+    }
+
+    pub fn to_chat(&self) -> Vec<rig::message::Message> {
+        self.messages
+            .iter()
+            .map(|msg| match msg.role {
+                MessageRole::User => rig::message::Message::user(&msg.content),
+                MessageRole::Assistant => rig::message::Message::assistant(&msg.content),
+            })
+            .collect()
+    }
+}
+
+/// The history before `input`, which callers may already have recorded as
+/// its last message
+pub fn before_input<'a>(history: &'a [Message], input: &str) -> &'a [Message] {
+    match history.split_last() {
+        Some((last, earlier)) if last.role == MessageRole::User && last.content == input => earlier,
+        _ => history,
+    }
+}
+
+/// The latest messages that fit in `budget` tokens, oldest first, counting
+/// `skipped` ones already taken out of `history` as left out. The newest
+/// message is shortened rather than dropped when it alone is over budget.
+pub fn fit_history(history: &[Message], budget: usize, skipped: usize) -> HistoryWindow {
+    let mut messages = Vec::new();
+    let mut used = 0;
+    for msg in history.iter().rev().take(MAX_HISTORY_MESSAGES) {
+        let cost = estimate_tokens(&msg.content) + MESSAGE_OVERHEAD;
+        if used + cost > budget {
+            if messages.is_empty() && budget > MESSAGE_OVERHEAD {
+                let chars = (budget - MESSAGE_OVERHEAD) * 4;
+                let content = format!("{}…", msg.content.chars().take(chars.saturating_sub(1)).collect::<String>());
+                messages.push(Message { content, ..msg.clone() });
+            }
+            break;
+        }
+        used += cost;
+        messages.push(msg.clone());
+    }
+    messages.reverse();
+
+    let omitted = history.len() - messages.len() + skipped;
+    HistoryWindow { messages, omitted }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: MessageRole, content: &str) -> Message {
+        Message { role, content: content.to_string(), timestamp: 0, emotion: None, strategy: None, speaker: None, aspects: Vec::new(), cause: None, score: None }
+    }
+
+    #[test]
+    fn test_window_by_model() {
+        assert_eq!(ContextWindow::for_model("gpt-4o-mini").tokens, 128_000);
+        assert_eq!(ContextWindow::for_model("GPT-4").tokens, 8_192);
+        assert_eq!(ContextWindow::for_model("glm-4-flash").tokens, 128_000);
+        assert_eq!(ContextWindow::for_model("my-local-model").tokens, DEFAULT_CONTEXT_TOKENS);
+
+        let window = ContextWindow { tokens: 2_000 };
+        assert_eq!(window.history_budget(&["x".repeat(400).as_str()], Some(500)), 1_400);
+        assert_eq!(window.history_budget(&[], None), 976);
+        assert_eq!(window.history_budget(&["x".repeat(9_000).as_str()], None), 0);
+    }
+
+    #[test]
+    fn test_history_fills_the_budget() {
+        let history: Vec<Message> = (0..60)
+            .map(|i| message(if i % 2 == 0 { MessageRole::User } else { MessageRole::Assistant }, &format!("message {:02}", i)))
+            .collect();
+
+        let all = fit_history(&history, 100_000, 0);
+        assert_eq!(all.omitted, 20);
+        assert_eq!(all.messages.last().unwrap().content, "message 59");
+        assert_eq!(all.to_chat().len(), 40);
+
+        let some = fit_history(&history, 30, 0);
+        assert_eq!(some.messages.len(), 4);
+        assert_eq!(some.messages[0].content, "message 56");
+        assert_eq!(some.note().unwrap(), "56 earlier messages of this conversation are not shown.");
+
+        let long = [message(MessageRole::User, &"word ".repeat(500))];
+        let shortened = fit_history(&long, 50, 3);
+        assert!(shortened.messages[0].content.ends_with('…'));
+        assert!(estimate_tokens(&shortened.messages[0].content) + MESSAGE_OVERHEAD <= 50);
+        assert_eq!(shortened.omitted, 3);
+
+        assert_eq!(fit_history(&[], 100, 0).note().unwrap(), "This is a new conversation.");
+    }
+
+    #[test]
+    fn test_current_input_is_not_repeated() {
+        let history = [message(MessageRole::Assistant, "How was it?"), message(MessageRole::User, "Fine")];
+        assert_eq!(before_input(&history, "Fine").len(), 1);
+        assert_eq!(before_input(&history, "Something else").len(), 2);
+        assert_eq!(before_input(&history[..1], "How was it?").len(), 1);
+    }
+}
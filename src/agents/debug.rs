@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::models::{Message, MessageRole};
+
+/// Switch shared by the agents of one chat that prints every assembled prompt
+/// to stderr; it can be flipped while the chat is running
+#[derive(Debug, Clone, Default)]
+pub struct PromptDebug(Arc<AtomicBool>);
+
+impl PromptDebug {
+    pub fn new(enabled: bool) -> Self {
+        Self(Arc::new(AtomicBool::new(enabled)))
+    }
+
+    pub fn set(&self, enabled: bool) {
+        self.0.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn print(&self, call: &str, model: &str, prompt: &str) {
+        if self.enabled() {
+            eprintln!("🔍 {} prompt to {}:\n{}\n", call, model, prompt);
+        }
+    }
+}
+
+/// Lays out a prompt the way it is sent: the system message, earlier chat
+/// messages, then the user input
+pub fn format_prompt(system: &str, history: &[Message], user: &str) -> String {
+    let mut prompt = format!("--- system ---\n{}\n", system);
+    for msg in history {
+        let role = match msg.role {
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+        };
+        prompt.push_str(&format!("--- {} ---\n{}\n", role, msg.content));
+    }
+    prompt.push_str(&format!("--- user ---\n{}", user));
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch_is_shared_between_clones() {
+        let debug = PromptDebug::default();
+        let agent_copy = debug.clone();
+
+        debug.set(true);
+        assert!(agent_copy.enabled());
+    }
+
+    #[test]
+    fn test_format_prompt() {
+        let earlier = Message {
+            role: MessageRole::Assistant,
+            content: "Hello!".to_string(),
+            timestamp: 1,
+            emotion: None,
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
+        };
+        assert_eq!(
+            format_prompt("Be kind.", &[earlier], "hi"),
+            "--- system ---\nBe kind.\n--- assistant ---\nHello!\n--- user ---\nhi"
+        );
+        assert_eq!(format_prompt("Classify.", &[], "hi"), "--- system ---\nClassify.\n--- user ---\nhi");
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use crate::models::Message;
+use super::EmbeddingAgent;
+
+/// Shorter histories are sent as they are
+const MIN_MESSAGES: usize = 10;
+
+/// Only this many recent messages are compared, twice what a prompt can hold
+const CANDIDATES: usize = 2 * super::context::MAX_HISTORY_MESSAGES;
+
+/// Embeddings kept between turns, so each message is embedded once
+const CACHE_SIZE: usize = 512;
+
+/// Leaves repeated messages out of a long history, so the context window holds
+/// more distinct ones: a message whose embedding is as close as `threshold` to
+/// a later one from the same speaker is dropped.
+pub struct HistoryDedup {
+    embedder: EmbeddingAgent,
+    threshold: f64,
+    cache: Mutex<HashMap<String, Vec<f64>>>,
+}
+
+impl HistoryDedup {
+    pub fn new(embedder: EmbeddingAgent, threshold: f64) -> Self {
+        Self { embedder, threshold, cache: Mutex::new(HashMap::new()) }
+    }
+
+    /// The messages worth sending, oldest first, and how many were dropped. The
+    /// history is kept whole when embedding fails.
+    pub async fn compress(&self, history: &[Message]) -> (Vec<Message>, usize) {
+        if history.len() < MIN_MESSAGES {
+            return (history.to_vec(), 0);
+        }
+        let recent = &history[history.len().saturating_sub(CANDIDATES)..];
+        let vectors = match self.embed(recent).await {
+            Ok(vectors) => vectors,
+            Err(e) => {
+                tracing::warn!(error = %e, "embedding history failed, sending it without deduplication");
+                return (history.to_vec(), 0);
+            }
+        };
+
+        let keep = distinct(recent, &vectors, self.threshold);
+        let kept: Vec<Message> = recent.iter().zip(&keep).filter(|(_, keep)| **keep).map(|(m, _)| m.clone()).collect();
+        let dropped = recent.len() - kept.len();
+        let mut messages = history[..history.len() - recent.len()].to_vec();
+        messages.extend(kept);
+        (messages, dropped)
+    }
+
+    async fn embed(&self, messages: &[Message]) -> Result<Vec<Vec<f64>>, super::AgentError> {
+        let missing: Vec<String> = {
+            let cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+            let mut missing: Vec<String> = Vec::new();
+            for m in messages {
+                if !cache.contains_key(&m.content) && !missing.contains(&m.content) {
+                    missing.push(m.content.clone());
+                }
+            }
+            missing
+        };
+        let embedded = if missing.is_empty() { Vec::new() } else { self.embedder.embed(&missing).await? };
+
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.len() + missing.len() > CACHE_SIZE {
+            cache.clear();
+        }
+        cache.extend(missing.into_iter().zip(embedded));
+        Ok(messages
+            .iter()
+            .map(|m| cache.get(&m.content).cloned().unwrap_or_default())
+            .collect())
+    }
+}
+
+/// Which messages to keep: walking back from the newest, each one unless it
+/// repeats a kept message from the same speaker
+fn distinct(messages: &[Message], vectors: &[Vec<f64>], threshold: f64) -> Vec<bool> {
+    let mut keep = vec![false; messages.len()];
+    let mut kept: Vec<usize> = Vec::new();
+    for i in (0..messages.len()).rev() {
+        let repeats = kept.iter().any(|&j| {
+            messages[j].role == messages[i].role && cosine(&vectors[i], &vectors[j]) >= threshold
+        });
+        if !repeats {
+            keep[i] = true;
+            kept.push(i);
+        }
+    }
+    keep
+}
+
+fn cosine(a: &[f64], b: &[f64]) -> f64 {
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f64]| v.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms > 0.0 { dot / norms } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MessageRole;
+    use rig::providers::openai;
+
+    fn message(role: MessageRole, content: &str) -> Message {
+        Message { role, content: content.to_string(), timestamp: 0, emotion: None, strategy: None, speaker: None, aspects: Vec::new(), cause: None, score: None }
+    }
+
+    #[tokio::test]
+    async fn test_repeats_are_dropped_keeping_the_latest() {
+        let client = openai::Client::from_url("test-key", "https://api.example.com");
+        let dedup = HistoryDedup::new(EmbeddingAgent::new(client, "test-embedding").with_dry_run(true), 0.95);
+
+        let mut history = Vec::new();
+        for i in 0..6 {
+            history.push(message(MessageRole::User, "I still cannot sleep because of the deadline"));
+            history.push(message(MessageRole::Assistant, &format!("Maybe try {}", ["breathing", "walking", "journaling", "stretching", "reading", "music"][i])));
+        }
+        history.push(message(MessageRole::User, "Anyway, my sister visited yesterday"));
+
+        let (messages, dropped) = dedup.compress(&history).await;
+        assert_eq!(dropped, 5);
+        assert_eq!(messages.len(), 8);
+        assert_eq!(messages.iter().filter(|m| m.content.contains("sleep")).count(), 1);
+        assert_eq!(messages.last().unwrap().content, "Anyway, my sister visited yesterday");
+
+        let (short, dropped) = dedup.compress(&history[..4]).await;
+        assert_eq!((short.len(), dropped), (4, 0));
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// How often the agents sharing it got by without the model's answer, so a
+/// provider that quietly degrades shows up in summaries and metrics
+#[derive(Debug, Default)]
+pub struct Degradation {
+    fallbacks: AtomicU64,
+    failed_replies: AtomicU64,
+    retries: AtomicU64,
+}
+
+impl Degradation {
+    /// A classification went to the fallback policy
+    pub fn record_fallback(&self) {
+        self.fallbacks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A reply failed, or was the holding message sent while the circuit is open
+    pub fn record_failed_reply(&self) {
+        self.failed_replies.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A provider call was retried after a transient error
+    pub fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn fallbacks(&self) -> u64 {
+        self.fallbacks.load(Ordering::Relaxed)
+    }
+
+    pub fn failed_replies(&self) -> u64 {
+        self.failed_replies.load(Ordering::Relaxed)
+    }
+
+    pub fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// One line naming whatever degraded, or `None` when nothing did
+    pub fn describe(&self) -> Option<String> {
+        let parts: Vec<String> = [
+            (self.fallbacks(), "classification fell back", "classifications fell back"),
+            (self.failed_replies(), "reply failed", "replies failed"),
+            (self.retries(), "provider call was retried", "provider calls were retried"),
+        ]
+        .into_iter()
+        .filter(|(count, _, _)| *count > 0)
+        .map(|(count, one, many)| format!("{} {}", count, if count == 1 { one } else { many }))
+        .collect();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        let degradation = Degradation::default();
+        assert_eq!(degradation.describe(), None);
+
+        degradation.record_fallback();
+        degradation.record_retry();
+        degradation.record_retry();
+        assert_eq!(degradation.describe().unwrap(), "1 classification fell back, 2 provider calls were retried");
+    }
+}
@@ -0,0 +1,436 @@
+use serde_json::{Map, Value};
+use crate::{AspectSentiment, EmotionLabel, Sentiment, SentimentClassification};
+use crate::models::ReplyScore;
+use super::assessment::{Assessment, Decision};
+use super::entities::{Entity, EntityKind};
+use super::taxonomy::{LabelScore, Taxonomy, TaxonomyClassification};
+
+const POSITIVE: &[&str] = &[
+    "good", "great", "happy", "love", "glad", "thanks", "thank", "awesome", "excited",
+    "better", "nice", "wonderful", "fun", "proud", "relieved", "calm", "amazing",
+    "开心", "高兴", "喜欢", "谢谢", "很好", "不错",
+];
+
+const NEGATIVE: &[&str] = &[
+    "bad", "sad", "angry", "hate", "tired", "awful", "terrible", "upset", "worried",
+    "anxious", "nervous", "stressed", "lonely", "hurt", "worse", "afraid", "scared", "rough", "cry",
+    "难过", "伤心", "生气", "累", "焦虑", "害怕", "糟糕",
+];
+
+/// Common words that never make a key phrase
+const STOPWORDS: &[&str] = &[
+    "about", "after", "again", "also", "because", "been", "before", "being", "could", "does",
+    "doing", "from", "have", "having", "into", "just", "like", "more", "much", "only", "other",
+    "really", "should", "some", "than", "that", "their", "them", "then", "there", "these",
+    "they", "this", "today", "very", "want", "were", "what", "when", "where", "which", "while",
+    "will", "with", "would", "your",
+];
+
+/// Deterministic stand-in for the model in `--dry-run`: counts words from two
+/// small lexicons, so the same text always gets the same classification
+pub fn pseudo_classify(text: &str) -> SentimentClassification {
+    let text = text.to_lowercase();
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric() && c.is_ascii())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let hits = |lexicon: &[&str]| -> i32 {
+        lexicon
+            .iter()
+            .map(|term| {
+                if term.is_ascii() {
+                    words.iter().filter(|w| *w == term).count() as i32
+                } else {
+                    text.matches(term).count() as i32
+                }
+            })
+            .sum()
+    };
+
+    let (positive, negative) = (hits(POSITIVE), hits(NEGATIVE));
+    let score = positive - negative;
+    let sentiment = match score {
+        0 => Sentiment::Neutral,
+        s if s > 0 => Sentiment::Positive,
+        _ => Sentiment::Negative,
+    };
+    let confidence = (0.6 + 0.1 * score.unsigned_abs() as f32).min(0.95);
+
+    // Words from both lexicons make a mixed message, each side as sure as its share
+    let labels = if positive > 0 && negative > 0 {
+        let share = |hits: i32| hits as f32 / (positive + negative) as f32;
+        let mut labels = vec![
+            EmotionLabel { sentiment: Sentiment::Positive, confidence: share(positive) },
+            EmotionLabel { sentiment: Sentiment::Negative, confidence: share(negative) },
+        ];
+        labels.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        labels
+    } else {
+        Vec::new()
+    };
+
+    SentimentClassification { sentiment, confidence, labels, is_fallback: false }
+}
+
+/// Stand-in for the simulated user in `--dry-run`: canned messages in turn,
+/// worded so `pseudo_classify` reads them as `sentiment`
+pub fn pseudo_user_message(sentiment: Sentiment, turn: usize) -> &'static str {
+    let messages: &[&str] = match sentiment {
+        Sentiment::Negative => &[
+            "This is terrible, I'm so angry nobody has fixed it.",
+            "I'm still upset, it's been awful dealing with this.",
+            "Honestly I'm tired and stressed about the whole thing.",
+        ],
+        Sentiment::Neutral => &[
+            "Okay. What happens next?",
+            "Fine, I'll wait for the update then.",
+            "Alright, so how long does that usually take?",
+        ],
+        Sentiment::Positive => &[
+            "Thanks, that's great news, I'm relieved.",
+            "That's good to hear, thank you for the help.",
+            "Awesome, I feel much better now.",
+        ],
+    };
+    messages[turn % messages.len()]
+}
+
+/// Stand-in for keyphrase extraction in `--dry-run`: the most frequent longer
+/// words, ties broken by first appearance
+pub fn pseudo_keyphrases(text: &str, max: usize) -> Vec<String> {
+    let text = text.to_lowercase();
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        if word.chars().count() < 4 || STOPWORDS.contains(&word) {
+            continue;
+        }
+        match counts.iter_mut().find(|(w, _)| *w == word) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((word, 1)),
+        }
+    }
+
+    // Stable sort keeps first appearance order among equal counts
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    counts.into_iter().take(max).map(|(word, _)| word.to_string()).collect()
+}
+
+/// Words that introduce what a clause is about
+const DETERMINERS: &[&str] = &["the", "my", "our", "your", "this", "his", "her", "their"];
+
+/// Stand-in for aspect extraction in `--dry-run`: each clause split on "but"
+/// and punctuation is about the word after its first determiner, with the
+/// clause's own sentiment. Neutral clauses and clauses without one are skipped.
+pub fn pseudo_aspects(text: &str) -> Vec<AspectSentiment> {
+    let text = text.to_lowercase();
+    let mut aspects: Vec<AspectSentiment> = Vec::new();
+
+    for clause in text.split(" but ").flat_map(|part| part.split(['.', ',', ';', '!', '?'])) {
+        let words: Vec<&str> = clause.split(|c: char| !c.is_alphanumeric() && c != '\'').filter(|w| !w.is_empty()).collect();
+        let Some(aspect) = words.windows(2).find(|pair| DETERMINERS.contains(&pair[0])).map(|pair| pair[1]) else {
+            continue;
+        };
+        let sentiment = pseudo_classify(clause).sentiment;
+        if sentiment != Sentiment::Neutral && !aspects.iter().any(|a| a.aspect == aspect) {
+            aspects.push(AspectSentiment { aspect: aspect.to_string(), sentiment });
+        }
+    }
+    aspects
+}
+
+/// Words after which a text says why it feels the way it does
+const CAUSE_MARKERS: &[&str] = &["because of", "because", "due to", "about", "since"];
+
+/// Stand-in for cause extraction in `--dry-run`: the rest of the clause after
+/// "because", "about" and the like, or `None` when the text gives no reason
+pub fn pseudo_cause(text: &str) -> Option<String> {
+    let text = text.to_lowercase();
+    CAUSE_MARKERS.iter().find_map(|marker| {
+        let start = text.find(&format!(" {} ", marker))? + marker.len() + 2;
+        let cause = text[start..].split(['.', ',', ';', '!', '?']).next()?.trim();
+        (!cause.is_empty()).then(|| cause.to_string())
+    })
+}
+
+/// Words after which a capitalized word names a place
+const PLACE_MARKERS: &[&str] = &["in", "at", "from", "to", "near"];
+
+/// Stand-in for entity extraction in `--dry-run`: runs of capitalized words
+/// that do not start a sentence or line, places after "in", "at" and the like
+/// and people otherwise
+pub fn pseudo_entities(text: &str) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = Vec::new();
+    let mut run: Vec<&str> = Vec::new();
+    let mut kind = EntityKind::Person;
+    let mut previous = String::new();
+
+    for line in text.lines() {
+        let mut sentence_start = true;
+        for token in line.split_whitespace() {
+            let word = token.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'');
+            let word = word.strip_suffix("'s").unwrap_or(word);
+            let named = word.chars().next().is_some_and(char::is_uppercase) && word != "I" && !word.contains('\'');
+            if named && !sentence_start {
+                if run.is_empty() {
+                    kind = if PLACE_MARKERS.contains(&previous.as_str()) { EntityKind::Location } else { EntityKind::Person };
+                }
+                run.push(word);
+            } else {
+                end_run(&mut run, kind, &mut entities);
+            }
+            // Punctuation after a word ends the name it belongs to
+            if token.ends_with(|c: char| !c.is_alphanumeric()) {
+                end_run(&mut run, kind, &mut entities);
+            }
+            sentence_start = token.ends_with(['.', '!', '?']);
+            previous = word.to_lowercase();
+        }
+        end_run(&mut run, kind, &mut entities);
+    }
+    entities
+}
+
+fn end_run(run: &mut Vec<&str>, kind: EntityKind, entities: &mut Vec<Entity>) {
+    if run.is_empty() {
+        return;
+    }
+    let text = run.join(" ");
+    if !entities.iter().any(|e| e.text == text) {
+        entities.push(Entity { text, kind });
+    }
+    run.clear();
+}
+
+/// Words with which a reply acknowledges how the user feels
+const ACKNOWLEDGING: &[&str] = &["sorry", "hear", "understand", "feel", "feels", "sounds", "must"];
+
+/// Stand-in for the reply evaluator in `--dry-run`: more empathy for each
+/// acknowledging word, and the same adherence for every reply
+pub fn pseudo_reply_score(reply: &str) -> ReplyScore {
+    let reply = reply.to_lowercase();
+    let hits = reply.split(|c: char| !c.is_alphanumeric()).filter(|w| ACKNOWLEDGING.contains(w)).count();
+    ReplyScore { strategy_adherence: 0.7, empathy: (0.4 + 0.15 * hits as f32).min(0.95) }
+}
+
+/// Stand-in for the assessment in `--dry-run`: the decision restated, with
+/// the emotion words that led to it
+pub fn pseudo_assessment(text: &str, decision: &Decision) -> Assessment {
+    let text = text.to_lowercase();
+    let mut cues: Vec<&str> = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        if (POSITIVE.contains(&word) || NEGATIVE.contains(&word)) && !cues.contains(&word) {
+            cues.push(word);
+        }
+    }
+    let emotion = if cues.is_empty() {
+        format!("Read as {}, with no strong emotion words", decision.describe_emotion())
+    } else {
+        format!("Read as {} from \"{}\"", decision.describe_emotion(), cues.join("\", \""))
+    };
+    Assessment { emotion, strategy: format!("Answered {}", decision.describe_strategy()) }
+}
+
+/// Length of the vectors from `pseudo_embedding`
+const EMBEDDING_DIMS: usize = 64;
+
+/// Stand-in for an embedding model in `--dry-run`: longer words hashed into a
+/// fixed number of buckets, so texts sharing vocabulary end up close together
+pub fn pseudo_embedding(text: &str) -> Vec<f64> {
+    let text = text.to_lowercase();
+    let mut vector = vec![0.0; EMBEDDING_DIMS];
+
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        if word.chars().count() < 4 || STOPWORDS.contains(&word) {
+            continue;
+        }
+        // FNV-1a, stable across runs unlike the std hasher
+        let hash = word.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+        vector[(hash % EMBEDDING_DIMS as u64) as usize] += 1.0;
+    }
+
+    let norm = vector.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+/// Stand-in for a taxonomy classifier in `--dry-run`: the first label named
+/// in the text, or else the first label, less surely. When `scored`, the
+/// other labels share what is left.
+pub fn pseudo_label(text: &str, taxonomy: &Taxonomy, scored: bool) -> TaxonomyClassification {
+    let text = text.to_lowercase();
+    let named = taxonomy.labels.iter().position(|label| text.contains(&label.name.to_lowercase()));
+    let (best, confidence) = match named {
+        Some(i) => (i, 0.8),
+        None => (0, 0.5),
+    };
+
+    let scores = if scored {
+        let rest = (1.0 - confidence) / (taxonomy.labels.len() - 1) as f32;
+        taxonomy
+            .labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| LabelScore { label: label.name.clone(), score: if i == best { confidence } else { rest } })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    TaxonomyClassification { label: taxonomy.labels[best].name.clone(), confidence, scores }
+}
+
+/// Stand-in for a schema extraction in `--dry-run`: every property filled with
+/// a placeholder of its type, the first value of an enum, and empty arrays
+pub fn pseudo_extraction(schema: &Value) -> Value {
+    placeholder(schema, schema)
+}
+
+fn placeholder(root: &Value, schema: &Value) -> Value {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.trim_start_matches("#/definitions/");
+        return root.pointer(&format!("/definitions/{}", name)).map_or(Value::Null, |s| placeholder(root, s));
+    }
+    if let Some(first) = schema.get("enum").and_then(Value::as_array).and_then(|values| values.first()) {
+        return first.clone();
+    }
+    for key in ["allOf", "anyOf", "oneOf"] {
+        if let Some(option) = schema.get(key).and_then(Value::as_array).and_then(|options| options.first()) {
+            return placeholder(root, option);
+        }
+    }
+
+    let kind = match schema.get("type") {
+        Some(Value::String(kind)) => kind.as_str(),
+        Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).find(|k| *k != "null").unwrap_or("null"),
+        _ if schema.get("properties").is_some() => "object",
+        _ => "null",
+    };
+    match kind {
+        "object" => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let fields: Map<String, Value> = properties
+                .into_iter()
+                .flatten()
+                .map(|(name, property)| (name.clone(), placeholder(root, property)))
+                .collect();
+            Value::Object(fields)
+        }
+        "array" => Value::Array(Vec::new()),
+        "string" => Value::String("[dry run]".to_string()),
+        "number" | "integer" => {
+            let minimum = schema.get("minimum").and_then(Value::as_f64).unwrap_or(0.0).max(0.0);
+            serde_json::json!(minimum as i64)
+        }
+        "boolean" => Value::Bool(false),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pseudo_classify() {
+        assert_eq!(pseudo_classify("I'm so happy, thanks!").sentiment, Sentiment::Positive);
+        assert_eq!(pseudo_classify("Rough day, I feel tired and sad").sentiment, Sentiment::Negative);
+        assert_eq!(pseudo_classify("The meeting is at 3").sentiment, Sentiment::Neutral);
+        assert_eq!(pseudo_classify("今天很开心").sentiment, Sentiment::Positive);
+
+        // "goodbye" is not "good"
+        assert_eq!(pseudo_classify("goodbye").sentiment, Sentiment::Neutral);
+    }
+
+    #[test]
+    fn test_pseudo_classify_mixed() {
+        let emotion = pseudo_classify("So excited for the trip, but nervous about flying");
+        assert_eq!(emotion.labels.len(), 2);
+        assert!((emotion.score()).abs() < 1e-6);
+        assert!(pseudo_classify("so happy").labels.is_empty());
+    }
+
+    #[test]
+    fn test_pseudo_classify_is_deterministic() {
+        let first = pseudo_classify("bad bad good");
+        let second = pseudo_classify("bad bad good");
+        assert_eq!(first.sentiment, second.sentiment);
+        assert_eq!(first.confidence, second.confidence);
+        assert!((first.confidence - 0.7).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_pseudo_aspects() {
+        let aspects = pseudo_aspects("The job is great but my manager is awful");
+        let pairs: Vec<(&str, Sentiment)> = aspects.iter().map(|a| (a.aspect.as_str(), a.sentiment)).collect();
+        assert_eq!(pairs, [("job", Sentiment::Positive), ("manager", Sentiment::Negative)]);
+        assert!(pseudo_aspects("The meeting is at 3").is_empty());
+        assert!(pseudo_aspects("I feel great").is_empty());
+    }
+
+    #[test]
+    fn test_pseudo_cause() {
+        assert_eq!(pseudo_cause("I'm stressed because of the deadline tomorrow. Help"), Some("the deadline tomorrow".to_string()));
+        assert_eq!(pseudo_cause("Still upset about the argument with my partner"), Some("the argument with my partner".to_string()));
+        assert_eq!(pseudo_cause("I feel awful"), None);
+    }
+
+    #[test]
+    fn test_pseudo_entities() {
+        let entities = pseudo_entities("Yesterday I met Sam Lee in New York. Sam's sister Ana was there too\nThat's all.");
+        let found: Vec<(&str, EntityKind)> = entities.iter().map(|e| (e.text.as_str(), e.kind)).collect();
+        assert_eq!(found, [("Sam Lee", EntityKind::Person), ("New York", EntityKind::Location), ("Ana", EntityKind::Person)]);
+    }
+
+    #[test]
+    fn test_pseudo_assessment() {
+        let text = "Rough day, so tired and tired";
+        let emotion = pseudo_classify(text);
+        let blend = crate::strategy::StrategyBlend::single(crate::strategy::ResponseStrategy::Empathetic);
+        let decision = Decision { emotion: &emotion, trend: crate::state::EmotionTrend::Stable, blend: &blend, suppressed: None };
+
+        let assessment = pseudo_assessment(text, &decision);
+        assert!(assessment.emotion.starts_with("Read as Negative"));
+        assert!(assessment.emotion.ends_with("from \"rough\", \"tired\""));
+        assert_eq!(assessment.strategy, "Answered Empathetic for a Stable trend");
+    }
+
+    #[test]
+    fn test_pseudo_embedding_groups_shared_words() {
+        let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f64>();
+        let work = pseudo_embedding("deadline at work, manager angry about the deadline");
+        let more_work = pseudo_embedding("another deadline, my manager again");
+        let beach = pseudo_embedding("sunny beach holiday with friends");
+
+        assert_eq!(work.len(), EMBEDDING_DIMS);
+        assert!((dot(&work, &work) - 1.0).abs() < 1e-9);
+        assert!(dot(&work, &more_work) > dot(&work, &beach));
+    }
+
+    #[test]
+    fn test_pseudo_extraction_matches_schema() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["name", "rating", "tags", "mood"],
+            "properties": {
+                "name": { "type": "string" },
+                "rating": { "type": "integer", "minimum": 1, "maximum": 5 },
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "mood": { "$ref": "#/definitions/Mood" },
+                "address": { "type": ["object", "null"], "properties": { "city": { "type": "string" } } }
+            },
+            "definitions": { "Mood": { "enum": ["calm", "tense"] } }
+        });
+        let value = pseudo_extraction(&schema);
+        assert_eq!(value, serde_json::json!({
+            "name": "[dry run]",
+            "rating": 1,
+            "tags": [],
+            "mood": "calm",
+            "address": { "city": "[dry run]" }
+        }));
+        assert_eq!(crate::agents::repair::validate_value(&schema, &value), Ok(()));
+    }
+}
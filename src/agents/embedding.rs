@@ -0,0 +1,49 @@
+use rig::embeddings::EmbeddingModel;
+use rig::providers::openai;
+use super::dry_run::pseudo_embedding;
+use super::{AgentError, RetryPolicy};
+
+/// Texts sent per embedding request; some providers cap the batch size
+const EMBED_BATCH: usize = 16;
+
+pub struct EmbeddingAgent {
+    client: openai::Client,
+    model: String,
+    retry: RetryPolicy,
+    dry_run: bool,
+}
+
+impl EmbeddingAgent {
+    pub fn new(client: openai::Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+            retry: RetryPolicy::default(),
+            dry_run: false,
+        }
+    }
+
+    /// Hash words locally instead of calling the provider
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// One vector per text, in input order
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f64>>, AgentError> {
+        if self.dry_run {
+            return Ok(texts.iter().map(|text| pseudo_embedding(text)).collect());
+        }
+
+        let model = self.client.embedding_model(&self.model);
+        let model = &model;
+        let mut vectors = Vec::with_capacity(texts.len());
+        for chunk in texts.chunks(EMBED_BATCH) {
+            let embeddings = self.retry
+                .run(|| async move { Ok(model.embed_texts(chunk.to_vec()).await?) })
+                .await?;
+            vectors.extend(embeddings.into_iter().map(|embedding| embedding.vec));
+        }
+        Ok(vectors)
+    }
+}
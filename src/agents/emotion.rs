@@ -1,10 +1,72 @@
-use anyhow::Result;
+use futures::future;
+use futures::stream::{self, StreamExt};
+use rig::extractor::Extractor;
 use rig::providers::openai;
-use crate::SentimentClassification;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use crate::{AspectSentiment, SentimentClassification};
+use super::cache::ClassificationCache;
+use super::coalesce::Coalescer;
+use super::consistency::{self, SelfConsistency};
+use super::debug::format_prompt;
+use super::dry_run::{pseudo_aspects, pseudo_cause, pseudo_classify};
+use super::examples::{self, FewShotExample};
+use super::fallback::FallbackPolicy;
+use super::repair::{self, DEFAULT_REPAIR_ATTEMPTS};
+use super::{AgentError, CircuitBreaker, Degradation, ErrorAction, PromptDebug, RepairStats, RetryPolicy};
+
+/// Classifications in flight at once for a batch
+const BATCH_CONCURRENCY: usize = 4;
+
+/// Part of the cache key; bump when the prompts below change so old results
+/// aren't served for the new wording
+pub const PROMPT_VERSION: &str = "emotion-v4";
+
+const ASPECT_PREAMBLE: &str = "You are a sentiment analysis expert. List the things the user's text \
+     expresses a feeling about, such as their job, a person or a place, each as a short noun phrase in the \
+     text's language with the sentiment (Positive/Negative/Neutral) the text holds toward it. \
+     Leave out things mentioned without any feeling, and return an empty list when there are none.";
+
+const CAUSE_PREAMBLE: &str = "The user's text expresses a negative emotion. Name what most likely \
+     causes it, as a short phrase such as \"deadline pressure\" or \"argument with partner\", taken from \
+     what the text says. Leave the cause empty when the text gives no reason.";
+
+/// The answer to the cause prompt
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct Cause {
+    /// Empty when the text does not say
+    cause: String,
+}
+
+/// The answer to the aspect prompt
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct Aspects {
+    aspects: Vec<AspectSentiment>,
+}
 
 pub struct EmotionDetector {
     client: openai::Client,
     model: String,
+    retry: RetryPolicy,
+    breaker: Arc<CircuitBreaker>,
+    dry_run: bool,
+    debug: PromptDebug,
+    cache: Option<Arc<ClassificationCache>>,
+    in_flight: Coalescer<Result<SentimentClassification, AgentError>>,
+    repair_attempts: usize,
+    repairs: RepairStats,
+    examples: Vec<FewShotExample>,
+    /// `PROMPT_VERSION`, marked with the examples when they aren't the defaults
+    prompt_version: String,
+    consistency: Option<SelfConsistency>,
+    resampled: AtomicU64,
+    fallback: FallbackPolicy,
+    degradation: Arc<Degradation>,
+    aspects: bool,
+    causes: bool,
 }
 
 impl EmotionDetector {
@@ -12,11 +74,158 @@ impl EmotionDetector {
         Self {
             client,
             model: model.to_string(),
+            retry: RetryPolicy::default(),
+            breaker: Arc::new(CircuitBreaker::default()),
+            dry_run: false,
+            debug: PromptDebug::default(),
+            cache: None,
+            in_flight: Coalescer::default(),
+            repair_attempts: DEFAULT_REPAIR_ATTEMPTS,
+            repairs: RepairStats::default(),
+            examples: examples::default_examples(),
+            prompt_version: PROMPT_VERSION.to_string(),
+            consistency: None,
+            resampled: AtomicU64::new(0),
+            fallback: FallbackPolicy::default(),
+            degradation: Arc::new(Degradation::default()),
+            aspects: false,
+            causes: false,
+        }
+    }
+
+    /// Counts fallbacks and retries together with other agents
+    pub fn with_degradation(mut self, degradation: Arc<Degradation>) -> Self {
+        self.retry.degradation = Some(degradation.clone());
+        self.degradation = degradation;
+        self
+    }
+
+    /// What a classification becomes when the model gives no usable answer
+    pub fn with_fallback(mut self, fallback: FallbackPolicy) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Samples low-confidence texts several times and keeps the majority answer
+    pub fn with_self_consistency(mut self, consistency: Option<SelfConsistency>) -> Self {
+        self.consistency = consistency;
+        self
+    }
+
+    /// Classifications that took extra samples for self-consistency
+    pub fn resampled(&self) -> u64 {
+        self.resampled.load(Ordering::Relaxed)
+    }
+
+    /// Labelled texts shown to the model before each classification; none
+    /// classifies zero-shot
+    pub fn with_examples(mut self, examples: Vec<FewShotExample>) -> Self {
+        self.prompt_version = if examples == examples::default_examples() {
+            PROMPT_VERSION.to_string()
+        } else {
+            format!("{}-{}", PROMPT_VERSION, examples::fingerprint(&examples))
+        };
+        self.examples = examples;
+        self
+    }
+
+    /// Corrective follow-ups after an invalid answer before falling back to Neutral
+    pub fn with_repair_attempts(mut self, attempts: usize) -> Self {
+        self.repair_attempts = attempts;
+        self
+    }
+
+    /// How often answers needed repairing
+    pub fn repairs(&self) -> &RepairStats {
+        &self.repairs
+    }
+
+    /// Classify locally and deterministically instead of calling the provider
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Prints each assembled prompt while `debug` is switched on
+    pub fn with_prompt_debug(mut self, debug: PromptDebug) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Reuses earlier results for the same text, model and prompt version
+    pub fn with_cache(mut self, cache: Option<Arc<ClassificationCache>>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Shares a circuit breaker with other agents calling the same provider
+    pub fn with_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    /// Also extracts the sentiment toward each thing a message talks about
+    pub fn with_aspect_sentiment(mut self, aspects: bool) -> Self {
+        self.aspects = aspects;
+        self
+    }
+
+    pub fn extracts_aspects(&self) -> bool {
+        self.aspects
+    }
+
+    /// Also asks what a negative emotion comes from
+    pub fn with_cause_extraction(mut self, causes: bool) -> Self {
+        self.causes = causes;
+        self
+    }
+
+    pub fn extracts_causes(&self) -> bool {
+        self.causes
+    }
+
+    pub async fn analyze(&self, text: &str) -> Result<SentimentClassification, AgentError> {
+        self.analyze_after(text, None).await
+    }
+
+    /// Like `analyze`, with the emotion of the user's previous message for
+    /// the `Previous` fallback policy
+    #[tracing::instrument(
+        name = "classification",
+        skip_all,
+        fields(model = %self.model, text_len = text.len(), sentiment = tracing::field::Empty)
+    )]
+    pub async fn analyze_after(
+        &self,
+        text: &str,
+        previous: Option<&SentimentClassification>,
+    ) -> Result<SentimentClassification, AgentError> {
+        if self.dry_run {
+            return Ok(pseudo_classify(text));
+        }
+
+        // Identical texts arriving together, e.g. a burst of requests, share
+        // one provider call
+        let result = match self.in_flight.run(text, || self.classify(text)).await {
+            // 没有可用的答案（熔断器打开或修复失败）：按降级策略处理
+            Err(e) if e.action() == ErrorAction::Fallback => {
+                self.degradation.record_fallback();
+                self.fallback.resolve(text, previous, e)
+            }
+            result => result,
+        };
+        if let Ok(classification) = &result {
+            tracing::Span::current().record("sentiment", tracing::field::debug(classification.sentiment));
         }
+        result
     }
 
-    pub async fn analyze(&self, text: &str) -> Result<SentimentClassification> {
-        use crate::Sentiment;
+    async fn classify(&self, text: &str) -> Result<SentimentClassification, AgentError> {
+        if let Some(cache) = &self.cache
+            && let Some(cached) = cache.get(&self.model, &self.prompt_version, text).await
+        {
+            return Ok(cached);
+        }
 
         // 构建 prompt，对短文本提供更多上下文指导
         let input_prompt = if text.trim().len() < 5 {
@@ -30,7 +239,7 @@ impl EmotionDetector {
             text.to_string()
         };
 
-        let system_prompt = if text.trim().len() < 5 {
+        let instructions = if text.trim().len() < 5 {
             "You are a sentiment analysis expert specializing in brief text analysis. \
              For short inputs like names, greetings, or single words, use contextual clues. \
              Return sentiment type (Positive/Negative/Neutral) and confidence score (0-1). \
@@ -38,38 +247,166 @@ impl EmotionDetector {
         } else {
             "You are a sentiment analysis expert. Analyze the emotional tone of the user's text. \
              Return the sentiment type (Positive/Negative/Neutral) and a confidence score (0-1). \
+             When the text carries more than one emotion at once, such as \"excited but nervous\", \
+             also list every one of them in `labels`, each with its own confidence, and put the \
+             strongest in `sentiment`. Leave `labels` empty for a single emotion. \
              Be accurate and thoughtful in your assessment."
         };
+        let system_prompt = match examples::preamble_section(&self.examples) {
+            Some(examples) => format!("{}\n\n{}", instructions, examples),
+            None => instructions.to_string(),
+        };
+        let system_prompt = system_prompt.as_str();
+
+        self.debug.print("Classification", &self.model, &format_prompt(system_prompt, &[], &input_prompt));
 
         let extractor = self.client
             .extractor::<SentimentClassification>(&self.model)
             .preamble(system_prompt)
             .build();
 
-        // 尝试提取，如果失败则使用降级策略
-        match extractor.extract(&input_prompt).await {
-            Ok(result) => Ok(result),
-            Err(e) => {
-                // 检查错误类型 - 如果是反序列化错误（空响应或无效JSON），返回默认值
-                let error_msg = e.to_string();
-                if error_msg.contains("deserialize") || error_msg.contains("expected value") {
-                    // 降级策略：返回 Neutral 情感，中等置信度
-                    Ok(SentimentClassification {
-                        sentiment: Sentiment::Neutral,
-                        confidence: 0.5,
-                    })
-                } else {
-                    // 其他错误类型（如网络错误）转换为 anyhow::Error 后向上传递
-                    Err(anyhow::anyhow!("API error: {}", e))
+        let first = self.extract(&extractor, &input_prompt).await?;
+        let result = match self.consistency.filter(|consistency| consistency.wants_more(&first)) {
+            Some(consistency) => {
+                // 难以判断的文本：多次采样，取多数情感
+                self.resampled.fetch_add(1, Ordering::Relaxed);
+                let extra = (1..consistency.samples).map(|_| self.extract(&extractor, &input_prompt));
+                let mut samples = vec![first.clone()];
+                for sample in future::join_all(extra).await {
+                    match sample {
+                        Ok(sample) => samples.push(sample),
+                        Err(e) => tracing::warn!(error = %e, "extra classification sample failed"),
+                    }
                 }
+                consistency::aggregate(&samples).unwrap_or(first)
             }
+            None => first,
+        };
+
+        // Only real answers are cached, never the fallbacks
+        if let Some(cache) = &self.cache {
+            cache.put(&self.model, &self.prompt_version, text, &result).await;
         }
+        Ok(result)
+    }
+
+    /// One valid answer; an error the fallback policy handles when the circuit
+    /// is open or no answer was valid
+    async fn extract(
+        &self,
+        extractor: &Extractor<openai::CompletionModel, SentimentClassification>,
+        input_prompt: &str,
+    ) -> Result<SentimentClassification, AgentError> {
+        // 尝试提取：临时错误重试；答案无效时附上问题重新提问，仍无效才降级
+        let mut prompt = input_prompt.to_string();
+        let mut problem = String::new();
+        for repairs in 0..=self.repair_attempts {
+            let input = prompt.as_str();
+            let call = self.retry
+                .run(|| async move { extractor.extract(input).await.map_err(AgentError::from) });
+
+            // 熔断器打开时不调用 API，直接降级
+            let Some(result) = self.breaker.call(call).await else {
+                return Err(AgentError::CircuitOpen);
+            };
+
+            problem = match result.map(|result| repair::validate(&result).map(|()| result)) {
+                Ok(Ok(result)) => {
+                    self.repairs.record(repairs, true);
+                    return Ok(result);
+                }
+                Ok(Err(problem)) => problem,
+                // 提取失败（空响应或无效JSON）：修复后重试
+                Err(e) if e.action() == ErrorAction::Fallback => e.to_string(),
+                // 其他错误（重试耗尽、鉴权失败等）向上传递
+                Err(e) => return Err(e),
+            };
+            tracing::warn!(attempt = repairs + 1, %problem, "invalid classification");
+            prompt = repair::correction(input_prompt, &problem);
+        }
+
+        // 修复次数用尽：交给降级策略
+        self.repairs.record(self.repair_attempts, false);
+        Err(AgentError::Extraction(problem))
+    }
+
+    /// The things `text` expresses a feeling about, each with its sentiment,
+    /// e.g. job:positive and manager:negative for "the job is great but my
+    /// manager is awful"
+    #[tracing::instrument(name = "aspects", skip_all, fields(model = %self.model, text_len = text.len()))]
+    pub async fn aspects(&self, text: &str) -> Result<Vec<AspectSentiment>, AgentError> {
+        if self.dry_run {
+            return Ok(pseudo_aspects(text));
+        }
+
+        self.debug.print("Aspects", &self.model, &format_prompt(ASPECT_PREAMBLE, &[], text));
+        let extractor = self.client
+            .extractor::<Aspects>(&self.model)
+            .preamble(ASPECT_PREAMBLE)
+            .build();
+
+        let extractor = &extractor;
+        let call = self.retry.run(|| async move { Ok(extractor.extract(text).await?) });
+        let Some(result) = self.breaker.call(call).await else {
+            return Err(AgentError::CircuitOpen);
+        };
+        Ok(result?.aspects.into_iter().filter(|a| !a.aspect.trim().is_empty()).collect())
+    }
+
+    /// The likely cause of the negative emotion in `text`, such as "deadline
+    /// pressure"; `None` when the text gives no reason
+    #[tracing::instrument(name = "cause", skip_all, fields(model = %self.model, text_len = text.len()))]
+    pub async fn cause(&self, text: &str) -> Result<Option<String>, AgentError> {
+        if self.dry_run {
+            return Ok(pseudo_cause(text));
+        }
+
+        self.debug.print("Cause", &self.model, &format_prompt(CAUSE_PREAMBLE, &[], text));
+        let extractor = self.client
+            .extractor::<Cause>(&self.model)
+            .preamble(CAUSE_PREAMBLE)
+            .build();
+
+        let extractor = &extractor;
+        let call = self.retry.run(|| async move { Ok(extractor.extract(text).await?) });
+        let Some(result) = self.breaker.call(call).await else {
+            return Err(AgentError::CircuitOpen);
+        };
+        let cause = result?.cause.trim().to_string();
+        Ok((!cause.is_empty()).then_some(cause))
+    }
+
+    /// Classifies several texts concurrently; results keep the input order and
+    /// fail independently. Repeated texts are classified once.
+    pub async fn analyze_batch(&self, texts: &[&str]) -> Vec<Result<SentimentClassification, AgentError>> {
+        let mut unique: Vec<&str> = Vec::new();
+        let mut seen = HashMap::new();
+        let positions: Vec<usize> = texts
+            .iter()
+            .map(|&text| {
+                *seen.entry(text).or_insert_with(|| {
+                    unique.push(text);
+                    unique.len() - 1
+                })
+            })
+            .collect();
+
+        // Futures are lazy, so collecting them starts nothing; mapping inside the
+        // stream instead trips the `Send` check on axum handlers
+        let calls: Vec<_> = unique.iter().map(|text| self.analyze(text)).collect();
+        let results: Vec<_> = stream::iter(calls)
+            .buffered(BATCH_CONCURRENCY)
+            .collect()
+            .await;
+
+        positions.into_iter().map(|i| results[i].clone()).collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Sentiment;
 
     #[test]
     fn test_emotion_detector_new() {
@@ -81,4 +418,100 @@ mod tests {
 
         assert_eq!(detector.model, "test-model");
     }
+
+    #[tokio::test]
+    async fn test_analyze_batch_keeps_order() {
+        let client = openai::Client::from_url("test-key", "https://api.example.com");
+        let detector = EmotionDetector::new(client, "test-model").with_dry_run(true);
+
+        let results = detector.analyze_batch(&["so happy", "the bus is at 9", "awful day", "so happy"]).await;
+        let sentiments: Vec<_> = results.into_iter().map(|r| r.unwrap().sentiment).collect();
+        assert_eq!(sentiments, [Sentiment::Positive, Sentiment::Neutral, Sentiment::Negative, Sentiment::Positive]);
+    }
+
+    /// An OpenAI-compatible provider answering each request with the next of
+    /// `answers` as `submit` arguments, and the request bodies it received
+    async fn provider(answers: &[&str]) -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        use axum::{Json, Router, routing::post};
+
+        let answers: Arc<std::sync::Mutex<Vec<String>>> = Arc::new(std::sync::Mutex::new(answers.iter().rev().map(|a| a.to_string()).collect()));
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let received = requests.clone();
+        let app = Router::new().route("/chat/completions", post(move |body: String| async move {
+            received.lock().unwrap().push(body);
+            let arguments = answers.lock().unwrap().pop().unwrap_or_default();
+            Json(serde_json::json!({
+                "id": "test", "object": "chat.completion", "created": 0, "model": "test-model",
+                "choices": [{
+                    "index": 0, "finish_reason": "tool_calls",
+                    "message": { "role": "assistant", "tool_calls": [{
+                        "id": "call", "type": "function", "function": { "name": "submit", "arguments": arguments },
+                    }]},
+                }],
+            }))
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, requests)
+    }
+
+    #[tokio::test]
+    async fn test_invalid_answers_are_repaired() {
+        let (url, requests) = provider(&[
+            r#"{"sentiment":"Happy","confidence":0.9}"#,
+            r#"{"sentiment":"Positive","confidence":9}"#,
+            r#"{"sentiment":"Positive","confidence":0.9}"#,
+        ]).await;
+        let detector = EmotionDetector::new(openai::Client::from_url("test-key", &url), "test-model");
+
+        let classification = detector.analyze("What a wonderful morning").await.unwrap();
+        assert_eq!(classification.sentiment, Sentiment::Positive);
+        assert_eq!((detector.repairs().extractions(), detector.repairs().repaired(), detector.repairs().failed()), (1, 1, 0));
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[2].contains("confidence is 9, above the maximum 1"));
+    }
+
+    #[tokio::test]
+    async fn test_unrepaired_answers_fall_back() {
+        let (url, requests) = provider(&[r#"{"sentiment":"Happy"}"#; 3]).await;
+        let detector = EmotionDetector::new(openai::Client::from_url("test-key", &url), "test-model")
+            .with_repair_attempts(1);
+
+        let classification = detector.analyze("What a wonderful morning").await.unwrap();
+        assert_eq!((classification.sentiment, classification.is_fallback), (Sentiment::Neutral, true));
+        assert_eq!(detector.degradation.fallbacks(), 1);
+        assert_eq!((detector.repairs().repaired(), detector.repairs().failed()), (0, 1));
+        assert_eq!(requests.lock().unwrap().len(), 2);
+
+        let (url, _) = provider(&[r#"{"sentiment":"Happy"}"#; 2]).await;
+        let detector = EmotionDetector::new(openai::Client::from_url("test-key", &url), "test-model")
+            .with_repair_attempts(1)
+            .with_fallback(FallbackPolicy::Error);
+        let error = detector.analyze_after("What a wonderful morning", None).await.unwrap_err();
+        assert!(matches!(error, AgentError::Extraction(ref problem) if problem.contains("unknown variant")));
+    }
+
+    #[tokio::test]
+    async fn test_unsure_answers_are_sampled_again() {
+        let (url, requests) = provider(&[
+            r#"{"sentiment":"Neutral","confidence":0.4}"#,
+            r#"{"sentiment":"Negative","confidence":0.8}"#,
+            r#"{"sentiment":"Negative","confidence":0.6}"#,
+            r#"{"sentiment":"Positive","confidence":0.9}"#,
+        ]).await;
+        let detector = EmotionDetector::new(openai::Client::from_url("test-key", &url), "test-model")
+            .with_self_consistency(Some(SelfConsistency { samples: 3, below: 0.6 }));
+
+        let classification = detector.analyze("Well, that happened").await.unwrap();
+        assert_eq!(classification.sentiment, Sentiment::Negative);
+        assert!((classification.confidence - 0.7).abs() < 1e-6);
+        assert_eq!(detector.resampled(), 1);
+
+        let classification = detector.analyze("Best news all year").await.unwrap();
+        assert_eq!(classification.sentiment, Sentiment::Positive);
+        assert_eq!(detector.resampled(), 1);
+        assert_eq!(requests.lock().unwrap().len(), 4);
+    }
 }
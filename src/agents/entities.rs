@@ -0,0 +1,91 @@
+use rig::providers::openai;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use super::dry_run::pseudo_entities;
+use super::{AgentError, RetryPolicy};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, JsonSchema)]
+pub enum EntityKind {
+    Person,
+    Location,
+    Organization,
+    /// An email address, phone number, handle or account number
+    Contact,
+}
+
+impl EntityKind {
+    /// The label of the kind's placeholders, e.g. `PERSON`
+    pub fn label(self) -> &'static str {
+        match self {
+            EntityKind::Person => "PERSON",
+            EntityKind::Location => "LOCATION",
+            EntityKind::Organization => "ORGANIZATION",
+            EntityKind::Contact => "CONTACT",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct Entity {
+    /// Exactly as written in the text
+    pub text: String,
+    pub kind: EntityKind,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct Entities {
+    entities: Vec<Entity>,
+}
+
+/// Finds the names, places and other identifiers a text mentions
+pub struct EntityExtractor {
+    client: openai::Client,
+    model: String,
+    retry: RetryPolicy,
+    dry_run: bool,
+}
+
+impl EntityExtractor {
+    pub fn new(client: openai::Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+            retry: RetryPolicy::default(),
+            dry_run: false,
+        }
+    }
+
+    /// Take capitalized words for names and places instead of calling the provider
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub async fn extract(&self, text: &str) -> Result<Vec<Entity>, AgentError> {
+        if text.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        if self.dry_run {
+            return Ok(pseudo_entities(text));
+        }
+
+        let extractor = self.client
+            .extractor::<Entities>(&self.model)
+            .preamble(
+                "List every identifier in this conversation that could point to a real person: \
+                 names and nicknames of people and pets, places smaller than a country such as \
+                 cities, streets and schools, employers and other organizations, and contact \
+                 details such as email addresses, phone numbers and social media handles. \
+                 Copy each exactly as written. Skip the assistant, common nouns and relations \
+                 without a name, such as \"my sister\".",
+            )
+            .build();
+
+        let extractor = &extractor;
+        let entities = self.retry
+            .run(|| async move { Ok(extractor.extract(text).await?) })
+            .await?
+            .entities;
+        Ok(entities.into_iter().filter(|e| !e.text.trim().is_empty()).collect())
+    }
+}
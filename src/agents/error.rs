@@ -0,0 +1,206 @@
+use rig::completion::{CompletionError, PromptError};
+use rig::embeddings::EmbeddingError;
+use rig::extractor::ExtractionError;
+use rig::tool::{ToolError, ToolSetError};
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Failure of a model call, classified so callers can decide whether to retry,
+/// back off, or fall back
+#[derive(Debug, Clone, Error)]
+pub enum AgentError {
+    #[error("provider rate limit hit: {0}")]
+    RateLimited(String),
+
+    #[error("provider request timed out")]
+    Timeout,
+
+    #[error("could not reach the provider: {0}")]
+    Connection(String),
+
+    #[error("provider is temporarily unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("provider rejected the request: {0}")]
+    Rejected(String),
+
+    #[error("provider returned an error: {0}")]
+    Provider(String),
+
+    #[error("could not extract structured output: {0}")]
+    Extraction(String),
+
+    #[error("provider circuit is open after repeated failures")]
+    CircuitOpen,
+}
+
+/// What a caller should do about a failed model call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorAction {
+    /// Transient; the same request may succeed shortly
+    Retry,
+    /// No usable output, as the answer was invalid or the circuit is open; use a local default
+    Fallback,
+    /// Retrying cannot help (bad key, exhausted quota, unknown model, ...)
+    Abort,
+}
+
+impl AgentError {
+    pub fn action(&self) -> ErrorAction {
+        match self {
+            AgentError::RateLimited(_)
+            | AgentError::Timeout
+            | AgentError::Connection(_)
+            | AgentError::Unavailable(_) => ErrorAction::Retry,
+            AgentError::Extraction(_) | AgentError::CircuitOpen => ErrorAction::Fallback,
+            AgentError::Rejected(_) | AgentError::Provider(_) => ErrorAction::Abort,
+        }
+    }
+}
+
+impl From<CompletionError> for AgentError {
+    fn from(error: CompletionError) -> Self {
+        match error {
+            CompletionError::HttpError(e) if e.is_timeout() => AgentError::Timeout,
+            CompletionError::HttpError(e) => AgentError::Connection(e.to_string()),
+            // An empty or malformed body, usually from a model that ignored the schema
+            CompletionError::JsonError(e) => AgentError::Extraction(e.to_string()),
+            CompletionError::ResponseError(message) => AgentError::Extraction(message),
+            CompletionError::ProviderError(body) => classify_provider_error(body),
+            CompletionError::RequestError(e) => AgentError::Provider(e.to_string()),
+        }
+    }
+}
+
+impl From<PromptError> for AgentError {
+    fn from(error: PromptError) -> Self {
+        match error {
+            PromptError::CompletionError(e) => e.into(),
+            // The model called the extractor's `submit` with arguments its type
+            // rejects, or called a tool that doesn't exist
+            PromptError::ToolError(
+                e @ (ToolSetError::ToolCallError(ToolError::JsonError(_))
+                | ToolSetError::JsonError(_)
+                | ToolSetError::ToolNotFoundError(_)),
+            ) => AgentError::Extraction(e.to_string()),
+            PromptError::ToolError(e) => AgentError::Provider(e.to_string()),
+        }
+    }
+}
+
+impl From<ExtractionError> for AgentError {
+    fn from(error: ExtractionError) -> Self {
+        match error {
+            ExtractionError::NoData => AgentError::Extraction("no data extracted".to_string()),
+            ExtractionError::DeserializationError(e) => AgentError::Extraction(e.to_string()),
+            ExtractionError::PromptError(e) => e.into(),
+        }
+    }
+}
+
+impl From<EmbeddingError> for AgentError {
+    fn from(error: EmbeddingError) -> Self {
+        match error {
+            EmbeddingError::HttpError(e) if e.is_timeout() => AgentError::Timeout,
+            EmbeddingError::HttpError(e) => AgentError::Connection(e.to_string()),
+            EmbeddingError::ProviderError(body) => classify_provider_error(body),
+            other => AgentError::Provider(other.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorEnvelope {
+    error: ErrorDetail,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorDetail {
+    #[serde(default)]
+    code: Option<serde_json::Value>,
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+/// rig hands back either the body of a non-2xx response or the message of an
+/// in-band error. Both are OpenAI-style `{"error": {"code", "type", "message"}}`
+/// envelopes, with Zhipu using numeric codes.
+fn classify_provider_error(body: String) -> AgentError {
+    let Ok(ErrorEnvelope { error }) = serde_json::from_str::<ErrorEnvelope>(&body) else {
+        return AgentError::Provider(body);
+    };
+
+    let code = match &error.code {
+        Some(serde_json::Value::String(code)) => code.clone(),
+        Some(serde_json::Value::Number(code)) => code.to_string(),
+        _ => String::new(),
+    };
+    let message = error.message.unwrap_or_else(|| body.clone());
+
+    match (code.as_str(), error.kind.as_deref()) {
+        // Zhipu: 1302 concurrency, 1303 frequency, 1305 traffic
+        ("rate_limit_exceeded" | "1302" | "1303" | "1305", _) => AgentError::RateLimited(message),
+        // OpenAI reports rate limits only through the type on some endpoints
+        (_, Some("requests" | "tokens")) => AgentError::RateLimited(message),
+        // Zhipu: 1234 network error, 1230 service busy
+        ("server_error" | "1230" | "1234", _) | (_, Some("server_error")) => AgentError::Unavailable(message),
+        // Zhipu: 1000-1004 auth, 1113 arrears, 1211 unknown model
+        ("invalid_api_key" | "insufficient_quota" | "model_not_found"
+            | "1000" | "1001" | "1002" | "1003" | "1004" | "1113" | "1211", _)
+        | (_, Some("invalid_request_error" | "authentication_error" | "insufficient_quota")) => {
+            AgentError::Rejected(message)
+        }
+        _ => AgentError::Provider(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(body: &str) -> AgentError {
+        AgentError::from(CompletionError::ProviderError(body.to_string()))
+    }
+
+    #[test]
+    fn test_provider_errors_classified() {
+        let error = provider(r#"{"error":{"message":"Rate limit reached","type":"requests","code":"rate_limit_exceeded"}}"#);
+        assert!(matches!(error, AgentError::RateLimited(ref m) if m == "Rate limit reached"));
+
+        let error = provider(r#"{"error":{"code":"1302","message":"您的账户已达到速率限制"}}"#);
+        assert!(matches!(error, AgentError::RateLimited(_)));
+
+        let error = provider(r#"{"error":{"message":"Incorrect API key","type":"invalid_request_error","code":"invalid_api_key"}}"#);
+        assert!(matches!(error, AgentError::Rejected(_)));
+
+        let error = provider(r#"{"error":{"code":1234,"message":"网络错误"}}"#);
+        assert!(matches!(error, AgentError::Unavailable(_)));
+
+        assert!(matches!(provider("<html>Bad Gateway</html>"), AgentError::Provider(_)));
+    }
+
+    #[test]
+    fn test_extraction_errors_classified() {
+        let json_error = serde_json::from_str::<serde_json::Value>("").unwrap_err();
+        let error = AgentError::from(ExtractionError::PromptError(PromptError::CompletionError(
+            CompletionError::JsonError(json_error),
+        )));
+        assert!(matches!(error, AgentError::Extraction(_)));
+
+        assert!(matches!(AgentError::from(ExtractionError::NoData), AgentError::Extraction(_)));
+
+        let json_error = serde_json::from_str::<crate::Sentiment>(r#""Happy""#).unwrap_err();
+        let error = AgentError::from(PromptError::ToolError(ToolSetError::ToolCallError(ToolError::JsonError(json_error))));
+        assert!(matches!(error, AgentError::Extraction(_)));
+    }
+
+    #[test]
+    fn test_error_actions() {
+        assert_eq!(AgentError::Timeout.action(), ErrorAction::Retry);
+        assert_eq!(AgentError::RateLimited(String::new()).action(), ErrorAction::Retry);
+        assert_eq!(AgentError::Extraction(String::new()).action(), ErrorAction::Fallback);
+        assert_eq!(AgentError::Rejected(String::new()).action(), ErrorAction::Abort);
+    }
+}
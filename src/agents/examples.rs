@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use crate::Sentiment;
+
+/// A labelled message shown to the classifier ahead of the real one
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FewShotExample {
+    pub text: String,
+    pub sentiment: Sentiment,
+    pub confidence: f32,
+    /// Why the label is what it is, for cases the model tends to get wrong
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl FewShotExample {
+    fn new(text: &str, sentiment: Sentiment, confidence: f32, note: Option<&str>) -> Self {
+        Self { text: text.to_string(), sentiment, confidence, note: note.map(str::to_string) }
+    }
+}
+
+/// The curated examples, aimed at what zero-shot classification misreads:
+/// sarcasm, mixed feelings and very short texts
+pub fn default_examples() -> Vec<FewShotExample> {
+    use Sentiment::*;
+    vec![
+        FewShotExample::new(
+            "Oh great, another meeting that could have been an email. Just what I needed.",
+            Negative,
+            0.85,
+            Some("sarcasm: the praise is ironic"),
+        ),
+        FewShotExample::new(
+            "Wow, I actually passed. Still can't believe it!",
+            Positive,
+            0.9,
+            Some("surprise at good news, not doubt"),
+        ),
+        FewShotExample::new(
+            "The new job pays better, but I miss my old team a lot.",
+            Negative,
+            0.55,
+            Some("mixed feelings: weigh which one the writer dwells on, with lower confidence"),
+        ),
+        FewShotExample::new(
+            "It was a sad goodbye, but I'm so proud of her for going.",
+            Positive,
+            0.6,
+            Some("mixed feelings ending on the stronger, positive one"),
+        ),
+        FewShotExample::new("ok", Neutral, 0.6, Some("short acknowledgement")),
+        FewShotExample::new("ugh", Negative, 0.7, Some("short, but clearly frustrated")),
+        FewShotExample::new("thx!!", Positive, 0.65, None),
+        FewShotExample::new("The train leaves at 7:40 from platform 3.", Neutral, 0.9, Some("plain information")),
+    ]
+}
+
+/// Reads a JSON list of examples; an empty list classifies zero-shot
+pub fn load_examples(path: &Path) -> Result<Vec<FewShotExample>> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let examples: Vec<FewShotExample> =
+        serde_json::from_str(&json).with_context(|| format!("failed to parse {}", path.display()))?;
+    if let Some(example) = examples.iter().find(|example| !(0.0..=1.0).contains(&example.confidence)) {
+        anyhow::bail!("{}: confidence of {:?} must be between 0 and 1", path.display(), example.text);
+    }
+    Ok(examples)
+}
+
+/// The part of the classifier's preamble that shows the examples, if any
+pub fn preamble_section(examples: &[FewShotExample]) -> Option<String> {
+    if examples.is_empty() {
+        return None;
+    }
+    let mut section = String::from("Examples of texts and the answers to submit for them:");
+    for example in examples {
+        section.push_str(&format!(
+            "\n\nText: {:?}\nAnswer: sentiment {:?}, confidence {}",
+            example.text, example.sentiment, example.confidence
+        ));
+        if let Some(note) = &example.note {
+            section.push_str(&format!(" ({})", note));
+        }
+    }
+    Some(section)
+}
+
+/// Short hash of `examples`, which goes into the cache key so results from
+/// other examples aren't reused
+pub fn fingerprint(examples: &[FewShotExample]) -> String {
+    let json = serde_json::to_string(examples).unwrap_or_default();
+    let digest = Sha256::digest(json.as_bytes());
+    digest[..4].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preamble_section() {
+        let examples = [
+            FewShotExample::new("ugh", Sentiment::Negative, 0.7, Some("short, but clearly frustrated")),
+            FewShotExample::new("ok", Sentiment::Neutral, 0.6, None),
+        ];
+        let section = preamble_section(&examples).unwrap();
+        assert!(section.contains("Text: \"ugh\"\nAnswer: sentiment Negative, confidence 0.7 (short, but clearly frustrated)"));
+        assert!(section.ends_with("Text: \"ok\"\nAnswer: sentiment Neutral, confidence 0.6"));
+        assert_eq!(preamble_section(&[]), None);
+
+        assert_eq!(fingerprint(&examples).len(), 8);
+        assert_ne!(fingerprint(&examples), fingerprint(&examples[..1]));
+    }
+
+    #[test]
+    fn test_examples_file_parsing() {
+        let path = std::env::temp_dir().join(format!("tce-examples-{}.json", std::process::id()));
+        std::fs::write(&path, r#"[{"text": "yeah right", "sentiment": "Negative", "confidence": 0.7, "note": "sarcasm"}]"#).unwrap();
+        let examples = load_examples(&path).unwrap();
+        assert_eq!(examples[0].note.as_deref(), Some("sarcasm"));
+
+        std::fs::write(&path, r#"[{"text": "fine", "sentiment": "Neutral", "confidence": 5}]"#).unwrap();
+        assert!(load_examples(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
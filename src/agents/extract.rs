@@ -0,0 +1,147 @@
+use rig::completion::{Prompt, ToolDefinition};
+use rig::providers::openai;
+use rig::tool::Tool;
+use serde_json::Value;
+use super::dry_run::pseudo_extraction;
+use super::repair::{self, DEFAULT_REPAIR_ATTEMPTS};
+use super::{AgentError, ErrorAction, RetryPolicy};
+
+/// Same instructions as rig's typed extractor, whose schema is fixed at compile time
+const PREAMBLE: &str = "\
+    You extract structured data from the provided text.\n\
+    You have a `submit` function whose parameters define the structure of the data to extract.\n\
+    Fill in every field you can find in the text and ALWAYS call the `submit` function, \
+    with empty or default values if the text does not say.";
+
+/// Extracts whatever structure a JSON schema describes, for schemas that are
+/// only known at run time
+pub struct SchemaExtractor {
+    client: openai::Client,
+    model: String,
+    schema: Value,
+    instructions: Option<String>,
+    retry: RetryPolicy,
+    repair_attempts: usize,
+    dry_run: bool,
+}
+
+impl SchemaExtractor {
+    /// Fails unless `schema` describes an object, which function parameters must be
+    pub fn new(client: openai::Client, model: &str, schema: Value) -> anyhow::Result<Self> {
+        if schema.get("type").and_then(Value::as_str) != Some("object") {
+            anyhow::bail!("the schema must describe an object (\"type\": \"object\")");
+        }
+        Ok(Self {
+            client,
+            model: model.to_string(),
+            schema,
+            instructions: None,
+            retry: RetryPolicy::default(),
+            repair_attempts: DEFAULT_REPAIR_ATTEMPTS,
+            dry_run: false,
+        })
+    }
+
+    /// Fill the schema with placeholders instead of calling the provider
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// What to extract, beyond what the schema says, added to the preamble
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Corrective follow-ups sent when an answer does not match the schema
+    pub fn with_repair_attempts(mut self, attempts: usize) -> Self {
+        self.repair_attempts = attempts;
+        self
+    }
+
+    /// The structure found in `text`, valid by the schema
+    pub async fn extract(&self, text: &str) -> Result<Value, AgentError> {
+        if self.dry_run {
+            return Ok(pseudo_extraction(&self.schema));
+        }
+
+        let preamble = match &self.instructions {
+            Some(instructions) => format!("{}\n\n{}", PREAMBLE, instructions),
+            None => PREAMBLE.to_string(),
+        };
+        let agent = self.client
+            .agent(&self.model)
+            .preamble(&preamble)
+            .tool(Submit { schema: self.schema.clone() })
+            .build();
+
+        let agent = &agent;
+        let mut prompt = text.to_string();
+        let mut problem = String::new();
+        for repairs in 0..=self.repair_attempts {
+            let input = prompt.as_str();
+            let result = self.retry
+                .run(|| async move {
+                    let answer = agent.prompt(input).await?;
+                    serde_json::from_str::<Value>(&answer)
+                        .map_err(|_| AgentError::Extraction("the answer was not a `submit` call".to_string()))
+                })
+                .await;
+
+            problem = match result.map(|value| repair::validate_value(&self.schema, &value).map(|()| value)) {
+                Ok(Ok(value)) => return Ok(value),
+                Ok(Err(problem)) => problem,
+                Err(e) if e.action() == ErrorAction::Fallback => e.to_string(),
+                Err(e) => return Err(e),
+            };
+            tracing::warn!(attempt = repairs + 1, %problem, "invalid extraction");
+            prompt = repair::correction(text, &problem);
+        }
+
+        Err(AgentError::Extraction(problem))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("submit failed")]
+struct SubmitError;
+
+/// The `submit` function, taking arguments shaped by the user's schema
+struct Submit {
+    schema: Value,
+}
+
+impl Tool for Submit {
+    const NAME: &'static str = "submit";
+    type Error = SubmitError;
+    type Args = Value;
+    type Output = Value;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Submit the structured data you extracted from the provided text.".to_string(),
+            parameters: self.schema.clone(),
+        }
+    }
+
+    async fn call(&self, data: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_schema_must_describe_an_object() {
+        let client = openai::Client::from_url("test-key", "https://api.example.com");
+        assert!(SchemaExtractor::new(client.clone(), "test-model", serde_json::json!({ "type": "string" })).is_err());
+
+        let schema = serde_json::json!({ "type": "object", "properties": { "city": { "type": "string" } } });
+        let extractor = SchemaExtractor::new(client, "test-model", schema).unwrap().with_dry_run(true);
+        assert_eq!(extractor.extract("I live in Lyon").await.unwrap(), serde_json::json!({ "city": "[dry run]" }));
+    }
+}
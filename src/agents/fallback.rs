@@ -0,0 +1,84 @@
+use std::str::FromStr;
+use crate::{Sentiment, SentimentClassification};
+use super::AgentError;
+use super::dry_run::pseudo_classify;
+
+/// What a classification becomes when the model gives no usable answer: the
+/// circuit is open, or every repair attempt came back invalid
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FallbackPolicy {
+    /// Neutral at 0.5 confidence
+    #[default]
+    Neutral,
+    /// Fail the classification with the error
+    Error,
+    /// The emotion of the user's previous message, or Neutral when there is none
+    Previous,
+    /// The local word-list classifier `--dry-run` uses
+    Local,
+}
+
+impl FallbackPolicy {
+    /// The classification to use instead of `error`, marked as a fallback, or
+    /// the error itself
+    pub fn resolve(
+        self,
+        text: &str,
+        previous: Option<&SentimentClassification>,
+        error: AgentError,
+    ) -> Result<SentimentClassification, AgentError> {
+        let classification = match (self, previous) {
+            (FallbackPolicy::Error, _) => return Err(error),
+            (FallbackPolicy::Previous, Some(previous)) => previous.clone(),
+            (FallbackPolicy::Local, _) => pseudo_classify(text),
+            (FallbackPolicy::Neutral | FallbackPolicy::Previous, _) => SentimentClassification {
+                sentiment: Sentiment::Neutral,
+                confidence: 0.5,
+                labels: Vec::new(),
+                is_fallback: false,
+            },
+        };
+        tracing::warn!(error = %error, policy = ?self, "classification fell back");
+        Ok(SentimentClassification { is_fallback: true, ..classification })
+    }
+}
+
+impl FromStr for FallbackPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "neutral" => Ok(FallbackPolicy::Neutral),
+            "error" => Ok(FallbackPolicy::Error),
+            "previous" => Ok(FallbackPolicy::Previous),
+            "local" => Ok(FallbackPolicy::Local),
+            other => anyhow::bail!("unknown fallback policy '{}', expected neutral, error, previous or local", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policies() {
+        let error = || AgentError::Extraction("no data extracted".to_string());
+        let previous = SentimentClassification { sentiment: Sentiment::Negative, confidence: 0.8, labels: Vec::new(), is_fallback: false };
+
+        let neutral = FallbackPolicy::Neutral.resolve("so happy", Some(&previous), error()).unwrap();
+        assert_eq!((neutral.sentiment, neutral.confidence, neutral.is_fallback), (Sentiment::Neutral, 0.5, true));
+
+        assert!(matches!(FallbackPolicy::Error.resolve("so happy", None, error()), Err(AgentError::Extraction(_))));
+
+        let reused = FallbackPolicy::Previous.resolve("so happy", Some(&previous), error()).unwrap();
+        assert_eq!((reused.sentiment, reused.is_fallback), (Sentiment::Negative, true));
+        assert_eq!(FallbackPolicy::Previous.resolve("so happy", None, error()).unwrap().sentiment, Sentiment::Neutral);
+
+        let local = FallbackPolicy::Local.resolve("so happy", None, error()).unwrap();
+        assert_eq!((local.sentiment, local.is_fallback), (Sentiment::Positive, true));
+
+        assert_eq!("Previous".parse::<FallbackPolicy>().unwrap(), FallbackPolicy::Previous);
+        assert!("skip".parse::<FallbackPolicy>().is_err());
+    }
+}
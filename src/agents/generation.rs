@@ -0,0 +1,243 @@
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use crate::Sentiment;
+use crate::models::{Message, MessageRole};
+use crate::strategy::ResponseStrategy;
+
+/// How long a reply should run, asked of the model in its preamble
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    Brief,
+    Normal,
+    Detailed,
+}
+
+impl Verbosity {
+    pub fn instruction(self) -> Option<&'static str> {
+        match self {
+            Verbosity::Brief => Some("Keep your reply short: two or three calm sentences at most."),
+            Verbosity::Normal => None,
+            Verbosity::Detailed => Some("Feel free to reply at some length, with energy and concrete detail."),
+        }
+    }
+
+    /// Sentences a reply is cut to when the model runs past its instruction
+    pub fn max_sentences(self) -> Option<usize> {
+        match self {
+            Verbosity::Brief => Some(3),
+            Verbosity::Normal => Some(8),
+            Verbosity::Detailed => None,
+        }
+    }
+}
+
+impl FromStr for Verbosity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "brief" => Ok(Verbosity::Brief),
+            "normal" => Ok(Verbosity::Normal),
+            "detailed" => Ok(Verbosity::Detailed),
+            other => bail!("unknown response length '{}', expected brief, normal or detailed", other),
+        }
+    }
+}
+
+/// The user's preferred reply length, shared by the agents of one chat and
+/// changeable while it runs; it wins over the generation profile
+#[derive(Debug, Clone, Default)]
+pub struct LengthPreference(Arc<Mutex<Option<Verbosity>>>);
+
+impl LengthPreference {
+    pub fn new(verbosity: Option<Verbosity>) -> Self {
+        Self(Arc::new(Mutex::new(verbosity)))
+    }
+
+    pub fn set(&self, verbosity: Option<Verbosity>) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = verbosity;
+    }
+
+    pub fn get(&self) -> Option<Verbosity> {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// `text` up to the end of sentence number `max`, with its layout kept
+pub fn trim_sentences(text: &str, max: usize) -> &str {
+    let mut sentences = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let full_width = matches!(c, '。' | '！' | '？');
+        if !(full_width || matches!(c, '.' | '!' | '?')) {
+            continue;
+        }
+        // "...", "?!" and closing quotes belong to the sentence they end
+        let mut end = i + c.len_utf8();
+        while let Some(&(j, next)) = chars.peek()
+            && matches!(next, '.' | '!' | '?' | '。' | '！' | '？' | '"' | '\'' | '”' | '’' | ')')
+        {
+            end = j + next.len_utf8();
+            chars.next();
+        }
+        let at_break = chars.peek().is_none_or(|&(_, next)| next.is_whitespace());
+        if full_width || at_break {
+            sentences += 1;
+            if sentences == max {
+                return text[..end].trim_end();
+            }
+        }
+    }
+    text.trim_end()
+}
+
+/// Sampling settings for a reply; fields left out fall back to the layer below
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenerationParams {
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u64>,
+    pub verbosity: Option<Verbosity>,
+}
+
+impl GenerationParams {
+    fn or(self, fallback: Self) -> Self {
+        Self {
+            temperature: self.temperature.or(fallback.temperature),
+            max_tokens: self.max_tokens.or(fallback.max_tokens),
+            verbosity: self.verbosity.or(fallback.verbosity),
+        }
+    }
+
+    fn validate(&self, layer: &str) -> Result<()> {
+        if let Some(temperature) = self.temperature
+            && !(0.0..=2.0).contains(&temperature)
+        {
+            bail!("{}: temperature {} is outside 0 to 2", layer, temperature);
+        }
+        if self.max_tokens == Some(0) {
+            bail!("{}: max_tokens must be at least 1", layer);
+        }
+        Ok(())
+    }
+}
+
+/// Generation settings by strategy and by the sentiment of the user's latest
+/// message, from `GENERATION_FILE`. Sentiment settings win over strategy
+/// ones, which win over `default`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenerationProfile {
+    #[serde(default)]
+    pub default: GenerationParams,
+    #[serde(default)]
+    pub strategies: HashMap<ResponseStrategy, GenerationParams>,
+    #[serde(default)]
+    pub sentiments: HashMap<Sentiment, GenerationParams>,
+}
+
+impl GenerationProfile {
+    pub fn resolve(&self, strategy: ResponseStrategy, sentiment: Option<Sentiment>) -> GenerationParams {
+        let by_strategy = self.strategies.get(&strategy).copied().unwrap_or_default();
+        let by_sentiment = sentiment.and_then(|s| self.sentiments.get(&s)).copied().unwrap_or_default();
+        by_sentiment.or(by_strategy).or(self.default)
+    }
+
+    /// Settings for a reply to `history`, whose latest user message carries the detected emotion
+    pub fn for_reply(&self, strategy: ResponseStrategy, history: &[Message]) -> GenerationParams {
+        let sentiment = history
+            .iter()
+            .rev()
+            .find(|m| matches!(m.role, MessageRole::User))
+            .and_then(|m| m.emotion.as_ref())
+            .map(|e| e.sentiment);
+        self.resolve(strategy, sentiment)
+    }
+
+    fn validate(&self) -> Result<()> {
+        self.default.validate("default")?;
+        for (strategy, params) in &self.strategies {
+            params.validate(&format!("{:?}", strategy))?;
+        }
+        for (sentiment, params) in &self.sentiments {
+            params.validate(&format!("{:?}", sentiment))?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a JSON generation profile
+pub fn load_profile(path: &Path) -> Result<GenerationProfile> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let profile: GenerationProfile = serde_json::from_str(&json).with_context(|| format!("failed to parse {}", path.display()))?;
+    profile.validate().with_context(|| format!("invalid generation settings in {}", path.display()))?;
+    Ok(profile)
+}
+
+/// Reads `STRATEGY_MODELS`, e.g. `empathetic=gpt-4o,neutral=gpt-4o-mini`
+pub fn parse_strategy_models(spec: &str) -> Result<HashMap<ResponseStrategy, String>> {
+    let mut models = HashMap::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let Some((name, model)) = entry.split_once('=') else {
+            bail!("expected strategy=model in STRATEGY_MODELS, got '{}'", entry);
+        };
+        let Some(strategy) = ResponseStrategy::from_name(name.trim()) else {
+            bail!("unknown strategy '{}' in STRATEGY_MODELS, expected empathetic, encouraging, cheerful or neutral", name.trim());
+        };
+        let model = model.trim();
+        if model.is_empty() {
+            bail!("no model for {} in STRATEGY_MODELS", name.trim());
+        }
+        models.insert(strategy, model.to_string());
+    }
+    Ok(models)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentiment_wins_over_strategy_and_default() {
+        let profile: GenerationProfile = serde_json::from_str(r#"{
+            "default": {"temperature": 0.7, "max_tokens": 400},
+            "strategies": {"Empathetic": {"temperature": 0.4, "verbosity": "brief"}},
+            "sentiments": {"Negative": {"max_tokens": 150}}
+        }"#).unwrap();
+
+        let params = profile.resolve(ResponseStrategy::Empathetic, Some(Sentiment::Negative));
+        assert_eq!(params, GenerationParams { temperature: Some(0.4), max_tokens: Some(150), verbosity: Some(Verbosity::Brief) });
+        let params = profile.resolve(ResponseStrategy::Cheerful, None);
+        assert_eq!(params, GenerationParams { temperature: Some(0.7), max_tokens: Some(400), verbosity: None });
+
+        assert!(profile.validate().is_ok());
+        let hot: GenerationProfile = serde_json::from_str(r#"{"strategies": {"Cheerful": {"temperature": 3.0}}}"#).unwrap();
+        assert!(hot.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_strategy_models() {
+        let models = parse_strategy_models("Empathetic=gpt-4o, neutral = gpt-4o-mini").unwrap();
+        assert_eq!(models.get(&ResponseStrategy::Empathetic).map(String::as_str), Some("gpt-4o"));
+        assert_eq!(models.get(&ResponseStrategy::Neutral).map(String::as_str), Some("gpt-4o-mini"));
+        assert_eq!(models.len(), 2);
+        assert!(parse_strategy_models("grumpy=gpt-4o").is_err());
+        assert!(parse_strategy_models("empathetic").is_err());
+    }
+
+    #[test]
+    fn test_trim_sentences() {
+        let reply = "That sounds hard. I'm sorry... Do you want to talk about it?! Maybe a walk helps.\n\nOr rest.";
+        assert_eq!(trim_sentences(reply, 3), "That sounds hard. I'm sorry... Do you want to talk about it?!");
+        assert_eq!(trim_sentences(reply, 10), reply);
+        assert_eq!(trim_sentences("It costs 3.50 now. Fine.", 1), "It costs 3.50 now.");
+        assert_eq!(trim_sentences("我明白。你不是一个人。慢慢来。", 2), "我明白。你不是一个人。");
+        assert_eq!("Brief".parse::<Verbosity>().unwrap(), Verbosity::Brief);
+        assert!("terse".parse::<Verbosity>().is_err());
+    }
+}
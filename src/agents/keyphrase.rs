@@ -0,0 +1,80 @@
+use rig::providers::openai;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use super::dry_run::pseudo_keyphrases;
+use super::{AgentError, RetryPolicy};
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct Keyphrases {
+    /// Most important first
+    pub phrases: Vec<String>,
+}
+
+pub struct KeyphraseAgent {
+    client: openai::Client,
+    model: String,
+    retry: RetryPolicy,
+    dry_run: bool,
+}
+
+impl KeyphraseAgent {
+    pub fn new(client: openai::Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+            retry: RetryPolicy::default(),
+            dry_run: false,
+        }
+    }
+
+    /// Count frequent words locally instead of calling the provider
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Up to `max` short phrases naming what `text` is about
+    pub async fn extract(&self, text: &str, max: usize) -> Result<Vec<String>, AgentError> {
+        if text.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        if self.dry_run {
+            return Ok(pseudo_keyphrases(text, max));
+        }
+
+        let extractor = self.client
+            .extractor::<Keyphrases>(&self.model)
+            .preamble(&format!(
+                "List the {} key phrases that best describe what the user talks about in these \
+                 messages: people, places, activities, and concerns. Use short noun phrases \
+                 (1-3 words) in the messages' language, most important first. \
+                 Skip greetings and filler words.",
+                max
+            ))
+            .build();
+
+        let extractor = &extractor;
+        let mut phrases = self.retry
+            .run(|| async move { Ok(extractor.extract(text).await?) })
+            .await?
+            .phrases;
+        phrases.truncate(max);
+        Ok(phrases)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dry_run_counts_words() {
+        let client = openai::Client::from_url("test-key", "https://api.example.com");
+        let agent = KeyphraseAgent::new(client, "test-model").with_dry_run(true);
+
+        let phrases = agent.extract("Work was long. My manager moved the work deadline again.", 2).await.unwrap();
+        assert_eq!(phrases[0], "work");
+        assert_eq!(phrases.len(), 2);
+        assert!(agent.extract("  ", 3).await.unwrap().is_empty());
+    }
+}
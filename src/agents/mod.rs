@@ -1,7 +1,56 @@
 //! Agent implementations for emotion detection and chat
 
 pub mod emotion;
+pub mod error;
+pub mod retry;
+pub mod breaker;
+pub mod cache;
+pub mod assessment;
+pub mod candidates;
+pub mod coalesce;
+pub mod consistency;
+pub mod context;
 pub mod chat;
+pub mod debug;
+pub mod degradation;
+pub mod dedup;
+pub mod embedding;
+pub mod entities;
+pub mod examples;
+pub mod extract;
+pub mod fallback;
+pub mod generation;
+pub mod dry_run;
+pub mod keyphrase;
+pub mod repair;
+pub mod simulator;
+pub mod stream;
+pub mod summary;
+pub mod taxonomy;
+pub mod topics;
 
+pub use assessment::Assessment;
+pub use breaker::{CircuitBreaker, CircuitState};
+pub use cache::ClassificationCache;
+pub use consistency::SelfConsistency;
+pub use context::ContextWindow;
+pub use debug::PromptDebug;
+pub use degradation::Degradation;
+pub use dedup::HistoryDedup;
+pub use embedding::EmbeddingAgent;
+pub use entities::{Entity, EntityExtractor, EntityKind};
+pub use examples::FewShotExample;
+pub use extract::SchemaExtractor;
+pub use fallback::FallbackPolicy;
+pub use generation::{GenerationParams, GenerationProfile, LengthPreference, Verbosity};
 pub use emotion::EmotionDetector;
-pub use chat::ChatAgent;
+pub use error::{AgentError, ErrorAction};
+pub use keyphrase::KeyphraseAgent;
+pub use repair::RepairStats;
+pub use retry::RetryPolicy;
+pub use simulator::{EmotionScript, ScriptStep, UserSimulator};
+pub use chat::{ChatAgent, TokenUsage};
+pub use stream::{Endpoint, ReplyChunk, ReplyStream};
+pub use summary::SummaryAgent;
+pub use taxonomy::{LabelScore, Taxonomy, TaxonomyClassification, TaxonomyClassifier};
+pub use topics::{BlockedTopic, TopicGuard};
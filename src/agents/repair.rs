@@ -0,0 +1,174 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Corrective follow-ups sent after an invalid extraction before falling back
+pub const DEFAULT_REPAIR_ATTEMPTS: usize = 2;
+
+/// What is wrong with `value` by the JSON schema of its type, such as a
+/// confidence outside 0 to 1
+pub fn validate<T: JsonSchema + Serialize>(value: &T) -> Result<(), String> {
+    let schema = serde_json::to_value(schemars::schema_for!(T)).map_err(|e| e.to_string())?;
+    let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    check(&schema, &schema, &value, "")
+}
+
+/// What is wrong with `value` by a JSON schema given at run time
+pub fn validate_value(schema: &Value, value: &Value) -> Result<(), String> {
+    check(schema, schema, value, "")
+}
+
+/// The message re-asking for `input` after an invalid answer
+pub fn correction(input: &str, problem: &str) -> String {
+    format!(
+        "{}\n\nYour previous answer was invalid: {}. Call the `submit` function again with every field \
+         filled in and every value allowed by its schema.",
+        input, problem
+    )
+}
+
+fn check(root: &Value, schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    let at = if path.is_empty() { "the answer" } else { path };
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.trim_start_matches("#/definitions/");
+        let definition = root.pointer(&format!("/definitions/{}", name)).ok_or_else(|| format!("unknown schema {}", reference))?;
+        return check(root, definition, value, path);
+    }
+    for key in ["allOf", "anyOf", "oneOf"] {
+        let Some(options) = schema.get(key).and_then(Value::as_array) else { continue };
+        let mut results = options.iter().map(|option| check(root, option, value, path));
+        let valid = if key == "allOf" { results.all(|r| r.is_ok()) } else { results.any(|r| r.is_ok()) };
+        if !valid {
+            return Err(format!("{} does not match any allowed form", at));
+        }
+    }
+
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::String(kind) => vec![kind.as_str()],
+            Value::Array(kinds) => kinds.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.iter().any(|kind| is_type(value, kind)) {
+            return Err(format!("{} must be of type {}", at, types.join(" or ")));
+        }
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array).filter(|allowed| !allowed.contains(value)) {
+        let names: Vec<String> = allowed.iter().map(Value::to_string).collect();
+        return Err(format!("{} must be one of {}", at, names.join(", ")));
+    }
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64).filter(|minimum| number < *minimum) {
+            return Err(format!("{} is {}, below the minimum {}", at, number, minimum));
+        }
+        if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64).filter(|maximum| number > *maximum) {
+            return Err(format!("{} is {}, above the maximum {}", at, number, maximum));
+        }
+    }
+
+    if let Value::Object(fields) = value {
+        for required in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+            if !fields.contains_key(required) {
+                return Err(format!("{} is missing", join(path, required)));
+            }
+        }
+        if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+            for (name, field) in fields {
+                if let Some(property) = properties.get(name) {
+                    check(root, property, field, &join(path, name))?;
+                }
+            }
+        }
+    }
+    if let (Value::Array(elements), Some(items)) = (value, schema.get("items")) {
+        for (i, element) in elements.iter().enumerate() {
+            check(root, items, element, &format!("{}[{}]", path, i))?;
+        }
+    }
+    Ok(())
+}
+
+fn is_type(value: &Value, kind: &str) -> bool {
+    match kind {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    }
+}
+
+fn join(path: &str, field: &str) -> String {
+    if path.is_empty() { field.to_string() } else { format!("{}.{}", path, field) }
+}
+
+/// How often extraction answers needed repairing, and whether that worked
+#[derive(Debug, Default)]
+pub struct RepairStats {
+    extractions: AtomicU64,
+    repaired: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl RepairStats {
+    /// Records one finished extraction that took `repairs` corrective follow-ups
+    pub fn record(&self, repairs: usize, valid: bool) {
+        self.extractions.fetch_add(1, Ordering::Relaxed);
+        if !valid {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+        } else if repairs > 0 {
+            self.repaired.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Extractions that reached the provider
+    pub fn extractions(&self) -> u64 {
+        self.extractions.load(Ordering::Relaxed)
+    }
+
+    /// Extractions that were valid only after a corrective follow-up
+    pub fn repaired(&self) -> u64 {
+        self.repaired.load(Ordering::Relaxed)
+    }
+
+    /// Extractions still invalid after every follow-up, which fell back
+    pub fn failed(&self) -> u64 {
+        self.failed.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Sentiment, SentimentClassification};
+
+    #[test]
+    fn test_classification_validated_against_schema() {
+        let valid = SentimentClassification { sentiment: Sentiment::Positive, confidence: 0.8, labels: Vec::new(), is_fallback: false };
+        assert_eq!(validate(&valid), Ok(()));
+
+        let invalid = SentimentClassification { confidence: 7.0, ..valid.clone() };
+        assert_eq!(validate(&invalid), Err("confidence is 7, above the maximum 1".to_string()));
+        let invalid = SentimentClassification { confidence: -0.5, ..valid };
+        assert_eq!(validate(&invalid), Err("confidence is -0.5, below the minimum 0".to_string()));
+
+        let root = serde_json::to_value(schemars::schema_for!(SentimentClassification)).unwrap();
+        let missing = serde_json::json!({ "confidence": 0.5 });
+        assert_eq!(check(&root, &root, &missing, ""), Err("sentiment is missing".to_string()));
+        let unknown = serde_json::json!({ "sentiment": "Happy", "confidence": 0.5 });
+        assert!(check(&root, &root, &unknown, "").unwrap_err().starts_with("sentiment must be one of"));
+    }
+
+    #[test]
+    fn test_repair_stats() {
+        let stats = RepairStats::default();
+        stats.record(0, true);
+        stats.record(1, true);
+        stats.record(2, false);
+        assert_eq!((stats.extractions(), stats.repaired(), stats.failed()), (3, 1, 1));
+    }
+}
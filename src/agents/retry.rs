@@ -0,0 +1,96 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use super::degradation::Degradation;
+use super::error::{AgentError, ErrorAction};
+
+/// Retries model calls whose error is classified as transient, with
+/// exponential backoff between attempts
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    /// Counts each retry when set
+    pub degradation: Option<Arc<Degradation>>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            degradation: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub async fn run<T, F, Fut>(&self, mut call: F) -> Result<T, AgentError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, AgentError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match call().await {
+                Err(e) if e.action() == ErrorAction::Retry && attempt < self.max_attempts => {
+                    let delay = self.base_delay * 2u32.pow(attempt - 1);
+                    tracing::warn!(attempt, ?delay, error = %e, "retrying provider call");
+                    if let Some(degradation) = &self.degradation {
+                        degradation.record_retry();
+                    }
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    const POLICY: RetryPolicy = RetryPolicy { max_attempts: 3, base_delay: Duration::ZERO, degradation: None };
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let calls = Cell::new(0);
+        let degradation = Arc::new(Degradation::default());
+        let policy = RetryPolicy { degradation: Some(degradation.clone()), ..POLICY };
+        let result = policy.run(|| async {
+            calls.set(calls.get() + 1);
+            if calls.get() < 3 { Err(AgentError::Timeout) } else { Ok("done") }
+        }).await;
+
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(calls.get(), 3);
+        assert_eq!(degradation.retries(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let calls = Cell::new(0);
+        let result: Result<(), _> = POLICY.run(|| async {
+            calls.set(calls.get() + 1);
+            Err(AgentError::Connection("refused".to_string()))
+        }).await;
+
+        assert!(matches!(result, Err(AgentError::Connection(_))));
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_other_errors() {
+        let calls = Cell::new(0);
+        let result: Result<(), _> = POLICY.run(|| async {
+            calls.set(calls.get() + 1);
+            Err(AgentError::Rejected("bad key".to_string()))
+        }).await;
+
+        assert!(matches!(result, Err(AgentError::Rejected(_))));
+        assert_eq!(calls.get(), 1);
+    }
+}
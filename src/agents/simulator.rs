@@ -0,0 +1,149 @@
+use rig::completion::Prompt;
+use rig::providers::openai;
+use serde::Deserialize;
+use crate::Sentiment;
+use crate::models::{Message, MessageRole};
+use super::dry_run::pseudo_user_message;
+use super::{AgentError, RetryPolicy};
+
+/// One stretch of a script: how the simulated user feels for some turns
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptStep {
+    pub sentiment: Sentiment,
+    pub turns: usize,
+    /// How the user comes across, e.g. "angry, short and blunt"; a plain
+    /// description of the sentiment when omitted
+    #[serde(default)]
+    pub mood: Option<String>,
+}
+
+impl ScriptStep {
+    fn describe(&self) -> &str {
+        match (&self.mood, self.sentiment) {
+            (Some(mood), _) => mood,
+            (None, Sentiment::Negative) => "upset",
+            (None, Sentiment::Neutral) => "calm and matter-of-fact",
+            (None, Sentiment::Positive) => "pleased",
+        }
+    }
+}
+
+/// Who the simulated user is and how their mood moves over a conversation
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmotionScript {
+    pub persona: String,
+    pub steps: Vec<ScriptStep>,
+}
+
+impl EmotionScript {
+    /// Starts angry and gradually calms down
+    pub fn calming() -> Self {
+        let step = |sentiment, turns, mood: &str| ScriptStep { sentiment, turns, mood: Some(mood.to_string()) };
+        Self {
+            persona: "someone whose train home was cancelled for the third time this week".to_string(),
+            steps: vec![
+                step(Sentiment::Negative, 3, "angry, short and blunt"),
+                step(Sentiment::Negative, 2, "still annoyed, but starting to listen"),
+                step(Sentiment::Neutral, 2, "calmer, asking practical questions"),
+                step(Sentiment::Positive, 1, "relieved and grateful"),
+            ],
+        }
+    }
+
+    /// The step each turn of the script follows, in order
+    pub fn turns(&self) -> Vec<&ScriptStep> {
+        self.steps.iter().flat_map(|step| std::iter::repeat_n(step, step.turns)).collect()
+    }
+}
+
+/// Plays the user of a conversation, following an emotion script, so the
+/// pipeline can be driven without anyone typing
+pub struct UserSimulator {
+    client: openai::Client,
+    model: String,
+    retry: RetryPolicy,
+    dry_run: bool,
+}
+
+impl UserSimulator {
+    pub fn new(client: openai::Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+            retry: RetryPolicy::default(),
+            dry_run: false,
+        }
+    }
+
+    /// Picks canned messages that the dry-run classifier reads as scripted
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// The user's next message, feeling as `step` says, after `history`
+    pub async fn next_message(&self, script: &EmotionScript, step: &ScriptStep, history: &[Message]) -> Result<String, AgentError> {
+        if self.dry_run {
+            let turn = history.iter().filter(|m| m.role == MessageRole::User).count();
+            return Ok(pseudo_user_message(step.sentiment, turn).to_string());
+        }
+        let agent = self.client
+            .agent(&self.model)
+            .preamble(&format!(
+                "You are role-playing the user in a chat with an assistant, for testing. \
+                 You are {}. Right now you feel {}. Write the user's next message only: \
+                 one to three sentences, in plain everyday language, without quotes or \
+                 stage directions.",
+                script.persona,
+                step.describe()
+            ))
+            .build();
+
+        let transcript = transcript(history);
+        let (agent, transcript) = (&agent, transcript.as_str());
+        let message = self.retry
+            .run(|| async move { Ok(agent.prompt(transcript).await?) })
+            .await?;
+        Ok(message.trim().trim_matches('"').to_string())
+    }
+}
+
+/// The conversation so far from the user's side, or a cue to open it
+fn transcript(history: &[Message]) -> String {
+    if history.is_empty() {
+        return "(The conversation has not started. Write your first message.)".to_string();
+    }
+    history
+        .iter()
+        .map(|m| match m.role {
+            MessageRole::User => format!("You: {}", m.content),
+            _ => format!("Assistant: {}", m.content),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::dry_run::pseudo_classify;
+
+    #[test]
+    fn test_calming_script() {
+        let script = EmotionScript::calming();
+        let sentiments: Vec<Sentiment> = script.turns().iter().map(|step| step.sentiment).collect();
+        assert_eq!(sentiments.len(), 8);
+        assert_eq!(sentiments.first(), Some(&Sentiment::Negative));
+        assert_eq!(sentiments.last(), Some(&Sentiment::Positive));
+
+        // Dry-run messages read as the sentiment they were written for
+        for (turn, step) in script.turns().into_iter().enumerate() {
+            assert_eq!(pseudo_classify(pseudo_user_message(step.sentiment, turn)).sentiment, step.sentiment);
+        }
+
+        let custom: EmotionScript = serde_json::from_str(r#"{"persona": "a student", "steps": [{"sentiment": "Neutral", "turns": 2}]}"#).unwrap();
+        assert_eq!(custom.turns()[0].describe(), "calm and matter-of-fact");
+    }
+}
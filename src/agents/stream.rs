@@ -0,0 +1,207 @@
+//! Streamed replies read straight from an OpenAI-compatible endpoint, since
+//! rig's stream drops the token usage the provider sends in its last chunk
+
+use futures::{Stream, StreamExt};
+use rig::completion::CompletionError;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use crate::models::{Message, MessageRole, ReplyScore};
+use super::chat::TokenUsage;
+use super::generation::GenerationParams;
+use super::AgentError;
+
+/// A piece of a streamed reply
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplyChunk {
+    Text(String),
+    /// What the reply cost, after its text, when the provider reports it
+    Usage(TokenUsage),
+    /// The scoring pass's rating, when the reply was generated whole and scored
+    Score(ReplyScore),
+}
+
+pub type ReplyStream = Pin<Box<dyn Stream<Item = Result<ReplyChunk, AgentError>> + Send>>;
+
+/// A stream of just `text`
+pub fn once(text: String) -> ReplyStream {
+    Box::pin(futures::stream::once(async move { Ok(ReplyChunk::Text(text)) }))
+}
+
+/// Where streamed replies are requested from
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    http: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+#[derive(Deserialize)]
+struct Chunk {
+    #[serde(default)]
+    choices: Vec<Choice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    delta: Delta,
+}
+
+#[derive(Deserialize)]
+struct Delta {
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    prompt_tokens: usize,
+    total_tokens: usize,
+}
+
+impl Endpoint {
+    pub fn new(api_key: &str, base_url: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_key: api_key.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Starts a reply to `user_input` after `history`, asking for its usage at the end
+    pub async fn stream(
+        &self,
+        model: &str,
+        system: &str,
+        history: &[Message],
+        user_input: &str,
+        params: GenerationParams,
+    ) -> Result<ReplyStream, AgentError> {
+        let mut messages = vec![json!({ "role": "system", "content": system })];
+        messages.extend(history.iter().map(|message| {
+            let role = match message.role {
+                MessageRole::User => "user",
+                MessageRole::Assistant => "assistant",
+            };
+            json!({ "role": role, "content": message.content })
+        }));
+        messages.push(json!({ "role": "user", "content": user_input }));
+
+        let mut body = json!({
+            "model": model,
+            "messages": messages,
+            "stream": true,
+            "stream_options": { "include_usage": true },
+        });
+        if let Some(temperature) = params.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = params.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+
+        let response = self.http
+            .post(format!("{}/chat/completions", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| AgentError::from(CompletionError::HttpError(e)))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(CompletionError::ProviderError(format!("{}: {}", status, body)).into());
+        }
+
+        let chunks = futures::stream::unfold(
+            (response.bytes_stream(), Lines::default(), VecDeque::new()),
+            |(mut bytes, mut lines, mut ready)| async move {
+                loop {
+                    if let Some(chunk) = ready.pop_front() {
+                        return Some((chunk, (bytes, lines, ready)));
+                    }
+                    match bytes.next().await {
+                        Some(Ok(data)) => ready.extend(lines.feed(&data)),
+                        Some(Err(e)) => {
+                            // The body is unusable after a transport error
+                            let error = AgentError::from(CompletionError::HttpError(e));
+                            return Some((Err(error), (bytes, lines, ready)));
+                        }
+                        None => return None,
+                    }
+                }
+            },
+        );
+        Ok(Box::pin(chunks))
+    }
+}
+
+/// Splits a server-sent event body into chunks, holding back an incomplete last line
+#[derive(Debug, Default)]
+struct Lines {
+    partial: Vec<u8>,
+}
+
+impl Lines {
+    fn feed(&mut self, data: &[u8]) -> Vec<Result<ReplyChunk, AgentError>> {
+        self.partial.extend_from_slice(data);
+        let Some(end) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Vec::new();
+        };
+        let complete: Vec<u8> = self.partial.drain(..=end).collect();
+        String::from_utf8_lossy(&complete).lines().flat_map(parse_line).collect()
+    }
+}
+
+fn parse_line(line: &str) -> Vec<Result<ReplyChunk, AgentError>> {
+    let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+        return Vec::new();
+    };
+    if data.is_empty() || data == "[DONE]" {
+        return Vec::new();
+    }
+    let value: Value = match serde_json::from_str(data) {
+        Ok(value) => value,
+        Err(_) => return vec![Err(AgentError::Provider(format!("malformed stream chunk: {}", data)))],
+    };
+    // Some providers send errors in the stream itself
+    if let Some(error) = value.get("error") {
+        return vec![Err(CompletionError::ProviderError(error.to_string()).into())];
+    }
+    let chunk: Chunk = match serde_json::from_value(value) {
+        Ok(chunk) => chunk,
+        Err(e) => return vec![Err(AgentError::Provider(format!("malformed stream chunk: {}", e)))],
+    };
+    let text = chunk.choices.into_iter().filter_map(|choice| choice.delta.content).filter(|text| !text.is_empty());
+    let usage = chunk.usage.map(|usage| TokenUsage {
+        prompt: usage.prompt_tokens,
+        completion: usage.total_tokens.saturating_sub(usage.prompt_tokens),
+    });
+    text.map(ReplyChunk::Text).chain(usage.map(ReplyChunk::Usage)).map(Ok).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_carry_text_and_usage() {
+        let mut lines = Lines::default();
+        let first = "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\ndata: {\"choices\":[{\"del";
+        let chunks: Vec<ReplyChunk> = lines.feed(first.as_bytes()).into_iter().map(Result::unwrap).collect();
+        assert_eq!(chunks, vec![ReplyChunk::Text("Hel".to_string())]);
+
+        let rest = "ta\":{\"content\":\"lo\"}}]}\n\ndata: {\"choices\":[],\"usage\":{\"prompt_tokens\":10,\"total_tokens\":15}}\n\ndata: [DONE]\n\n";
+        let chunks: Vec<ReplyChunk> = lines.feed(rest.as_bytes()).into_iter().map(Result::unwrap).collect();
+        assert_eq!(chunks, vec![ReplyChunk::Text("lo".to_string()), ReplyChunk::Usage(TokenUsage { prompt: 10, completion: 5 })]);
+
+        // A character split across reads is decoded once the line is complete
+        let emoji = "data: {\"choices\":[{\"delta\":{\"content\":\"🙂\"}}]}\n".as_bytes();
+        let split = emoji.iter().position(|&b| b >= 0x80).unwrap() + 1;
+        assert!(lines.feed(&emoji[..split]).is_empty());
+        assert_eq!(lines.feed(&emoji[split..]).into_iter().map(Result::unwrap).collect::<Vec<_>>(), vec![ReplyChunk::Text("🙂".to_string())]);
+
+        assert!(matches!(parse_line("data: {\"error\": {\"message\": \"overloaded\"}}")[..], [Err(_)]));
+    }
+}
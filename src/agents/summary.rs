@@ -0,0 +1,119 @@
+use rig::completion::Prompt;
+use rig::providers::openai;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use super::{AgentError, RetryPolicy};
+
+/// Messages at the start of a session its title is written from
+pub const TITLE_MESSAGES: usize = 6;
+
+/// Characters a title is cut to
+const MAX_TITLE_CHARS: usize = 60;
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct ConversationSummary {
+    pub summary: String,
+    pub topics: Vec<String>,
+}
+
+pub struct SummaryAgent {
+    client: openai::Client,
+    model: String,
+    retry: RetryPolicy,
+}
+
+impl SummaryAgent {
+    pub fn new(client: openai::Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub async fn summarize(&self, transcript: &str) -> Result<ConversationSummary, AgentError> {
+        let extractor = self.client
+            .extractor::<ConversationSummary>(&self.model)
+            .preamble(
+                "You are reviewing a user's emotional chat history, one line per message. \
+                 Write a short, warm summary (3-5 sentences) of how the user's mood developed \
+                 and what seemed to drive it. Also list up to 5 dominant topics as short phrases.",
+            )
+            .build();
+
+        let extractor = &extractor;
+        self.retry
+            .run(|| async move { Ok(extractor.extract(transcript).await?) })
+            .await
+    }
+
+    /// A few words on what a conversation is about, from the user's first messages
+    pub async fn title(&self, opening: &str) -> Result<String, AgentError> {
+        let agent = self.client
+            .agent(&self.model)
+            .preamble(
+                "Write a title of three to six words for the conversation these messages \
+                 open, naming what the user wants to talk about, in the messages' own \
+                 language. Reply with the title only, without quotes.",
+            )
+            .max_tokens(20)
+            .build();
+
+        let agent = &agent;
+        let title = self.retry
+            .run(|| async move { Ok(agent.prompt(opening).await?) })
+            .await?;
+        Ok(clean_title(&title))
+    }
+
+    /// Shortens a document the user attached, keeping what matters emotionally
+    pub async fn condense(&self, document: &str, max_words: usize) -> Result<String, AgentError> {
+        let agent = self.client
+            .agent(&self.model)
+            .preamble(&format!(
+                "Summarize the document the user shares in at most {} words. Keep the facts, \
+                 events, and feelings it describes, in the document's own language. \
+                 Reply with the summary only.",
+                max_words
+            ))
+            .build();
+
+        let agent = &agent;
+        self.retry
+            .run(|| async move { Ok(agent.prompt(document).await?) })
+            .await
+    }
+}
+
+/// The model's title without the quotes, label and full stop it sometimes adds
+fn clean_title(title: &str) -> String {
+    let title = title.lines().find(|line| !line.trim().is_empty()).unwrap_or_default().trim();
+    let title = title.strip_prefix("Title:").unwrap_or(title);
+    let title = title.trim().trim_matches(|c| matches!(c, '"' | '\'' | '“' | '”' | '*')).trim_end_matches('.').trim();
+    if title.chars().count() <= MAX_TITLE_CHARS {
+        return title.to_string();
+    }
+    format!("{}…", title.chars().take(MAX_TITLE_CHARS).collect::<String>().trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_agent_new() {
+        let api_key = "test-key";
+        let base_url = "https://api.example.com";
+        let client = openai::Client::from_url(api_key, base_url);
+        let agent = SummaryAgent::new(client, "test-model");
+
+        assert_eq!(agent.model, "test-model");
+    }
+
+    #[test]
+    fn test_clean_title() {
+        assert_eq!(clean_title("\"Exam nerves and lost sleep.\"\n"), "Exam nerves and lost sleep");
+        assert_eq!(clean_title("Title: **Moving to Leeds**"), "Moving to Leeds");
+        assert_eq!(clean_title(&"word ".repeat(20)).chars().count(), MAX_TITLE_CHARS);
+    }
+}
@@ -0,0 +1,261 @@
+use anyhow::{Context, Result};
+use futures::stream::{self, StreamExt};
+use rig::providers::openai;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::path::Path;
+use utoipa::ToSchema;
+use super::dry_run::pseudo_label;
+use super::{AgentError, SchemaExtractor};
+
+/// Texts of a batch classified at once
+const BATCH_CONCURRENCY: usize = 4;
+
+/// A label set the operator defines in place of the built-in sentiments,
+/// such as support-ticket categories or toxicity levels
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Taxonomy {
+    pub name: String,
+    pub labels: Vec<TaxonomyLabel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaxonomyLabel {
+    pub name: String,
+    /// What the classifier is told the label covers
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl Taxonomy {
+    /// An unnamed taxonomy of labels given at run time, e.g. `urgent,spam,other`
+    pub fn from_names(names: &[String]) -> Result<Self> {
+        let labels = names
+            .iter()
+            .map(|name| TaxonomyLabel { name: name.trim().to_string(), description: None })
+            .filter(|label| !label.name.is_empty())
+            .collect();
+        let taxonomy = Self { name: String::new(), labels };
+        taxonomy.validate()?;
+        Ok(taxonomy)
+    }
+
+    /// Fails on fewer than two labels, or a label named twice
+    pub fn validate(&self) -> Result<()> {
+        let title = if self.name.is_empty() { "the label list".to_string() } else { format!("taxonomy '{}'", self.name) };
+        if self.labels.len() < 2 {
+            anyhow::bail!("{} needs at least two labels", title);
+        }
+        let mut seen = HashSet::new();
+        for label in &self.labels {
+            if label.name.trim().is_empty() {
+                anyhow::bail!("{} has a label without a name", title);
+            }
+            if !seen.insert(label.name.as_str()) {
+                anyhow::bail!("{} names '{}' twice", title, label.name);
+            }
+        }
+        Ok(())
+    }
+
+    /// The answer the model submits: one of the labels and a confidence, or
+    /// a score for every label when `scored`
+    fn schema(&self, scored: bool) -> Value {
+        let names: Vec<&str> = self.labels.iter().map(|l| l.name.as_str()).collect();
+        if scored {
+            let score = json!({ "type": "number", "minimum": 0.0, "maximum": 1.0 });
+            let properties: serde_json::Map<String, Value> = names.iter().map(|name| (name.to_string(), score.clone())).collect();
+            return json!({
+                "type": "object",
+                "required": ["scores"],
+                "properties": {
+                    "scores": { "type": "object", "required": names, "properties": properties }
+                }
+            });
+        }
+        json!({
+            "type": "object",
+            "required": ["label", "confidence"],
+            "properties": {
+                "label": { "type": "string", "enum": names },
+                "confidence": { "type": "number", "minimum": 0.0, "maximum": 1.0 }
+            }
+        })
+    }
+
+    fn instructions(&self, scored: bool) -> String {
+        let taxonomy = if self.name.is_empty() { String::new() } else { format!(" of the \"{}\" taxonomy", self.name) };
+        let mut instructions = if scored {
+            format!("Score how well the text fits each label{}, from 0 (not at all) to 1 (certainly). The labels:", taxonomy)
+        } else {
+            format!("Classify the text into exactly one label{} and give your confidence (0-1). The labels:", taxonomy)
+        };
+        for label in &self.labels {
+            match &label.description {
+                Some(description) => instructions.push_str(&format!("\n- {}: {}", label.name, description)),
+                None => instructions.push_str(&format!("\n- {}", label.name)),
+            }
+        }
+        instructions
+    }
+}
+
+/// Reads a taxonomy from a JSON object with a name and a list of labels
+pub fn load_taxonomy(path: &Path) -> Result<Taxonomy> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let taxonomy: Taxonomy = serde_json::from_str(&json).with_context(|| format!("failed to parse {}", path.display()))?;
+    taxonomy.validate().with_context(|| path.display().to_string())?;
+    Ok(taxonomy)
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct TaxonomyClassification {
+    pub label: String,
+    pub confidence: f32,
+    /// How well the text fits every label, in the taxonomy's order, when scored
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scores: Vec<LabelScore>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct LabelScore {
+    pub label: String,
+    pub score: f32,
+}
+
+impl TaxonomyClassification {
+    /// The best of `scores`, the first on a tie
+    fn from_scores(scores: Vec<LabelScore>) -> Option<Self> {
+        let best = scores.iter().fold(None, |best: Option<&LabelScore>, s| match best {
+            Some(b) if b.score >= s.score => Some(b),
+            _ => Some(s),
+        })?;
+        Some(Self { label: best.label.clone(), confidence: best.score, scores: scores.clone() })
+    }
+}
+
+/// Classifies texts against a [`Taxonomy`] instead of the sentiments
+pub struct TaxonomyClassifier {
+    taxonomy: Taxonomy,
+    extractor: SchemaExtractor,
+    scored: bool,
+    dry_run: bool,
+}
+
+impl TaxonomyClassifier {
+    pub fn new(client: openai::Client, model: &str, taxonomy: Taxonomy) -> Self {
+        Self::build(client, model, taxonomy, false)
+    }
+
+    /// Asks for a score for every label; the best one is the label
+    pub fn scored(client: openai::Client, model: &str, taxonomy: Taxonomy) -> Self {
+        Self::build(client, model, taxonomy, true)
+    }
+
+    fn build(client: openai::Client, model: &str, taxonomy: Taxonomy, scored: bool) -> Self {
+        let extractor = SchemaExtractor::new(client, model, taxonomy.schema(scored))
+            .expect("a taxonomy schema describes an object")
+            .with_instructions(taxonomy.instructions(scored));
+        Self { taxonomy, extractor, scored, dry_run: false }
+    }
+
+    /// Match label names in the text instead of calling the provider
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn with_repair_attempts(mut self, attempts: usize) -> Self {
+        self.extractor = self.extractor.with_repair_attempts(attempts);
+        self
+    }
+
+    pub fn taxonomy(&self) -> &Taxonomy {
+        &self.taxonomy
+    }
+
+    pub async fn classify(&self, text: &str) -> Result<TaxonomyClassification, AgentError> {
+        if self.dry_run {
+            return Ok(pseudo_label(text, &self.taxonomy, self.scored));
+        }
+        let answer = self.extractor.extract(text).await?;
+        if !self.scored {
+            return serde_json::from_value(answer).map_err(|e| AgentError::Extraction(e.to_string()));
+        }
+
+        // Validated against the schema, so every label has a score
+        let scores = self.taxonomy
+            .labels
+            .iter()
+            .map(|label| LabelScore {
+                label: label.name.clone(),
+                score: answer["scores"][label.name.as_str()].as_f64().unwrap_or_default() as f32,
+            })
+            .collect();
+        TaxonomyClassification::from_scores(scores).ok_or_else(|| AgentError::Extraction("no scores".to_string()))
+    }
+
+    /// Classifies several texts concurrently; results keep the input order
+    /// and fail independently
+    pub async fn classify_batch(&self, texts: &[&str]) -> Vec<Result<TaxonomyClassification, AgentError>> {
+        let calls: Vec<_> = texts.iter().map(|text| self.classify(text)).collect();
+        stream::iter(calls).buffered(BATCH_CONCURRENCY).collect().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taxonomy(labels: &[&str]) -> Taxonomy {
+        Taxonomy {
+            name: "support".to_string(),
+            labels: labels.iter().map(|name| TaxonomyLabel { name: name.to_string(), description: None }).collect(),
+        }
+    }
+
+    #[test]
+    fn test_validate_and_schema() {
+        assert!(taxonomy(&["billing", "bug"]).validate().is_ok());
+        assert!(taxonomy(&["billing"]).validate().is_err());
+        assert!(taxonomy(&["billing", "bug", "billing"]).validate().is_err());
+
+        let schema = taxonomy(&["billing", "bug"]).schema(false);
+        let valid = json!({ "label": "bug", "confidence": 0.7 });
+        assert_eq!(super::super::repair::validate_value(&schema, &valid), Ok(()));
+        let unknown = json!({ "label": "sales", "confidence": 0.7 });
+        assert!(super::super::repair::validate_value(&schema, &unknown).unwrap_err().starts_with("label must be one of"));
+    }
+
+    #[test]
+    fn test_runtime_labels_are_scored() {
+        let names: Vec<String> = ["urgent", " spam", "", "other"].map(String::from).to_vec();
+        let taxonomy = Taxonomy::from_names(&names).unwrap();
+        assert_eq!(taxonomy.labels.len(), 3);
+        assert!(Taxonomy::from_names(&["spam".to_string()]).is_err());
+
+        let schema = taxonomy.schema(true);
+        let answer = json!({ "scores": { "urgent": 0.2, "spam": 0.7, "other": 0.1 } });
+        assert_eq!(super::super::repair::validate_value(&schema, &answer), Ok(()));
+        let missing = json!({ "scores": { "urgent": 0.2, "spam": 0.7 } });
+        assert_eq!(super::super::repair::validate_value(&schema, &missing), Err("scores.other is missing".to_string()));
+
+        let score = |label: &str, score| LabelScore { label: label.to_string(), score };
+        let best = TaxonomyClassification::from_scores(vec![score("urgent", 0.4), score("spam", 0.7), score("other", 0.7)]).unwrap();
+        assert_eq!((best.label.as_str(), best.confidence, best.scores.len()), ("spam", 0.7, 3));
+        assert!(TaxonomyClassification::from_scores(Vec::new()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_batch_keeps_order() {
+        let client = openai::Client::from_url("test-key", "https://api.example.com");
+        let classifier = TaxonomyClassifier::new(client, "test-model", taxonomy(&["billing", "bug"])).with_dry_run(true);
+
+        let results = classifier.classify_batch(&["The app crashes, looks like a bug", "Wrong billing amount"]).await;
+        let labels: Vec<String> = results.into_iter().map(|r| r.unwrap().label).collect();
+        assert_eq!(labels, ["bug", "billing"]);
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use rig::providers::openai;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use super::RetryPolicy;
+
+/// Sent when a topic has no refusal of its own; `{topic}` is replaced with its name
+pub const DEFAULT_REFUSAL: &str = "I'm sorry, but I can't help with {topic}. \
+     Is there something else on your mind we could talk about?";
+
+/// A subject the assistant declines, defined by the operator
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BlockedTopic {
+    pub name: String,
+    /// What the classifier is told the topic covers
+    pub description: String,
+    /// Words or phrases that block the topic without asking the model
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Reply template; `{topic}` is replaced with the topic's name
+    #[serde(default)]
+    pub refusal: Option<String>,
+}
+
+impl BlockedTopic {
+    pub fn refusal(&self) -> String {
+        self.refusal.as_deref().unwrap_or(DEFAULT_REFUSAL).replace("{topic}", &self.name)
+    }
+
+    fn matches_keyword(&self, lowered: &str) -> bool {
+        self.keywords.iter().any(|k| contains_words(lowered, &k.to_lowercase()))
+    }
+}
+
+/// Whether `phrase` appears in `text` as whole words, so that "sex" is not
+/// found in "bisexual"; digits may touch it, as in "500mg of"
+fn contains_words(text: &str, phrase: &str) -> bool {
+    !phrase.is_empty()
+        && text.match_indices(phrase).any(|(start, _)| {
+            let before = text[..start].chars().next_back();
+            let after = text[start + phrase.len()..].chars().next();
+            !before.is_some_and(char::is_alphabetic) && !after.is_some_and(char::is_alphabetic)
+        })
+}
+
+/// Reads a JSON list of blocked topics
+pub fn load_topics(path: &Path) -> Result<Vec<BlockedTopic>> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_str(&json).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+/// The first topic whose keywords appear in `text`, found without asking the model
+pub fn match_keywords<'a>(topics: &'a [BlockedTopic], text: &str) -> Option<&'a BlockedTopic> {
+    let lowered = text.to_lowercase();
+    topics.iter().find(|t| t.matches_keyword(&lowered))
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct TopicMatch {
+    /// Name of the blocked topic the message asks about, or null when it asks about none
+    topic: Option<String>,
+}
+
+/// Checks each message against the blocked topics before a reply is generated
+pub struct TopicGuard {
+    client: openai::Client,
+    model: String,
+    topics: Vec<BlockedTopic>,
+    retry: RetryPolicy,
+    dry_run: bool,
+}
+
+impl TopicGuard {
+    pub fn new(client: openai::Client, model: &str, topics: Vec<BlockedTopic>) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+            topics,
+            retry: RetryPolicy::default(),
+            dry_run: false,
+        }
+    }
+
+    /// Only match keywords instead of calling the provider
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// The blocked topic `input` asks about. Keywords are checked first; if
+    /// the classifier then fails, the message is let through with a warning.
+    pub async fn check(&self, input: &str) -> Option<&BlockedTopic> {
+        if self.topics.is_empty() {
+            return None;
+        }
+        if let Some(topic) = match_keywords(&self.topics, input) {
+            return Some(topic);
+        }
+        if self.dry_run {
+            return None;
+        }
+
+        match self.classify(input).await {
+            Ok(name) => {
+                let name = name?;
+                self.topics.iter().find(|t| t.name.eq_ignore_ascii_case(name.trim()))
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "topic classification failed, letting the message through");
+                None
+            }
+        }
+    }
+
+    async fn classify(&self, input: &str) -> Result<Option<String>, super::AgentError> {
+        let topics: Vec<String> = self.topics.iter().map(|t| format!("- {}: {}", t.name, t.description)).collect();
+        let extractor = self.client
+            .extractor::<TopicMatch>(&self.model)
+            .preamble(&format!(
+                "Decide whether the user's message asks for help with one of these blocked topics:\n{}\n\
+                 Return the topic's name exactly as written, or null if the message is about none of them. \
+                 Merely mentioning a subject in passing is not asking for help with it.",
+                topics.join("\n")
+            ))
+            .build();
+
+        let extractor = &extractor;
+        let matched = self.retry
+            .run(|| async move { Ok(extractor.extract(input).await?) })
+            .await?;
+        Ok(matched.topic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dosing() -> BlockedTopic {
+        BlockedTopic {
+            name: "medication dosing".to_string(),
+            description: "How much of a medicine to take".to_string(),
+            keywords: vec!["dose".to_string(), "mg of".to_string()],
+            refusal: None,
+        }
+    }
+
+    fn guard(topics: Vec<BlockedTopic>) -> TopicGuard {
+        let client = openai::Client::from_url("test-key", "https://api.example.com");
+        TopicGuard::new(client, "test-model", topics).with_dry_run(true)
+    }
+
+    #[tokio::test]
+    async fn test_keywords_block() {
+        let guard = guard(vec![dosing()]);
+        assert_eq!(guard.check("How many MG of ibuprofen can I take?").await, Some(&dosing()));
+        assert_eq!(guard.check("I had a rough day at work").await, None);
+        assert_eq!(guard.check("Is 500mg of it too much?").await, Some(&dosing()));
+        // Keywords are whole words, not parts of longer ones
+        assert_eq!(guard.check("She took the doses in the wrong order").await, None);
+    }
+
+    #[test]
+    fn test_refusal_template() {
+        assert_eq!(
+            dosing().refusal(),
+            "I'm sorry, but I can't help with medication dosing. Is there something else on your mind we could talk about?"
+        );
+        let custom = BlockedTopic { refusal: Some("Please ask a pharmacist about {topic}.".to_string()), ..dosing() };
+        assert_eq!(custom.refusal(), "Please ask a pharmacist about medication dosing.");
+    }
+
+    #[test]
+    fn test_topics_file_parsing() {
+        let topics: Vec<BlockedTopic> = serde_json::from_str(r#"[
+            {"name": "legal advice", "description": "Advice on a specific legal case"}
+        ]"#).unwrap();
+        assert!(topics[0].keywords.is_empty());
+        assert_eq!(topics[0].refusal, None);
+    }
+}
@@ -0,0 +1,216 @@
+//! Replacing names, places and other identifiers in saved sessions with
+//! placeholders, for sessions shared for research (`export --anonymize`)
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use crate::agents::{Entity, EntityKind};
+use crate::storage::StoredSession;
+
+/// Digits a run needs to be taken for a phone or account number
+const MIN_NUMBER_DIGITS: usize = 7;
+
+/// Gives each identifier one placeholder, such as `[PERSON_1]`, used wherever
+/// it appears in any session it anonymizes
+#[derive(Debug, Default)]
+pub struct Anonymizer {
+    /// By the lowercased identifier
+    placeholders: HashMap<String, String>,
+    counts: HashMap<EntityKind, usize>,
+}
+
+impl Anonymizer {
+    /// Numbers the identifiers not seen before within their kind
+    pub fn learn(&mut self, entities: impl IntoIterator<Item = Entity>) {
+        for entity in entities {
+            let key = entity.text.trim().to_lowercase();
+            if key.is_empty() || self.placeholders.contains_key(&key) {
+                continue;
+            }
+            let count = self.counts.entry(entity.kind).or_default();
+            *count += 1;
+            self.placeholders.insert(key, format!("[{}_{}]", entity.kind.label(), count));
+        }
+    }
+
+    /// `text` with every known identifier replaced, ignoring case. Only whole
+    /// words match, and longer identifiers before the shorter ones inside them.
+    pub fn apply(&self, text: &str) -> String {
+        let mut known: Vec<(&str, &str)> = self.placeholders.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        known.sort_by_key(|(identifier, _)| Reverse(identifier.len()));
+
+        let mut anonymized = String::with_capacity(text.len());
+        let mut i = 0;
+        while let Some(c) = text[i..].chars().next() {
+            let starts_word = text[..i].chars().next_back().is_none_or(|before| !before.is_alphanumeric());
+            let found = starts_word
+                .then(|| known.iter().find_map(|(identifier, placeholder)| Some((matches_at(text, i, identifier)?, placeholder))))
+                .flatten();
+            match found {
+                Some((len, placeholder)) => {
+                    anonymized.push_str(placeholder);
+                    i += len;
+                }
+                None => {
+                    anonymized.push(c);
+                    i += c.len_utf8();
+                }
+            }
+        }
+        anonymized
+    }
+
+    /// Replaces identifiers in the session's title, tags and messages, and in
+    /// the speakers, causes and aspects kept with them
+    pub fn anonymize(&self, session: &mut StoredSession) {
+        session.title = session.title.as_deref().map(|title| self.apply(title));
+        for tag in &mut session.tags {
+            *tag = self.apply(tag);
+        }
+        for message in &mut session.messages {
+            message.content = self.apply(&message.content);
+            message.speaker = message.speaker.as_deref().map(|speaker| self.apply(speaker));
+            message.cause = message.cause.as_deref().map(|cause| self.apply(cause));
+            for aspect in &mut message.aspects {
+                aspect.aspect = self.apply(&aspect.aspect);
+            }
+        }
+    }
+}
+
+/// The length in `text`'s bytes of `identifier` at byte `i`, followed by the
+/// end of a word. Characters are lowercased one at a time, since lowercasing
+/// changes the byte length of some, such as the "İ" of "İbrahim".
+fn matches_at(text: &str, i: usize, identifier: &str) -> Option<usize> {
+    let mut expected = identifier.chars();
+    let mut len = None;
+    for (offset, c) in text[i..].char_indices() {
+        if !c.to_lowercase().all(|lowered| expected.next() == Some(lowered)) {
+            return None;
+        }
+        if expected.as_str().is_empty() {
+            len = Some(offset + c.len_utf8());
+            break;
+        }
+    }
+    let len = len?;
+    text[i + len..].chars().next().is_none_or(|after| !after.is_alphanumeric()).then_some(len)
+}
+
+/// Everything in a session the entity extractor should read: who spoke and
+/// what they said
+pub fn session_text(session: &StoredSession) -> String {
+    let mut lines = Vec::new();
+    for message in &session.messages {
+        match &message.speaker {
+            Some(speaker) => lines.push(format!("{}: {}", speaker, message.content)),
+            None => lines.push(message.content.clone()),
+        }
+    }
+    lines.join("\n")
+}
+
+/// The participants of an imported chat, who are people whatever the
+/// extractor makes of their names
+pub fn speakers(session: &StoredSession) -> Vec<Entity> {
+    session
+        .messages
+        .iter()
+        .filter_map(|m| m.speaker.clone())
+        .map(|text| Entity { text, kind: EntityKind::Person })
+        .collect()
+}
+
+/// Email addresses, handles and phone numbers, found without the model
+pub fn find_contacts(text: &str) -> Vec<Entity> {
+    let mut contacts = Vec::new();
+    for token in text.split_whitespace() {
+        let token = token.trim_matches(|c: char| !c.is_alphanumeric() && c != '@');
+        let email = token.contains('@') && token.contains('.') && !token.starts_with('@');
+        let handle = token.starts_with('@') && token.len() > 1;
+        if email || handle {
+            contacts.push(Entity { text: token.to_string(), kind: EntityKind::Contact });
+        }
+    }
+
+    let is_number_char = |c: char| c.is_ascii_digit() || matches!(c, '+' | '-' | '(' | ')' | '.' | ' ');
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit() || c == '+' || c == '(') {
+        let run = &rest[start..];
+        let end = run.find(|c: char| !is_number_char(c)).unwrap_or(run.len());
+        let number = run[..end].trim_end_matches(|c: char| !c.is_ascii_digit());
+        if number.chars().filter(char::is_ascii_digit).count() >= MIN_NUMBER_DIGITS && !is_date(number) {
+            contacts.push(Entity { text: number.to_string(), kind: EntityKind::Contact });
+        }
+        rest = &run[end.max(1)..];
+    }
+    contacts
+}
+
+/// YYYY-MM-DD and the like, which have as many digits as a short phone number
+fn is_date(number: &str) -> bool {
+    let parts: Vec<&str> = number.split(['-', '/', '.']).collect();
+    parts.len() == 3 && parts.iter().all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Message, MessageRole};
+
+    fn message(role: MessageRole, content: &str) -> Message {
+        Message {
+            role,
+            content: content.to_string(),
+            timestamp: 0,
+            emotion: None,
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
+        }
+    }
+
+    #[test]
+    fn test_placeholders_are_consistent() {
+        let mut anonymizer = Anonymizer::default();
+        anonymizer.learn([
+            Entity { text: "Sam".to_string(), kind: EntityKind::Person },
+            Entity { text: "Sam Lee".to_string(), kind: EntityKind::Person },
+            Entity { text: "Leeds".to_string(), kind: EntityKind::Location },
+            Entity { text: "sam".to_string(), kind: EntityKind::Person },
+        ]);
+
+        let mut user = message(MessageRole::User, "Sam Lee moved to Leeds. I miss sam, and Samantha too.");
+        user.cause = Some("Sam moving away".to_string());
+        let assistant = message(MessageRole::Assistant, "It sounds like you really miss Sam.");
+        let mut session = StoredSession::new(0, vec![user, assistant]);
+        session.title = Some("Missing Sam after the move to Leeds".to_string());
+        session.tags = vec!["leeds".to_string(), "family".to_string()];
+        anonymizer.anonymize(&mut session);
+
+        assert_eq!(session.title.as_deref(), Some("Missing [PERSON_1] after the move to [LOCATION_1]"));
+        assert_eq!(session.tags, ["[LOCATION_1]", "family"]);
+
+        assert_eq!(session.messages[0].content, "[PERSON_2] moved to [LOCATION_1]. I miss [PERSON_1], and Samantha too.");
+        assert_eq!(session.messages[0].cause.as_deref(), Some("[PERSON_1] moving away"));
+        assert_eq!(session.messages[1].content, "It sounds like you really miss [PERSON_1].");
+    }
+
+    #[test]
+    fn test_names_that_lowercase_to_another_length_are_replaced() {
+        let mut anonymizer = Anonymizer::default();
+        anonymizer.learn([
+            Entity { text: "İbrahim".to_string(), kind: EntityKind::Person },
+            Entity { text: "Zoë".to_string(), kind: EntityKind::Person },
+        ]);
+        assert_eq!(anonymizer.apply("İbrahim and ZOË came, İbrahimova did not."), "[PERSON_1] and [PERSON_2] came, İbrahimova did not.");
+    }
+
+    #[test]
+    fn test_contacts_are_found_without_the_model() {
+        let text = "Mail sam.lee@example.com or @samlee, call +44 20 7946 0958. Since 2024-05-01 it's been 3 weeks.";
+        let found: Vec<String> = find_contacts(text).into_iter().map(|e| e.text).collect();
+        assert_eq!(found, ["sam.lee@example.com", "@samlee", "+44 20 7946 0958"]);
+    }
+}
@@ -0,0 +1,226 @@
+//! Spend limits for chat sessions and days (`SESSION_BUDGET_USD`,
+//! `DAILY_BUDGET_USD`), priced from the token usage the provider reports
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use crate::agents::TokenUsage;
+use crate::commands::recap::TokenPrices;
+
+/// Where replies go once a limit is reached
+#[derive(Debug, Clone, PartialEq)]
+pub enum BudgetFallback {
+    /// A cheaper model from the same provider, from `BUDGET_FALLBACK_MODEL`
+    Model(String),
+    /// A provider profile such as a local Ollama, from `BUDGET_FALLBACK_PROFILE`
+    Profile(String),
+    /// No more replies until the budget allows them again
+    Stop,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetPolicy {
+    /// Dollars one chat session may spend
+    pub session: Option<f64>,
+    /// Dollars all chat sessions of one day may spend together
+    pub daily: Option<f64>,
+    pub prices: TokenPrices,
+    pub fallback: BudgetFallback,
+}
+
+/// The limit a chat has reached
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Session(f64),
+    Daily(f64),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Session(dollars) => write!(f, "session budget of ${:.2}", dollars),
+            Limit::Daily(dollars) => write!(f, "daily budget of ${:.2}", dollars),
+        }
+    }
+}
+
+/// What one day's sessions have spent, kept between them
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+struct DailySpend {
+    day: Option<NaiveDate>,
+    dollars: f64,
+}
+
+/// Adds up a chat's spend against its policy
+#[derive(Debug)]
+pub struct Budget {
+    policy: BudgetPolicy,
+    session: f64,
+    daily: DailySpend,
+    /// The day's spend is saved here; a dry run keeps it in memory
+    path: Option<PathBuf>,
+}
+
+impl Budget {
+    /// Picks up the spend saved at `path` by earlier sessions today
+    pub fn open(policy: BudgetPolicy, path: Option<PathBuf>, today: NaiveDate) -> Self {
+        let mut daily = match &path {
+            Some(path) => load(path).unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "failed to read today's spend, starting from zero");
+                DailySpend::default()
+            }),
+            None => DailySpend::default(),
+        };
+        if daily.day != Some(today) {
+            daily = DailySpend { day: Some(today), dollars: 0.0 };
+        }
+        Self { policy, session: 0.0, daily, path }
+    }
+
+    pub fn policy(&self) -> &BudgetPolicy {
+        &self.policy
+    }
+
+    /// Prices a turn's usage into the session and the day, and adds it to the
+    /// day's saved total, which picks up what other sessions spent meanwhile
+    pub fn record(&mut self, usage: TokenUsage, today: NaiveDate) -> Result<()> {
+        let cost = self.policy.prices.cost(usage);
+        if self.daily.day != Some(today) {
+            self.daily = DailySpend { day: Some(today), dollars: 0.0 };
+        }
+        self.session += cost;
+        self.daily.dollars += cost;
+        if let Some(path) = &self.path {
+            self.daily = add_saved(path, today, cost)?;
+        }
+        Ok(())
+    }
+
+    /// The first limit reached, the session's before the day's
+    pub fn exceeded(&self) -> Option<Limit> {
+        if let Some(limit) = self.policy.session
+            && self.session >= limit
+        {
+            return Some(Limit::Session(limit));
+        }
+        if let Some(limit) = self.policy.daily
+            && self.daily.dollars >= limit
+        {
+            return Some(Limit::Daily(limit));
+        }
+        None
+    }
+}
+
+fn load(path: &Path) -> Result<DailySpend> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).with_context(|| format!("failed to parse {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(DailySpend::default()),
+        Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+/// Adds `dollars` to the spend saved at `path`, re-read under a lock so that
+/// sessions recording at the same time all count
+fn add_saved(path: &Path, today: NaiveDate, dollars: f64) -> Result<DailySpend> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    // Released when the file is closed
+    file.lock().with_context(|| format!("failed to lock {}", path.display()))?;
+
+    let mut json = String::new();
+    file.read_to_string(&mut json).with_context(|| format!("failed to read {}", path.display()))?;
+    let mut spend = match json.trim() {
+        "" => DailySpend::default(),
+        json => serde_json::from_str(json).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "failed to parse today's spend, starting from zero");
+            DailySpend::default()
+        }),
+    };
+    if spend.day != Some(today) {
+        spend = DailySpend { day: Some(today), dollars: 0.0 };
+    }
+    spend.dollars += dollars;
+
+    let json = serde_json::to_string_pretty(&spend)?;
+    file.set_len(0).and_then(|()| file.seek(SeekFrom::Start(0))).and_then(|_| file.write_all(json.as_bytes()))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(spend)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(session: Option<f64>, daily: Option<f64>) -> BudgetPolicy {
+        BudgetPolicy {
+            session,
+            daily,
+            prices: TokenPrices { prompt: 1.0, completion: 2.0 },
+            fallback: BudgetFallback::Stop,
+        }
+    }
+
+    #[test]
+    fn test_limits_are_reached_by_session_then_day() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let mut budget = Budget::open(policy(Some(1.0), Some(5.0)), None, today);
+        budget.record(TokenUsage { prompt: 400, completion: 200 }, today).unwrap();
+        assert_eq!(budget.exceeded(), None);
+        budget.record(TokenUsage { prompt: 300, completion: 0 }, today).unwrap();
+        assert_eq!(budget.exceeded(), Some(Limit::Session(1.0)));
+        assert_eq!(Limit::Session(1.0).to_string(), "session budget of $1.00");
+
+        let mut budget = Budget::open(policy(None, Some(0.5)), None, today);
+        budget.record(TokenUsage { prompt: 500, completion: 0 }, today).unwrap();
+        assert_eq!(budget.exceeded(), Some(Limit::Daily(0.5)));
+        // A new day starts from zero
+        budget.record(TokenUsage { prompt: 0, completion: 0 }, today.succ_opt().unwrap()).unwrap();
+        assert_eq!(budget.exceeded(), None);
+    }
+
+    #[test]
+    fn test_daily_spend_carries_over_between_sessions() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let path = std::env::temp_dir().join(format!("tce-spend-{}.json", std::process::id()));
+        let mut first = Budget::open(policy(None, Some(1.0)), Some(path.clone()), today);
+        first.record(TokenUsage { prompt: 600, completion: 0 }, today).unwrap();
+
+        let mut second = Budget::open(policy(None, Some(1.0)), Some(path.clone()), today);
+        assert_eq!(second.exceeded(), None);
+        second.record(TokenUsage { prompt: 500, completion: 0 }, today).unwrap();
+        assert_eq!(second.exceeded(), Some(Limit::Daily(1.0)));
+
+        let tomorrow = Budget::open(policy(None, Some(1.0)), Some(path.clone()), today.succ_opt().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(tomorrow.exceeded(), None);
+    }
+
+    #[test]
+    fn test_sessions_open_at_once_add_to_the_same_day() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let path = std::env::temp_dir().join(format!("tce-spend-shared-{}.json", std::process::id()));
+        let mut first = Budget::open(policy(None, Some(1.0)), Some(path.clone()), today);
+        let mut second = Budget::open(policy(None, Some(1.0)), Some(path.clone()), today);
+        first.record(TokenUsage { prompt: 600, completion: 0 }, today).unwrap();
+        // The second session's record adds to the first's rather than overwriting it
+        second.record(TokenUsage { prompt: 500, completion: 0 }, today).unwrap();
+        assert_eq!(second.exceeded(), Some(Limit::Daily(1.0)));
+
+        let third = Budget::open(policy(None, Some(1.0)), Some(path.clone()), today);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(third.exceeded(), Some(Limit::Daily(1.0)));
+    }
+}
@@ -0,0 +1,755 @@
+use anyhow::Result;
+use chrono::{Days, NaiveDate};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use crate::render::OutputFormat;
+use crate::report::last_week;
+use crate::storage::normalize_tag;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Emotional-aware chat system")]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Args)]
+pub struct GlobalArgs {
+    /// Don't check that the provider and model respond before starting
+    #[arg(long, global = true)]
+    pub skip_health_check: bool,
+
+    /// Classify locally and echo prompts instead of calling the provider
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Use this provider profile from `PROVIDERS_FILE` for the endpoint, model
+    /// and key (switch in chat with /provider)
+    #[arg(long, global = true, env = "PROVIDER_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Only show the conversation, without emotion, trend and strategy lines
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Also show latency and token usage for each turn
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
+    /// How chat turns are shown
+    #[arg(long, global = true, value_enum, env = "OUTPUT_FORMAT", default_value_t = OutputFormat::Plain)]
+    pub output_format: OutputFormat,
+
+    /// Print the exact prompts sent to the model (toggle in chat with /debug on|off)
+    #[arg(long, global = true)]
+    pub debug_prompts: bool,
+
+    /// Generate this many candidate replies per turn and keep the best-scored one
+    /// (see the others with /alts)
+    #[arg(long, global = true, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=8))]
+    pub candidates: u8,
+
+    /// Mix neighbouring strategies by weight instead of switching between them,
+    /// so the tone changes gradually as the mood shifts
+    #[arg(long, global = true)]
+    pub blend_strategies: bool,
+
+    /// End the chat recap with the model's short reflection on the conversation
+    #[arg(long, global = true)]
+    pub reflect: bool,
+
+    /// Read emotions as they are instead of against your usual mood from saved sessions
+    #[arg(long, global = true)]
+    pub no_baseline: bool,
+
+    /// Frame the assistant as not a therapist, point to professional help, add
+    /// periodic disclaimers and never diagnose; use this for public deployments
+    #[arg(long, global = true, env = "WELLBEING_SAFE")]
+    pub wellbeing_safe: bool,
+
+    /// Use stricter prompts for children and teenagers, decline topics such as
+    /// alcohol and gambling, and screen replies for profanity and those topics
+    #[arg(long, global = true, env = "MINOR_SAFE")]
+    pub minor_safe: bool,
+
+    /// Also extract the sentiment toward each thing a message talks about,
+    /// e.g. job:positive and manager:negative, kept with the message for reports
+    #[arg(long, global = true, env = "ASPECT_SENTIMENT")]
+    pub aspects: bool,
+
+    /// Ask what a negative emotion comes from, e.g. deadline pressure, so the
+    /// reply can address it and the conversation remembers it
+    #[arg(long, global = true, env = "EMOTION_CAUSES")]
+    pub causes: bool,
+
+    /// Rate every reply for empathy and strategy adherence, kept with the
+    /// message so sessions can be audited
+    #[arg(long, global = true, env = "SCORE_REPLIES")]
+    pub score_replies: bool,
+
+    /// Explain each turn's detected emotion and chosen strategy in a separate
+    /// call, shown in chat with /why and never in the reply
+    #[arg(long, global = true, env = "EXPLAIN_TURNS")]
+    pub explain: bool,
+
+    /// Greet fresh chat sessions and ask the onboarding questions, whose
+    /// answers are kept in the user profile
+    #[arg(long, global = true, env = "ONBOARDING")]
+    pub onboarding: bool,
+
+    /// Open fresh chat sessions with a message from the assistant, drawing
+    /// on the user profile and the last session
+    #[arg(long, global = true, env = "SPEAK_FIRST")]
+    pub speak_first: bool,
+}
+
+/// How much the chat prints besides the conversation itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+impl GlobalArgs {
+    pub fn verbosity(&self) -> Verbosity {
+        if self.quiet {
+            Verbosity::Quiet
+        } else if self.verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start an interactive chat session (default)
+    Chat,
+    /// Classify each paragraph of a document and profile its overall sentiment
+    Analyze(AnalyzeArgs),
+    /// Classify one text and print the result as JSON, exiting nonzero on failure
+    Classify(ClassifyArgs),
+    /// Extract the structure a JSON schema describes from a text and print it as JSON
+    Extract(ExtractArgs),
+    /// Classify texts into the labels of TAXONOMY_FILE, printing JSON lines
+    Categorize(CategorizeArgs),
+    /// Cluster saved sessions by topic and mood
+    AnalyzeCorpus(CorpusArgs),
+    /// Compare classification accuracy with and without the few-shot examples on labelled JSON lines
+    Evaluate(EvaluateArgs),
+    /// Answer the inputs of a JSON lines file with two sets of strategy prompts and print the replies side by side
+    ComparePrompts(CompareArgs),
+    /// Write saved sessions as JSON lines, optionally with names and other identifiers replaced
+    Export(ExportArgs),
+    /// Classify an exported WhatsApp or Telegram chat (or JSON lines) and save it as sessions
+    Import(ImportArgs),
+    /// Take turns and answer session queries over a local socket, without the HTTP server
+    Ipc(IpcArgs),
+    /// Send turns to the HTTP or WebSocket endpoints from concurrent clients and report throughput and latency
+    Loadtest(LoadtestArgs),
+    /// Save the API key for OPENAI_BASE_URL in the system keyring, used when OPENAI_API_KEY is unset
+    #[cfg(feature = "keyring")]
+    Login(LoginArgs),
+    /// Summarize stored sessions over a date range
+    Report(ReportArgs),
+    /// Run the HTTP API server
+    Serve(ServeArgs),
+    /// List saved sessions with their title, date, dominant emotion and turns
+    Sessions(SessionsArgs),
+    /// Hold conversations with a simulated user following an emotion script, to test trend detection and strategy changes
+    Simulate(SimulateArgs),
+    /// Classify lines as they are appended to a file, printing JSON lines
+    Watch(WatchArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct AnalyzeArgs {
+    /// Text file to analyze; paragraphs are separated by blank lines
+    pub file: PathBuf,
+
+    /// Print the profile as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ClassifyArgs {
+    /// Text to classify; read from stdin when omitted
+    pub text: Option<String>,
+
+    /// Print a fallback classification and succeed, instead of failing when
+    /// the model gave no usable answer
+    #[arg(long)]
+    pub allow_fallback: bool,
+
+    /// Classify into these comma-separated labels instead of the sentiments,
+    /// scoring each, e.g. `urgent,spam,billing,other`
+    #[arg(long, value_delimiter = ',', conflicts_with = "allow_fallback")]
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ExtractArgs {
+    /// JSON schema of the object to extract
+    #[arg(long)]
+    pub schema: PathBuf,
+
+    /// Text file to extract from; read from stdin when omitted
+    #[arg(long)]
+    pub input: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct CategorizeArgs {
+    /// Text to classify; read from stdin when omitted
+    #[arg(conflicts_with = "file")]
+    pub text: Option<String>,
+
+    /// Classify each non-empty line of this file instead
+    #[arg(long)]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct EvaluateArgs {
+    /// One {"text", "sentiment"} object per line
+    pub file: PathBuf,
+
+    /// Print the scores as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct CompareArgs {
+    /// One {"text", "strategy"} object per line; the strategy is picked from
+    /// the text's emotion when omitted
+    pub file: PathBuf,
+
+    /// JSON object of prompts by strategy, e.g. {"Empathetic": "..."}
+    #[arg(long)]
+    pub a: PathBuf,
+
+    /// The prompts to compare against those of --a
+    #[arg(long)]
+    pub b: PathBuf,
+
+    /// Width of the two columns together
+    #[arg(long, default_value_t = 100)]
+    pub width: usize,
+
+    /// Print the replies as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct CorpusArgs {
+    /// Number of clusters; picked from the number of sessions when omitted
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    pub clusters: Option<u16>,
+
+    /// Only cluster sessions with this tag
+    #[arg(long, value_parser = parse_tag)]
+    pub tag: Option<String>,
+
+    /// Print the clusters as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct IpcArgs {
+    /// Unix socket to listen on, or a named pipe such as \\.\pipe\emotion on Windows
+    #[arg(long)]
+    pub socket: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct LoginArgs {
+    /// Remove the saved key instead
+    #[arg(long)]
+    pub delete: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct WatchArgs {
+    /// Log or transcript to follow
+    pub file: PathBuf,
+
+    /// Classify the lines already in the file first, instead of only new ones
+    #[arg(long)]
+    pub from_start: bool,
+
+    /// How often to check the file for new lines, in milliseconds
+    #[arg(long, default_value_t = 500, value_parser = clap::value_parser!(u64).range(50..))]
+    pub interval_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TranscriptFormat {
+    /// WhatsApp "Export chat" text, from Android or iOS
+    Whatsapp,
+    /// Telegram Desktop's result.json
+    Telegram,
+    /// One {"from", "text", "timestamp"} object per line
+    Jsonl,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// The exported chat
+    pub file: PathBuf,
+
+    /// Format of the export; guessed from the file when omitted
+    #[arg(long, value_enum)]
+    pub format: Option<TranscriptFormat>,
+
+    /// Participant whose messages are classified and count as yours; the others
+    /// are kept as the other side. Everyone counts when omitted.
+    #[arg(long)]
+    pub speaker: Option<String>,
+
+    /// Tag the imported sessions, besides `imported`
+    #[arg(long, value_parser = parse_tag)]
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Export a single saved session instead of all of them
+    #[arg(long)]
+    pub session: Option<String>,
+
+    /// Only export sessions with this tag
+    #[arg(long, value_parser = parse_tag, conflicts_with = "session")]
+    pub tag: Option<String>,
+
+    /// Replace names, places, organizations and contact details with
+    /// placeholders such as [PERSON_1], the same ones across all sessions
+    #[arg(long)]
+    pub anonymize: bool,
+
+    /// Write the sessions to a file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Html,
+}
+
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    /// Cover the last seven days, ending today
+    #[arg(long, conflicts_with_all = ["from", "to"])]
+    pub weekly: bool,
+
+    /// Report on a single saved session instead of a date range
+    #[arg(long, conflicts_with_all = ["weekly", "from", "to"])]
+    pub session: Option<String>,
+
+    /// Only include sessions with this tag
+    #[arg(long, value_parser = parse_tag, conflicts_with = "session")]
+    pub tag: Option<String>,
+
+    /// First day to include (YYYY-MM-DD)
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+
+    /// Last day to include (YYYY-MM-DD), defaults to today
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+
+    /// Write the report to a file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct SessionsArgs {
+    /// Only list sessions with this tag
+    #[arg(long, value_parser = parse_tag)]
+    pub tag: Option<String>,
+
+    /// Write titles for sessions saved without one, such as imported chats,
+    /// and save them
+    #[arg(long)]
+    pub generate_titles: bool,
+
+    /// Print the list as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct SimulateArgs {
+    /// JSON emotion script: {"persona", "steps": [{"sentiment", "turns", "mood"}]};
+    /// a user who starts angry and gradually calms when omitted
+    #[arg(long)]
+    pub script: Option<PathBuf>,
+
+    /// Conversations to hold, each from the start of the script
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub runs: u16,
+
+    /// Model playing the user; MODEL when omitted
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Print the turns as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// The endpoint `loadtest` sends turns to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadEndpoint {
+    /// POST /turns, a new conversation each request
+    Turns,
+    /// POST /sessions/{id}/messages, one session per client
+    Messages,
+    /// The /sessions/{id}/live WebSocket, one per client
+    Live,
+}
+
+#[derive(Debug, Args)]
+pub struct LoadtestArgs {
+    #[arg(long, value_enum, default_value_t = LoadEndpoint::Turns)]
+    pub endpoint: LoadEndpoint,
+
+    /// Clients sending requests at the same time
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+
+    /// Requests to send across all clients
+    #[arg(long, default_value_t = 200, value_parser = clap::value_parser!(u32).range(1..))]
+    pub requests: u32,
+
+    /// A running server such as http://127.0.0.1:3000; by default one is
+    /// started here, answered by a mock provider
+    #[arg(long)]
+    pub target: Option<String>,
+
+    /// API key for --target, sent as a bearer token
+    #[arg(long, requires = "target")]
+    pub api_key: Option<String>,
+
+    /// How long the mock provider takes to answer, standing in for the model
+    #[arg(long, default_value_t = 0, conflicts_with = "target")]
+    pub mock_latency_ms: u64,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:3000")]
+    pub addr: SocketAddr,
+
+    /// Maximum number of texts accepted by one /classify request
+    #[arg(long, default_value_t = 32)]
+    pub max_batch: usize,
+
+    /// JSON file listing API keys with their rate limits and daily quotas
+    #[arg(long)]
+    pub api_keys: Option<PathBuf>,
+
+    /// JSON file defining tenants with their own provider, model and strategy prompts
+    #[arg(long)]
+    pub tenants: Option<PathBuf>,
+
+    /// Deliver long replies on live (WebSocket) sessions in parts at this many
+    /// characters per second, with typing events in between
+    #[arg(long, env = "REPLY_PACING_CPS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub pace_replies: Option<u32>,
+
+    /// Also serve the gRPC interface on this address
+    #[cfg(feature = "grpc")]
+    #[arg(long)]
+    pub grpc_addr: Option<SocketAddr>,
+
+    /// Keep chat sessions in Redis so they are shared across instances
+    #[cfg(feature = "redis")]
+    #[arg(long)]
+    pub redis_url: Option<String>,
+
+    /// Seconds an idle session is kept in Redis
+    #[cfg(feature = "redis")]
+    #[arg(long, default_value_t = 86_400)]
+    pub session_ttl: u64,
+}
+
+fn parse_tag(tag: &str) -> Result<String, String> {
+    normalize_tag(tag).ok_or_else(|| "tags are one word of letters, digits, - and _".to_string())
+}
+
+impl ReportArgs {
+    pub fn date_range(&self, today: NaiveDate) -> Result<(NaiveDate, NaiveDate)> {
+        if self.weekly {
+            return Ok(last_week(today));
+        }
+
+        let to = self.to.unwrap_or(today);
+        let from = match self.from {
+            Some(from) => from,
+            None => last_week(to).0,
+        };
+
+        if from > to {
+            anyhow::bail!("--from {} is after --to {}", from, to);
+        }
+
+        // Guard against accidental multi-year ranges producing huge daily tables
+        if to.checked_sub_days(Days::new(366)).is_some_and(|limit| from < limit) {
+            anyhow::bail!("date range is limited to one year");
+        }
+
+        Ok((from, to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_no_subcommand_defaults_to_chat() {
+        let cli = Cli::try_parse_from(["app"]).unwrap();
+        assert!(cli.command.is_none());
+    }
+
+    #[test]
+    fn test_global_flags_after_subcommand() {
+        let cli = Cli::try_parse_from(["app", "serve", "--skip-health-check"]).unwrap();
+        assert!(cli.global.skip_health_check);
+
+        let cli = Cli::try_parse_from(["app", "chat", "--debug-prompts", "--candidates", "3"]).unwrap();
+        assert!(cli.global.debug_prompts);
+        assert_eq!(cli.global.candidates, 3);
+        assert!(Cli::try_parse_from(["app", "--candidates", "0"]).is_err());
+
+        let cli = Cli::try_parse_from(["app", "chat", "--blend-strategies", "--no-baseline"]).unwrap();
+        assert!(cli.global.blend_strategies);
+        assert!(cli.global.no_baseline);
+
+        let cli = Cli::try_parse_from(["app", "serve", "--wellbeing-safe"]).unwrap();
+        assert!(cli.global.wellbeing_safe);
+
+        let cli = Cli::try_parse_from(["app", "serve", "--minor-safe"]).unwrap();
+        assert!(cli.global.minor_safe);
+
+        let cli = Cli::try_parse_from(["app", "chat", "--aspects"]).unwrap();
+        assert!(cli.global.aspects);
+
+        let cli = Cli::try_parse_from(["app", "serve", "--causes"]).unwrap();
+        assert!(cli.global.causes);
+
+        let cli = Cli::try_parse_from(["app", "chat", "--score-replies"]).unwrap();
+        assert!(cli.global.score_replies);
+
+        let cli = Cli::try_parse_from(["app", "chat", "--explain"]).unwrap();
+        assert!(cli.global.explain);
+
+        let cli = Cli::try_parse_from(["app", "chat", "--onboarding"]).unwrap();
+        assert!(cli.global.onboarding);
+
+        let cli = Cli::try_parse_from(["app", "chat", "--speak-first"]).unwrap();
+        assert!(cli.global.speak_first);
+
+        let cli = Cli::try_parse_from(["app", "chat", "--profile", "local-ollama"]).unwrap();
+        assert_eq!(cli.global.profile.as_deref(), Some("local-ollama"));
+    }
+
+    #[test]
+    fn test_classify_text_is_optional() {
+        let Some(Command::Classify(args)) = Cli::try_parse_from(["app", "classify", "so tired", "--dry-run"]).unwrap().command else {
+            panic!("expected classify");
+        };
+        assert_eq!(args.text.as_deref(), Some("so tired"));
+        assert!(!args.allow_fallback);
+        assert!(args.labels.is_empty());
+
+        let cli = Cli::try_parse_from(["app", "classify", "hi", "--labels", "urgent,spam,other"]).unwrap();
+        let Some(Command::Classify(args)) = cli.command else {
+            panic!("expected classify");
+        };
+        assert_eq!(args.labels, ["urgent", "spam", "other"]);
+        assert!(matches!(Cli::try_parse_from(["app", "classify"]).unwrap().command, Some(Command::Classify(ClassifyArgs { text: None, .. }))));
+    }
+
+    #[test]
+    fn test_extract_needs_a_schema() {
+        let cli = Cli::try_parse_from(["app", "extract", "--schema", "invoice.json", "--input", "mail.txt"]).unwrap();
+        let Some(Command::Extract(args)) = cli.command else {
+            panic!("expected extract");
+        };
+        assert_eq!(args.schema, PathBuf::from("invoice.json"));
+        assert_eq!(args.input, Some(PathBuf::from("mail.txt")));
+        assert!(Cli::try_parse_from(["app", "extract", "--input", "mail.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_categorize_text_or_file() {
+        let cli = Cli::try_parse_from(["app", "categorize", "--file", "tickets.txt"]).unwrap();
+        let Some(Command::Categorize(args)) = cli.command else {
+            panic!("expected categorize");
+        };
+        assert_eq!(args.file, Some(PathBuf::from("tickets.txt")));
+        assert!(Cli::try_parse_from(["app", "categorize", "refund please", "--file", "tickets.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_verbosity() {
+        let verbosity = |args: &[&str]| Cli::try_parse_from(args).unwrap().global.verbosity();
+        assert_eq!(verbosity(&["app"]), Verbosity::Normal);
+        assert_eq!(verbosity(&["app", "chat", "-q"]), Verbosity::Quiet);
+        assert_eq!(verbosity(&["app", "-v"]), Verbosity::Verbose);
+
+        assert!(Cli::try_parse_from(["app", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn test_output_format() {
+        let cli = Cli::try_parse_from(["app", "chat", "--output-format", "json"]).unwrap();
+        assert_eq!(cli.global.output_format, OutputFormat::Json);
+    }
+
+    #[test]
+    fn test_report_weekly_range() {
+        let cli = Cli::try_parse_from(["app", "report", "--weekly"]).unwrap();
+        let Some(Command::Report(args)) = cli.command else { panic!("expected report") };
+
+        let range = args.date_range(date("2026-02-10")).unwrap();
+        assert_eq!(range, (date("2026-02-04"), date("2026-02-10")));
+    }
+
+    #[test]
+    fn test_report_explicit_range() {
+        let cli = Cli::try_parse_from([
+            "app", "report", "--from", "2026-01-01", "--to", "2026-01-31", "--format", "json",
+        ]).unwrap();
+        let Some(Command::Report(args)) = cli.command else { panic!("expected report") };
+
+        assert_eq!(args.format, ReportFormat::Json);
+        let range = args.date_range(date("2026-02-10")).unwrap();
+        assert_eq!(range, (date("2026-01-01"), date("2026-01-31")));
+    }
+
+    #[test]
+    fn test_report_rejects_inverted_range() {
+        let cli = Cli::try_parse_from([
+            "app", "report", "--from", "2026-02-01", "--to", "2026-01-01",
+        ]).unwrap();
+        let Some(Command::Report(args)) = cli.command else { panic!("expected report") };
+
+        assert!(args.date_range(date("2026-02-10")).is_err());
+    }
+
+    #[test]
+    fn test_report_session_html() {
+        let cli = Cli::try_parse_from([
+            "app", "report", "--session", "20260204-120000", "--format", "html", "-o", "out.html",
+        ]).unwrap();
+        let Some(Command::Report(args)) = cli.command else { panic!("expected report") };
+
+        assert_eq!(args.session.as_deref(), Some("20260204-120000"));
+        assert_eq!(args.format, ReportFormat::Html);
+        assert_eq!(args.output, Some(PathBuf::from("out.html")));
+    }
+
+    #[test]
+    fn test_report_tag_filter() {
+        let cli = Cli::try_parse_from(["app", "report", "--tag", "#Work"]).unwrap();
+        let Some(Command::Report(args)) = cli.command else { panic!("expected report") };
+        assert_eq!(args.tag.as_deref(), Some("work"));
+
+        assert!(Cli::try_parse_from(["app", "report", "--tag", "a b"]).is_err());
+        assert!(Cli::try_parse_from(["app", "report", "--tag", "work", "--session", "x"]).is_err());
+    }
+
+    #[test]
+    fn test_serve_defaults() {
+        let cli = Cli::try_parse_from(["app", "serve"]).unwrap();
+        let Some(Command::Serve(args)) = cli.command else { panic!("expected serve") };
+
+        assert_eq!(args.addr.port(), 3000);
+        assert_eq!(args.max_batch, 32);
+    }
+
+    #[test]
+    fn test_export_anonymize() {
+        let cli = Cli::try_parse_from(["app", "export", "--tag", "research", "--anonymize", "-o", "out.jsonl"]).unwrap();
+        let Some(Command::Export(args)) = cli.command else { panic!("expected export") };
+        assert_eq!(args.tag.as_deref(), Some("research"));
+        assert!(args.anonymize);
+        assert!(Cli::try_parse_from(["app", "export", "--tag", "work", "--session", "x"]).is_err());
+    }
+
+    #[test]
+    fn test_sessions_listing() {
+        let cli = Cli::try_parse_from(["app", "sessions", "--tag", "#Sleep", "--generate-titles"]).unwrap();
+        let Some(Command::Sessions(args)) = cli.command else { panic!("expected sessions") };
+        assert_eq!(args.tag.as_deref(), Some("sleep"));
+        assert!(args.generate_titles && !args.json);
+    }
+
+    #[test]
+    fn test_compare_prompts() {
+        let cli = Cli::try_parse_from(["app", "compare-prompts", "inputs.jsonl", "--a", "warm.json", "--b", "brief.json"]).unwrap();
+        let Some(Command::ComparePrompts(args)) = cli.command else { panic!("expected compare-prompts") };
+        assert_eq!((args.a.to_str(), args.b.to_str()), (Some("warm.json"), Some("brief.json")));
+        assert_eq!(args.width, 100);
+        assert!(Cli::try_parse_from(["app", "compare-prompts", "inputs.jsonl", "--a", "warm.json"]).is_err());
+    }
+
+    #[test]
+    fn test_simulate_runs() {
+        let cli = Cli::try_parse_from(["app", "simulate", "--runs", "5", "--script", "calming.json"]).unwrap();
+        let Some(Command::Simulate(args)) = cli.command else { panic!("expected simulate") };
+        assert_eq!((args.runs, args.script.as_deref().and_then(|p| p.to_str())), (5, Some("calming.json")));
+        assert!(Cli::try_parse_from(["app", "simulate", "--runs", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_loadtest_defaults() {
+        let cli = Cli::try_parse_from(["app", "loadtest", "--endpoint", "live", "--concurrency", "32"]).unwrap();
+        let Some(Command::Loadtest(args)) = cli.command else { panic!("expected loadtest") };
+        assert_eq!((args.endpoint, args.concurrency, args.requests), (LoadEndpoint::Live, 32, 200));
+        assert!(Cli::try_parse_from(["app", "loadtest", "--target", "http://127.0.0.1:3000", "--mock-latency-ms", "50"]).is_err());
+    }
+
+    #[cfg(feature = "keyring")]
+    #[test]
+    fn test_login_delete() {
+        let cli = Cli::try_parse_from(["app", "login", "--delete"]).unwrap();
+        let Some(Command::Login(args)) = cli.command else { panic!("expected login") };
+        assert!(args.delete);
+    }
+
+    #[test]
+    fn test_report_weekly_conflicts_with_from() {
+        let result = Cli::try_parse_from(["app", "report", "--weekly", "--from", "2026-01-01"]);
+        assert!(result.is_err());
+    }
+}
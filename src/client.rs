@@ -0,0 +1,319 @@
+//! Typed client for the HTTP server mode
+
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The server answered with an error status
+    #[error("server returned {status}: {message}")]
+    Api { status: u16, message: String },
+
+    #[error("invalid stream event: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Sentiment {
+    Positive,
+    Negative,
+    Neutral,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Classification {
+    pub sentiment: Sentiment,
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Trend {
+    Improving,
+    Declining,
+    Stable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Strategy {
+    Empathetic,
+    Encouraging,
+    Neutral,
+    Cheerful,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandoffReason {
+    Crisis,
+    SustainedNegativity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Forecast {
+    pub sentiment: Sentiment,
+    pub score: f32,
+    pub confidence: f32,
+}
+
+/// Confidence-weighted sentiment over the session's latest messages
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Mood {
+    pub sentiment: Sentiment,
+    pub score: f32,
+    pub confidence: f32,
+    pub messages: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Usage {
+    pub prompt: usize,
+    pub completion: usize,
+}
+
+/// One answered message, as returned by `POST /sessions/{id}/messages`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Turn {
+    pub emotion: Classification,
+    pub trend: Trend,
+    pub forecast: Option<Forecast>,
+    pub strategy: Strategy,
+    pub suppressed_strategy: Option<Strategy>,
+    /// Set while the session waits for an operator; `response` is then a fixed notice
+    pub handoff: Option<HandoffReason>,
+    pub blocked_topic: Option<String>,
+    #[serde(default)]
+    pub break_suggested: bool,
+    pub response: String,
+    pub usage: Option<Usage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+    pub timestamp: i64,
+    pub emotion: Option<Classification>,
+    pub strategy: Option<Strategy>,
+    /// Who wrote it, in an imported transcript
+    #[serde(default)]
+    pub speaker: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Session {
+    pub session_id: String,
+    pub tenant: String,
+    pub messages: Vec<Message>,
+    pub trend: Trend,
+    pub mood: Option<Mood>,
+    pub forecast: Option<Forecast>,
+    pub handoff: Option<HandoffReason>,
+}
+
+/// What a streamed turn delivers, in order: `Typing`, `Token`s, then `Turn` or `Error`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    Typing,
+    Token { text: String },
+    Turn(Turn),
+    Error { status: u16, error: String },
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+/// Talks to a server started with `serve`
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    tenant: Option<String>,
+}
+
+impl Client {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: None,
+            tenant: None,
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    /// Sends `x-tenant` with every request; the server's default tenant is used otherwise
+    pub fn with_tenant(mut self, tenant: &str) -> Self {
+        self.tenant = Some(tenant.to_string());
+        self
+    }
+
+    pub async fn classify(&self, text: &str) -> Result<Classification, ClientError> {
+        let response = self.request(reqwest::Method::POST, "/classify").json(&json!({ "text": text })).send().await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    pub async fn chat_turn(&self, session_id: &str, text: &str) -> Result<Turn, ClientError> {
+        let response = self
+            .request(reqwest::Method::POST, &format!("/sessions/{}/messages", session_id))
+            .json(&json!({ "text": text }))
+            .send()
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    pub async fn get_history(&self, session_id: &str) -> Result<Session, ClientError> {
+        let response = self.request(reqwest::Method::GET, &format!("/sessions/{}", session_id)).send().await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    /// A turn whose reply arrives token by token, over server-sent events
+    pub async fn chat_turn_stream(
+        &self,
+        session_id: &str,
+        text: &str,
+    ) -> Result<impl Stream<Item = Result<StreamEvent, ClientError>> + use<>, ClientError> {
+        let response = self
+            .request(reqwest::Method::POST, &format!("/sessions/{}/messages/stream", session_id))
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .json(&json!({ "text": text }))
+            .send()
+            .await?;
+        let body = check(response).await?.bytes_stream();
+
+        let events = futures::stream::unfold((body, String::new()), |(mut body, mut buffer)| async move {
+            loop {
+                if let Some(end) = buffer.find("\n\n") {
+                    let frame: String = buffer.drain(..end + 2).collect();
+                    match parse_frame(&frame) {
+                        Some(event) => return Some((event, (body, buffer))),
+                        None => continue,
+                    }
+                }
+                match body.next().await? {
+                    Ok(bytes) => buffer.push_str(&String::from_utf8_lossy(&bytes)),
+                    Err(e) => return Some((Err(e.into()), (body, buffer))),
+                }
+            }
+        });
+        Ok(events)
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let mut request = self.http.request(method, format!("{}{}", self.base_url, path));
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+        if let Some(tenant) = &self.tenant {
+            request = request.header("x-tenant", tenant);
+        }
+        request
+    }
+}
+
+async fn check(response: reqwest::Response) -> Result<reqwest::Response, ClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<ErrorBody>(&body).map(|b| b.error).unwrap_or(body);
+    Err(ClientError::Api { status: status.as_u16(), message })
+}
+
+/// The event in one server-sent event frame; keep-alive comments have none
+fn parse_frame(frame: &str) -> Option<Result<StreamEvent, ClientError>> {
+    let data: Vec<&str> = frame
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    if data.is_empty() {
+        return None;
+    }
+    Some(serde_json::from_str(&data.join("\n")).map_err(ClientError::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::{get, post};
+
+    const TURN: &str = r#"{"emotion":{"sentiment":"Negative","confidence":0.8},"trend":"Declining",
+        "strategy":"Empathetic","response":"That sounds hard.","usage":{"prompt":120,"completion":9}}"#;
+
+    async fn serve(app: Router) -> Client {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        Client::new(&format!("http://{}/", addr))
+    }
+
+    #[tokio::test]
+    async fn test_chat_turn_and_api_errors() {
+        let app = Router::new()
+            .route("/sessions/{id}/messages", post(|headers: HeaderMap| async move {
+                assert_eq!(headers["authorization"], "Bearer secret");
+                assert_eq!(headers["x-tenant"], "acme");
+                TURN
+            }))
+            .route("/sessions/{id}", get(|| async { (StatusCode::NOT_FOUND, r#"{"error":"session 'x' not found"}"#) }));
+        let client = serve(app).await.with_api_key("secret").with_tenant("acme");
+
+        let turn = client.chat_turn("s1", "rough day").await.unwrap();
+        assert_eq!(turn.strategy, Strategy::Empathetic);
+        assert_eq!(turn.usage, Some(Usage { prompt: 120, completion: 9 }));
+        assert!(!turn.break_suggested);
+
+        match client.get_history("x").await {
+            Err(ClientError::Api { status, message }) => {
+                assert_eq!(status, 404);
+                assert_eq!(message, "session 'x' not found");
+            }
+            other => panic!("expected an API error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_parses_events() {
+        let body = format!(
+            "event: typing\ndata: {{\"type\":\"typing\"}}\n\n:\n\nevent: token\ndata: {{\"type\":\"token\",\"text\":\"That \"}}\n\n\
+             event: turn\ndata: {{\"type\":\"turn\",{}\n\n",
+            &TURN.replace('\n', "")[1..]
+        );
+        let app = Router::new().route("/sessions/{id}/messages/stream", post(move || async move { body }));
+        let client = serve(app).await;
+
+        let events: Vec<StreamEvent> = client
+            .chat_turn_stream("s1", "rough day")
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0], StreamEvent::Typing);
+        assert_eq!(events[1], StreamEvent::Token { text: "That ".to_string() });
+        assert!(matches!(&events[2], StreamEvent::Turn(turn) if turn.response == "That sounds hard."));
+    }
+}
@@ -0,0 +1,202 @@
+use anyhow::{Context, Result};
+use rig::providers::openai;
+use serde::Serialize;
+use crate::cli::{AnalyzeArgs, GlobalArgs};
+use crate::agents::{EmotionDetector, PromptDebug};
+use crate::{Config, Sentiment, SentimentClassification};
+
+/// Characters of a passage shown in the text report
+const PREVIEW_CHARS: usize = 160;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Passage {
+    pub index: usize,
+    pub text: String,
+    pub classification: SentimentClassification,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DocumentProfile {
+    pub paragraphs: usize,
+    pub positive: usize,
+    pub neutral: usize,
+    pub negative: usize,
+    /// Paragraphs the provider could not classify
+    pub failed: usize,
+    /// Confidence-weighted sentiment score from -1 (negative) to 1 (positive)
+    pub mean_score: f32,
+    pub most_negative: Option<Passage>,
+    pub most_positive: Option<Passage>,
+}
+
+/// Blank-line separated paragraphs, trimmed, without empty ones
+pub fn split_paragraphs(document: &str) -> Vec<&str> {
+    let mut paragraphs = Vec::new();
+    let mut start = None;
+    let mut end = 0;
+
+    for (offset, line) in line_offsets(document) {
+        if line.trim().is_empty() {
+            if let Some(s) = start.take() {
+                paragraphs.push(document[s..end].trim());
+            }
+        } else {
+            start.get_or_insert(offset);
+            end = offset + line.len();
+        }
+    }
+    if let Some(s) = start {
+        paragraphs.push(document[s..end].trim());
+    }
+    paragraphs
+}
+
+fn line_offsets(document: &str) -> impl Iterator<Item = (usize, &str)> {
+    document.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, line))
+    })
+}
+
+pub fn profile(passages: &[Passage], failed: usize) -> DocumentProfile {
+    let count = |sentiment| passages.iter().filter(|p| p.classification.sentiment == sentiment).count();
+    let weighted = |p: &Passage| p.classification.score() * p.classification.confidence;
+
+    let mean_score = if passages.is_empty() {
+        0.0
+    } else {
+        passages.iter().map(weighted).sum::<f32>() / passages.len() as f32
+    };
+    let strongest = |sentiment| {
+        passages
+            .iter()
+            .filter(|p| p.classification.sentiment == sentiment)
+            .max_by(|a, b| a.classification.confidence.total_cmp(&b.classification.confidence))
+            .cloned()
+    };
+
+    DocumentProfile {
+        paragraphs: passages.len() + failed,
+        positive: count(Sentiment::Positive),
+        neutral: count(Sentiment::Neutral),
+        negative: count(Sentiment::Negative),
+        failed,
+        mean_score,
+        most_negative: strongest(Sentiment::Negative),
+        most_positive: strongest(Sentiment::Positive),
+    }
+}
+
+impl DocumentProfile {
+    pub fn render_text(&self) -> String {
+        let share = |n: usize| if self.paragraphs == 0 { 0.0 } else { n as f32 * 100.0 / self.paragraphs as f32 };
+
+        let mut out = format!("📄 {} paragraphs\n", self.paragraphs);
+        out.push_str(&format!("   Positive: {} ({:.0}%)\n", self.positive, share(self.positive)));
+        out.push_str(&format!("   Neutral:  {} ({:.0}%)\n", self.neutral, share(self.neutral)));
+        out.push_str(&format!("   Negative: {} ({:.0}%)\n", self.negative, share(self.negative)));
+        if self.failed > 0 {
+            out.push_str(&format!("   Not classified: {}\n", self.failed));
+        }
+        out.push_str(&format!("📊 Overall score: {:+.2} (-1 negative, +1 positive)\n", self.mean_score));
+
+        for (label, passage) in [("Most negative", &self.most_negative), ("Most positive", &self.most_positive)] {
+            if let Some(passage) = passage {
+                out.push_str(&format!(
+                    "\n{} (paragraph {}, confidence {:.2}):\n   {}\n",
+                    label,
+                    passage.index + 1,
+                    passage.classification.confidence,
+                    preview(&passage.text)
+                ));
+            }
+        }
+        out
+    }
+}
+
+fn preview(text: &str) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() <= PREVIEW_CHARS {
+        return flat;
+    }
+    let cut: String = flat.chars().take(PREVIEW_CHARS).collect();
+    format!("{}…", cut.trim_end())
+}
+
+pub async fn run(config: &Config, global: &GlobalArgs, args: &AnalyzeArgs) -> Result<()> {
+    let document = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("Cannot read {}", args.file.display()))?;
+    let paragraphs = split_paragraphs(&document);
+    if paragraphs.is_empty() {
+        anyhow::bail!("{} has no text to analyze", args.file.display());
+    }
+
+    let client = openai::Client::from_url(&config.api_key, &config.base_url);
+    let detector = EmotionDetector::new(client, &config.model)
+        .with_dry_run(config.dry_run)
+        .with_repair_attempts(config.repair_attempts)
+        .with_self_consistency(config.self_consistency)
+        .with_fallback(config.fallback)
+        .with_examples(config.emotion_examples.clone())
+        .with_cache(config.classification_cache(None, &config.base_url))
+        .with_prompt_debug(PromptDebug::new(global.debug_prompts));
+
+    let mut passages = Vec::new();
+    let mut failed = 0;
+    for (index, (text, result)) in paragraphs.iter().zip(detector.analyze_batch(&paragraphs).await).enumerate() {
+        match result {
+            Ok(classification) => passages.push(Passage { index, text: text.to_string(), classification }),
+            Err(e) => {
+                eprintln!("❌ Paragraph {}: {}", index + 1, e);
+                failed += 1;
+            }
+        }
+    }
+
+    let profile = profile(&passages, failed);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&profile)?);
+    } else {
+        print!("{}", profile.render_text());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passage(index: usize, sentiment: Sentiment, confidence: f32) -> Passage {
+        Passage {
+            index,
+            text: format!("paragraph {}", index),
+            classification: SentimentClassification { sentiment, confidence, labels: Vec::new(), is_fallback: false },
+        }
+    }
+
+    #[test]
+    fn test_split_paragraphs() {
+        let document = "First line\nstill first.\n\n\n  Second.  \n   \nThird\n";
+        assert_eq!(split_paragraphs(document), ["First line\nstill first.", "Second.", "Third"]);
+        assert!(split_paragraphs("\n \n").is_empty());
+    }
+
+    #[test]
+    fn test_profile() {
+        let passages = [
+            passage(0, Sentiment::Negative, 0.6),
+            passage(1, Sentiment::Negative, 0.9),
+            passage(2, Sentiment::Positive, 0.8),
+            passage(3, Sentiment::Neutral, 0.7),
+        ];
+        let profile = profile(&passages, 1);
+
+        assert_eq!(profile.paragraphs, 5);
+        assert_eq!((profile.positive, profile.neutral, profile.negative), (1, 1, 2));
+        assert!((profile.mean_score - (-0.6 - 0.9 + 0.8) / 4.0).abs() < 1e-6);
+        assert_eq!(profile.most_negative.unwrap().index, 1);
+        assert_eq!(profile.most_positive.unwrap().index, 2);
+    }
+}
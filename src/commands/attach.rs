@@ -0,0 +1,130 @@
+//! Inlines `@path` file references into a chat message
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use crate::agents::SummaryAgent;
+
+/// Files above this size are refused rather than read
+pub const MAX_FILE_BYTES: u64 = 256 * 1024;
+
+/// Rough token budget for one attachment; longer ones are summarized
+pub const TOKEN_BUDGET: usize = 2_000;
+
+/// Words asked for when summarizing an attachment over budget
+const SUMMARY_WORDS: usize = 300;
+
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub name: String,
+    pub content: String,
+    /// Summarized, or truncated in a dry run, to fit the token budget
+    pub shortened: bool,
+}
+
+/// `@` words in `input` that name an existing file; anything else (a mention,
+/// an email address) is left alone
+pub fn attachment_paths(input: &str) -> Vec<PathBuf> {
+    input
+        .split_whitespace()
+        .filter_map(|word| word.strip_prefix('@'))
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// About four characters per token for English text, which is close enough
+/// to decide when to summarize
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Reads the files referenced in `input`, summarizing any over the token
+/// budget; without a summarizer (dry run) long files are truncated
+pub async fn load(input: &str, summarizer: Option<&SummaryAgent>) -> Result<Vec<Attachment>> {
+    let mut attachments = Vec::new();
+    for path in attachment_paths(input) {
+        let content = read(&path)?;
+        let name = path.display().to_string();
+
+        if estimate_tokens(&content) <= TOKEN_BUDGET {
+            attachments.push(Attachment { name, content, shortened: false });
+            continue;
+        }
+
+        let content = match summarizer {
+            Some(summarizer) => summarizer
+                .condense(&content, SUMMARY_WORDS)
+                .await
+                .with_context(|| format!("Failed to summarize {}", name))?,
+            None => content.chars().take(TOKEN_BUDGET * 4).collect(),
+        };
+        attachments.push(Attachment { name, content, shortened: true });
+    }
+    Ok(attachments)
+}
+
+fn read(path: &Path) -> Result<String> {
+    let size = path.metadata().with_context(|| format!("Cannot read {}", path.display()))?.len();
+    if size > MAX_FILE_BYTES {
+        anyhow::bail!("{} is {} KiB, attachments are limited to {} KiB", path.display(), size / 1024, MAX_FILE_BYTES / 1024);
+    }
+    std::fs::read_to_string(path).with_context(|| format!("Cannot read {} as UTF-8 text", path.display()))
+}
+
+/// The message as sent to the model: the user's text followed by each file
+pub fn inline(input: &str, attachments: &[Attachment]) -> String {
+    let mut message = input.to_string();
+    for attachment in attachments {
+        let label = if attachment.shortened { "shortened attached file" } else { "attached file" };
+        message.push_str(&format!(
+            "\n\n--- {} {} ---\n{}\n--- end of {} ---",
+            label,
+            attachment.name,
+            attachment.content.trim_end(),
+            attachment.name
+        ));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_inlines_existing_files_only() {
+        let dir = std::env::temp_dir().join(format!("tce-attach-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let diary = dir.join("diary.txt");
+        std::fs::write(&diary, "Today was long.\n").unwrap();
+
+        let input = format!("Read this @{} and ping @alice or bob@example.com", diary.display());
+        let attachments = load(&input, None).await.unwrap();
+        assert_eq!(attachments.len(), 1);
+
+        let message = inline(&input, &attachments);
+        assert!(message.starts_with(&input));
+        assert!(message.contains("--- attached file"));
+        assert!(message.contains("Today was long.\n--- end of"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_long_files_are_cut_to_budget_without_summarizer() {
+        let dir = std::env::temp_dir().join(format!("tce-attach-long-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let long = dir.join("long.txt");
+        std::fs::write(&long, "word ".repeat(TOKEN_BUDGET * 2)).unwrap();
+        let huge = dir.join("huge.txt");
+        std::fs::write(&huge, vec![b'a'; MAX_FILE_BYTES as usize + 1]).unwrap();
+
+        let attachments = load(&format!("@{}", long.display()), None).await.unwrap();
+        assert!(attachments[0].shortened);
+        assert!(estimate_tokens(&attachments[0].content) <= TOKEN_BUDGET);
+
+        assert!(load(&format!("@{}", huge.display()), None).await.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use rig::providers::openai;
+use serde::Serialize;
+use crate::cli::CategorizeArgs;
+use crate::agents::{TaxonomyClassification, TaxonomyClassifier};
+use crate::Config;
+use super::classify;
+
+/// One line of batch output
+#[derive(Debug, Serialize)]
+struct Line<'a> {
+    index: usize,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    classification: Option<&'a TaxonomyClassification>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// The non-empty lines of a batch file, trimmed
+pub fn batch_lines(content: &str) -> Vec<&str> {
+    content.lines().map(str::trim).filter(|line| !line.is_empty()).collect()
+}
+
+pub async fn run(config: &Config, args: &CategorizeArgs) -> Result<()> {
+    let Some(taxonomy) = config.taxonomy.clone() else {
+        anyhow::bail!("set TAXONOMY_FILE to the labels to classify against");
+    };
+
+    let client = openai::Client::from_url(&config.api_key, &config.base_url);
+    let classifier = TaxonomyClassifier::new(client, &config.model, taxonomy)
+        .with_dry_run(config.dry_run)
+        .with_repair_attempts(config.repair_attempts);
+
+    let Some(path) = &args.file else {
+        let text = classify::input(args.text.as_deref(), std::io::stdin())?;
+        let classification = classifier.classify(&text).await.context("Classification failed")?;
+        println!("{}", serde_json::to_string(&classification)?);
+        return Ok(());
+    };
+
+    let content = std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let texts = batch_lines(&content);
+    if texts.is_empty() {
+        anyhow::bail!("{} has no text to classify", path.display());
+    }
+
+    let results = classifier.classify_batch(&texts).await;
+    let mut failed = 0;
+    for (index, result) in results.iter().enumerate() {
+        let line = match result {
+            Ok(classification) => Line { index, classification: Some(classification), error: None },
+            Err(e) => {
+                failed += 1;
+                Line { index, classification: None, error: Some(e.to_string()) }
+            }
+        };
+        println!("{}", serde_json::to_string(&line)?);
+    }
+    if failed > 0 {
+        anyhow::bail!("{} of {} texts could not be classified", failed, texts.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_lines_and_output() {
+        assert_eq!(batch_lines("refund please\n\n  app crashes  \n"), ["refund please", "app crashes"]);
+
+        let classification = TaxonomyClassification { label: "billing".to_string(), confidence: 0.9, scores: Vec::new() };
+        let line = Line { index: 0, classification: Some(&classification), error: None };
+        assert_eq!(serde_json::to_string(&line).unwrap(), r#"{"index":0,"label":"billing","confidence":0.9}"#);
+        let line = Line { index: 1, classification: None, error: Some("boom".to_string()) };
+        assert_eq!(serde_json::to_string(&line).unwrap(), r#"{"index":1,"error":"boom"}"#);
+    }
+}
@@ -0,0 +1,893 @@
+use anyhow::Result;
+use rig::providers::openai;
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::{Cmd, Editor, ExternalPrinter, KeyCode, KeyEvent, Modifiers};
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use crate::Config;
+use crate::budget::{Budget, BudgetFallback};
+use crate::cli::GlobalArgs;
+use crate::events::{Event, EventBus};
+use crate::handoff::HandoffRequest;
+use crate::health::check_provider;
+use crate::profiles::{self, Provider, ProviderProfile};
+use crate::agents::summary::TITLE_MESSAGES;
+use crate::agents::{Assessment, ChatAgent, TokenUsage, CircuitBreaker, Degradation, EmotionDetector, KeyphraseAgent, LengthPreference, PromptDebug, SummaryAgent, TopicGuard, Verbosity};
+use crate::models::{Message, MessageRole};
+use crate::render::{OutputRenderer, renderer};
+use crate::report::user_text;
+use crate::onboarding::{self, OnboardingQuestion, OnboardingScript};
+use crate::opener::{LastSession, OpenerContext};
+use crate::state::{CHECK_IN_MESSAGE, ConversationManager, ConversationState, EmotionBaseline, UserProfile};
+use crate::storage::{self, Autosave, StoredSession, TurnLog};
+use crate::turn::{StrategyMode, StrategyPolicy, run_turn};
+use super::attach;
+use super::recap::SessionRecap;
+use super::slash::{self, ChatHelper, SlashCommand};
+
+/// Key phrases shown by `/stats`
+const STATS_KEYPHRASES: usize = 5;
+
+pub async fn run(config: &Config, global: &GlobalArgs) -> Result<()> {
+    let degradation = Arc::new(Degradation::default());
+    let debug = PromptDebug::new(global.debug_prompts);
+    let length = LengthPreference::new(config.response_length);
+    let mut provider = config.provider();
+    let mut agents = Agents::new(config, global, &provider, &degradation, &debug, &length);
+    if !global.skip_health_check && !config.dry_run {
+        check_provider(&agents.client, &provider.base_url, &provider.model).await?;
+    }
+
+    let mut output = renderer(global.output_format, global.verbosity());
+    output.banner(&provider.model, config.dry_run)?;
+    if global.reflect && agents.summarizer.is_none() {
+        output.notice("⚠️  --reflect needs the model, so this dry run's recap has no reflection\n")?;
+    }
+
+    let handoff = config.handoff_backend();
+    let events = EventBus::default().with(|event: &Event| {
+        if let Event::TrendChanged { from, to } = event {
+            tracing::info!(?from, ?to, "emotional trend changed");
+        }
+    });
+    let mut state_manager = ConversationManager::new().with_trend_analyzer(config.trend_analyzer.clone());
+    state_manager.set_events(events.clone());
+    let mut started_at = chrono::Utc::now().timestamp();
+    let mut pinned = None;
+    let mut tags: Vec<String> = Vec::new();
+    let mut title: Option<String> = None;
+    let mut bookmarks: Vec<usize> = Vec::new();
+    let mut alternatives: Vec<String> = Vec::new();
+    let mut assessment: Option<Assessment> = None;
+    let mut usage: Option<TokenUsage> = None;
+    let mut budget = config.budget.clone().map(|policy| {
+        let path = (!config.dry_run).then(|| config.sessions_dir.join("spend.json"));
+        Budget::open(policy, path, chrono::Utc::now().date_naive())
+    });
+    // Set once a limit is reached, so the fallback is only switched to once
+    let mut over_budget = false;
+    let mut replies_paused = false;
+
+    let store = match storage::open(config.database_url.as_deref(), &config.sessions_dir).await {
+        Ok(store) => Some(store),
+        Err(e) => {
+            output.error(&e.context("Session storage unavailable"))?;
+            None
+        }
+    };
+    let saved = match &store {
+        Some(store) => store.load_all().await.unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let mut autosave = Autosave::new(if config.dry_run { 0 } else { config.autosave_every });
+    let turn_log = (!config.dry_run).then(|| TurnLog::new(config.sessions_dir.join("turns.wal")));
+    // Messages of the live session already in the turn log, and whether the
+    // next write replaces what it holds
+    let mut logged = 0;
+    let mut log_reset = false;
+    let baseline = if global.no_baseline {
+        None
+    } else {
+        usual_mood(&saved)
+    };
+    if let Some(baseline) = baseline {
+        output.notice(&format!(
+            "📐 Usual mood {:+.2} from {} past messages (--no-baseline to ignore)\n",
+            baseline.mean, baseline.samples
+        ))?;
+    }
+    state_manager.set_baseline(baseline);
+    let mut known_tags: Vec<String> = saved.iter().flat_map(|s| s.tags.iter().cloned()).collect();
+    known_tags.sort();
+    known_tags.dedup();
+
+    let last_session = last_own_session(&saved).cloned();
+
+    let mut editor = Editor::new()?;
+    editor.set_helper(Some(ChatHelper {
+        session_ids: saved.into_iter().map(|s| s.id).collect(),
+        tags: known_tags,
+        providers: config.profiles.iter().map(|p| p.name.clone()).collect(),
+    }));
+    editor.bind_sequence(KeyEvent(KeyCode::Enter, Modifiers::ALT), Cmd::Newline);
+    let history_path = config.sessions_dir.join("input_history.txt");
+    // Missing on first run
+    let _ = editor.load_history(&history_path);
+
+    let unfinished = match turn_log.as_ref().map(TurnLog::recover) {
+        Some(Ok(unfinished)) => unfinished,
+        Some(Err(e)) => {
+            output.error(&e.context("Cannot read the turn log"))?;
+            None
+        }
+        None => None,
+    };
+    if let (Some(mut session), Some(turn_log)) = (unfinished, &turn_log) {
+        if let Some(store) = &store
+            && let Ok(checkpoint) = store.load(&session.id).await
+        {
+            session.title = checkpoint.title;
+            session.tags = checkpoint.tags;
+            session.bookmarks = checkpoint.bookmarks;
+        }
+        output.notice(&format!("♻️  Session {} did not finish ({} messages)", session.id, session.messages.len()))?;
+        let resume = std::io::stdin().is_terminal()
+            && editor.readline("Resume it? [Y/n] ").is_ok_and(|answer| !answer.trim().to_ascii_lowercase().starts_with('n'));
+        if resume {
+            let count = session.messages.len();
+            state_manager.restore(session_state(session.messages, session.started_at));
+            logged = count;
+            started_at = session.started_at;
+            title = session.title;
+            tags = session.tags;
+            bookmarks = session.bookmarks;
+            output.notice(&format!("📂 Continuing session {} ({} messages)\n", session.id, count))?;
+        } else if let Some(store) = &store {
+            match store.save(&session).await {
+                Ok(()) => {
+                    output.notice(&format!("💾 Kept it as session {}, /load {} continues it\n", session.id, session.id))?;
+                    if let Err(e) = turn_log.clear() {
+                        tracing::warn!(error = %e, "failed to clear the turn log");
+                    }
+                }
+                Err(e) => output.error(&e.context("Failed to save the unfinished session"))?,
+            }
+        }
+    }
+
+    // Fresh sessions open with the onboarding questions; resumed ones keep the saved profile
+    let mut profile = None;
+    if let Some(script) = &config.onboarding {
+        let path = config.sessions_dir.join("profile.json");
+        let saved = onboarding::load_profile(&path).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "failed to read the user profile");
+            UserProfile::default()
+        });
+        profile = Some(if state_manager.get_history().is_empty() && std::io::stdin().is_terminal() {
+            onboard(script, saved, &mut editor, output.as_mut(), (!config.dry_run).then_some(path.as_path()))?
+        } else {
+            saved
+        });
+    }
+    if let Some(profile) = &profile {
+        state_manager.set_profile(profile.clone());
+    }
+
+    if global.speak_first && state_manager.get_history().is_empty() {
+        let mut context = OpenerContext { profile: profile.clone(), last_session: None };
+        if let Some(session) = &last_session {
+            let mut last = LastSession::of(session, started_at);
+            if let Some(summarizer) = &agents.summarizer {
+                match summarizer.summarize(&user_text(&session.messages)).await {
+                    Ok(summary) => last.summary = Some(summary.summary),
+                    Err(e) => tracing::warn!(error = %e, "failed to summarize the last session"),
+                }
+            }
+            context.last_session = Some(last);
+        }
+        let opener = agents.chat_agent.open(&context).await.unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "failed to write an opener");
+            context.fallback()
+        });
+        output.opener(&opener)?;
+        state_manager.add_message(MessageRole::Assistant, &opener);
+    }
+
+    let check_in = match config.check_in {
+        Some(policy) => match editor.create_external_printer() {
+            Ok(printer) => Some((policy, CheckInTimer::spawn(printer))),
+            Err(e) => {
+                tracing::warn!(error = %e, "terminal cannot print while reading input, check-ins are off");
+                None
+            }
+        },
+        None => None,
+    };
+
+    loop {
+        if !over_budget
+            && let Some(budget) = &budget
+            && let Some(limit) = budget.exceeded()
+        {
+            over_budget = true;
+            match budget_fallback(config, &budget.policy().fallback, &provider) {
+                Ok(Some(next)) => {
+                    agents = Agents::new(config, global, &next, &degradation, &debug, &length);
+                    provider = next;
+                    output.notice(&format!("💸 Reached the {}, answering with {} from now on\n", limit, provider.model))?;
+                }
+                Ok(None) => {
+                    replies_paused = true;
+                    output.notice(&format!("💸 Reached the {}, replies are paused\n", limit))?;
+                }
+                Err(e) => {
+                    replies_paused = true;
+                    output.error(&e.context(format!("Reached the {} and cannot switch to the fallback", limit)))?;
+                }
+            }
+        }
+        if let Some((policy, timer)) = &check_in
+            && state_manager.check_in_due(policy)
+        {
+            timer.arm(policy.idle, output.check_in(CHECK_IN_MESSAGE));
+        }
+        let line = read_message(&mut editor, output.prompt());
+        if let Some((_, timer)) = &check_in
+            && timer.disarm()
+        {
+            state_manager.add_check_in();
+        }
+        let line = match line {
+            Ok(line) => line,
+            // Ctrl+C discards the line being typed
+            Err(ReadlineError::Interrupted) => continue,
+            // Ctrl+D ends the chat like `quit`
+            Err(ReadlineError::Eof) => {
+                output.notice("👋 Goodbye!")?;
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let input = slash::unwrap_block(&line);
+
+        if input.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line.trim())?;
+
+        if input.eq_ignore_ascii_case("quit") || input.eq_ignore_ascii_case("exit") {
+            output.notice("👋 Goodbye!")?;
+            break;
+        }
+
+        match slash::parse(input) {
+            None => {}
+            Some(Err(usage)) => {
+                output.notice(&format!("{}\n", usage))?;
+                continue;
+            }
+            Some(Ok(SlashCommand::Help)) => {
+                output.notice(&format!("{}\n", slash::HELP))?;
+                continue;
+            }
+            Some(Ok(SlashCommand::Stats)) => {
+                output.notice(&session_stats(&state_manager, &agents.keyphrases, &degradation).await)?;
+                continue;
+            }
+            Some(Ok(SlashCommand::Debug(enabled))) => {
+                if let Some(enabled) = enabled {
+                    debug.set(enabled);
+                }
+                let status = if debug.enabled() { "on" } else { "off" };
+                output.notice(&format!("🔍 Prompt debugging is {}\n", status))?;
+                continue;
+            }
+            Some(Ok(SlashCommand::Brief)) => {
+                if length.get() == Some(Verbosity::Brief) {
+                    length.set(config.response_length.filter(|&v| v != Verbosity::Brief));
+                    output.notice("✂️  Replies are back to their usual length\n")?;
+                } else {
+                    length.set(Some(Verbosity::Brief));
+                    output.notice("✂️  Keeping replies brief until /brief again\n")?;
+                }
+                continue;
+            }
+            Some(Ok(SlashCommand::Strategy(strategy))) => {
+                pinned = strategy;
+                match pinned {
+                    Some(strategy) => output.notice(&format!("🎯 Answering with {:?} until /strategy auto\n", strategy))?,
+                    None => output.notice("🎯 Strategy follows your emotions again\n")?,
+                }
+                continue;
+            }
+            Some(Ok(SlashCommand::Load(id))) => {
+                let Some(store) = &store else {
+                    output.notice("No session storage available\n")?;
+                    continue;
+                };
+                match store.load(&id).await {
+                    Ok(session) => {
+                        let count = session.messages.len();
+                        state_manager.restore(session_state(session.messages, session.started_at));
+                        if let Some(profile) = &profile {
+                            state_manager.set_profile(profile.clone());
+                        }
+                        autosave.saved(count);
+                        log_reset = true;
+                        started_at = session.started_at;
+                        title = session.title;
+                        tags = session.tags;
+                        bookmarks = session.bookmarks;
+                        output.notice(&format!("📂 Continuing session {} ({} messages)\n", session.id, count))?;
+                    }
+                    Err(e) => output.error(&e)?,
+                }
+                continue;
+            }
+            Some(Ok(SlashCommand::Tag(tag))) => {
+                if let Some(tag) = tag
+                    && !tags.contains(&tag)
+                {
+                    tags.push(tag);
+                }
+                if tags.is_empty() {
+                    output.notice("🏷  No tags yet, add one with /tag <tag>\n")?;
+                } else {
+                    output.notice(&format!("🏷  Tags: {}\n", tags.join(", ")))?;
+                }
+                continue;
+            }
+            Some(Ok(SlashCommand::Untag(tag))) => {
+                tags.retain(|t| *t != tag);
+                output.notice(&format!("🏷  Removed {}\n", tag))?;
+                continue;
+            }
+            Some(Ok(SlashCommand::Bookmark(number))) => {
+                let history = state_manager.get_history();
+                let index = match number {
+                    None => history.len().checked_sub(1),
+                    Some(n) => (n <= history.len()).then(|| n - 1),
+                };
+                match index {
+                    Some(index) => {
+                        if !bookmarks.contains(&index) {
+                            bookmarks.push(index);
+                        }
+                        output.notice(&format!("📌 Bookmarked message {}: {}\n", index + 1, one_line(&history[index].content)))?;
+                    }
+                    None => output.notice(&format!("This session has {} messages\n", history.len()))?,
+                }
+                continue;
+            }
+            Some(Ok(SlashCommand::Alts)) => {
+                if alternatives.is_empty() {
+                    let hint = if global.candidates > 1 { "" } else { ", start with --candidates 3 to get some" };
+                    output.notice(&format!("💡 No alternative replies{}\n", hint))?;
+                }
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    output.notice(&format!("💡 Alternative {}:\n{}\n", i + 1, alternative))?;
+                }
+                continue;
+            }
+            Some(Ok(SlashCommand::Why)) => {
+                match &assessment {
+                    Some(assessment) => output.notice(&format!(
+                        "🧠 Emotion: {}\n🧠 Strategy: {}\n",
+                        assessment.emotion, assessment.strategy
+                    ))?,
+                    None if global.explain => output.notice("🧠 Nothing to explain yet\n")?,
+                    None => output.notice("🧠 No assessment, start with --explain to get one\n")?,
+                }
+                continue;
+            }
+            Some(Ok(SlashCommand::Provider(None))) => {
+                output.notice(&provider_list(&config.profiles, &provider))?;
+                continue;
+            }
+            Some(Ok(SlashCommand::Provider(Some(name)))) => {
+                let next = match profiles::find(&config.profiles, &name).and_then(|p| p.resolve(config.dry_run)) {
+                    Ok(next) => next,
+                    Err(e) => {
+                        output.error(&e)?;
+                        continue;
+                    }
+                };
+                let switched = Agents::new(config, global, &next, &degradation, &debug, &length);
+                if !global.skip_health_check
+                    && !config.dry_run
+                    && let Err(e) = check_provider(&switched.client, &next.base_url, &next.model).await
+                {
+                    output.error(&e.context(format!("Still using {}", provider.model)))?;
+                    continue;
+                }
+                // The conversation lives in the state manager, so it carries over
+                agents = switched;
+                provider = next;
+                output.notice(&format!("🔌 Switched to {} ({})\n", name, provider.model))?;
+                // Over budget, only the fallback may answer
+                let limit = budget.as_ref().and_then(|budget| {
+                    budget.exceeded().filter(|_| !is_budget_fallback(&budget.policy().fallback, &provider))
+                });
+                replies_paused = limit.is_some();
+                if let Some(limit) = limit {
+                    output.notice(&format!("💸 Still over the {}, replies stay paused\n", limit))?;
+                }
+                continue;
+            }
+            Some(Ok(SlashCommand::Bookmarks)) => {
+                let current = stored_session(started_at, &state_manager, &tags, &bookmarks, title.as_deref());
+
+                let mut sessions = match &store {
+                    Some(store) => match store.load_all().await {
+                        Ok(all) => all,
+                        Err(e) => {
+                            output.error(&e)?;
+                            Vec::new()
+                        }
+                    },
+                    None => Vec::new(),
+                };
+                // The saved copy of a loaded session is replaced by the live one
+                sessions.retain(|s| s.id != current.id);
+                sessions.push(current);
+                output.notice(&bookmark_list(&sessions))?;
+                continue;
+            }
+        }
+
+        if replies_paused {
+            let hint = match budget.as_ref().map(|budget| &budget.policy().fallback) {
+                Some(BudgetFallback::Profile(name)) => format!("; /provider {} switches to the budget fallback", name),
+                _ => String::new(),
+            };
+            output.notice(&format!("💸 Over budget, replies are paused{}\n", hint))?;
+            continue;
+        }
+
+        let attachments = match attach::load(input, agents.summarizer.as_ref()).await {
+            Ok(attachments) => attachments,
+            Err(e) => {
+                output.error(&e)?;
+                continue;
+            }
+        };
+        for attachment in attachments.iter().filter(|a| a.shortened) {
+            output.notice(&format!("📎 {} is long, sending a shortened version", attachment.name))?;
+        }
+        let message = attach::inline(input, &attachments);
+
+        let mode = match pinned {
+            Some(strategy) => StrategyMode::Pinned(strategy),
+            None if global.blend_strategies => StrategyMode::Blended,
+            None => StrategyMode::Single,
+        };
+        let turn_started = Instant::now();
+        let policy = StrategyPolicy { mode, ..config.strategy };
+        let turn = tokio::select! {
+            result = run_turn(&agents.emotion_detector, &agents.chat_agent, &agents.topics, &mut state_manager, &message, policy, &config.pipeline) => Some(result),
+            _ = tokio::signal::ctrl_c() => None,
+        };
+        let Some(result) = turn else {
+            // Shuts down like `quit`, so the session is still saved
+            output.notice("👋 Interrupted, goodbye!")?;
+            break;
+        };
+        // Logged before it is shown, so a crash while rendering loses nothing
+        if let Some(turn_log) = &turn_log {
+            let history = state_manager.get_history();
+            match turn_log.append(started_at, log_reset, &history[if log_reset { 0 } else { logged.min(history.len()) }..]) {
+                Ok(()) => (logged, log_reset) = (history.len(), false),
+                Err(e) => tracing::warn!(error = %e, "failed to write the turn log"),
+            }
+        }
+        match result {
+            Ok(outcome) => {
+                output.turn(&outcome, turn_started.elapsed())?;
+                if let Some(turn_usage) = outcome.usage {
+                    let total = usage.get_or_insert(TokenUsage { prompt: 0, completion: 0 });
+                    total.prompt += turn_usage.prompt;
+                    total.completion += turn_usage.completion;
+                    if let Some(budget) = &mut budget
+                        && let Err(e) = budget.record(turn_usage, chrono::Utc::now().date_naive())
+                    {
+                        tracing::warn!(error = %e, "failed to save today's spend");
+                    }
+                }
+                if outcome.escalated
+                    && let (Some(backend), Some(reason)) = (&handoff, outcome.handoff)
+                {
+                    let session_id = StoredSession::new(started_at, Vec::new()).id;
+                    let request = HandoffRequest::new(Some(session_id), reason, &state_manager);
+                    match backend.escalate(&request).await {
+                        Ok(()) => output.notice("🙋 An operator has been notified\n")?,
+                        Err(e) => output.error(&e.context("Handoff failed"))?,
+                    }
+                }
+                alternatives = outcome.alternatives;
+                assessment = outcome.assessment;
+            }
+            Err(e) => output.error(&e)?,
+        }
+
+        if let Some(store) = &store
+            && autosave.due(state_manager.get_history().len())
+        {
+            let session = stored_session(started_at, &state_manager, &tags, &bookmarks, title.as_deref());
+            match store.save(&session).await {
+                Ok(()) => autosave.saved(session.messages.len()),
+                Err(e) => tracing::warn!(session = %session.id, error = %e, "failed to checkpoint session"),
+            }
+        }
+    }
+
+    let saved_history = std::fs::create_dir_all(&config.sessions_dir)
+        .map_err(ReadlineError::from)
+        .and_then(|()| editor.save_history(&history_path));
+    if let Err(e) = saved_history {
+        tracing::warn!(error = %e, "failed to save input history");
+    }
+
+    let mut recap = SessionRecap::new(&state_manager, chrono::Utc::now().timestamp(), usage, config.token_prices);
+    if recap.turns > 0 {
+        if global.reflect
+            && let Some(summarizer) = &agents.summarizer
+        {
+            match summarizer.summarize(&user_text(state_manager.get_history())).await {
+                Ok(summary) => recap.reflection = Some(summary.summary),
+                Err(e) => tracing::warn!(error = %e, "failed to reflect on the session"),
+            }
+        }
+        output.recap(&recap)?;
+    }
+
+    if let Some(degraded) = degradation.describe() {
+        output.notice(&format!("⚠️  {} this session", degraded))?;
+    }
+
+    if config.dry_run {
+        output.notice("🧪 Dry run, session not saved")?;
+    } else if let Some(store) = &store
+        // A session holding only the assistant's opener is not worth keeping
+        && state_manager.get_history().iter().any(|m| matches!(m.role, MessageRole::User))
+    {
+        if title.is_none()
+            && let Some(summarizer) = &agents.summarizer
+        {
+            let history = state_manager.get_history();
+            match summarizer.title(&user_text(&history[..history.len().min(TITLE_MESSAGES)])).await {
+                Ok(written) => title = Some(written),
+                Err(e) => tracing::warn!(error = %e, "failed to title the session"),
+            }
+        }
+        let session = stored_session(started_at, &state_manager, &tags, &bookmarks, title.as_deref());
+        match store.save(&session).await {
+            Ok(()) => output.notice(&format!("💾 Session {} saved to {}", session.id, store.location()))?,
+            // The turn log is kept, so the session can still be recovered
+            Err(e) => {
+                output.error(&e.context("Failed to save session"))?;
+                return Ok(());
+            }
+        }
+    }
+    if let Some(turn_log) = &turn_log
+        && (store.is_some() || state_manager.get_history().is_empty())
+        && let Err(e) = turn_log.clear()
+    {
+        tracing::warn!(error = %e, "failed to clear the turn log");
+    }
+
+    Ok(())
+}
+
+/// A stored session's messages as a conversation to carry on
+fn session_state(messages: Vec<Message>, started_at: i64) -> ConversationState {
+    let emotion_history = messages.iter().filter_map(|m| m.emotion.clone()).collect();
+    ConversationState { messages, emotion_history, started_at: Some(started_at), ..ConversationState::default() }
+}
+
+/// The live session as storage keeps it
+fn stored_session(started_at: i64, state: &ConversationManager, tags: &[String], bookmarks: &[usize], title: Option<&str>) -> StoredSession {
+    let mut session = StoredSession::new(started_at, state.get_history().to_vec());
+    session.title = title.map(str::to_string);
+    session.tags = tags.to_vec();
+    session.bookmarks = bookmarks.to_vec();
+    session
+}
+
+/// Everything that calls the provider, rebuilt when `/provider` switches it
+struct Agents {
+    client: openai::Client,
+    emotion_detector: EmotionDetector,
+    summarizer: Option<SummaryAgent>,
+    keyphrases: KeyphraseAgent,
+    topics: TopicGuard,
+    chat_agent: ChatAgent,
+}
+
+impl Agents {
+    fn new(
+        config: &Config,
+        global: &GlobalArgs,
+        provider: &Provider,
+        degradation: &Arc<Degradation>,
+        debug: &PromptDebug,
+        length: &LengthPreference,
+    ) -> Self {
+        let client = openai::Client::from_url(&provider.api_key, &provider.base_url);
+        let model = &provider.model;
+        let breaker = Arc::new(CircuitBreaker::default());
+        let emotion_detector = EmotionDetector::new(client.clone(), model)
+            .with_breaker(breaker.clone())
+            .with_dry_run(config.dry_run)
+            .with_aspect_sentiment(config.aspect_sentiment)
+            .with_cause_extraction(config.emotion_causes)
+            .with_repair_attempts(config.repair_attempts)
+            .with_self_consistency(config.self_consistency)
+            .with_fallback(config.fallback)
+            .with_examples(config.emotion_examples.clone())
+            .with_cache(config.classification_cache(None, &provider.base_url))
+            .with_degradation(degradation.clone())
+            .with_prompt_debug(debug.clone());
+        let summarizer = (!config.dry_run).then(|| SummaryAgent::new(client.clone(), model));
+        let keyphrases = KeyphraseAgent::new(client.clone(), model).with_dry_run(config.dry_run);
+        let topics = TopicGuard::new(client.clone(), model, config.blocked_topics(None)).with_dry_run(config.dry_run);
+        let chat_agent = ChatAgent::new(client.clone(), model)
+            .with_breaker(breaker)
+            .with_dry_run(config.dry_run)
+            .with_candidates(global.candidates.into())
+            .with_wellbeing_safe(config.wellbeing_safe)
+            .with_minor_safe(config.minor_safe)
+            .with_profanity_filter(config.profanity)
+            .with_reply_scoring(config.score_replies)
+            .with_assessment(global.explain)
+            .with_generation(config.generation.clone())
+            .with_strategy_models(config.strategy_models(provider))
+            .with_length(length.clone())
+            .with_context_window(config.context_window)
+            .with_history_dedup(config.history_dedup(&client))
+            .with_degradation(degradation.clone())
+            .with_prompt_debug(debug.clone());
+        Self { client, emotion_detector, summarizer, keyphrases, topics, chat_agent }
+    }
+}
+
+/// The provider replies move to once the budget is spent, or `None` when they stop
+fn budget_fallback(config: &Config, fallback: &BudgetFallback, current: &Provider) -> Result<Option<Provider>> {
+    match fallback {
+        BudgetFallback::Model(model) => Ok(Some(Provider { model: model.clone(), ..current.clone() })),
+        BudgetFallback::Profile(name) => profiles::find(&config.profiles, name)?.resolve(config.dry_run).map(Some),
+        BudgetFallback::Stop => Ok(None),
+    }
+}
+
+/// Whether `provider` is where the budget sends replies once it is spent
+fn is_budget_fallback(fallback: &BudgetFallback, provider: &Provider) -> bool {
+    match fallback {
+        BudgetFallback::Model(model) => provider.model == *model,
+        BudgetFallback::Profile(name) => provider.profile.as_ref() == Some(name),
+        BudgetFallback::Stop => false,
+    }
+}
+
+/// The profiles `/provider` can switch to, with the one in use marked
+fn provider_list(profiles: &[ProviderProfile], current: &Provider) -> String {
+    if profiles.is_empty() {
+        return format!("🔌 Using {} at {}; list profiles in PROVIDERS_FILE to switch\n", current.model, current.base_url);
+    }
+    let width = profiles.iter().map(|p| p.name.len()).max().unwrap_or_default();
+    let mut out = String::from("🔌 Providers:\n");
+    for profile in profiles {
+        let marker = if current.profile.as_ref() == Some(&profile.name) { '*' } else { ' ' };
+        out.push_str(&format!("{} {:width$}  {} at {}\n", marker, profile.name, profile.model, profile.base_url));
+    }
+    out
+}
+
+/// Prints a check-in above the prompt unless disarmed in time
+struct CheckInTimer {
+    pending: watch::Sender<Option<(tokio::time::Instant, String)>>,
+    printed: Arc<AtomicBool>,
+}
+
+impl CheckInTimer {
+    fn spawn(mut printer: impl ExternalPrinter + Send + 'static) -> Self {
+        let (pending, mut rx) = watch::channel(None::<(tokio::time::Instant, String)>);
+        let printed = Arc::new(AtomicBool::new(false));
+        let flag = printed.clone();
+        tokio::spawn(async move {
+            loop {
+                let next = rx.borrow_and_update().clone();
+                if let Some((deadline, text)) = next {
+                    tokio::select! {
+                        _ = tokio::time::sleep_until(deadline) => {
+                            if printer.print(text).is_ok() {
+                                flag.store(true, Ordering::SeqCst);
+                            }
+                        }
+                        // Re-armed or disarmed before the deadline
+                        _ = rx.changed() => continue,
+                    }
+                }
+                if rx.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+        Self { pending, printed }
+    }
+
+    fn arm(&self, idle: Duration, text: String) {
+        self.pending.send_replace(Some((tokio::time::Instant::now() + idle, text)));
+    }
+
+    /// True when the check-in was printed before this was called
+    fn disarm(&self) -> bool {
+        self.pending.send_replace(None);
+        self.printed.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// Greets a fresh session and asks the script's open questions. The answers
+/// are saved to `save` for later sessions.
+fn onboard(
+    script: &OnboardingScript,
+    saved: UserProfile,
+    editor: &mut Editor<ChatHelper, DefaultHistory>,
+    output: &mut dyn OutputRenderer,
+    save: Option<&Path>,
+) -> Result<UserProfile> {
+    let mut profile = script.start(saved);
+    output.notice(&script.greeting(&profile))?;
+    let questions: Vec<OnboardingQuestion> = script.pending(&profile).cloned().collect();
+    for question in questions {
+        output.notice(&question.ask)?;
+        match editor.readline(output.prompt()) {
+            Ok(answer) => question.record(&answer, &mut profile),
+            // Ctrl+C or Ctrl+D skips the remaining questions
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if let Some(path) = save
+        && let Err(e) = onboarding::save_profile(path, &profile)
+    {
+        tracing::warn!(error = %e, "failed to save the user profile");
+    }
+    Ok(profile)
+}
+
+/// Reads one message, following a `"""` block over several lines when the
+/// editor could not do it itself (e.g. piped input)
+fn read_message(editor: &mut Editor<ChatHelper, DefaultHistory>, prompt: &str) -> rustyline::Result<String> {
+    let mut message = editor.readline(prompt)?;
+    while slash::is_open_block(&message) {
+        match editor.readline("... ") {
+            Ok(line) => {
+                message.push('\n');
+                message.push_str(&line);
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(message)
+}
+
+/// Characters of a message shown in bookmark listings
+const PREVIEW_CHARS: usize = 80;
+
+fn one_line(text: &str) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() <= PREVIEW_CHARS {
+        return flat;
+    }
+    format!("{}…", flat.chars().take(PREVIEW_CHARS).collect::<String>().trim_end())
+}
+
+fn bookmark_list(sessions: &[StoredSession]) -> String {
+    let mut out = String::new();
+    for session in sessions {
+        for (index, msg) in session.bookmarked() {
+            let who = match msg.role {
+                MessageRole::User => "you",
+                MessageRole::Assistant => "assistant",
+            };
+            out.push_str(&format!("📌 {} #{} ({}): {}\n", session.id, index + 1, who, one_line(&msg.content)));
+        }
+    }
+    if out.is_empty() {
+        out.push_str("📌 No bookmarks yet, mark a message with /bookmark\n");
+    }
+    out
+}
+
+async fn session_stats(state: &ConversationManager, keyphrases: &KeyphraseAgent, degradation: &Degradation) -> String {
+    let history = state.get_history();
+    let counts = state.emotion_distribution();
+    let strategies = state.strategy_usage();
+
+    let user_messages = history.iter().filter(|m| matches!(m.role, MessageRole::User)).count();
+    let mut out = format!(
+        "📊 {} messages from you: {} positive, {} neutral, {} negative\n",
+        user_messages, counts.positive, counts.neutral, counts.negative
+    );
+    let trend = state.get_recent_emotion_trend();
+    out.push_str(&format!("📈 Trend: {:?} (magnitude {:.2}, confidence {:.2})\n", trend.direction, trend.magnitude, trend.confidence));
+    if let Some(mood) = state.overall_mood() {
+        out.push_str(&format!("🌡️  Mood: {:?} ({:+.2} over the last {} messages)\n", mood.sentiment, mood.score, mood.messages));
+    }
+    if !strategies.is_empty() {
+        let usage: Vec<String> = strategies.iter().map(|u| format!("{:?} ×{}", u.strategy, u.count)).collect();
+        out.push_str(&format!("🎯 Strategies: {}\n", usage.join(", ")));
+    }
+    if let Some(confidence) = state.average_confidence_by_sentiment().describe() {
+        out.push_str(&format!("🎚️  Confidence: {}\n", confidence));
+    }
+    if let Some(degraded) = degradation.describe() {
+        out.push_str(&format!("⚠️  Degraded: {}\n", degraded));
+    }
+    match keyphrases.extract(&user_text(history), STATS_KEYPHRASES).await {
+        Ok(phrases) if !phrases.is_empty() => out.push_str(&format!("🔑 Key phrases: {}\n", phrases.join(", "))),
+        Ok(_) => {}
+        Err(e) => out.push_str(&format!("🔑 Key phrases unavailable: {}\n", e)),
+    }
+    out
+}
+
+/// The user's baseline from their own sessions; imported chats hold other
+/// people's moods too
+fn usual_mood(saved: &[StoredSession]) -> Option<EmotionBaseline> {
+    EmotionBaseline::from_messages(saved.iter().filter(|s| !s.is_imported()).flat_map(|s| &s.messages))
+}
+
+/// The session `--speak-first` follows up on, never an imported chat
+fn last_own_session(saved: &[StoredSession]) -> Option<&StoredSession> {
+    saved.iter().filter(|s| !s.is_imported()).max_by_key(|s| s.started_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::IMPORTED_TAG;
+    use text_classifier_extractor::{Sentiment, SentimentClassification};
+
+    fn session(started_at: i64, sentiment: Sentiment, imported: bool) -> StoredSession {
+        let message = |_| Message {
+            role: MessageRole::User,
+            content: "...".to_string(),
+            timestamp: started_at,
+            emotion: Some(SentimentClassification { sentiment, confidence: 0.8, labels: Vec::new(), is_fallback: false }),
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
+        };
+        let mut session = StoredSession::new(started_at, (0..20).map(message).collect());
+        if imported {
+            session.tags.push(IMPORTED_TAG.to_string());
+        }
+        session
+    }
+
+    #[test]
+    fn test_imported_chats_do_not_set_the_baseline() {
+        let own = session(100, Sentiment::Positive, false);
+        let imported = session(200, Sentiment::Negative, true);
+        let baseline = usual_mood(&[own, imported.clone()]).unwrap();
+        assert_eq!(baseline.samples, 20);
+        assert!(baseline.mean > 0.0);
+        assert!(usual_mood(&[imported]).is_none());
+    }
+
+    #[test]
+    fn test_imported_chats_are_not_the_last_session() {
+        let saved = [session(100, Sentiment::Neutral, false), session(200, Sentiment::Neutral, true)];
+        assert_eq!(last_own_session(&saved).map(|s| s.started_at), Some(100));
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::{Context, Result};
+use rig::providers::openai;
+use std::io::Read;
+use crate::cli::{ClassifyArgs, GlobalArgs};
+use crate::agents::{EmotionDetector, PromptDebug, Taxonomy, TaxonomyClassifier};
+use crate::Config;
+
+/// The text given on the command line, or else everything on `stdin`
+pub fn input(text: Option<&str>, mut stdin: impl Read) -> Result<String> {
+    let text = match text {
+        Some(text) => text.to_string(),
+        None => {
+            let mut text = String::new();
+            stdin.read_to_string(&mut text).context("Cannot read the text from stdin")?;
+            text
+        }
+    };
+    let text = text.trim();
+    if text.is_empty() {
+        anyhow::bail!("no text to classify");
+    }
+    Ok(text.to_string())
+}
+
+pub async fn run(config: &Config, global: &GlobalArgs, args: &ClassifyArgs) -> Result<()> {
+    let text = input(args.text.as_deref(), std::io::stdin())?;
+
+    let client = openai::Client::from_url(&config.api_key, &config.base_url);
+    if !args.labels.is_empty() {
+        let taxonomy = Taxonomy::from_names(&args.labels).context("Invalid --labels")?;
+        let classifier = TaxonomyClassifier::scored(client, &config.model, taxonomy)
+            .with_dry_run(config.dry_run)
+            .with_repair_attempts(config.repair_attempts);
+        let classification = classifier.classify(&text).await.context("Classification failed")?;
+        println!("{}", serde_json::to_string(&classification)?);
+        return Ok(());
+    }
+
+    let detector = EmotionDetector::new(client, &config.model)
+        .with_dry_run(config.dry_run)
+        .with_repair_attempts(config.repair_attempts)
+        .with_self_consistency(config.self_consistency)
+        .with_fallback(config.fallback)
+        .with_examples(config.emotion_examples.clone())
+        .with_cache(config.classification_cache(None, &config.base_url))
+        .with_prompt_debug(PromptDebug::new(global.debug_prompts));
+
+    let classification = detector.analyze(&text).await.context("Classification failed")?;
+    if classification.is_fallback && !args.allow_fallback {
+        anyhow::bail!("the model gave no usable classification (--allow-fallback prints the fallback instead)");
+    }
+    println!("{}", serde_json::to_string(&classification)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_from_argument_or_stdin() {
+        assert_eq!(input(Some(" so tired "), std::io::empty()).unwrap(), "so tired");
+        assert_eq!(input(None, "from a pipe\n".as_bytes()).unwrap(), "from a pipe");
+        assert!(input(None, " \n".as_bytes()).is_err());
+        assert!(input(Some(""), "ignored".as_bytes()).is_err());
+    }
+}
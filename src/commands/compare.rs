@@ -0,0 +1,178 @@
+use anyhow::{Context, Result};
+use rig::providers::openai;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use crate::Config;
+use crate::agents::chat::Reply;
+use crate::agents::{AgentError, ChatAgent, EmotionDetector, PromptDebug};
+use crate::cli::{CompareArgs, GlobalArgs};
+use crate::state::EmotionTrend;
+use crate::strategy::{ResponseStrategy, StrategyBlend, select_strategy};
+
+/// Narrowest column a response is wrapped to
+const MIN_COLUMN: usize = 20;
+
+/// One line of the fixture file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Case {
+    pub text: String,
+    /// Picked from the text's emotion, as in a chat's first turn, when omitted
+    #[serde(default)]
+    pub strategy: Option<ResponseStrategy>,
+}
+
+/// Both prompt sets' responses to one input
+#[derive(Debug, Serialize)]
+pub struct Comparison {
+    pub text: String,
+    pub strategy: ResponseStrategy,
+    pub a: String,
+    pub b: String,
+}
+
+pub fn parse_cases(content: &str) -> Result<Vec<Case>> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).with_context(|| format!("line {}: expected {{\"text\", \"strategy\"}}", i + 1)))
+        .collect()
+}
+
+/// Reads a JSON object of prompts by strategy, e.g. `{"Empathetic": "..."}`;
+/// strategies it leaves out keep their built-in prompt
+pub fn load_prompts(path: &Path) -> Result<HashMap<ResponseStrategy, String>> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    serde_json::from_str(&json).with_context(|| format!("Cannot parse {}", path.display()))
+}
+
+pub async fn run(config: &Config, global: &GlobalArgs, args: &CompareArgs) -> Result<()> {
+    // Minor-safe prompts replace both sets, which would then compare equal
+    if config.minor_safe {
+        anyhow::bail!("--minor-safe replaces the strategy prompts being compared; run compare-prompts without it (or MINOR_SAFE)");
+    }
+    let content = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("Cannot read {}", args.file.display()))?;
+    let cases = parse_cases(&content).with_context(|| format!("Cannot parse {}", args.file.display()))?;
+    if cases.is_empty() {
+        anyhow::bail!("{} has no inputs", args.file.display());
+    }
+
+    let client = openai::Client::from_url(&config.api_key, &config.base_url);
+    let agent = |prompts| {
+        ChatAgent::new(client.clone(), &config.model)
+            .with_dry_run(config.dry_run)
+            .with_wellbeing_safe(config.wellbeing_safe)
+            .with_profanity_filter(config.profanity)
+            .with_generation(config.generation.clone())
+            .with_context_window(config.context_window)
+            .with_strategy_prompts(prompts)
+            .with_prompt_debug(PromptDebug::new(global.debug_prompts))
+    };
+    let a = agent(load_prompts(&args.a)?);
+    let b = agent(load_prompts(&args.b)?);
+    let detector = EmotionDetector::new(client.clone(), &config.model).with_dry_run(config.dry_run);
+
+    let mut comparisons = Vec::with_capacity(cases.len());
+    for case in cases {
+        let strategy = match case.strategy {
+            Some(strategy) => strategy,
+            None => {
+                let emotion = detector.analyze(&case.text).await.with_context(|| format!("Classifying \"{}\" failed", case.text))?;
+                select_strategy(&emotion, EmotionTrend::Stable)
+            }
+        };
+        let blend = StrategyBlend::single(strategy);
+        let (reply_a, reply_b) = tokio::join!(
+            a.respond(&case.text, &blend, &[], None),
+            b.respond(&case.text, &blend, &[], None),
+        );
+        let text = |reply: Result<Reply, AgentError>| match reply {
+            Ok(reply) => reply.text,
+            Err(e) => format!("⚠️ {}", e),
+        };
+        comparisons.push(Comparison { text: case.text, strategy, a: text(reply_a), b: text(reply_b) });
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&comparisons)?);
+    } else {
+        let label = |path: &Path| path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let (label_a, label_b) = (label(&args.a), label(&args.b));
+        for (i, comparison) in comparisons.iter().enumerate() {
+            println!("── {}. {:?} ({:?})", i + 1, comparison.text, comparison.strategy);
+            print!("{}", side_by_side((&label_a, &comparison.a), (&label_b, &comparison.b), args.width));
+            println!();
+        }
+    }
+    Ok(())
+}
+
+/// Two labelled texts in columns `width` characters wide together, each
+/// wrapped at word boundaries
+fn side_by_side(a: (&str, &str), b: (&str, &str), width: usize) -> String {
+    let column = (width.saturating_sub(3) / 2).max(MIN_COLUMN);
+    let left = std::iter::once(a.0.to_string()).chain(wrap(a.1, column)).collect::<Vec<_>>();
+    let right = std::iter::once(b.0.to_string()).chain(wrap(b.1, column)).collect::<Vec<_>>();
+    let mut out = String::new();
+    for i in 0..left.len().max(right.len()) {
+        let l = left.get(i).map(String::as_str).unwrap_or("");
+        let r = right.get(i).map(String::as_str).unwrap_or("");
+        let line = format!("{:<column$} │ {}", l, r);
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// Lines of at most `column` characters, breaking longer words
+fn wrap(text: &str, column: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word = word.to_string();
+            while word.chars().count() > column {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                let split = word.char_indices().nth(column).map(|(i, _)| i).unwrap_or(word.len());
+                lines.push(word[..split].to_string());
+                word = word[split..].to_string();
+            }
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > column {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cases_and_columns() {
+        let cases = parse_cases("{\"text\": \"I failed my exam\"}\n\n{\"text\": \"Got the job!\", \"strategy\": \"Cheerful\"}\n").unwrap();
+        assert_eq!(cases.len(), 2);
+        assert_eq!((cases[0].strategy, cases[1].strategy), (None, Some(ResponseStrategy::Cheerful)));
+        let error = parse_cases("{\"text\": \"hi\", \"strategy\": \"Sarcastic\"}").unwrap_err();
+        assert!(format!("{:#}", error).starts_with("line 1:"));
+
+        let columns = side_by_side(("warm", "That sounds really hard, I'm sorry."), ("brief", "Sorry to hear that."), 43);
+        assert_eq!(
+            columns,
+            "warm                 │ brief\n\
+             That sounds really   │ Sorry to hear that.\n\
+             hard, I'm sorry.     │\n"
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use anyhow::{Context, Result};
+use rig::providers::openai;
+use serde::Serialize;
+use crate::Config;
+use crate::agents::{EmbeddingAgent, KeyphraseAgent};
+use crate::cli::CorpusArgs;
+use crate::models::MessageRole;
+use crate::report::{EmotionCounts, user_text};
+use crate::storage::{self, StoredSession};
+
+/// Characters of a session's user text that are embedded
+const EMBED_CHARS: usize = 8_000;
+
+/// How much the emotion mix counts against the topic embedding when clustering
+const MOOD_WEIGHT: f64 = 0.5;
+
+/// Key phrases naming each cluster
+const LABEL_PHRASES: usize = 3;
+
+/// Sessions listed as typical of a cluster
+const REPRESENTATIVES: usize = 3;
+
+const MAX_ITERATIONS: usize = 100;
+
+#[derive(Debug, Serialize)]
+pub struct Cluster {
+    pub label: String,
+    pub keyphrases: Vec<String>,
+    pub mood: EmotionCounts,
+    /// Sessions closest to the cluster centre first
+    pub representatives: Vec<String>,
+    pub sessions: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CorpusProfile {
+    pub sessions: usize,
+    pub clusters: Vec<Cluster>,
+}
+
+/// Roughly √(n/2) clusters, capped so the output stays readable
+pub fn default_clusters(sessions: usize) -> usize {
+    ((sessions as f64 / 2.0).sqrt().round() as usize).clamp(1, 8)
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+fn normalize(mut vector: Vec<f64>) -> Vec<f64> {
+    let norm = vector.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+/// k-means with farthest-first seeding, so the same corpus always clusters the
+/// same way. Returns each point's cluster and the cluster centres.
+pub fn kmeans(points: &[Vec<f64>], k: usize) -> (Vec<usize>, Vec<Vec<f64>>) {
+    let k = k.clamp(1, points.len().max(1));
+    let Some(dims) = points.first().map(Vec::len) else {
+        return (Vec::new(), Vec::new());
+    };
+
+    let mut centroids = vec![points[0].clone()];
+    while centroids.len() < k {
+        let farthest = points
+            .iter()
+            .max_by(|a, b| {
+                let nearest = |p: &Vec<f64>| centroids.iter().map(|c| distance(p, c)).fold(f64::INFINITY, f64::min);
+                nearest(a).total_cmp(&nearest(b))
+            })
+            .expect("points is not empty");
+        centroids.push(farthest.clone());
+    }
+
+    let mut assignment = vec![usize::MAX; points.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let next: Vec<usize> = points
+            .iter()
+            .map(|p| {
+                (0..k)
+                    .min_by(|&a, &b| distance(p, &centroids[a]).total_cmp(&distance(p, &centroids[b])))
+                    .expect("k is at least one")
+            })
+            .collect();
+        if next == assignment {
+            break;
+        }
+        assignment = next;
+
+        for (cluster, centroid) in centroids.iter_mut().enumerate() {
+            let members: Vec<&Vec<f64>> = points.iter().zip(&assignment).filter(|(_, a)| **a == cluster).map(|(p, _)| p).collect();
+            // An emptied cluster keeps its old centre
+            if members.is_empty() {
+                continue;
+            }
+            *centroid = (0..dims).map(|d| members.iter().map(|p| p[d]).sum::<f64>() / members.len() as f64).collect();
+        }
+    }
+    (assignment, centroids)
+}
+
+fn mood(session: &StoredSession) -> EmotionCounts {
+    let mut counts = EmotionCounts::default();
+    for msg in &session.messages {
+        if let (MessageRole::User, Some(emotion)) = (&msg.role, &msg.emotion) {
+            counts.record(emotion.sentiment);
+        }
+    }
+    counts
+}
+
+/// Share of each emotion, so long and short sessions weigh the same
+fn mood_features(counts: &EmotionCounts) -> [f64; 3] {
+    let total = counts.total().max(1) as f64;
+    [counts.positive, counts.neutral, counts.negative].map(|n| n as f64 / total)
+}
+
+impl CorpusProfile {
+    pub fn render_text(&self) -> String {
+        let mut out = format!("🗂  {} sessions in {} clusters\n", self.sessions, self.clusters.len());
+        for (i, cluster) in self.clusters.iter().enumerate() {
+            let mood = match cluster.mood.dominant() {
+                Some(sentiment) => format!("mostly {:?}", sentiment),
+                None => "no recorded mood".to_string(),
+            };
+            out.push_str(&format!(
+                "\n{}. {} ({} sessions, {}: {} positive, {} neutral, {} negative)\n",
+                i + 1,
+                cluster.label,
+                cluster.sessions.len(),
+                mood,
+                cluster.mood.positive,
+                cluster.mood.neutral,
+                cluster.mood.negative
+            ));
+            out.push_str(&format!("   Representative: {}\n", cluster.representatives.join(", ")));
+        }
+        out
+    }
+}
+
+pub async fn run(config: &Config, args: &CorpusArgs) -> Result<()> {
+    let store = storage::open(config.database_url.as_deref(), &config.sessions_dir).await?;
+    let sessions: Vec<(StoredSession, String)> = store
+        .load_all()
+        .await?
+        .into_iter()
+        .filter(|session| args.tag.as_ref().is_none_or(|tag| session.has_tag(tag)))
+        .map(|session| {
+            let text = user_text(&session.messages);
+            (session, text)
+        })
+        .filter(|(_, text)| !text.trim().is_empty())
+        .collect();
+    if sessions.len() < 2 {
+        anyhow::bail!("clustering needs at least two saved sessions with messages, found {}", sessions.len());
+    }
+
+    let client = openai::Client::from_url(&config.api_key, &config.base_url);
+    let embedder = EmbeddingAgent::new(client.clone(), &config.embedding_model).with_dry_run(config.dry_run);
+    let keyphrases = KeyphraseAgent::new(client, &config.model).with_dry_run(config.dry_run);
+
+    let texts: Vec<String> = sessions.iter().map(|(_, text)| text.chars().take(EMBED_CHARS).collect()).collect();
+    let embeddings = embedder.embed(&texts).await.context("Embedding sessions failed")?;
+    if embeddings.len() != sessions.len() {
+        anyhow::bail!("the provider returned {} embeddings for {} sessions", embeddings.len(), sessions.len());
+    }
+
+    let points: Vec<Vec<f64>> = sessions
+        .iter()
+        .zip(embeddings)
+        .map(|((session, _), embedding)| {
+            let mut point = normalize(embedding);
+            point.extend(mood_features(&mood(session)).map(|share| share * MOOD_WEIGHT));
+            point
+        })
+        .collect();
+
+    let k = args.clusters.map_or_else(|| default_clusters(sessions.len()), usize::from);
+    let (assignment, centroids) = kmeans(&points, k);
+
+    let mut clusters = Vec::new();
+    for (index, centroid) in centroids.iter().enumerate() {
+        let mut members: Vec<usize> = (0..sessions.len()).filter(|&i| assignment[i] == index).collect();
+        if members.is_empty() {
+            continue;
+        }
+        members.sort_by(|&a, &b| distance(&points[a], centroid).total_cmp(&distance(&points[b], centroid)));
+
+        let mut counts = EmotionCounts::default();
+        for &i in &members {
+            let m = mood(&sessions[i].0);
+            counts.positive += m.positive;
+            counts.neutral += m.neutral;
+            counts.negative += m.negative;
+        }
+
+        let text = members.iter().map(|&i| sessions[i].1.as_str()).collect::<Vec<_>>().join("\n");
+        let phrases = match keyphrases.extract(&text, LABEL_PHRASES).await {
+            Ok(phrases) => phrases,
+            Err(e) => {
+                eprintln!("❌ Naming cluster {} failed: {}", index + 1, e);
+                Vec::new()
+            }
+        };
+        let label = if phrases.is_empty() { format!("Cluster {}", index + 1) } else { phrases.join(", ") };
+
+        let ids: Vec<String> = members.iter().map(|&i| sessions[i].0.id.clone()).collect();
+        clusters.push(Cluster {
+            label,
+            keyphrases: phrases,
+            mood: counts,
+            representatives: ids.iter().take(REPRESENTATIVES).cloned().collect(),
+            sessions: ids,
+        });
+    }
+    clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.sessions.len()));
+
+    let profile = CorpusProfile { sessions: sessions.len(), clusters };
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&profile)?);
+    } else {
+        print!("{}", profile.render_text());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kmeans_separates_groups() {
+        let points = vec![
+            vec![0.0, 0.0],
+            vec![10.0, 10.0],
+            vec![0.2, 0.1],
+            vec![9.8, 10.1],
+            vec![0.1, 0.3],
+        ];
+        let (assignment, centroids) = kmeans(&points, 2);
+
+        assert_eq!(centroids.len(), 2);
+        assert_eq!(assignment[0], assignment[2]);
+        assert_eq!(assignment[0], assignment[4]);
+        assert_eq!(assignment[1], assignment[3]);
+        assert_ne!(assignment[0], assignment[1]);
+
+        // More clusters than points is capped
+        assert_eq!(kmeans(&points[..1], 3).1.len(), 1);
+    }
+
+    #[test]
+    fn test_default_clusters() {
+        assert_eq!(default_clusters(2), 1);
+        assert_eq!(default_clusters(18), 3);
+        assert_eq!(default_clusters(1_000), 8);
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use rig::providers::openai;
+use serde::{Deserialize, Serialize};
+use crate::cli::{EvaluateArgs, GlobalArgs};
+use crate::agents::{AgentError, EmotionDetector, PromptDebug};
+use crate::{Config, Sentiment, SentimentClassification};
+
+/// One line of the labelled file
+#[derive(Debug, Deserialize)]
+pub struct Labelled {
+    pub text: String,
+    pub sentiment: Sentiment,
+}
+
+/// How one prompt did on the labelled texts
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Score {
+    pub correct: usize,
+    pub total: usize,
+    /// Texts the provider could not classify; they count as wrong
+    pub failed: usize,
+    pub accuracy: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Evaluation {
+    pub examples: usize,
+    pub zero_shot: Score,
+    /// Missing when no examples are configured, as both prompts are the same
+    pub few_shot: Option<Score>,
+}
+
+pub fn parse_labelled(content: &str) -> Result<Vec<Labelled>> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).with_context(|| format!("line {}: expected {{\"text\", \"sentiment\"}}", i + 1)))
+        .collect()
+}
+
+pub fn score(labelled: &[Labelled], results: &[Result<SentimentClassification, AgentError>]) -> Score {
+    let mut score = Score { total: labelled.len(), ..Score::default() };
+    for (label, result) in labelled.iter().zip(results) {
+        match result {
+            Ok(classification) if classification.sentiment == label.sentiment => score.correct += 1,
+            Ok(_) => {}
+            Err(_) => score.failed += 1,
+        }
+    }
+    if score.total > 0 {
+        score.accuracy = score.correct as f32 / score.total as f32;
+    }
+    score
+}
+
+impl Score {
+    fn render(&self, name: &str) -> String {
+        let mut line = format!("   {:<10} {:>5.1}% ({}/{})", name, self.accuracy * 100.0, self.correct, self.total);
+        if self.failed > 0 {
+            line.push_str(&format!(", {} failed", self.failed));
+        }
+        line
+    }
+}
+
+pub async fn run(config: &Config, global: &GlobalArgs, args: &EvaluateArgs) -> Result<()> {
+    let content = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("Cannot read {}", args.file.display()))?;
+    let labelled = parse_labelled(&content).with_context(|| format!("Cannot parse {}", args.file.display()))?;
+    if labelled.is_empty() {
+        anyhow::bail!("{} has no labelled texts", args.file.display());
+    }
+    let texts: Vec<&str> = labelled.iter().map(|l| l.text.as_str()).collect();
+
+    // Uncached, so both prompts really answer every text
+    let client = openai::Client::from_url(&config.api_key, &config.base_url);
+    let detector = |examples| {
+        EmotionDetector::new(client.clone(), &config.model)
+            .with_dry_run(config.dry_run)
+            .with_repair_attempts(config.repair_attempts)
+            .with_self_consistency(config.self_consistency)
+            .with_fallback(config.fallback)
+            .with_examples(examples)
+            .with_prompt_debug(PromptDebug::new(global.debug_prompts))
+    };
+
+    let zero_shot = score(&labelled, &detector(Vec::new()).analyze_batch(&texts).await);
+    let few_shot = if config.emotion_examples.is_empty() {
+        None
+    } else {
+        Some(score(&labelled, &detector(config.emotion_examples.clone()).analyze_batch(&texts).await))
+    };
+    let evaluation = Evaluation { examples: config.emotion_examples.len(), zero_shot, few_shot };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&evaluation)?);
+    } else {
+        println!("📏 {} labelled texts", labelled.len());
+        println!("{}", evaluation.zero_shot.render("zero-shot"));
+        if let Some(few_shot) = &evaluation.few_shot {
+            println!("{}", few_shot.render(&format!("{}-shot", evaluation.examples)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score() {
+        let labelled = parse_labelled("{\"text\": \"yay\", \"sentiment\": \"Positive\"}\n\n{\"text\": \"meh\", \"sentiment\": \"Negative\"}\n{\"text\": \"ok\", \"sentiment\": \"Neutral\"}\n").unwrap();
+        let results = [
+            Ok(SentimentClassification { sentiment: Sentiment::Positive, confidence: 0.9, labels: Vec::new(), is_fallback: false }),
+            Ok(SentimentClassification { sentiment: Sentiment::Neutral, confidence: 0.6, labels: Vec::new(), is_fallback: false }),
+            Err(AgentError::Timeout),
+        ];
+        let score = score(&labelled, &results);
+        assert_eq!((score.correct, score.total, score.failed), (1, 3, 1));
+        assert!((score.accuracy - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(score.render("zero-shot"), "   zero-shot   33.3% (1/3), 1 failed");
+
+        let error = parse_labelled("{\"text\": \"yay\"}").unwrap_err();
+        assert!(format!("{:#}", error).starts_with("line 1:"));
+    }
+}
@@ -0,0 +1,50 @@
+use anyhow::{Context, Result};
+use rig::providers::openai;
+use crate::Config;
+use crate::agents::EntityExtractor;
+use crate::anonymize::{self, Anonymizer};
+use crate::cli::ExportArgs;
+use crate::storage;
+
+pub async fn run(config: &Config, args: &ExportArgs) -> Result<()> {
+    let store = storage::open(config.database_url.as_deref(), &config.sessions_dir).await?;
+    let mut sessions = match &args.session {
+        Some(id) => vec![store.load(id).await?],
+        None => store.load_all().await?,
+    };
+    if let Some(tag) = &args.tag {
+        sessions.retain(|s| s.has_tag(tag));
+    }
+
+    if args.anonymize {
+        let client = openai::Client::from_url(&config.api_key, &config.base_url);
+        let extractor = EntityExtractor::new(client, &config.model).with_dry_run(config.dry_run);
+        // One anonymizer for the whole export, so a name gets the same placeholder in every session
+        let mut anonymizer = Anonymizer::default();
+        for session in &sessions {
+            let text = anonymize::session_text(session);
+            // A session whose identifiers are unknown must not leave unanonymized
+            let entities = extractor.extract(&text).await.with_context(|| format!("Entity extraction for session {} failed", session.id))?;
+            anonymizer.learn(anonymize::speakers(session));
+            anonymizer.learn(entities);
+            anonymizer.learn(anonymize::find_contacts(&text));
+        }
+        for session in &mut sessions {
+            anonymizer.anonymize(session);
+        }
+    }
+
+    let mut lines = String::new();
+    for session in &sessions {
+        lines.push_str(&serde_json::to_string(session)?);
+        lines.push('\n');
+    }
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, lines).with_context(|| format!("Cannot write {}", path.display()))?;
+            println!("📦 Exported {} sessions to {}", sessions.len(), path.display());
+        }
+        None => print!("{}", lines),
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use anyhow::{Context, Result};
+use rig::providers::openai;
+use std::io::Read;
+use crate::cli::ExtractArgs;
+use crate::agents::SchemaExtractor;
+use crate::Config;
+
+pub async fn run(config: &Config, args: &ExtractArgs) -> Result<()> {
+    let schema = std::fs::read_to_string(&args.schema)
+        .with_context(|| format!("Cannot read {}", args.schema.display()))?;
+    let schema = serde_json::from_str(&schema)
+        .with_context(|| format!("failed to parse {}", args.schema.display()))?;
+
+    let text = match &args.input {
+        Some(path) => std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?,
+        None => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text).context("Cannot read the text from stdin")?;
+            text
+        }
+    };
+    if text.trim().is_empty() {
+        anyhow::bail!("no text to extract from");
+    }
+
+    let client = openai::Client::from_url(&config.api_key, &config.base_url);
+    let extractor = SchemaExtractor::new(client, &config.model, schema)
+        .with_context(|| format!("Cannot use {}", args.schema.display()))?
+        .with_dry_run(config.dry_run)
+        .with_repair_attempts(config.repair_attempts);
+
+    let extracted = extractor.extract(text.trim()).await.context("Extraction failed")?;
+    println!("{}", serde_json::to_string_pretty(&extracted)?);
+    Ok(())
+}
@@ -0,0 +1,388 @@
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use rig::providers::openai;
+use serde::Deserialize;
+use std::path::Path;
+use crate::Config;
+use crate::agents::EmotionDetector;
+use crate::cli::{ImportArgs, TranscriptFormat};
+use crate::models::{Message, MessageRole};
+use crate::storage::{self, IMPORTED_TAG, StoredSession};
+
+/// Silence that starts a new session
+const SESSION_GAP_SECS: i64 = 6 * 60 * 60;
+
+/// One message of an exported chat
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptMessage {
+    /// `None` when the export does not say who wrote it
+    pub from: Option<String>,
+    pub text: String,
+    pub timestamp: i64,
+}
+
+/// The format by file extension, then by how the content starts
+pub fn detect_format(path: &Path, content: &str) -> TranscriptFormat {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("jsonl" | "ndjson") => return TranscriptFormat::Jsonl,
+        Some("json") => return TranscriptFormat::Telegram,
+        _ => {}
+    }
+    let first = content.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+    if first.starts_with('{') && first.ends_with('}') {
+        TranscriptFormat::Jsonl
+    } else if first.starts_with('{') {
+        TranscriptFormat::Telegram
+    } else {
+        TranscriptFormat::Whatsapp
+    }
+}
+
+pub fn parse(format: TranscriptFormat, content: &str) -> Result<Vec<TranscriptMessage>> {
+    match format {
+        TranscriptFormat::Whatsapp => parse_whatsapp(content),
+        TranscriptFormat::Telegram => parse_telegram(content),
+        TranscriptFormat::Jsonl => parse_jsonl(content),
+    }
+}
+
+/// Date and time of a WhatsApp line before we know whether days or months come first
+#[derive(Debug, Clone, Copy)]
+struct Stamp {
+    first: u32,
+    second: u32,
+    year: i32,
+    hour: u32,
+    minute: u32,
+    seconds: u32,
+}
+
+/// `[31/12/2023, 21:41:05] Alice: Hi` (iOS) or `12/31/23, 9:41 PM - Alice: Hi` (Android).
+/// Lines without a timestamp continue the previous message; system notices,
+/// which have no sender, and omitted media are skipped.
+pub fn parse_whatsapp(content: &str) -> Result<Vec<TranscriptMessage>> {
+    let mut entries: Vec<(Stamp, Option<(String, String)>)> = Vec::new();
+    for line in content.lines() {
+        let line = line.trim_start_matches(['\u{feff}', '\u{200e}']);
+        match whatsapp_header(line) {
+            Some((stamp, rest)) => {
+                let message = rest.split_once(": ").map(|(from, text)| (from.to_string(), text.to_string()));
+                entries.push((stamp, message));
+            }
+            None => {
+                if let Some((_, Some((_, text)))) = entries.last_mut() {
+                    text.push('\n');
+                    text.push_str(line);
+                }
+            }
+        }
+    }
+
+    // Day first unless some date only makes sense month first
+    let month_first = !entries.iter().any(|(stamp, _)| stamp.first > 12) && entries.iter().any(|(stamp, _)| stamp.second > 12);
+    let mut messages = Vec::new();
+    for (stamp, message) in entries {
+        let Some((from, text)) = message else { continue };
+        let text = text.trim();
+        if text.is_empty() || text == "<Media omitted>" || text.starts_with('\u{200e}') {
+            continue;
+        }
+        let (month, day) = if month_first { (stamp.first, stamp.second) } else { (stamp.second, stamp.first) };
+        let timestamp = NaiveDate::from_ymd_opt(stamp.year, month, day)
+            .and_then(|date| date.and_hms_opt(stamp.hour, stamp.minute, stamp.seconds))
+            .with_context(|| format!("Invalid date in WhatsApp message from {}", from))?
+            .and_utc()
+            .timestamp();
+        messages.push(TranscriptMessage { from: Some(from), text: text.to_string(), timestamp });
+    }
+    Ok(messages)
+}
+
+fn whatsapp_header(line: &str) -> Option<(Stamp, &str)> {
+    let (stamp, rest) = match line.strip_prefix('[') {
+        Some(line) => line.split_once("] ")?,
+        None => line.split_once(" - ")?,
+    };
+    let (date, time) = stamp.split_once(", ")?;
+
+    let mut parts = date.split(['/', '.', '-']).map(|part| part.trim().parse::<u32>().ok());
+    let (first, second, year) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() {
+        return None;
+    }
+    let year = if year < 100 { 2000 + year as i32 } else { year as i32 };
+
+    let time = time.replace('\u{202f}', " ");
+    let (clock, meridiem) = match time.trim().rsplit_once(' ') {
+        Some((clock, meridiem)) => (clock, Some(meridiem.to_ascii_uppercase())),
+        None => (time.trim(), None),
+    };
+    let mut fields = clock.split(':').map(|field| field.parse::<u32>().ok());
+    let (mut hour, minute) = (fields.next()??, fields.next()??);
+    let seconds = fields.next().flatten().unwrap_or(0);
+    match meridiem.as_deref() {
+        Some("AM") if hour == 12 => hour = 0,
+        Some("PM") if hour < 12 => hour += 12,
+        Some("AM" | "PM") | None => {}
+        Some(_) => return None,
+    }
+
+    Some((Stamp { first, second, year, hour, minute, seconds }, rest))
+}
+
+#[derive(Deserialize)]
+struct TelegramExport {
+    messages: Vec<TelegramMessage>,
+}
+
+#[derive(Deserialize)]
+struct TelegramMessage {
+    #[serde(rename = "type")]
+    kind: String,
+    date: Option<String>,
+    date_unixtime: Option<String>,
+    from: Option<String>,
+    #[serde(default)]
+    text: TelegramText,
+}
+
+/// Plain text, or runs of text and formatted entities
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TelegramText {
+    Plain(String),
+    Parts(Vec<TelegramPart>),
+}
+
+impl Default for TelegramText {
+    fn default() -> Self {
+        Self::Plain(String::new())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TelegramPart {
+    Plain(String),
+    Entity { text: String },
+}
+
+/// Telegram Desktop's JSON export of a single chat; service messages are skipped
+pub fn parse_telegram(content: &str) -> Result<Vec<TranscriptMessage>> {
+    let export: TelegramExport = serde_json::from_str(content).context("Not a Telegram chat export")?;
+    let mut messages = Vec::new();
+    for message in export.messages.into_iter().filter(|m| m.kind == "message") {
+        let text = match message.text {
+            TelegramText::Plain(text) => text,
+            TelegramText::Parts(parts) => parts
+                .into_iter()
+                .map(|part| match part {
+                    TelegramPart::Plain(text) | TelegramPart::Entity { text } => text,
+                })
+                .collect(),
+        };
+        if text.trim().is_empty() {
+            continue;
+        }
+        let timestamp = match (&message.date_unixtime, &message.date) {
+            (Some(unix), _) => unix.parse().ok(),
+            (None, Some(date)) => NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S").ok().map(|dt| dt.and_utc().timestamp()),
+            (None, None) => None,
+        }
+        .context("Telegram message without a readable date")?;
+        messages.push(TranscriptMessage { from: message.from, text: text.trim().to_string(), timestamp });
+    }
+    Ok(messages)
+}
+
+#[derive(Deserialize)]
+struct JsonlMessage {
+    from: Option<String>,
+    text: String,
+    timestamp: Option<JsonlTime>,
+}
+
+/// Unix seconds or RFC 3339
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonlTime {
+    Unix(i64),
+    Text(String),
+}
+
+/// `{"from": "Alice", "text": "Hi", "timestamp": 1704058865}` per line; a
+/// message without a timestamp takes the previous one's
+pub fn parse_jsonl(content: &str) -> Result<Vec<TranscriptMessage>> {
+    let mut messages: Vec<TranscriptMessage> = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message: JsonlMessage = serde_json::from_str(line).with_context(|| format!("Line {} is not a message", index + 1))?;
+        let timestamp = match message.timestamp {
+            Some(JsonlTime::Unix(seconds)) => seconds,
+            Some(JsonlTime::Text(text)) => chrono::DateTime::parse_from_rfc3339(&text)
+                .with_context(|| format!("Line {} has an invalid timestamp", index + 1))?
+                .timestamp(),
+            None => messages.last().map_or_else(|| chrono::Utc::now().timestamp(), |last| last.timestamp),
+        };
+        if !message.text.trim().is_empty() {
+            messages.push(TranscriptMessage { from: message.from, text: message.text.trim().to_string(), timestamp });
+        }
+    }
+    Ok(messages)
+}
+
+/// Messages in time order, cut into sessions wherever the chat went quiet
+pub fn into_sessions(mut messages: Vec<Message>) -> Vec<Vec<Message>> {
+    messages.sort_by_key(|m| m.timestamp);
+    let mut sessions: Vec<Vec<Message>> = Vec::new();
+    for message in messages {
+        match sessions.last_mut() {
+            Some(session) if session.last().is_some_and(|last| message.timestamp - last.timestamp <= SESSION_GAP_SECS) => {
+                session.push(message);
+            }
+            _ => sessions.push(vec![message]),
+        }
+    }
+    sessions
+}
+
+pub async fn run(config: &Config, args: &ImportArgs) -> Result<()> {
+    let content = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("Cannot read {}", args.file.display()))?;
+    let format = args.format.unwrap_or_else(|| detect_format(&args.file, &content));
+    let transcript = parse(format, &content).with_context(|| format!("Cannot parse {}", args.file.display()))?;
+    if transcript.is_empty() {
+        anyhow::bail!("{} has no messages to import", args.file.display());
+    }
+
+    let is_speaker = |from: &Option<String>| match (&args.speaker, from) {
+        (None, _) => true,
+        (Some(speaker), Some(from)) => from.trim().eq_ignore_ascii_case(speaker.trim()),
+        (Some(_), None) => false,
+    };
+    if let Some(speaker) = &args.speaker
+        && !transcript.iter().any(|m| is_speaker(&m.from))
+    {
+        let mut participants: Vec<&str> = transcript.iter().filter_map(|m| m.from.as_deref()).collect();
+        participants.sort_unstable();
+        participants.dedup();
+        anyhow::bail!("no messages from '{}'; the participants are {}", speaker, participants.join(", "));
+    }
+
+    let mut messages: Vec<Message> = transcript
+        .iter()
+        .map(|m| Message {
+            role: if is_speaker(&m.from) { MessageRole::User } else { MessageRole::Assistant },
+            content: m.text.clone(),
+            timestamp: m.timestamp,
+            emotion: None,
+            strategy: None,
+            speaker: m.from.clone(),
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
+        })
+        .collect();
+
+    let client = openai::Client::from_url(&config.api_key, &config.base_url);
+    let detector = EmotionDetector::new(client, &config.model)
+        .with_dry_run(config.dry_run)
+        .with_repair_attempts(config.repair_attempts)
+        .with_self_consistency(config.self_consistency)
+        .with_fallback(config.fallback)
+        .with_examples(config.emotion_examples.clone())
+        .with_cache(config.classification_cache(None, &config.base_url));
+    let texts: Vec<&str> = transcript.iter().filter(|m| is_speaker(&m.from)).map(|m| m.text.as_str()).collect();
+    let results = detector.analyze_batch(&texts).await;
+    let mut failed = 0;
+    let speaker_messages = messages.iter_mut().filter(|m| matches!(m.role, MessageRole::User));
+    for (message, result) in speaker_messages.zip(results) {
+        match result {
+            Ok(emotion) => message.emotion = Some(emotion),
+            Err(e) => {
+                tracing::warn!(error = %e, "could not classify an imported message");
+                failed += 1;
+            }
+        }
+    }
+
+    let store = storage::open(config.database_url.as_deref(), &config.sessions_dir).await?;
+    let sessions = into_sessions(messages);
+    for messages in &sessions {
+        let mut session = StoredSession::new(messages[0].timestamp, messages.clone());
+        session.id = format!("import-{}", session.id);
+        session.tags = std::iter::once(IMPORTED_TAG.to_string()).chain(args.tag.clone()).collect();
+        store.save(&session).await?;
+        println!("💾 {} ({} messages)", session.id, session.messages.len());
+    }
+
+    println!(
+        "📥 Imported {} messages as {} sessions into {}: {} classified, {} not classified",
+        transcript.len(),
+        sessions.len(),
+        store.location(),
+        texts.len() - failed,
+        failed
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_whatsapp_android_and_ios() {
+        let android = "12/31/23, 9:41 PM - Messages are end-to-end encrypted.\n\
+                       12/31/23, 9:41 PM - Alice: Rough day\n\
+                       and a rough week\n\
+                       1/1/24, 12:05 AM - Bob: <Media omitted>\n\
+                       1/1/24, 12:06 AM - Bob: Happy new year!\n";
+        let messages = parse_whatsapp(android).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].from.as_deref(), Some("Alice"));
+        assert_eq!(messages[0].text, "Rough day\nand a rough week");
+        assert_eq!(messages[1].timestamp - messages[0].timestamp, (2 * 60 + 25) * 60);
+
+        let ios = "[31/12/2023, 21:41:05] Alice: Rough day\n[01/01/2024, 00:06:00] Bob: \u{200e}image omitted\n";
+        let messages = parse_whatsapp(ios).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].timestamp, NaiveDate::from_ymd_opt(2023, 12, 31).unwrap().and_hms_opt(21, 41, 5).unwrap().and_utc().timestamp());
+    }
+
+    #[test]
+    fn test_parse_telegram() {
+        let export = r#"{"name": "Bob", "messages": [
+            {"id": 1, "type": "service", "date": "2024-01-01T00:00:00", "action": "create_group"},
+            {"id": 2, "type": "message", "date": "2024-01-01T00:01:00", "date_unixtime": "1704067260", "from": "Alice", "text": "Hi"},
+            {"id": 3, "type": "message", "date": "2024-01-01T00:02:00", "from": "Bob", "text": ["so ", {"type": "bold", "text": "tired"}]}
+        ]}"#;
+        let messages = parse_telegram(export).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].timestamp, 1704067260);
+        assert_eq!(messages[1].text, "so tired");
+        assert_eq!(messages[1].timestamp, 1704067320);
+    }
+
+    #[test]
+    fn test_parse_jsonl_and_detect() {
+        let content = "{\"from\": \"me\", \"text\": \"Hi\", \"timestamp\": \"2024-01-01T00:00:00Z\"}\n\n{\"text\": \"Still here\"}\n";
+        let messages = parse_jsonl(content).unwrap();
+        assert_eq!(messages[0].timestamp, 1704067200);
+        assert_eq!(messages[1].timestamp, 1704067200);
+        assert_eq!(messages[1].from, None);
+
+        assert_eq!(detect_format(Path::new("chat.txt"), content), TranscriptFormat::Jsonl);
+        assert_eq!(detect_format(Path::new("result.json"), "{\n \"name\": \"Bob\""), TranscriptFormat::Telegram);
+        assert_eq!(detect_format(Path::new("chat.txt"), "[31/12/2023, 21:41:05] Alice: Hi"), TranscriptFormat::Whatsapp);
+    }
+
+    #[test]
+    fn test_sessions_split_on_silence() {
+        let message = |timestamp| Message { role: MessageRole::User, content: "...".to_string(), timestamp, emotion: None, strategy: None, speaker: None, aspects: Vec::new(), cause: None, score: None };
+        let sessions = into_sessions(vec![message(0), message(60), message(SESSION_GAP_SECS + 61), message(30)]);
+        assert_eq!(sessions.iter().map(Vec::len).collect::<Vec<_>>(), [3, 1]);
+    }
+}
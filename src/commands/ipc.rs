@@ -0,0 +1,218 @@
+//! A local control interface: JSON lines over a Unix socket, or a named pipe
+//! on Windows, so desktop integrations can take turns without the HTTP server
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use crate::Config;
+use crate::cli::{GlobalArgs, IpcArgs};
+use crate::server::chat::{SessionStats, take_turn};
+use crate::server::error::ErrorBody;
+use crate::server::tenant::Tenant;
+
+/// One request line, such as `{"op": "turn", "session_id": "desk", "text": "hi"}`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum IpcRequest {
+    /// Answers a message, as `POST /sessions/{id}/messages` does
+    Turn { session_id: String, text: String },
+    Stats { session_id: String },
+    Ping,
+}
+
+pub async fn run(config: &Config, global: &GlobalArgs, args: &IpcArgs) -> Result<()> {
+    let tenant = Arc::new(Tenant::default_for(config));
+    if !global.skip_health_check && !config.dry_run {
+        tenant.check_health().await?;
+    }
+    serve(&args.socket, tenant).await
+}
+
+/// The reply line to one request line; failures are `{"error": ...}` as over HTTP
+pub async fn respond(tenant: &Tenant, line: &str) -> String {
+    let reply = match serde_json::from_str::<IpcRequest>(line) {
+        Ok(request) => handle(tenant, request).await,
+        Err(e) => Err(format!("invalid request: {}", e)),
+    };
+    let reply = reply.unwrap_or_else(|error| serde_json::json!(ErrorBody { error }));
+    reply.to_string()
+}
+
+async fn handle(tenant: &Tenant, request: IpcRequest) -> Result<serde_json::Value, String> {
+    match request {
+        IpcRequest::Turn { session_id, text } => {
+            let response = take_turn(tenant, &session_id, &text).await.map_err(|e| e.message)?;
+            Ok(serde_json::json!(response))
+        }
+        IpcRequest::Stats { session_id } => {
+            let state = tenant
+                .sessions
+                .find(&session_id)
+                .await
+                .map_err(|e| format!("{:#}", e))?
+                .ok_or_else(|| format!("session '{}' not found", session_id))?;
+
+            Ok(serde_json::json!(SessionStats::new(session_id, &state)))
+        }
+        IpcRequest::Ping => Ok(serde_json::json!({ "model": tenant.model })),
+    }
+}
+
+/// Answers request lines in order until the client hangs up
+async fn serve_connection<S>(stream: S, tenant: Arc<Tenant>) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut reply = respond(&tenant, &line).await;
+        reply.push('\n');
+        writer.write_all(reply.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+fn spawn_connection<S>(stream: S, tenant: Arc<Tenant>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        if let Err(e) = serve_connection(stream, tenant).await {
+            tracing::warn!(error = %format!("{:#}", e), "ipc connection failed");
+        }
+    });
+}
+
+#[cfg(unix)]
+async fn serve(path: &Path, tenant: Arc<Tenant>) -> Result<()> {
+    let listener = bind(path).await?;
+    println!("🔌 Listening on {}", path.display());
+
+    let accept = async {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            spawn_connection(stream, tenant.clone());
+        }
+    };
+    let result = tokio::select! {
+        result = accept => result,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    };
+    let _ = std::fs::remove_file(path);
+    result
+}
+
+/// Binds `path`, replacing a socket left behind by an earlier run. Only the
+/// owner may connect, since requests are not authenticated: the socket is
+/// bound inside a directory only the owner can enter, restricted, and only
+/// then moved into place, so it is never reachable with looser permissions.
+#[cfg(unix)]
+async fn bind(path: &Path) -> Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    if path.exists() {
+        if tokio::net::UnixStream::connect(path).await.is_ok() {
+            anyhow::bail!("{} is already in use", path.display());
+        }
+        std::fs::remove_file(path).with_context(|| format!("Cannot replace {}", path.display()))?;
+    }
+    let name = path.file_name().with_context(|| format!("{} is not a file path", path.display()))?;
+    let staging = path.with_file_name(format!(".{}.{}", name.to_string_lossy(), std::process::id()));
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&staging)
+        .with_context(|| format!("Cannot create {}", staging.display()))?;
+    let staged = staging.join("socket");
+    let bound = tokio::net::UnixListener::bind(&staged)
+        .and_then(|listener| {
+            std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+            std::fs::rename(&staged, path)?;
+            Ok(listener)
+        })
+        .with_context(|| format!("Cannot listen on {}", path.display()));
+    let _ = std::fs::remove_file(&staged);
+    let _ = std::fs::remove_dir(&staging);
+    bound
+}
+
+/// Serves a named pipe, which refuses clients on other machines
+#[cfg(windows)]
+async fn serve(path: &Path, tenant: Arc<Tenant>) -> Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(path)
+        .with_context(|| format!("Cannot listen on {}", path.display()))?;
+    println!("🔌 Listening on {}", path.display());
+
+    loop {
+        tokio::select! {
+            connected = server.connect() => connected?,
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+        let client = std::mem::replace(&mut server, ServerOptions::new().create(path)?);
+        spawn_connection(client, tenant.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_turn_then_stats() {
+        let tenant = Tenant::default_for(&Config { dry_run: true, ..Config::test() });
+
+        let reply: serde_json::Value = serde_json::from_str(&respond(&tenant, r#"{"op": "stats", "session_id": "desk"}"#).await).unwrap();
+        assert_eq!(reply["error"], "session 'desk' not found");
+
+        let reply: serde_json::Value =
+            serde_json::from_str(&respond(&tenant, r#"{"op": "turn", "session_id": "desk", "text": "I finally finished it!"}"#).await).unwrap();
+        assert!(reply["response"].as_str().unwrap().starts_with("[dry run]"));
+
+        let reply: serde_json::Value = serde_json::from_str(&respond(&tenant, r#"{"op": "stats", "session_id": "desk"}"#).await).unwrap();
+        assert_eq!(reply["messages"], 2);
+        assert_eq!(reply["user_messages"], 1);
+        let emotions: u64 = ["positive", "neutral", "negative"].iter().map(|s| reply["emotions"][s].as_u64().unwrap()).sum();
+        assert_eq!(emotions, 1);
+        assert_eq!(reply["strategies"].as_array().unwrap().len(), 1);
+
+        let reply: serde_json::Value = serde_json::from_str(&respond(&tenant, r#"{"op": "shutdown"}"#).await).unwrap();
+        assert!(reply["error"].as_str().unwrap().starts_with("invalid request"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_socket_answers_each_line() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("tce-ipc-{}.sock", std::process::id()));
+        let tenant = Arc::new(Tenant::default_for(&Config { dry_run: true, ..Config::test() }));
+        let listener = bind(&path).await.unwrap();
+        assert!(bind(&path).await.is_err());
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(!path.with_file_name(format!(".tce-ipc-{}.sock.{}", std::process::id(), std::process::id())).exists());
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                spawn_connection(stream, tenant.clone());
+            }
+        });
+
+        let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        writer.write_all(b"{\"op\": \"ping\"}\n\n{\"op\": \"ping\"}\n").await.unwrap();
+        let mut lines = BufReader::new(reader).lines();
+        for _ in 0..2 {
+            let reply: serde_json::Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+            assert_eq!(reply["model"], "test-model");
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
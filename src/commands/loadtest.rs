@@ -0,0 +1,280 @@
+use anyhow::{Context, Result};
+use futures::{SinkExt, StreamExt};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use crate::Config;
+use crate::cli::{LoadEndpoint, LoadtestArgs};
+use crate::mock::{MOCK_MODEL, MockProvider};
+use crate::server::tenant::{DEFAULT_TENANT, Tenant, TenantConfig, Tenants};
+use crate::server::{AppState, router};
+
+/// Messages the workers send in turn
+const MESSAGES: [&str; 4] = [
+    "I had a rough day at work",
+    "My sister is visiting this weekend",
+    "I finally finished the project!",
+    "Not sure what to do about it",
+];
+
+/// What one worker saw
+#[derive(Debug, Default)]
+struct Tally {
+    latencies: Vec<Duration>,
+    failed: usize,
+    first_error: Option<String>,
+}
+
+impl Tally {
+    fn fail(&mut self, error: String) {
+        self.failed += 1;
+        self.first_error.get_or_insert(error);
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Percentiles {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoadReport {
+    pub endpoint: LoadEndpoint,
+    pub concurrency: usize,
+    pub requests: usize,
+    pub failed: usize,
+    pub seconds: f64,
+    /// Successful requests per second
+    pub throughput: f64,
+    /// Of the successful requests
+    pub latency_ms: Percentiles,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_error: Option<String>,
+}
+
+pub async fn run(config: &Config, args: &LoadtestArgs) -> Result<()> {
+    let target = match &args.target {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => start_local(config, Duration::from_millis(args.mock_latency_ms)).await?,
+    };
+    let concurrency = usize::from(args.concurrency);
+    let requests = args.requests as usize;
+    eprintln!("🏋️  {} requests to {} {}, {} at a time", requests, target, path(args.endpoint, "{id}"), concurrency);
+
+    let http = reqwest::Client::new();
+    let issued = Arc::new(AtomicUsize::new(0));
+    let started = Instant::now();
+    // Fresh sessions each run, rather than ones an earlier run left on the target
+    let run_id = chrono::Utc::now().timestamp_millis();
+    let workers = (0..concurrency).map(|worker| {
+        let (http, target, issued, api_key) = (http.clone(), target.clone(), issued.clone(), args.api_key.clone());
+        let endpoint = args.endpoint;
+        tokio::spawn(async move {
+            let session_id = format!("loadtest-{}-{}", run_id, worker);
+            let claim = || Some(issued.fetch_add(1, Ordering::Relaxed)).filter(|&i| i < requests);
+            match endpoint {
+                LoadEndpoint::Live => drive_socket(&target, &session_id, api_key.as_deref(), claim).await,
+                _ => Ok(drive_http(&http, &target, endpoint, &session_id, api_key.as_deref(), claim).await),
+            }
+        })
+    });
+    let mut tally = Tally::default();
+    for result in futures::future::join_all(workers).await {
+        let worker = result.context("load test worker panicked")??;
+        tally.latencies.extend(worker.latencies);
+        tally.failed += worker.failed;
+        if tally.first_error.is_none() {
+            tally.first_error = worker.first_error;
+        }
+    }
+    let report = report(args.endpoint, concurrency, tally, started.elapsed());
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", render(&report));
+    }
+    Ok(())
+}
+
+/// Starts the mock provider and a server answering from it, returning the server's URL
+async fn start_local(config: &Config, latency: Duration) -> Result<String> {
+    let provider = MockProvider::new(latency).start().await?;
+    let tenant = Tenant::new(config, TenantConfig {
+        id: DEFAULT_TENANT.to_string(),
+        api_key: Some("mock".to_string()),
+        base_url: Some(provider.clone()),
+        model: Some(MOCK_MODEL.to_string()),
+        strategy_prompts: HashMap::new(),
+        strategy_models: None,
+        blocked_topics: None,
+    })
+    // Throwaway sessions, kept out of SESSIONS_DIR
+    .with_memory_sessions();
+    let state = AppState { tenants: Arc::new(Tenants::single(tenant)), max_batch: 32, api_keys: None, pacing: None };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, router(state)).await {
+            tracing::error!(error = %e, "load test server stopped");
+        }
+    });
+    eprintln!("🧪 Mock provider on {}, answering after {}ms", provider, latency.as_millis());
+    Ok(url)
+}
+
+async fn drive_http(
+    http: &reqwest::Client,
+    target: &str,
+    endpoint: LoadEndpoint,
+    session_id: &str,
+    api_key: Option<&str>,
+    claim: impl Fn() -> Option<usize>,
+) -> Tally {
+    let mut tally = Tally::default();
+    let url = format!("{}{}", target, path(endpoint, session_id));
+    while let Some(i) = claim() {
+        // Each /turns request starts a conversation of its own
+        let mut request = http.post(&url).json(&json!({ "text": MESSAGES[i % MESSAGES.len()] }));
+        if let Some(key) = api_key {
+            request = request.bearer_auth(key);
+        }
+        let sent = Instant::now();
+        match request.send().await {
+            Ok(response) if response.status().is_success() => match response.bytes().await {
+                Ok(_) => tally.latencies.push(sent.elapsed()),
+                Err(e) => tally.fail(e.to_string()),
+            },
+            Ok(response) => tally.fail(format!("HTTP {}", response.status())),
+            Err(e) => tally.fail(e.to_string()),
+        }
+    }
+    tally
+}
+
+/// One WebSocket per worker, with a turn at a time over it
+async fn drive_socket(target: &str, session_id: &str, api_key: Option<&str>, claim: impl Fn() -> Option<usize>) -> Result<Tally> {
+    let url = format!("{}{}", target.replacen("http", "ws", 1), path(LoadEndpoint::Live, session_id));
+    let mut request = url.as_str().into_client_request()?;
+    if let Some(key) = api_key {
+        request.headers_mut().insert("x-api-key", key.parse()?);
+    }
+    let (mut socket, _) = tokio_tungstenite::connect_async(request).await.with_context(|| format!("Cannot connect to {}", url))?;
+
+    let mut tally = Tally::default();
+    while let Some(i) = claim() {
+        let sent = Instant::now();
+        socket.send(WsMessage::text(json!({ "text": MESSAGES[i % MESSAGES.len()] }).to_string())).await?;
+        // Typing and reply parts come before the turn or an error
+        loop {
+            let Some(frame) = socket.next().await else {
+                anyhow::bail!("{} closed the connection", url);
+            };
+            let WsMessage::Text(text) = frame? else { continue };
+            let event: Value = serde_json::from_str(text.as_str())?;
+            match event["type"].as_str() {
+                Some("turn") => tally.latencies.push(sent.elapsed()),
+                Some("error") => tally.fail(event["error"].as_str().unwrap_or_default().to_string()),
+                _ => continue,
+            }
+            break;
+        }
+    }
+    socket.close(None).await?;
+    Ok(tally)
+}
+
+/// Where requests to `endpoint` go, for the given session
+fn path(endpoint: LoadEndpoint, session_id: &str) -> String {
+    match endpoint {
+        LoadEndpoint::Turns => "/turns".to_string(),
+        LoadEndpoint::Messages => format!("/sessions/{}/messages", session_id),
+        LoadEndpoint::Live => format!("/sessions/{}/live", session_id),
+    }
+}
+
+fn report(endpoint: LoadEndpoint, concurrency: usize, tally: Tally, elapsed: Duration) -> LoadReport {
+    let mut latencies: Vec<f64> = tally.latencies.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+    latencies.sort_by(f64::total_cmp);
+    let seconds = elapsed.as_secs_f64();
+    LoadReport {
+        endpoint,
+        concurrency,
+        requests: latencies.len() + tally.failed,
+        failed: tally.failed,
+        seconds,
+        throughput: if seconds > 0.0 { latencies.len() as f64 / seconds } else { 0.0 },
+        latency_ms: Percentiles {
+            p50: percentile(&latencies, 50.0),
+            p90: percentile(&latencies, 90.0),
+            p99: percentile(&latencies, 99.0),
+            max: latencies.last().copied().unwrap_or_default(),
+        },
+        first_error: tally.first_error,
+    }
+}
+
+/// Nearest-rank percentile of sorted values; 0 when there are none
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn render(report: &LoadReport) -> String {
+    let latency = &report.latency_ms;
+    let mut out = format!(
+        "   throughput  {:.1} req/s over {:.1}s ({} of {} failed)\n   latency     p50 {:.1}ms  p90 {:.1}ms  p99 {:.1}ms  max {:.1}ms\n",
+        report.throughput, report.seconds, report.failed, report.requests, latency.p50, latency.p90, latency.p99, latency.max
+    );
+    if let Some(error) = &report.first_error {
+        out.push_str(&format!("   first error {}\n", error));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let latencies: Vec<f64> = (1..=100).map(f64::from).collect();
+        assert_eq!((percentile(&latencies, 50.0), percentile(&latencies, 99.0)), (50.0, 99.0));
+        assert_eq!(percentile(&[7.0], 90.0), 7.0);
+        assert_eq!(percentile(&[], 50.0), 0.0);
+
+        let tally = Tally { latencies: vec![Duration::from_millis(10), Duration::from_millis(30)], failed: 1, first_error: Some("HTTP 503".to_string()) };
+        let report = report(LoadEndpoint::Turns, 2, tally, Duration::from_secs(2));
+        assert_eq!((report.requests, report.throughput), (3, 1.0));
+        assert_eq!(
+            render(&report),
+            "   throughput  1.0 req/s over 2.0s (1 of 3 failed)\n   latency     p50 10.0ms  p90 30.0ms  p99 30.0ms  max 30.0ms\n   first error HTTP 503\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_local_server_answers_from_the_mock_provider() {
+        let target = start_local(&Config::test(), Duration::ZERO).await.unwrap();
+        let http = reqwest::Client::new();
+        let issued = AtomicUsize::new(0);
+        let claim = || Some(issued.fetch_add(1, Ordering::Relaxed)).filter(|&i| i < 3);
+        let tally = drive_http(&http, &target, LoadEndpoint::Messages, "loadtest-0", None, claim).await;
+        assert_eq!((tally.latencies.len(), tally.failed), (3, 0), "{:?}", tally.first_error);
+
+        let issued = AtomicUsize::new(0);
+        let claim = || Some(issued.fetch_add(1, Ordering::Relaxed)).filter(|&i| i < 2);
+        let tally = drive_socket(&target, "loadtest-1", None, claim).await.unwrap();
+        assert_eq!((tally.latencies.len(), tally.failed), (2, 0), "{:?}", tally.first_error);
+    }
+}
@@ -0,0 +1,33 @@
+use anyhow::{Context, Result};
+use std::io::IsTerminal;
+use crate::cli::LoginArgs;
+use crate::credentials;
+
+/// Saves the provider API key in the system keyring, or removes it with `--delete`
+pub fn run(base_url: &str, args: &LoginArgs) -> Result<()> {
+    if args.delete {
+        if credentials::delete(base_url)? {
+            println!("🔑 Removed the API key for {}", base_url);
+        } else {
+            println!("🔑 No API key was saved for {}", base_url);
+        }
+        return Ok(());
+    }
+
+    // Typed without echo, or piped in from a password manager
+    let key = if std::io::stdin().is_terminal() {
+        rpassword::prompt_password(format!("API key for {}: ", base_url)).context("Cannot read the API key")?
+    } else {
+        let mut key = String::new();
+        std::io::stdin().read_line(&mut key).context("Cannot read the API key from stdin")?;
+        key
+    };
+    let key = key.trim();
+    if key.is_empty() {
+        anyhow::bail!("no API key given");
+    }
+
+    credentials::store(base_url, key)?;
+    println!("🔑 Saved the API key for {} in the system keyring", base_url);
+    Ok(())
+}
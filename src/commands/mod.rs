@@ -0,0 +1,24 @@
+//! CLI subcommand implementations
+
+pub mod analyze;
+pub mod attach;
+pub mod categorize;
+pub mod chat;
+pub mod classify;
+pub mod compare;
+pub mod corpus;
+pub mod evaluate;
+pub mod export;
+pub mod extract;
+pub mod import;
+pub mod ipc;
+pub mod loadtest;
+#[cfg(feature = "keyring")]
+pub mod login;
+pub mod recap;
+pub mod report;
+pub mod serve;
+pub mod sessions;
+pub mod simulate;
+pub mod slash;
+pub mod watch;
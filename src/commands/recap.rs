@@ -0,0 +1,134 @@
+//! The summary printed when a chat ends
+
+use serde::Serialize;
+use std::time::Duration;
+use crate::agents::TokenUsage;
+use crate::state::{ConversationManager, EmotionCounts, EmotionTrend, StrategyUsage};
+
+/// What the provider charges, in dollars per thousand tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenPrices {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+impl TokenPrices {
+    pub fn cost(&self, usage: TokenUsage) -> f64 {
+        (usage.prompt as f64 * self.prompt + usage.completion as f64 * self.completion) / 1000.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionRecap {
+    /// Length of the sitting that just ended, in seconds
+    pub duration_secs: u64,
+    /// User turns in the sitting
+    pub turns: usize,
+    pub emotions: EmotionCounts,
+    pub trajectory: Vec<EmotionTrend>,
+    pub strategies: Vec<StrategyUsage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+    /// Dollars, when prices are configured and the provider reported usage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+    /// The model's reflection on the conversation, with `--reflect`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reflection: Option<String>,
+}
+
+impl SessionRecap {
+    pub fn new(state: &ConversationManager, now: i64, usage: Option<TokenUsage>, prices: Option<TokenPrices>) -> Self {
+        let (duration, turns) = state.sitting(now);
+        Self {
+            duration_secs: duration.as_secs(),
+            turns,
+            emotions: state.emotion_distribution(),
+            trajectory: state.trend_trajectory(),
+            strategies: state.strategy_usage(),
+            usage,
+            cost: usage.zip(prices).map(|(usage, prices)| prices.cost(usage)),
+            reflection: None,
+        }
+    }
+
+    pub fn render_text(&self) -> String {
+        let mut out = String::from("📋 Session recap\n");
+        out.push_str(&format!(
+            "   ⏱️  {}, {} turn{}\n",
+            duration(Duration::from_secs(self.duration_secs)),
+            self.turns,
+            if self.turns == 1 { "" } else { "s" }
+        ));
+        out.push_str(&format!(
+            "   💬 {} positive, {} neutral, {} negative\n",
+            self.emotions.positive, self.emotions.neutral, self.emotions.negative
+        ));
+        if !self.trajectory.is_empty() {
+            let trajectory: Vec<String> = self.trajectory.iter().map(|trend| format!("{:?}", trend)).collect();
+            out.push_str(&format!("   📈 Trend: {}\n", trajectory.join(" → ")));
+        }
+        if !self.strategies.is_empty() {
+            let usage: Vec<String> = self.strategies.iter().map(StrategyUsage::describe).collect();
+            out.push_str(&format!("   🎯 Strategies: {}\n", usage.join(", ")));
+        }
+        if let Some(usage) = self.usage {
+            out.push_str(&format!("   🪙 Tokens: {} prompt + {} completion", usage.prompt, usage.completion));
+            if let Some(cost) = self.cost {
+                out.push_str(&format!(" (≈ ${:.4})", cost));
+            }
+            out.push('\n');
+        }
+        if let Some(reflection) = &self.reflection {
+            out.push_str(&format!("   🪞 {}\n", reflection));
+        }
+        out
+    }
+}
+
+/// e.g. `1h 05m`, `12m 30s` or `45s`
+fn duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {:02}s", m, s),
+        (h, m, _) => format!("{}h {:02}m", h, m),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MessageRole;
+    use crate::strategy::ResponseStrategy;
+    use crate::{Sentiment, SentimentClassification};
+
+    #[test]
+    fn test_recap_of_a_sitting() {
+        let mut state = ConversationManager::new();
+        for sentiment in [Sentiment::Negative, Sentiment::Positive] {
+            state.add_message(MessageRole::User, "...");
+            state.update_emotion(SentimentClassification { sentiment, confidence: 0.8, labels: Vec::new(), is_fallback: false });
+            state.add_message(MessageRole::Assistant, "...");
+            state.update_strategy(ResponseStrategy::Empathetic);
+        }
+        let now = state.get_history()[0].timestamp + 750;
+        let usage = Some(TokenUsage { prompt: 2000, completion: 500 });
+        let mut recap = SessionRecap::new(&state, now, usage, Some(TokenPrices { prompt: 0.5, completion: 1.5 }));
+        recap.reflection = Some("You started low and ended on a lighter note.".to_string());
+
+        assert_eq!((recap.turns, recap.duration_secs, recap.cost), (2, 750, Some(1.75)));
+        let text = recap.render_text();
+        assert!(text.contains("12m 30s, 2 turns"), "{}", text);
+        assert!(text.contains("1 positive, 0 neutral, 1 negative"));
+        assert!(text.contains("Empathetic ×2"));
+        assert!(text.contains("(≈ $1.7500)"));
+        assert!(text.contains("🪞 You started low"));
+
+        let unpriced = SessionRecap::new(&state, now, None, Some(TokenPrices { prompt: 0.5, completion: 1.5 }));
+        assert_eq!(unpriced.cost, None);
+        assert!(!unpriced.render_text().contains("Tokens"));
+        assert_eq!(duration(Duration::from_secs(3900)), "1h 05m");
+        assert_eq!(duration(Duration::from_secs(45)), "45s");
+    }
+}
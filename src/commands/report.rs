@@ -0,0 +1,89 @@
+use anyhow::Result;
+use rig::providers::openai;
+use crate::{Config, Sentiment};
+use crate::agents::{KeyphraseAgent, SummaryAgent};
+use crate::cli::{ReportArgs, ReportFormat};
+use crate::report::{SessionKeyphrases, WeeklyReport, build_transcript, messages_in_range, render_html, user_text};
+use crate::storage;
+
+/// Key phrases listed per session and for low-mood messages
+const KEYPHRASES: usize = 5;
+
+pub async fn run(config: &Config, args: &ReportArgs) -> Result<()> {
+    let store = storage::open(config.database_url.as_deref(), &config.sessions_dir).await?;
+
+    let (sessions, from, to) = match &args.session {
+        Some(id) => {
+            let session = store.load(id).await?;
+            let Some((from, to)) = session.date_span() else {
+                anyhow::bail!("session '{}' has no messages", id);
+            };
+            (vec![session], from, to)
+        }
+        None => {
+            let today = chrono::Utc::now().date_naive();
+            let (from, to) = args.date_range(today)?;
+            let mut sessions = store.load_all().await?;
+            if let Some(tag) = &args.tag {
+                sessions.retain(|s| s.has_tag(tag));
+            }
+            (sessions, from, to)
+        }
+    };
+
+    let mut report = WeeklyReport::new(&sessions, from, to);
+    let client = openai::Client::from_url(&config.api_key, &config.base_url);
+
+    if report.message_count > 0 {
+        let keyphrases = KeyphraseAgent::new(client.clone(), &config.model).with_dry_run(config.dry_run);
+
+        for session in &sessions {
+            let text = user_text(messages_in_range(std::slice::from_ref(session), from, to).map(|(_, msg)| msg));
+            match keyphrases.extract(&text, KEYPHRASES).await {
+                Ok(phrases) if !phrases.is_empty() => {
+                    report.session_keyphrases.push(SessionKeyphrases { session_id: session.id.clone(), phrases });
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("❌ Key phrases for session {} failed: {}", session.id, e),
+            }
+        }
+
+        let low_mood = user_text(
+            messages_in_range(&sessions, from, to)
+                .map(|(_, msg)| msg)
+                .filter(|msg| msg.emotion.as_ref().is_some_and(|e| e.sentiment == Sentiment::Negative)),
+        );
+        match keyphrases.extract(&low_mood, KEYPHRASES).await {
+            Ok(phrases) => report.low_mood_keyphrases = phrases,
+            Err(e) => eprintln!("❌ Low-mood key phrases failed: {}", e),
+        }
+    }
+
+    if report.message_count > 0 && !config.dry_run {
+        let summarizer = SummaryAgent::new(client, &config.model);
+
+        match summarizer.summarize(&build_transcript(&sessions, from, to)).await {
+            Ok(summary) => {
+                report.summary = Some(summary.summary);
+                report.topics = summary.topics;
+            }
+            Err(e) => eprintln!("❌ Summary generation failed: {}", e),
+        }
+    }
+
+    let rendered = match args.format {
+        ReportFormat::Text => report.render_text(),
+        ReportFormat::Json => format!("{}\n", serde_json::to_string_pretty(&report)?),
+        ReportFormat::Html => render_html(&report, &sessions),
+    };
+
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, rendered)?;
+            println!("📄 Report written to {}", path.display());
+        }
+        None => print!("{}", rendered),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::Config;
+use crate::cli::{GlobalArgs, ServeArgs};
+use crate::server::auth::ApiKeys;
+use crate::server::pacing::Pacing;
+use crate::server::sessions::SessionBackend;
+use crate::server::tenant::{Tenant, Tenants};
+use crate::state::CheckInPolicy;
+use crate::server::{AppState, router};
+
+pub async fn run(config: &Config, global: &GlobalArgs, args: &ServeArgs) -> Result<()> {
+    let session_backend = session_backend(args).await?;
+
+    let tenants = match &args.tenants {
+        Some(path) => {
+            let tenants = Tenants::load(path, config, session_backend)?;
+            for id in tenants.ids() {
+                if let Some(tenant) = tenants.resolve(Some(id)) {
+                    println!("🏢 Tenant {} → {}", tenant.id, tenant.model);
+                }
+            }
+            tenants
+        }
+        None => {
+            let tenant = Tenant::default_for(config);
+            Tenants::single(match session_backend {
+                Some(backend) => tenant.with_session_backend(backend),
+                None => tenant,
+            })
+        }
+    };
+    let tenants = Arc::new(tenants);
+
+    if !global.skip_health_check && !config.dry_run {
+        futures::future::try_join_all(tenants.all().map(|tenant| tenant.check_health())).await?;
+    }
+
+    let api_keys = match &args.api_keys {
+        Some(path) => {
+            let keys = ApiKeys::load(path)?;
+            if let Some(tenant) = keys.bound_tenants().find(|t| tenants.resolve(Some(t)).is_none()) {
+                anyhow::bail!("API key bound to unknown tenant '{}'", tenant);
+            }
+            println!("🔑 API keys: {}", keys.names().join(", "));
+            Some(Arc::new(keys))
+        }
+        None => {
+            eprintln!("⚠️  No --api-keys file given, the API is open to anyone who can reach it");
+            None
+        }
+    };
+
+    let state = AppState {
+        tenants: tenants.clone(),
+        max_batch: args.max_batch,
+        api_keys: api_keys.clone(),
+        pacing: args.pace_replies.map(|chars_per_sec| Pacing { chars_per_sec }),
+    };
+
+    if let Some(policy) = config.check_in {
+        tokio::spawn(check_in_idle_sessions(tenants.clone(), policy));
+    }
+    if let Some(ttl) = config.session_idle_ttl {
+        println!("💤 Sessions idle for {}s leave memory", ttl.as_secs());
+        tokio::spawn(evict_idle_sessions(tenants.clone(), ttl));
+    }
+
+    let listener = tokio::net::TcpListener::bind(args.addr).await?;
+    println!("🌐 Listening on http://{}", listener.local_addr()?);
+    println!("📊 Model: {}", config.model);
+
+    let http = async {
+        axum::serve(listener, router(state)).await?;
+        Ok::<_, anyhow::Error>(())
+    };
+
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_addr) = args.grpc_addr {
+        use crate::grpc::EmotionGrpc;
+
+        println!("🔌 gRPC listening on {}", grpc_addr);
+        tokio::try_join!(http, crate::grpc::serve(grpc_addr, EmotionGrpc::new(tenants, api_keys)))?;
+        return Ok(());
+    }
+
+    http.await
+}
+
+/// How often idle sessions are looked over for a check-in
+const CHECK_IN_SWEEP: Duration = Duration::from_secs(15);
+
+async fn check_in_idle_sessions(tenants: Arc<Tenants>, policy: CheckInPolicy) {
+    let mut sweep = tokio::time::interval(CHECK_IN_SWEEP);
+    loop {
+        sweep.tick().await;
+        for tenant in tenants.all() {
+            let added = tenant.sessions.check_in_idle(&policy).await;
+            if added > 0 {
+                tracing::info!(tenant = %tenant.id, added, "checked in on quiet sessions");
+            }
+        }
+    }
+}
+
+/// Idle sessions are looked for this often, or once per TTL when that is shorter
+const EVICTION_SWEEP: Duration = Duration::from_secs(60);
+
+async fn evict_idle_sessions(tenants: Arc<Tenants>, ttl: Duration) {
+    let mut sweep = tokio::time::interval(EVICTION_SWEEP.min(ttl).max(Duration::from_secs(1)));
+    loop {
+        sweep.tick().await;
+        for tenant in tenants.all() {
+            let evicted = tenant.sessions.evict_idle();
+            if evicted > 0 {
+                tracing::info!(tenant = %tenant.id, evicted, in_memory = tenant.sessions.len(), "evicted idle sessions");
+            }
+            match tenant.sessions.expire_stored().await {
+                Ok(0) => {}
+                Ok(expired) => tracing::info!(tenant = %tenant.id, expired, "deleted expired sessions"),
+                Err(e) => tracing::warn!(tenant = %tenant.id, error = %format!("{:#}", e), "failed to delete expired sessions"),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+async fn session_backend(args: &ServeArgs) -> Result<Option<Arc<dyn SessionBackend>>> {
+    use crate::server::cache::RedisSessions;
+    use std::time::Duration;
+
+    let Some(url) = &args.redis_url else {
+        return Ok(None);
+    };
+    let sessions = RedisSessions::connect(url, Duration::from_secs(args.session_ttl)).await?;
+    println!("🗄️  Sessions in Redis (idle TTL {}s)", args.session_ttl);
+    Ok(Some(Arc::new(sessions)))
+}
+
+#[cfg(not(feature = "redis"))]
+async fn session_backend(_args: &ServeArgs) -> Result<Option<Arc<dyn SessionBackend>>> {
+    Ok(None)
+}
@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rig::providers::openai;
+use serde::Serialize;
+use crate::Config;
+use crate::Sentiment;
+use crate::agents::SummaryAgent;
+use crate::agents::summary::TITLE_MESSAGES;
+use crate::cli::SessionsArgs;
+use crate::report::user_text;
+use crate::storage::{self, StoredSession};
+
+/// One row of the listing
+#[derive(Debug, Serialize)]
+struct SessionEntry {
+    id: String,
+    title: String,
+    started_at: Option<DateTime<Utc>>,
+    dominant_emotion: Option<Sentiment>,
+    turns: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+}
+
+impl SessionEntry {
+    fn of(session: &StoredSession) -> Self {
+        Self {
+            id: session.id.clone(),
+            title: session.display_title(),
+            started_at: DateTime::from_timestamp(session.started_at, 0),
+            dominant_emotion: session.dominant_emotion(),
+            turns: session.turns(),
+            tags: session.tags.clone(),
+        }
+    }
+}
+
+pub async fn run(config: &Config, args: &SessionsArgs) -> Result<()> {
+    let store = storage::open(config.database_url.as_deref(), &config.sessions_dir).await?;
+    let mut sessions = store.load_all().await?;
+    if let Some(tag) = &args.tag {
+        sessions.retain(|s| s.has_tag(tag));
+    }
+
+    if args.generate_titles {
+        if config.dry_run {
+            anyhow::bail!("--generate-titles asks the model for each title and cannot run with --dry-run");
+        }
+        let client = openai::Client::from_url(&config.api_key, &config.base_url);
+        let summarizer = SummaryAgent::new(client, &config.model);
+        let mut titled = 0;
+        for session in sessions.iter_mut().filter(|s| s.title.is_none() && s.turns() > 0) {
+            let opening = &session.messages[..session.messages.len().min(TITLE_MESSAGES)];
+            let title = summarizer.title(&user_text(opening)).await.with_context(|| format!("Titling session {} failed", session.id))?;
+            session.title = Some(title);
+            store.save(session).await?;
+            titled += 1;
+        }
+        eprintln!("🏷️  Titled {} sessions", titled);
+    }
+
+    // Newest first
+    let entries: Vec<SessionEntry> = sessions.iter().rev().map(SessionEntry::of).collect();
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+    } else {
+        print!("{}", render(&entries));
+    }
+    Ok(())
+}
+
+fn render(entries: &[SessionEntry]) -> String {
+    if entries.is_empty() {
+        return "No saved sessions\n".to_string();
+    }
+    let mut out = format!("{:<15}  {:<16}  {:<8}  {:>5}  {}\n", "ID", "STARTED", "MOOD", "TURNS", "TITLE");
+    for entry in entries {
+        let started = entry.started_at.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default();
+        let mood = entry.dominant_emotion.map(|s| format!("{:?}", s)).unwrap_or_else(|| "-".to_string());
+        let mut title = entry.title.clone();
+        for tag in &entry.tags {
+            title.push_str(&format!(" #{}", tag));
+        }
+        out.push_str(&format!("{:<15}  {:<16}  {:<8}  {:>5}  {}\n", entry.id, started, mood, entry.turns, title.trim_start()));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut session = StoredSession::new(1_770_000_000, Vec::new());
+        session.title = Some("Exam nerves".to_string());
+        session.tags = vec!["school".to_string()];
+        let listing = render(&[SessionEntry::of(&session)]);
+        let mut lines = listing.lines();
+        assert_eq!(lines.next(), Some("ID               STARTED           MOOD      TURNS  TITLE"));
+        assert_eq!(lines.next(), Some("20260202-024000  2026-02-02 02:40  -             0  Exam nerves #school"));
+        assert_eq!(render(&[]), "No saved sessions\n");
+    }
+}
@@ -0,0 +1,172 @@
+use anyhow::{Context, Result};
+use rig::providers::openai;
+use serde::Serialize;
+use crate::cli::{GlobalArgs, SimulateArgs};
+use crate::agents::{ChatAgent, EmotionDetector, EmotionScript, PromptDebug, TopicGuard, UserSimulator};
+use crate::health::check_provider;
+use crate::state::{ConversationManager, EmotionTrend};
+use crate::strategy::ResponseStrategy;
+use crate::turn::{StrategyMode, StrategyPolicy, run_turn};
+use crate::{Config, Sentiment};
+
+/// One simulated turn and what the pipeline made of it
+#[derive(Debug, Serialize)]
+pub struct SimulatedTurn {
+    pub run: usize,
+    pub turn: usize,
+    /// What the script asked the simulated user to feel
+    pub scripted: Sentiment,
+    pub message: String,
+    pub detected: Sentiment,
+    pub confidence: f32,
+    pub trend: EmotionTrend,
+    pub strategy: ResponseStrategy,
+    pub response: String,
+}
+
+pub async fn run(config: &Config, global: &GlobalArgs, args: &SimulateArgs) -> Result<()> {
+    let script = match &args.script {
+        Some(path) => {
+            let json = std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+            serde_json::from_str(&json).with_context(|| format!("Cannot parse {}", path.display()))?
+        }
+        None => EmotionScript::calming(),
+    };
+    let steps = script.turns();
+    if steps.is_empty() {
+        anyhow::bail!("the emotion script has no turns");
+    }
+
+    let client = openai::Client::from_url(&config.api_key, &config.base_url);
+    if !global.skip_health_check && !config.dry_run {
+        check_provider(&client, &config.base_url, &config.model).await?;
+    }
+    let debug = PromptDebug::new(global.debug_prompts);
+    let simulator = UserSimulator::new(client.clone(), args.model.as_deref().unwrap_or(&config.model)).with_dry_run(config.dry_run);
+    let detector = EmotionDetector::new(client.clone(), &config.model)
+        .with_dry_run(config.dry_run)
+        .with_repair_attempts(config.repair_attempts)
+        .with_self_consistency(config.self_consistency)
+        .with_fallback(config.fallback)
+        .with_examples(config.emotion_examples.clone())
+        .with_prompt_debug(debug.clone());
+    let topics = TopicGuard::new(client.clone(), &config.model, config.blocked_topics(None)).with_dry_run(config.dry_run);
+    let chat_agent = ChatAgent::new(client.clone(), &config.model)
+        .with_dry_run(config.dry_run)
+        .with_wellbeing_safe(config.wellbeing_safe)
+        .with_minor_safe(config.minor_safe)
+        .with_profanity_filter(config.profanity)
+        .with_generation(config.generation.clone())
+        .with_context_window(config.context_window)
+        .with_prompt_debug(debug);
+    let mode = if global.blend_strategies { StrategyMode::Blended } else { StrategyMode::Single };
+    let policy = StrategyPolicy { mode, ..config.strategy };
+
+    let mut turns = Vec::with_capacity(steps.len() * usize::from(args.runs));
+    for run in 1..=usize::from(args.runs) {
+        // Each run is a fresh conversation
+        let mut state = ConversationManager::new();
+        for (i, step) in steps.iter().enumerate() {
+            let message = simulator
+                .next_message(&script, step, state.get_history())
+                .await
+                .with_context(|| format!("Simulating turn {} of run {} failed", i + 1, run))?;
+            let outcome = run_turn(&detector, &chat_agent, &topics, &mut state, &message, policy, &config.pipeline).await?;
+            let turn = SimulatedTurn {
+                run,
+                turn: i + 1,
+                scripted: step.sentiment,
+                message,
+                detected: outcome.emotion.sentiment,
+                confidence: outcome.emotion.confidence,
+                trend: outcome.trend,
+                strategy: outcome.strategy,
+                response: outcome.response,
+            };
+            if !args.json {
+                print!("{}", render_turn(&turn));
+            }
+            turns.push(turn);
+        }
+        if !args.json {
+            let run_turns: Vec<&SimulatedTurn> = turns.iter().filter(|t| t.run == run).collect();
+            println!("📋 Run {}: {}: {}\n", run, summarize(&run_turns), strategy_path(&run_turns));
+        }
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&turns)?);
+    } else if args.runs > 1 {
+        println!("🧪 {} runs: {}", args.runs, summarize(&turns.iter().collect::<Vec<_>>()));
+    }
+    Ok(())
+}
+
+fn render_turn(turn: &SimulatedTurn) -> String {
+    let mut detected = format!("{:?} ({:.2}), {:?} → {:?}", turn.detected, turn.confidence, turn.trend, turn.strategy);
+    if turn.detected != turn.scripted {
+        detected.push_str(&format!("  ✗ scripted {:?}", turn.scripted));
+    }
+    format!("{}. 👤 {}\n   {}\n   🤖 {}\n", turn.turn, turn.message, detected, turn.response)
+}
+
+/// How often the scripted emotion was detected, and how often the strategy changed
+fn summarize(turns: &[&SimulatedTurn]) -> String {
+    let matched = turns.iter().filter(|t| t.detected == t.scripted).count();
+    // A new run starts over rather than transitioning
+    let transitions = turns.windows(2).filter(|w| w[0].run == w[1].run && w[0].strategy != w[1].strategy).count();
+    format!("scripted emotion detected in {}/{} turns, {} strategy transitions", matched, turns.len(), transitions)
+}
+
+/// The strategies one run's replies moved through, e.g. `Encouraging → Neutral → Cheerful`
+fn strategy_path(turns: &[&SimulatedTurn]) -> String {
+    let mut strategies: Vec<String> = Vec::new();
+    for (i, turn) in turns.iter().enumerate() {
+        if i == 0 || turns[i - 1].strategy != turn.strategy {
+            strategies.push(format!("{:?}", turn.strategy));
+        }
+    }
+    strategies.join(" → ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(run: usize, scripted: Sentiment, detected: Sentiment, strategy: ResponseStrategy) -> SimulatedTurn {
+        SimulatedTurn {
+            run,
+            turn: 1,
+            scripted,
+            message: "I'm so angry".to_string(),
+            detected,
+            confidence: 0.8,
+            trend: EmotionTrend::Stable,
+            strategy,
+            response: "That sounds frustrating.".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        use ResponseStrategy::{Cheerful, Empathetic, Encouraging};
+        use Sentiment::{Negative, Neutral, Positive};
+
+        let turns = [
+            turn(1, Negative, Negative, Encouraging),
+            turn(1, Negative, Negative, Empathetic),
+            turn(1, Neutral, Negative, Empathetic),
+            turn(1, Positive, Positive, Cheerful),
+            turn(2, Negative, Negative, Encouraging),
+        ];
+        let turns: Vec<&SimulatedTurn> = turns.iter().collect();
+        assert_eq!(summarize(&turns[..4]), "scripted emotion detected in 3/4 turns, 2 strategy transitions");
+        assert_eq!(strategy_path(&turns[..4]), "Encouraging → Empathetic → Cheerful");
+        // Runs do not transition into each other
+        assert_eq!(summarize(&turns), "scripted emotion detected in 4/5 turns, 2 strategy transitions");
+        assert_eq!(
+            render_turn(turns[2]),
+            "1. 👤 I'm so angry\n   Negative (0.80), Stable → Empathetic  ✗ scripted Neutral\n   🤖 That sounds frustrating.\n"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Slash commands, multi-line input and tab completion for the chat REPL
+
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Helper};
+use crate::storage::normalize_tag;
+use crate::strategy::ResponseStrategy;
+
+/// Fences a message that spans several lines
+const BLOCK: &str = "\"\"\"";
+
+const COMMANDS: &[&str] = &["/help", "/stats", "/debug", "/brief", "/strategy", "/load", "/tag", "/untag", "/bookmark", "/bookmarks", "/alts", "/why", "/provider"];
+
+pub const HELP: &str = "\
+/help                 Show this list
+/stats                Emotions, strategies and key phrases of this session
+/debug [on|off]       Show or hide the prompts sent to the model
+/brief                Toggle short replies
+/strategy <name|auto> Answer with a fixed strategy, or let emotions decide again
+/load <session>       Continue a saved session
+/tag [tag]            Tag this session, or list its tags
+/untag <tag>          Remove a tag from this session
+/bookmark [n]         Mark the last message, or message n, as important
+/bookmarks            List bookmarked messages from all sessions
+/alts                 Show the other candidate replies to your last message
+/why                  Explain the emotion and strategy behind the last reply
+/provider [name]      Switch to a provider profile, or list them
+quit, exit            Save the session and leave
+
+Start a message with \"\"\" and end it with \"\"\" to write several lines,
+or press Alt+Enter for a new line.";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlashCommand {
+    Help,
+    Stats,
+    /// `None` reports the current setting
+    Debug(Option<bool>),
+    Brief,
+    /// `None` goes back to automatic selection
+    Strategy(Option<ResponseStrategy>),
+    Load(String),
+    /// `None` lists the session's tags
+    Tag(Option<String>),
+    Untag(String),
+    /// 1-based message number; `None` marks the last message
+    Bookmark(Option<usize>),
+    Bookmarks,
+    Alts,
+    Why,
+    /// `None` lists the profiles
+    Provider(Option<String>),
+}
+
+/// Returns `None` for ordinary chat input, or the parsed command with a usage
+/// message on bad arguments
+pub fn parse(input: &str) -> Option<Result<SlashCommand, String>> {
+    let rest = input.strip_prefix('/')?;
+    let (name, arg) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let arg = arg.trim();
+
+    let command = match name {
+        "help" => Ok(SlashCommand::Help),
+        "stats" => Ok(SlashCommand::Stats),
+        "debug" => match arg {
+            "" => Ok(SlashCommand::Debug(None)),
+            "on" => Ok(SlashCommand::Debug(Some(true))),
+            "off" => Ok(SlashCommand::Debug(Some(false))),
+            _ => Err("Usage: /debug [on|off]".to_string()),
+        },
+        "brief" => Ok(SlashCommand::Brief),
+        "strategy" if arg.eq_ignore_ascii_case("auto") => Ok(SlashCommand::Strategy(None)),
+        "strategy" => ResponseStrategy::from_name(arg)
+            .map(|s| SlashCommand::Strategy(Some(s)))
+            .ok_or_else(|| format!("Usage: /strategy <{}|auto>", strategy_names().join("|"))),
+        "load" if !arg.is_empty() => Ok(SlashCommand::Load(arg.to_string())),
+        "load" => Err("Usage: /load <session>".to_string()),
+        "tag" if arg.is_empty() => Ok(SlashCommand::Tag(None)),
+        "tag" => normalize_tag(arg).map(|t| SlashCommand::Tag(Some(t))).ok_or_else(tag_usage),
+        "untag" => normalize_tag(arg).map(SlashCommand::Untag).ok_or_else(tag_usage),
+        "bookmark" if arg.is_empty() => Ok(SlashCommand::Bookmark(None)),
+        "bookmark" => match arg.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(SlashCommand::Bookmark(Some(n))),
+            _ => Err("Usage: /bookmark [message number]".to_string()),
+        },
+        "bookmarks" => Ok(SlashCommand::Bookmarks),
+        "alts" => Ok(SlashCommand::Alts),
+        "why" => Ok(SlashCommand::Why),
+        "provider" if arg.is_empty() => Ok(SlashCommand::Provider(None)),
+        "provider" if !arg.contains(char::is_whitespace) => Ok(SlashCommand::Provider(Some(arg.to_string()))),
+        "provider" => Err("Usage: /provider [name]".to_string()),
+        _ => Err(format!("Unknown command /{}, try /help", name)),
+    };
+    Some(command)
+}
+
+/// Whether `input` opens a `\"\"\"` block that has not been closed yet
+pub fn is_open_block(input: &str) -> bool {
+    input.trim_start().starts_with(BLOCK) && input.matches(BLOCK).count() % 2 == 1
+}
+
+/// Strips the `\"\"\"` fences around a multi-line message
+pub fn unwrap_block(input: &str) -> &str {
+    let input = input.trim();
+    match input.strip_prefix(BLOCK) {
+        Some(rest) => rest.strip_suffix(BLOCK).unwrap_or(rest).trim(),
+        None => input,
+    }
+}
+
+fn tag_usage() -> String {
+    "Usage: /tag <tag>, /untag <tag>; tags are one word of letters, digits, - and _".to_string()
+}
+
+fn strategy_names() -> Vec<String> {
+    ResponseStrategy::ALL.iter().map(|s| format!("{:?}", s).to_lowercase()).collect()
+}
+
+/// Completes command names, then each command's arguments
+pub struct ChatHelper {
+    pub session_ids: Vec<String>,
+    /// Tags used on saved sessions
+    pub tags: Vec<String>,
+    /// Names of the provider profiles
+    pub providers: Vec<String>,
+}
+
+impl ChatHelper {
+    fn candidates(&self, line: &str) -> (usize, Vec<String>) {
+        if !line.starts_with('/') {
+            return (0, Vec::new());
+        }
+
+        let Some((name, arg)) = line.split_once(' ') else {
+            let names = COMMANDS.iter().filter(|c| c.starts_with(line)).map(|c| c.to_string());
+            return (0, names.collect());
+        };
+
+        let options = match name {
+            "/debug" => vec!["on".to_string(), "off".to_string()],
+            "/strategy" => {
+                let mut names = strategy_names();
+                names.push("auto".to_string());
+                names
+            }
+            "/load" => self.session_ids.clone(),
+            "/tag" | "/untag" => self.tags.clone(),
+            "/provider" => self.providers.clone(),
+            _ => Vec::new(),
+        };
+        let arg = arg.trim_start();
+        (line.len() - arg.len(), options.into_iter().filter(|o| o.starts_with(arg)).collect())
+    }
+}
+
+impl Completer for ChatHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(&line[..pos]))
+    }
+}
+
+impl Hinter for ChatHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ChatHelper {}
+
+impl Validator for ChatHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if is_open_block(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl Helper for ChatHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("hello"), None);
+        assert_eq!(parse("/debug on"), Some(Ok(SlashCommand::Debug(Some(true)))));
+        assert_eq!(parse("/strategy Cheerful"), Some(Ok(SlashCommand::Strategy(Some(ResponseStrategy::Cheerful)))));
+        assert_eq!(parse("/strategy auto"), Some(Ok(SlashCommand::Strategy(None))));
+        assert_eq!(parse("/load 20260201-120000"), Some(Ok(SlashCommand::Load("20260201-120000".to_string()))));
+        assert_eq!(parse("/tag #Work-Stress"), Some(Ok(SlashCommand::Tag(Some("work-stress".to_string())))));
+        assert_eq!(parse("/tag"), Some(Ok(SlashCommand::Tag(None))));
+        assert!(parse("/tag two words").unwrap().is_err());
+        assert!(parse("/untag").unwrap().is_err());
+        assert_eq!(parse("/bookmark"), Some(Ok(SlashCommand::Bookmark(None))));
+        assert_eq!(parse("/bookmark 3"), Some(Ok(SlashCommand::Bookmark(Some(3)))));
+        assert!(parse("/bookmark 0").unwrap().is_err());
+        assert_eq!(parse("/why"), Some(Ok(SlashCommand::Why)));
+        assert_eq!(parse("/brief"), Some(Ok(SlashCommand::Brief)));
+        // Only an operator may hand a paused conversation back, never the user
+        assert!(parse("/release").unwrap().is_err());
+        assert_eq!(parse("/provider"), Some(Ok(SlashCommand::Provider(None))));
+        assert_eq!(parse("/provider local-ollama"), Some(Ok(SlashCommand::Provider(Some("local-ollama".to_string())))));
+        assert!(parse("/provider two words").unwrap().is_err());
+        assert!(parse("/strategy grumpy").unwrap().is_err());
+        assert!(parse("/nope").unwrap().is_err());
+    }
+
+    #[test]
+    fn test_multi_line_block() {
+        assert!(is_open_block("\"\"\"Dear diary,"));
+        assert!(is_open_block("  \"\"\""));
+        assert!(!is_open_block("\"\"\"Dear diary,\ntoday was long.\n\"\"\""));
+        assert!(!is_open_block("I said \"\"\" once"));
+
+        assert_eq!(unwrap_block("\"\"\"\nDear diary,\ntoday was long.\n\"\"\""), "Dear diary,\ntoday was long.");
+        assert_eq!(unwrap_block("  hello  "), "hello");
+    }
+
+    #[test]
+    fn test_completion() {
+        let helper = ChatHelper {
+            session_ids: vec!["20260201-120000".to_string(), "20260305-090000".to_string()],
+            tags: vec!["work-stress".to_string(), "sleep".to_string()],
+            providers: vec!["work-azure".to_string(), "local-ollama".to_string()],
+        };
+
+        assert_eq!(helper.candidates("/st"), (0, vec!["/stats".to_string(), "/strategy".to_string()]));
+        assert_eq!(helper.candidates("/strategy en"), (10, vec!["encouraging".to_string()]));
+        assert_eq!(helper.candidates("/load 202602"), (6, vec!["20260201-120000".to_string()]));
+        assert_eq!(helper.candidates("/debug  o").1, vec!["on".to_string(), "off".to_string()]);
+        assert_eq!(helper.candidates("/tag wo"), (5, vec!["work-stress".to_string()]));
+        assert_eq!(helper.candidates("/provider lo"), (10, vec!["local-ollama".to_string()]));
+        assert!(helper.candidates("hello").1.is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+use anyhow::{Context, Result};
+use rig::providers::openai;
+use serde::Serialize;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::time::Duration;
+use crate::cli::{GlobalArgs, WatchArgs};
+use crate::agents::{EmotionDetector, PromptDebug};
+use crate::{Config, SentimentClassification};
+
+/// One classified line, printed as a JSON line
+#[derive(Debug, Serialize)]
+pub struct WatchedLine {
+    pub text: String,
+    #[serde(flatten)]
+    pub classification: SentimentClassification,
+    /// When the line was classified, in Unix seconds
+    pub at: i64,
+}
+
+/// Follows a file as it grows, like `tail -f`
+#[derive(Debug)]
+pub struct Tail {
+    path: PathBuf,
+    offset: u64,
+    /// Which file `offset` is into, to notice one renamed over the path
+    identity: Option<FileIdentity>,
+    /// The last line, until its newline arrives; kept as bytes so a
+    /// character split across writes is decoded whole
+    partial: Vec<u8>,
+}
+
+impl Tail {
+    /// Starts at the end of the file, or at its beginning with `from_start`
+    pub fn open(path: PathBuf, from_start: bool) -> Result<Self> {
+        let metadata = std::fs::metadata(&path).with_context(|| format!("Cannot watch {}", path.display()))?;
+        let offset = if from_start { 0 } else { metadata.len() };
+        Ok(Self { path, offset, identity: FileIdentity::of(&metadata), partial: Vec::new() })
+    }
+
+    /// Complete, non-blank lines written since the last poll. A file that
+    /// shrank was truncated, and one that is no longer the file it was was
+    /// replaced; either is read again from the start. A missing one is being
+    /// rotated, and the file that replaces it is read from its start once it
+    /// appears.
+    pub fn poll(&mut self) -> Result<Vec<String>> {
+        let mut file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                self.offset = 0;
+                self.identity = None;
+                self.partial.clear();
+                return Ok(Vec::new());
+            }
+            Err(e) => return Err(e).with_context(|| format!("Cannot read {}", self.path.display())),
+        };
+        let metadata = file.metadata()?;
+        let identity = FileIdentity::of(&metadata);
+        if metadata.len() < self.offset || identity != self.identity {
+            self.offset = 0;
+            self.identity = identity;
+            self.partial.clear();
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        self.offset += file.read_to_end(&mut self.partial)? as u64;
+
+        let Some(end) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Ok(Vec::new());
+        };
+        let complete: Vec<u8> = self.partial.drain(..=end).collect();
+        Ok(String::from_utf8_lossy(&complete).lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect())
+    }
+}
+
+/// The device and inode of a file, which survive appends and truncation but
+/// not a new file renamed into its place
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileIdentity {
+    device: u64,
+    inode: u64,
+}
+
+impl FileIdentity {
+    /// `None` where the platform has no stable file identity in std, leaving
+    /// only a shrinking size to tell a replaced file apart
+    #[cfg(unix)]
+    fn of(metadata: &std::fs::Metadata) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+        Some(Self { device: metadata.dev(), inode: metadata.ino() })
+    }
+
+    #[cfg(not(unix))]
+    fn of(_metadata: &std::fs::Metadata) -> Option<Self> {
+        None
+    }
+}
+
+pub async fn run(config: &Config, global: &GlobalArgs, args: &WatchArgs) -> Result<()> {
+    let mut tail = Tail::open(args.file.clone(), args.from_start)?;
+    let client = openai::Client::from_url(&config.api_key, &config.base_url);
+    let detector = EmotionDetector::new(client, &config.model)
+        .with_dry_run(config.dry_run)
+        .with_repair_attempts(config.repair_attempts)
+        .with_self_consistency(config.self_consistency)
+        .with_fallback(config.fallback)
+        .with_examples(config.emotion_examples.clone())
+        .with_cache(config.classification_cache(None, &config.base_url))
+        .with_prompt_debug(PromptDebug::new(global.debug_prompts));
+
+    eprintln!("👀 Watching {} (Ctrl+C to stop)", args.file.display());
+    let mut interval = tokio::time::interval(Duration::from_millis(args.interval_ms));
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+            _ = interval.tick() => {}
+        }
+
+        let lines = tail.poll()?;
+        if lines.is_empty() {
+            continue;
+        }
+        let texts: Vec<&str> = lines.iter().map(String::as_str).collect();
+        for (text, result) in lines.iter().zip(detector.analyze_batch(&texts).await) {
+            match result {
+                Ok(classification) => {
+                    let line = WatchedLine { text: text.clone(), classification, at: chrono::Utc::now().timestamp() };
+                    println!("{}", serde_json::to_string(&line)?);
+                }
+                Err(e) => eprintln!("❌ {}: {}", text, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_tail_follows_appends_truncation_and_rotation() {
+        let path = std::env::temp_dir().join(format!("tce-watch-{}.log", std::process::id()));
+        std::fs::write(&path, "old line\n").unwrap();
+        let mut tail = Tail::open(path.clone(), false).unwrap();
+        assert!(tail.poll().unwrap().is_empty());
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "first\n\nsecond, still typ").unwrap();
+        assert_eq!(tail.poll().unwrap(), ["first"]);
+        writeln!(file, "ing").unwrap();
+        assert_eq!(tail.poll().unwrap(), ["second, still typing"]);
+
+        std::fs::write(&path, "rotated\n").unwrap();
+        assert_eq!(tail.poll().unwrap(), ["rotated"]);
+        assert_eq!(Tail::open(path.clone(), true).unwrap().poll().unwrap(), ["rotated"]);
+
+        // A character split between writes
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        let smile = "glad 🙂\n".as_bytes();
+        file.write_all(&smile[..6]).unwrap();
+        assert!(tail.poll().unwrap().is_empty());
+        file.write_all(&smile[6..]).unwrap();
+        assert_eq!(tail.poll().unwrap(), ["glad 🙂"]);
+
+        // Rotation removes the file for a moment; the new one is read from its start
+        std::fs::remove_file(&path).unwrap();
+        assert!(tail.poll().unwrap().is_empty());
+        std::fs::write(&path, "after rotation, a longer first line\n").unwrap();
+        assert_eq!(tail.poll().unwrap(), ["after rotation, a longer first line"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tail_rereads_a_longer_file_renamed_over_it() {
+        let path = std::env::temp_dir().join(format!("tce-watch-rename-{}.log", std::process::id()));
+        let next = path.with_extension("log.next");
+        std::fs::write(&path, "short\n").unwrap();
+        let mut tail = Tail::open(path.clone(), true).unwrap();
+        assert_eq!(tail.poll().unwrap(), ["short"]);
+
+        // Never shorter than what was read, so only its identity gives it away
+        std::fs::write(&next, "a new file\nthat is longer than the old one\n").unwrap();
+        std::fs::rename(&next, &path).unwrap();
+        assert_eq!(tail.poll().unwrap(), ["a new file", "that is longer than the old one"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
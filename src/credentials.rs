@@ -0,0 +1,35 @@
+//! The provider API key kept in the system keyring (`keyring` feature) rather
+//! than in a plaintext `.env` file
+
+use anyhow::{Context, Result};
+use keyring::Entry;
+
+/// The keyring service every key is saved under
+const SERVICE: &str = "text_classifier_extractor";
+
+/// One key per provider, so changing `OPENAI_BASE_URL` picks up that provider's key
+fn entry(base_url: &str) -> Result<Entry> {
+    Entry::new(SERVICE, base_url).context("cannot open the system keyring")
+}
+
+/// The key `login` saved for `base_url`, if any
+pub fn load(base_url: &str) -> Result<Option<String>> {
+    match entry(base_url)?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e).context("cannot read the API key from the system keyring"),
+    }
+}
+
+pub fn store(base_url: &str, key: &str) -> Result<()> {
+    entry(base_url)?.set_password(key).context("cannot save the API key in the system keyring")
+}
+
+/// Whether there was a key to remove
+pub fn delete(base_url: &str) -> Result<bool> {
+    match entry(base_url)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(e).context("cannot remove the API key from the system keyring"),
+    }
+}
@@ -0,0 +1,63 @@
+//! Conversation state changes as events, for listeners such as metrics that
+//! should not be wired into the turn itself
+
+use serde::Serialize;
+use std::sync::Arc;
+use crate::SentimentClassification;
+use crate::models::MessageRole;
+use crate::state::EmotionTrend;
+use crate::strategy::ResponseStrategy;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    MessageAdded { role: MessageRole, content: String },
+    /// The latest user message was classified
+    EmotionUpdated { emotion: SentimentClassification },
+    TrendChanged { from: EmotionTrend, to: EmotionTrend },
+    /// The last reply was recorded as answered with `strategy`
+    StrategySelected { strategy: ResponseStrategy },
+    /// The chat model replied; fixed notices and refusals are not included
+    ResponseGenerated { strategy: ResponseStrategy, response: String },
+}
+
+/// Listeners run in the publishing thread, so slow work belongs on a task
+pub trait Subscriber: Send + Sync {
+    fn notify(&self, event: &Event);
+}
+
+impl<F: Fn(&Event) + Send + Sync> Subscriber for F {
+    fn notify(&self, event: &Event) {
+        self(event)
+    }
+}
+
+/// Subscribers in registration order. A clone starts with the subscribers
+/// registered so far; later subscriptions only reach the bus they were made on.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Vec<Arc<dyn Subscriber>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self, subscriber: impl Subscriber + 'static) {
+        self.subscribers.push(Arc::new(subscriber));
+    }
+
+    pub fn with(mut self, subscriber: impl Subscriber + 'static) -> Self {
+        self.subscribe(subscriber);
+        self
+    }
+
+    pub fn publish(&self, event: Event) {
+        for subscriber in &self.subscribers {
+            subscriber.notify(&event);
+        }
+    }
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventBus").field("subscribers", &self.subscribers.len()).finish()
+    }
+}
@@ -0,0 +1,262 @@
+//! C ABI for embedding the chat pipeline in other languages; the declarations
+//! are in `include/tce.h`
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::ptr;
+use crate::SentimentClassification;
+use crate::agents::{ChatAgent, EmotionDetector, TopicGuard};
+use crate::handoff::HandoffReason;
+use crate::state::{ConversationManager, EmotionTrend};
+use crate::strategy::ResponseStrategy;
+use crate::turn::{Pipeline, StrategyPolicy, TurnOutcome, run_turn};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionConfig {
+    api_key: String,
+    #[serde(default = "default_base_url")]
+    base_url: String,
+    #[serde(default = "default_model")]
+    model: String,
+    /// Answer without calling the provider, for wiring up an integration
+    #[serde(default)]
+    dry_run: bool,
+}
+
+fn default_base_url() -> String {
+    "https://open.bigmodel.cn/api/paas/v4".to_string()
+}
+
+fn default_model() -> String {
+    "glm-4.7".to_string()
+}
+
+/// What `tce_session_turn` returns, with the same fields as the HTTP chat endpoint
+#[derive(Debug, Serialize)]
+struct TurnJson {
+    emotion: SentimentClassification,
+    trend: EmotionTrend,
+    strategy: ResponseStrategy,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    handoff: Option<HandoffReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blocked_topic: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    break_suggested: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    injection: Option<String>,
+    response: String,
+}
+
+impl From<TurnOutcome> for TurnJson {
+    fn from(outcome: TurnOutcome) -> Self {
+        Self {
+            emotion: outcome.emotion,
+            trend: outcome.trend,
+            strategy: outcome.strategy,
+            handoff: outcome.handoff,
+            blocked_topic: outcome.blocked_topic,
+            break_suggested: outcome.break_suggested,
+            injection: outcome.injection,
+            response: outcome.response,
+        }
+    }
+}
+
+/// One conversation with its own runtime; not safe to share between threads
+pub struct TceSession {
+    runtime: tokio::runtime::Runtime,
+    detector: EmotionDetector,
+    chat_agent: ChatAgent,
+    topics: TopicGuard,
+    state: ConversationManager,
+}
+
+impl TceSession {
+    fn new(config: &str) -> anyhow::Result<Self> {
+        let config: SessionConfig = serde_json::from_str(config)?;
+        let client = rig::providers::openai::Client::from_url(&config.api_key, &config.base_url);
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        Ok(Self {
+            runtime,
+            detector: EmotionDetector::new(client.clone(), &config.model).with_dry_run(config.dry_run),
+            chat_agent: ChatAgent::new(client.clone(), &config.model).with_dry_run(config.dry_run),
+            topics: TopicGuard::new(client, &config.model, Vec::new()),
+            state: ConversationManager::new(),
+        })
+    }
+
+    fn turn(&mut self, text: &str) -> anyhow::Result<String> {
+        let outcome = self.runtime.block_on(run_turn(
+            &self.detector,
+            &self.chat_agent,
+            &self.topics,
+            &mut self.state,
+            text,
+            StrategyPolicy::default(),
+            &Pipeline::default(),
+        ))?;
+        Ok(serde_json::to_string(&TurnJson::from(outcome))?)
+    }
+}
+
+fn set_error(error: impl std::fmt::Display) {
+    let message = CString::new(error.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Runs an exported function's body, turning a panic into `failed` with the
+/// panic as the last error, since unwinding across the C ABI aborts the host
+fn guard<T>(failed: T, body: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown cause".to_string());
+        set_error(format!("panicked: {}", message));
+        failed
+    })
+}
+
+/// Starts a session from a JSON config: `api_key` (required), `base_url`,
+/// `model` and `dry_run`. Returns null on failure; see `tce_last_error`.
+///
+/// # Safety
+///
+/// `config` must be a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tce_session_new(config: *const c_char) -> *mut TceSession {
+    guard(ptr::null_mut(), || {
+        if config.is_null() {
+            set_error("config is null");
+            return ptr::null_mut();
+        }
+        let config = match unsafe { CStr::from_ptr(config) }.to_str() {
+            Ok(config) => config,
+            Err(e) => {
+                set_error(e);
+                return ptr::null_mut();
+            }
+        };
+        match TceSession::new(config) {
+            Ok(session) => Box::into_raw(Box::new(session)),
+            Err(e) => {
+                set_error(format!("{:#}", e));
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Runs one turn and returns it as JSON, to be freed with `tce_string_free`.
+/// Blocks until the reply is ready. Returns null on failure; see `tce_last_error`.
+///
+/// # Safety
+///
+/// `session` must come from `tce_session_new` and `text` must be a valid
+/// NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tce_session_turn(session: *mut TceSession, text: *const c_char) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let Some(session) = (unsafe { session.as_mut() }) else {
+            set_error("session is null");
+            return ptr::null_mut();
+        };
+        if text.is_null() {
+            set_error("text is null");
+            return ptr::null_mut();
+        }
+        let result = unsafe { CStr::from_ptr(text) }
+            .to_str()
+            .map_err(anyhow::Error::from)
+            .and_then(|text| session.turn(text))
+            .and_then(|json| Ok(CString::new(json)?));
+        match result {
+            Ok(json) => json.into_raw(),
+            Err(e) => {
+                set_error(format!("{:#}", e));
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// The last error on this thread, or null. Valid until the next failing call.
+#[unsafe(no_mangle)]
+pub extern "C" fn tce_last_error() -> *const c_char {
+    guard(ptr::null(), || {
+        LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+    })
+}
+
+/// # Safety
+///
+/// `s` must come from this library and not have been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tce_string_free(s: *mut c_char) {
+    guard((), || {
+        if !s.is_null() {
+            drop(unsafe { CString::from_raw(s) });
+        }
+    })
+}
+
+/// # Safety
+///
+/// `session` must come from `tce_session_new` and not have been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tce_session_free(session: *mut TceSession) {
+    guard((), || {
+        if !session.is_null() {
+            drop(unsafe { Box::from_raw(session) });
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_turn_round_trip() {
+        let config = CString::new(r#"{"api_key": "test-key", "base_url": "https://api.example.com", "dry_run": true}"#).unwrap();
+        let text = CString::new("I had a good day").unwrap();
+        unsafe {
+            let session = tce_session_new(config.as_ptr());
+            assert!(!session.is_null());
+
+            let json = tce_session_turn(session, text.as_ptr());
+            assert!(!json.is_null());
+            let turn: serde_json::Value = serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+            assert!(turn["response"].as_str().unwrap().starts_with("[dry run]"));
+            assert!(turn["emotion"]["sentiment"].is_string());
+
+            tce_string_free(json);
+            tce_session_free(session);
+        }
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        let config = CString::new("{}").unwrap();
+        let session = unsafe { tce_session_new(config.as_ptr()) };
+        assert!(session.is_null());
+        let error = unsafe { CStr::from_ptr(tce_last_error()) }.to_str().unwrap();
+        assert!(error.contains("api_key"));
+    }
+
+    #[test]
+    fn test_panics_are_reported_as_errors() {
+        let result = guard(ptr::null_mut::<c_char>(), || panic!("hook exploded"));
+        assert!(result.is_null());
+        let error = unsafe { CStr::from_ptr(tce_last_error()) }.to_str().unwrap();
+        assert_eq!(error, "panicked: hook exploded");
+    }
+}
@@ -0,0 +1,9 @@
+//! gRPC interface alongside the HTTP server
+
+pub mod service;
+
+pub mod proto {
+    tonic::include_proto!("emotion.v1");
+}
+
+pub use service::{EmotionGrpc, serve};
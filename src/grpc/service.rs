@@ -0,0 +1,397 @@
+use anyhow::Result;
+use futures::{Stream, StreamExt};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tonic::{Request, Response, Status};
+use crate::agents::{AgentError, ReplyChunk};
+use crate::handoff;
+use crate::models::{Message, MessageRole};
+use crate::state::limits;
+use crate::server::auth::{ApiKeys, AuthError, AuthenticatedKey, extract_key};
+use crate::server::tenant::{TENANT_HEADER, Tenant, TenantRejection, Tenants};
+use crate::strategy::{StrategyBlend, preempt, select_strategy};
+use crate::{Sentiment, SentimentClassification};
+use super::proto::{self, chat_turn_event::Event};
+use super::proto::emotion_service_server::{EmotionService, EmotionServiceServer};
+
+type ChatTurnStream = Pin<Box<dyn Stream<Item = Result<proto::ChatTurnEvent, Status>> + Send>>;
+
+pub struct EmotionGrpc {
+    tenants: Arc<Tenants>,
+    /// Checked as for HTTP, from `x-api-key` or `authorization` metadata
+    api_keys: Option<Arc<ApiKeys>>,
+}
+
+impl EmotionGrpc {
+    pub fn new(tenants: Arc<Tenants>, api_keys: Option<Arc<ApiKeys>>) -> Self {
+        Self { tenants, api_keys }
+    }
+
+    /// Checks the caller's key, charging one item, and resolves their tenant
+    fn tenant<T>(&self, request: &Request<T>) -> Result<Arc<Tenant>, Status> {
+        let requested = request.metadata().get(TENANT_HEADER).and_then(|v| v.to_str().ok());
+        let key = match &self.api_keys {
+            Some(keys) => {
+                let headers = request.metadata().clone().into_headers();
+                let config = keys.check(extract_key(&headers), chrono::Utc::now()).map_err(auth_status)?;
+                Some(AuthenticatedKey::from(config))
+            }
+            None => None,
+        };
+
+        self.tenants.for_request(key.as_ref(), requested).map_err(|e| match e {
+            TenantRejection::Forbidden(id) => Status::permission_denied(format!("API key is not valid for tenant '{}'", id)),
+            TenantRejection::Unknown(id) => Status::not_found(format!("unknown tenant '{}'", id)),
+        })
+    }
+}
+
+pub async fn serve(addr: SocketAddr, service: EmotionGrpc) -> Result<()> {
+    tonic::transport::Server::builder()
+        .trace_fn(crate::telemetry::request_span)
+        .add_service(EmotionServiceServer::new(service))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
+
+#[tonic::async_trait]
+impl EmotionService for EmotionGrpc {
+    async fn classify(
+        &self,
+        request: Request<proto::ClassifyRequest>,
+    ) -> Result<Response<proto::Classification>, Status> {
+        let tenant = self.tenant(&request)?;
+        let text = request.into_inner().text;
+        if text.trim().is_empty() {
+            return Err(Status::invalid_argument("text must not be empty"));
+        }
+
+        let classification = tenant.detector
+            .analyze(&text)
+            .await
+            .map_err(|e| agent_status(&e))?;
+
+        Ok(Response::new(classification.into()))
+    }
+
+    type ChatTurnStream = ChatTurnStream;
+
+    async fn chat_turn(
+        &self,
+        request: Request<proto::ChatTurnRequest>,
+    ) -> Result<Response<Self::ChatTurnStream>, Status> {
+        let tenant = self.tenant(&request)?;
+        let request = request.into_inner();
+        if request.session_id.is_empty() {
+            return Err(Status::invalid_argument("session_id must not be empty"));
+        }
+        if request.text.trim().is_empty() {
+            return Err(Status::invalid_argument("text must not be empty"));
+        }
+
+        let mut state = tenant.sessions
+            .lock(&request.session_id)
+            .await
+            .map_err(|e| Status::unavailable(format!("{:#}", e)))?;
+        let (tx, rx) = mpsc::channel(32);
+
+        // Holding the session lock for the whole turn keeps turns within a session ordered
+        tokio::spawn(async move {
+
+            let previous = state.state().emotion_history.last().cloned();
+            let emotion = match tenant.detector.analyze_after(&request.text, previous.as_ref()).await {
+                Ok(e) => e,
+                Err(e) => {
+                    let _ = tx.send(Err(agent_status(&e))).await;
+                    return;
+                }
+            };
+
+            state.add_message(MessageRole::User, &request.text);
+            state.update_emotion(emotion.clone());
+
+            let trend = state.get_recent_emotion_trend().direction;
+            if let Some((reason, escalated)) = handoff::update(&mut state, &request.text, tenant.strategy.handoff) {
+                let response = handoff::notice(escalated);
+                let metadata = proto::TurnMetadata {
+                    emotion: Some(emotion.into()),
+                    trend: format!("{:?}", trend),
+                    strategy: "Handoff".to_string(),
+                };
+                let _ = tx.send(Ok(event(Event::Metadata(metadata)))).await;
+                let _ = tx.send(Ok(event(Event::Token(response.to_string())))).await;
+                state.add_message(MessageRole::Assistant, response);
+                if let Err(e) = tenant.sessions.persist(&request.session_id, &state).await {
+                    let _ = tx.send(Err(Status::unavailable(format!("{:#}", e)))).await;
+                }
+                if escalated {
+                    tenant.escalate(Some(&request.session_id), reason, &state).await;
+                }
+                return;
+            }
+            if let Some(topic) = tenant.topics.check(&request.text).await {
+                let response = topic.refusal();
+                let metadata = proto::TurnMetadata {
+                    emotion: Some(emotion.into()),
+                    trend: format!("{:?}", trend),
+                    strategy: "Blocked".to_string(),
+                };
+                let _ = tx.send(Ok(event(Event::Metadata(metadata)))).await;
+                let _ = tx.send(Ok(event(Event::Token(response.clone())))).await;
+                state.add_message(MessageRole::Assistant, &response);
+                if let Err(e) = tenant.sessions.persist(&request.session_id, &state).await {
+                    let _ = tx.send(Err(Status::unavailable(format!("{:#}", e)))).await;
+                }
+                return;
+            }
+            let mut proposed = select_strategy(&emotion, trend);
+            if tenant.strategy.preempt
+                && let Some(forecast) = state.forecast_next_emotion()
+            {
+                proposed = preempt(StrategyBlend::single(proposed), &forecast).primary();
+            }
+            let strategy = tenant.strategy.hysteresis.hold(state.get_history(), proposed, emotion.confidence).unwrap_or(proposed);
+            let now = chrono::Utc::now().timestamp();
+            let reminder = state.break_due(&tenant.strategy.limits, now).then(|| {
+                let (elapsed, turns) = state.sitting(now);
+                limits::break_reminder(elapsed, turns)
+            });
+
+            let metadata = proto::TurnMetadata {
+                emotion: Some(emotion.into()),
+                trend: format!("{:?}", trend),
+                strategy: format!("{:?}", strategy),
+            };
+            if tx.send(Ok(event(Event::Metadata(metadata)))).await.is_err() {
+                return;
+            }
+
+            let mut stream = match tenant.chat_agent.respond_stream(&request.text, &StrategyBlend::single(strategy), state.get_history(), reminder.as_deref()).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    let _ = tx.send(Err(agent_status(&e))).await;
+                    return;
+                }
+            };
+
+            let mut response = String::new();
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(ReplyChunk::Text(token)) => {
+                        response.push_str(&token);
+                        if tx.send(Ok(event(Event::Token(token)))).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(ReplyChunk::Usage(_) | ReplyChunk::Score(_)) => {}
+                    Err(e) => {
+                        let _ = tx.send(Err(agent_status(&e))).await;
+                        return;
+                    }
+                }
+            }
+
+            state.add_message(MessageRole::Assistant, &response);
+            state.update_strategy(strategy);
+            if reminder.is_some() {
+                state.mark_break_suggested();
+            }
+
+            if let Err(e) = tenant.sessions.persist(&request.session_id, &state).await {
+                let _ = tx.send(Err(Status::unavailable(format!("{:#}", e)))).await;
+            }
+        });
+
+        let stream = futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|item| (item, rx))
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn get_session(
+        &self,
+        request: Request<proto::GetSessionRequest>,
+    ) -> Result<Response<proto::Session>, Status> {
+        let tenant = self.tenant(&request)?;
+        let session_id = request.into_inner().session_id;
+        let state = tenant.sessions
+            .find(&session_id)
+            .await
+            .map_err(|e| Status::unavailable(format!("{:#}", e)))?
+            .ok_or_else(|| Status::not_found(format!("session '{}' not found", session_id)))?;
+
+        Ok(Response::new(proto::Session {
+            session_id,
+            messages: state.get_history().iter().map(Into::into).collect(),
+            trend: format!("{:?}", state.get_recent_emotion_trend().direction),
+        }))
+    }
+}
+
+fn agent_status(error: &AgentError) -> Status {
+    match error {
+        AgentError::RateLimited(_) => Status::resource_exhausted(error.to_string()),
+        AgentError::Timeout => Status::deadline_exceeded(error.to_string()),
+        _ => Status::unavailable(error.to_string()),
+    }
+}
+
+fn auth_status(error: AuthError) -> Status {
+    match error {
+        AuthError::MissingKey => Status::unauthenticated("missing API key"),
+        AuthError::UnknownKey => Status::unauthenticated("invalid API key"),
+        AuthError::RateLimited { .. } => Status::resource_exhausted("rate limit exceeded"),
+        AuthError::QuotaExceeded { .. } => Status::resource_exhausted("daily quota exceeded"),
+    }
+}
+
+fn event(event: Event) -> proto::ChatTurnEvent {
+    proto::ChatTurnEvent { event: Some(event) }
+}
+
+impl From<Sentiment> for proto::Sentiment {
+    fn from(sentiment: Sentiment) -> Self {
+        match sentiment {
+            Sentiment::Positive => proto::Sentiment::Positive,
+            Sentiment::Negative => proto::Sentiment::Negative,
+            Sentiment::Neutral => proto::Sentiment::Neutral,
+        }
+    }
+}
+
+impl From<SentimentClassification> for proto::Classification {
+    fn from(classification: SentimentClassification) -> Self {
+        Self {
+            sentiment: proto::Sentiment::from(classification.sentiment).into(),
+            confidence: classification.confidence,
+            is_fallback: classification.is_fallback,
+            labels: classification
+                .labels
+                .iter()
+                .map(|label| proto::EmotionLabel {
+                    sentiment: proto::Sentiment::from(label.sentiment).into(),
+                    confidence: label.confidence,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&Message> for proto::SessionMessage {
+    fn from(msg: &Message) -> Self {
+        Self {
+            role: format!("{:?}", msg.role),
+            content: msg.content.clone(),
+            timestamp: msg.timestamp,
+            emotion: msg.emotion.clone().map(Into::into),
+            strategy: msg.strategy.map(|s| format!("{:?}", s)).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+    use crate::strategy::ResponseStrategy;
+
+    fn service() -> EmotionGrpc {
+        EmotionGrpc::new(Arc::new(Tenants::single(Tenant::default_for(&Config::test()))), None)
+    }
+
+    #[test]
+    fn test_classification_conversion() {
+        let proto: proto::Classification = SentimentClassification {
+            sentiment: Sentiment::Negative,
+            confidence: 0.7,
+            labels: vec![crate::EmotionLabel { sentiment: Sentiment::Positive, confidence: 0.4 }],
+            is_fallback: false,
+        }.into();
+
+        assert_eq!(proto.sentiment(), proto::Sentiment::Negative);
+        assert_eq!(proto.confidence, 0.7);
+        assert_eq!(proto.labels[0].sentiment(), proto::Sentiment::Positive);
+    }
+
+    #[test]
+    fn test_session_message_conversion() {
+        let msg = Message {
+            role: MessageRole::Assistant,
+            content: "Hi".to_string(),
+            timestamp: 5,
+            emotion: None,
+            strategy: Some(ResponseStrategy::Cheerful),
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
+        };
+
+        let proto = proto::SessionMessage::from(&msg);
+        assert_eq!(proto.role, "Assistant");
+        assert_eq!(proto.strategy, "Cheerful");
+        assert!(proto.emotion.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_classify_rejects_empty_text() {
+        let status = service()
+            .classify(Request::new(proto::ClassifyRequest { text: " ".to_string() }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_tenant_rejected() {
+        let mut request = Request::new(proto::ClassifyRequest { text: "hi".to_string() });
+        request.metadata_mut().insert(TENANT_HEADER, "nope".parse().unwrap());
+
+        let status = service().classify(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_required() {
+        use crate::server::auth::ApiKeyConfig;
+
+        let keys = ApiKeys::new(vec![ApiKeyConfig {
+            key: "secret".to_string(),
+            name: "app".to_string(),
+            requests_per_minute: Some(1),
+            daily_quota: None,
+            tenant: None,
+            operator: false,
+        }]);
+        let service = EmotionGrpc { api_keys: Some(Arc::new(keys)), ..service() };
+        let request = |key: Option<&str>| {
+            let mut request = Request::new(proto::ClassifyRequest { text: " ".to_string() });
+            if let Some(key) = key {
+                request.metadata_mut().insert("authorization", format!("Bearer {}", key).parse().unwrap());
+            }
+            request
+        };
+
+        let status = service.classify(request(None)).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        let status = service.classify(request(Some("wrong"))).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        // A valid key reaches the handler, which rejects the empty text itself
+        let status = service.classify(request(Some("secret"))).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        let status = service.classify(request(Some("secret"))).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+    }
+
+    #[tokio::test]
+    async fn test_get_session_not_found() {
+        let status = service()
+            .get_session(Request::new(proto::GetSessionRequest { session_id: "nope".to_string() }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+}
@@ -0,0 +1,291 @@
+//! Escalation to a human operator when a conversation needs more than the bot
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+use crate::Sentiment;
+use crate::models::{Message, MessageRole};
+pub use crate::state::HandoffReason;
+use crate::state::ConversationManager;
+
+/// Sent instead of a generated reply while an operator is being brought in
+pub const HOLDING_MESSAGE: &str = "I'm bringing in a person from our team who can help more than I can. \
+     They'll be with you shortly. If you are in danger or thinking about harming yourself, please contact \
+     your local emergency number or a crisis line right away.";
+
+/// Sent for each further message until an operator releases the conversation
+pub const WAITING_MESSAGE: &str = "Thanks, I've passed that on. A person from our team will reply here soon.";
+
+/// Phrases that escalate on their own, whatever the classification says
+const CRISIS_PHRASES: &[&str] = &[
+    "kill myself",
+    "killing myself",
+    "suicide",
+    "suicidal",
+    "end my life",
+    "want to die",
+    "self harm",
+    "self-harm",
+    "hurt myself",
+];
+
+/// Classification confidence a negative turn needs to count as severe
+const SEVERE_CONFIDENCE: f32 = 0.8;
+
+/// Messages included with an escalation
+const RECENT_MESSAGES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandoffPolicy {
+    /// Severe negative turns in a row that escalate
+    pub negative_turns: usize,
+}
+
+impl HandoffPolicy {
+    /// Why the conversation should go to a person, judged after the user's
+    /// latest message has been recorded
+    pub fn check(&self, input: &str, state: &ConversationManager) -> Option<HandoffReason> {
+        if mentions_crisis(input) {
+            return Some(HandoffReason::Crisis);
+        }
+
+        let history = &state.state().emotion_history;
+        let severe = history
+            .iter()
+            .rev()
+            .take_while(|e| e.sentiment == Sentiment::Negative && e.confidence >= SEVERE_CONFIDENCE)
+            .count();
+        (self.negative_turns > 0 && severe >= self.negative_turns).then_some(HandoffReason::SustainedNegativity)
+    }
+}
+
+fn mentions_crisis(input: &str) -> bool {
+    let lowered = input.to_lowercase();
+    CRISIS_PHRASES.iter().any(|phrase| lowered.contains(phrase))
+}
+
+/// The handoff a history shows the conversation waiting on, from the notices
+/// it was sent. State a client keeps, as with `/turns`, could have had its
+/// `handoff` cleared by the client; no operator can release those.
+pub fn from_history(messages: &[Message]) -> Option<HandoffReason> {
+    let notice = |m: &Message, text: &str| m.role == MessageRole::Assistant && m.content == text;
+    match messages.iter().rposition(|m| notice(m, HOLDING_MESSAGE)) {
+        Some(held) => {
+            let trigger = messages[..held].iter().rev().find(|m| m.role == MessageRole::User);
+            Some(if trigger.is_some_and(|m| mentions_crisis(&m.content)) {
+                HandoffReason::Crisis
+            } else {
+                HandoffReason::SustainedNegativity
+            })
+        }
+        // The holding notice itself may have been evicted
+        None => messages.iter().any(|m| notice(m, WAITING_MESSAGE)).then_some(HandoffReason::SustainedNegativity),
+    }
+}
+
+/// Pauses the conversation when `policy` calls for a handoff. Returns the
+/// reason while it waits for an operator, with whether the user's latest
+/// message started the wait.
+pub fn update(state: &mut ConversationManager, input: &str, policy: Option<HandoffPolicy>) -> Option<(HandoffReason, bool)> {
+    if let Some(reason) = state.handoff() {
+        return Some((reason, false));
+    }
+    let reason = policy?.check(input, state)?;
+    tracing::warn!(?reason, "handing the conversation to an operator");
+    state.pause_for_handoff(reason);
+    Some((reason, true))
+}
+
+/// The fixed reply sent instead of a generated one while paused
+pub fn notice(escalated: bool) -> &'static str {
+    if escalated { HOLDING_MESSAGE } else { WAITING_MESSAGE }
+}
+
+/// What an operator receives when a conversation is handed over
+#[derive(Debug, Clone, Serialize)]
+pub struct HandoffRequest {
+    /// `None` for conversations the server keeps no state for
+    pub session_id: Option<String>,
+    pub tenant: Option<String>,
+    pub reason: HandoffReason,
+    pub summary: String,
+    pub recent_messages: Vec<Message>,
+    pub requested_at: i64,
+}
+
+impl HandoffRequest {
+    pub fn new(session_id: Option<String>, reason: HandoffReason, state: &ConversationManager) -> Self {
+        let messages = state.get_history();
+        Self {
+            session_id,
+            tenant: None,
+            reason,
+            summary: summarize(state),
+            recent_messages: messages[messages.len().saturating_sub(RECENT_MESSAGES)..].to_vec(),
+            requested_at: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    pub fn with_tenant(mut self, tenant: &str) -> Self {
+        self.tenant = Some(tenant.to_string());
+        self
+    }
+}
+
+fn summarize(state: &ConversationManager) -> String {
+    let counts = state.emotion_distribution();
+    let user_messages = state.get_history().iter().filter(|m| matches!(m.role, MessageRole::User)).count();
+    format!(
+        "user messages: {} ({} negative, {} neutral, {} positive), trend {:?}",
+        user_messages,
+        counts.negative,
+        counts.neutral,
+        counts.positive,
+        state.get_recent_emotion_trend().direction
+    )
+}
+
+/// Where escalations are delivered
+#[async_trait]
+pub trait HandoffBackend: Send + Sync {
+    async fn escalate(&self, request: &HandoffRequest) -> Result<()>;
+}
+
+/// POSTs each escalation as JSON
+pub struct WebhookBackend {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookBackend {
+    pub fn new(url: &str) -> Self {
+        Self { client: reqwest::Client::new(), url: url.to_string() }
+    }
+}
+
+#[async_trait]
+impl HandoffBackend for WebhookBackend {
+    async fn escalate(&self, request: &HandoffRequest) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(request)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("handoff webhook {} failed", self.url))?;
+        Ok(())
+    }
+}
+
+/// Appends each escalation as a JSON line for an operator tool to pick up
+pub struct QueueFileBackend {
+    path: PathBuf,
+}
+
+impl QueueFileBackend {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl HandoffBackend for QueueFileBackend {
+    async fn escalate(&self, request: &HandoffRequest) -> Result<()> {
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open handoff queue {}", self.path.display()))?;
+        file.write_all(line.as_bytes()).await?;
+        // tokio finishes file writes in the background until flushed
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SentimentClassification;
+
+    fn negative(state: &mut ConversationManager, confidence: f32) {
+        state.add_message(MessageRole::User, "...");
+        state.update_emotion(SentimentClassification { sentiment: Sentiment::Negative, confidence, labels: Vec::new(), is_fallback: false });
+    }
+
+    #[test]
+    fn test_crisis_phrases_escalate_at_once() {
+        let policy = HandoffPolicy { negative_turns: 3 };
+        let state = ConversationManager::new();
+        assert_eq!(policy.check("Sometimes I want to DIE", &state), Some(HandoffReason::Crisis));
+        assert_eq!(policy.check("This deadline is killing me", &state), None);
+    }
+
+    #[test]
+    fn test_sustained_severe_negativity_escalates() {
+        let policy = HandoffPolicy { negative_turns: 3 };
+        let mut state = ConversationManager::new();
+        negative(&mut state, 0.9);
+        negative(&mut state, 0.5);
+        negative(&mut state, 0.9);
+        negative(&mut state, 0.95);
+        assert_eq!(policy.check("...", &state), None);
+
+        negative(&mut state, 0.85);
+        assert_eq!(policy.check("...", &state), Some(HandoffReason::SustainedNegativity));
+    }
+
+    #[test]
+    fn test_update_pauses_until_released() {
+        let policy = Some(HandoffPolicy { negative_turns: 3 });
+        let mut state = ConversationManager::new();
+        assert_eq!(update(&mut state, "Fine, thanks", policy), None);
+        assert_eq!(update(&mut state, "I might hurt myself", None), None);
+
+        assert_eq!(update(&mut state, "I might hurt myself", policy), Some((HandoffReason::Crisis, true)));
+        assert_eq!(update(&mut state, "Hello?", policy), Some((HandoffReason::Crisis, false)));
+
+        assert!(state.release_handoff());
+        assert_eq!(update(&mut state, "Hello?", policy), None);
+    }
+
+    #[test]
+    fn test_from_history_finds_the_notices() {
+        let mut state = ConversationManager::new();
+        state.add_message(MessageRole::User, "Rough day");
+        state.add_message(MessageRole::Assistant, "I'm sorry to hear that.");
+        assert_eq!(from_history(state.get_history()), None);
+
+        state.add_message(MessageRole::User, "I keep thinking about suicide");
+        state.add_message(MessageRole::Assistant, HOLDING_MESSAGE);
+        state.add_message(MessageRole::User, "Hello?");
+        state.add_message(MessageRole::Assistant, WAITING_MESSAGE);
+        assert_eq!(from_history(state.get_history()), Some(HandoffReason::Crisis));
+        assert_eq!(from_history(&state.get_history()[5..]), Some(HandoffReason::SustainedNegativity));
+    }
+
+    #[tokio::test]
+    async fn test_queue_file_appends_json_lines() {
+        let path = std::env::temp_dir().join(format!("tce-handoff-{}.jsonl", std::process::id()));
+        let backend = QueueFileBackend::new(&path);
+        let mut state = ConversationManager::new();
+        negative(&mut state, 0.9);
+
+        let request = HandoffRequest::new(Some("s1".to_string()), HandoffReason::Crisis, &state).with_tenant("acme");
+        backend.escalate(&request).await.unwrap();
+        backend.escalate(&request).await.unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["reason"], "crisis");
+        assert_eq!(lines[0]["tenant"], "acme");
+        assert!(lines[0]["summary"].as_str().unwrap().starts_with("user messages: 1 (1 negative"));
+    }
+}
@@ -0,0 +1,72 @@
+//! Startup check that the provider is reachable and the model answers
+
+use anyhow::Result;
+use rig::completion::Prompt;
+use rig::providers::openai;
+use std::time::Duration;
+use crate::agents::{AgentError, ErrorAction};
+
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Sends a one-token prompt, turning any failure into a message that says
+/// which setting to fix
+pub async fn check_provider(client: &openai::Client, base_url: &str, model: &str) -> Result<()> {
+    let agent = client.agent(model).max_tokens(1).build();
+
+    let error = match tokio::time::timeout(TIMEOUT, agent.prompt("ping")).await {
+        Ok(Ok(_)) => return Ok(()),
+        Ok(Err(e)) => AgentError::from(e),
+        Err(_) => AgentError::Timeout,
+    };
+
+    // A reply we cannot parse still proves the model is there
+    if error.action() == ErrorAction::Fallback {
+        return Ok(());
+    }
+
+    anyhow::bail!(
+        "provider check failed for model '{}' at {}: {}\n   {}",
+        model,
+        base_url,
+        error,
+        hint(&error)
+    )
+}
+
+fn hint(error: &AgentError) -> &'static str {
+    match error {
+        AgentError::Connection(_) => "Check OPENAI_BASE_URL and your network connection.",
+        AgentError::Timeout => "The provider did not answer in time; check OPENAI_BASE_URL or try again.",
+        AgentError::Rejected(_) => "Check OPENAI_API_KEY, your account balance, and that MODEL names a model your key can use.",
+        AgentError::RateLimited(_) | AgentError::Unavailable(_) | AgentError::CircuitOpen => {
+            "The provider is busy; try again shortly or pass --skip-health-check."
+        }
+        AgentError::Provider(_) | AgentError::Extraction(_) => {
+            "Check OPENAI_BASE_URL points at an OpenAI-compatible API and that MODEL exists."
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hints_name_the_setting() {
+        assert!(hint(&AgentError::Connection("refused".to_string())).contains("OPENAI_BASE_URL"));
+        assert!(hint(&AgentError::Rejected("bad key".to_string())).contains("OPENAI_API_KEY"));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_provider_fails_fast() {
+        // Nothing listens on port 9 of localhost
+        let client = openai::Client::from_url("test-key", "http://127.0.0.1:9/v1");
+        let error = check_provider(&client, "http://127.0.0.1:9/v1", "test-model")
+            .await
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("test-model"));
+        assert!(error.contains("OPENAI_BASE_URL"));
+    }
+}
@@ -0,0 +1,238 @@
+//! Extension points that run inside each turn: WASM plugins and rhai scripts
+
+#[cfg(feature = "scripting")]
+pub mod script;
+#[cfg(feature = "plugins")]
+pub mod wasm;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use crate::SentimentClassification;
+use crate::models::MessageRole;
+use crate::state::{ConversationManager, EmotionTrend};
+use crate::strategy::ResponseStrategy;
+
+/// Classifications a snapshot keeps
+const SNAPSHOT_EMOTIONS: usize = 5;
+
+/// A copy of the conversation as it stood before the current message
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    /// User messages so far
+    pub turns: usize,
+    pub trend: EmotionTrend,
+    /// The latest classifications, oldest first
+    pub recent_emotions: Vec<SentimentClassification>,
+    /// The strategy of the last reply
+    pub strategy: Option<ResponseStrategy>,
+    /// Waiting for a human operator
+    pub handoff: bool,
+}
+
+impl Snapshot {
+    pub fn of(state: &ConversationManager) -> Self {
+        let emotions = &state.state().emotion_history;
+        Self {
+            turns: state.user_turns(),
+            trend: state.get_recent_emotion_trend().direction,
+            recent_emotions: emotions[emotions.len().saturating_sub(SNAPSHOT_EMOTIONS)..].to_vec(),
+            strategy: state
+                .get_history()
+                .iter()
+                .rev()
+                .find(|m| matches!(m.role, MessageRole::Assistant))
+                .and_then(|m| m.strategy),
+            handoff: state.handoff().is_some(),
+        }
+    }
+}
+
+/// What a hook sees when the strategy has been chosen
+#[derive(Debug, Clone, Serialize)]
+pub struct StrategyContext<'a> {
+    pub emotion: &'a SentimentClassification,
+    pub trend: EmotionTrend,
+    pub strategy: ResponseStrategy,
+    /// User messages so far, this one included
+    pub turns: usize,
+}
+
+/// A stage of the turn that can be changed from outside the crate. Every
+/// method defaults to leaving the turn as it is.
+pub trait TurnHook: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Rewrites the user's message before it is classified and answered
+    fn preprocess(&self, _input: &str, _snapshot: &Snapshot) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Replaces the message's classification before it is recorded
+    fn adjust_emotion(&self, _emotion: &SentimentClassification, _snapshot: &Snapshot) -> Result<Option<SentimentClassification>> {
+        Ok(None)
+    }
+
+    /// Replaces the strategy the turn answers with
+    fn adjust_strategy(&self, _context: &StrategyContext) -> Result<Option<ResponseStrategy>> {
+        Ok(None)
+    }
+
+    /// Rewrites the generated reply
+    fn postprocess(&self, _response: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// Hooks run in order, each seeing the previous one's result. A failing hook
+/// is logged and skipped rather than failing the turn.
+#[derive(Clone, Default)]
+pub struct Hooks(Vec<Arc<dyn TurnHook>>);
+
+impl Hooks {
+    pub fn new(hooks: Vec<Arc<dyn TurnHook>>) -> Self {
+        Self(hooks)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.0.iter().map(|hook| hook.name()).collect()
+    }
+
+    pub fn preprocess(&self, input: &str, snapshot: &Snapshot) -> String {
+        let mut input = input.to_string();
+        for hook in &self.0 {
+            if let Some(rewritten) = skip_failed(hook.as_ref(), "preprocess", hook.preprocess(&input, snapshot)) {
+                input = rewritten;
+            }
+        }
+        input
+    }
+
+    pub fn adjust_emotion(&self, emotion: SentimentClassification, snapshot: &Snapshot) -> SentimentClassification {
+        let mut emotion = emotion;
+        for hook in &self.0 {
+            if let Some(adjusted) = skip_failed(hook.as_ref(), "adjust_emotion", hook.adjust_emotion(&emotion, snapshot)) {
+                emotion = adjusted;
+            }
+        }
+        emotion
+    }
+
+    pub fn adjust_strategy(&self, mut context: StrategyContext) -> Option<ResponseStrategy> {
+        let chosen = context.strategy;
+        for hook in &self.0 {
+            if let Some(strategy) = skip_failed(hook.as_ref(), "adjust_strategy", hook.adjust_strategy(&context)) {
+                context.strategy = strategy;
+            }
+        }
+        (context.strategy != chosen).then_some(context.strategy)
+    }
+
+    pub fn postprocess(&self, response: String) -> String {
+        let mut response = response;
+        for hook in &self.0 {
+            if let Some(rewritten) = skip_failed(hook.as_ref(), "postprocess", hook.postprocess(&response)) {
+                response = rewritten;
+            }
+        }
+        response
+    }
+}
+
+/// Every plugin (`*.wasm`) and script (`*.rhai`) in `dir`, in file name order
+pub fn load_dir(dir: &Path) -> Result<Hooks> {
+    let mut paths = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read plugins directory {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.sort();
+
+    let mut hooks = Vec::new();
+    for path in paths {
+        if let Some(hook) = load_file(&path)? {
+            tracing::info!(hook = hook.name(), "loaded hook");
+            hooks.push(hook);
+        }
+    }
+    Ok(Hooks::new(hooks))
+}
+
+/// `None` for files that are not hooks
+fn load_file(path: &Path) -> Result<Option<Arc<dyn TurnHook>>> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        #[cfg(feature = "plugins")]
+        Some("wasm") => Ok(Some(Arc::new(wasm::WasmPlugin::load(path)?))),
+        #[cfg(not(feature = "plugins"))]
+        Some("wasm") => anyhow::bail!("{} needs a build with the 'plugins' feature", path.display()),
+        #[cfg(feature = "scripting")]
+        Some("rhai") => Ok(Some(Arc::new(script::ScriptHook::load(path)?))),
+        #[cfg(not(feature = "scripting"))]
+        Some("rhai") => anyhow::bail!("{} needs a build with the 'scripting' feature", path.display()),
+        _ => Ok(None),
+    }
+}
+
+impl std::fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+fn skip_failed<T>(hook: &dyn TurnHook, stage: &str, result: Result<Option<T>>) -> Option<T> {
+    result.unwrap_or_else(|e| {
+        tracing::warn!(hook = hook.name(), stage, error = %format!("{:#}", e), "hook failed, skipping it");
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sentiment;
+
+    struct Shout;
+
+    impl TurnHook for Shout {
+        fn name(&self) -> &str {
+            "shout"
+        }
+
+        fn postprocess(&self, response: &str) -> Result<Option<String>> {
+            Ok(Some(response.to_uppercase()))
+        }
+
+        fn adjust_strategy(&self, _context: &StrategyContext) -> Result<Option<ResponseStrategy>> {
+            anyhow::bail!("no opinion")
+        }
+    }
+
+    struct Calm;
+
+    impl TurnHook for Calm {
+        fn name(&self) -> &str {
+            "calm"
+        }
+
+        fn adjust_strategy(&self, context: &StrategyContext) -> Result<Option<ResponseStrategy>> {
+            Ok((context.strategy == ResponseStrategy::Cheerful).then_some(ResponseStrategy::Neutral))
+        }
+    }
+
+    #[test]
+    fn test_hooks_chain_and_skip_failures() {
+        let hooks = Hooks::new(vec![Arc::new(Shout), Arc::new(Calm)]);
+        let emotion = SentimentClassification { sentiment: Sentiment::Positive, confidence: 0.9, labels: Vec::new(), is_fallback: false };
+        let context = |strategy| StrategyContext { emotion: &emotion, trend: EmotionTrend::Stable, strategy, turns: 1 };
+
+        let snapshot = Snapshot::of(&ConversationManager::new());
+        assert_eq!(hooks.preprocess("hello", &snapshot), "hello");
+        assert_eq!(hooks.postprocess("hello".to_string()), "HELLO");
+        assert_eq!(hooks.adjust_strategy(context(ResponseStrategy::Cheerful)), Some(ResponseStrategy::Neutral));
+        assert_eq!(hooks.adjust_strategy(context(ResponseStrategy::Empathetic)), None);
+    }
+}
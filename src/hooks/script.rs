@@ -0,0 +1,141 @@
+//! Hooks written as rhai scripts. A script defines any of
+//! `on_message(text, state)`, `on_emotion(emotion, state)` and
+//! `on_strategy_selected(context)`, each returning the replacement (a string, a
+//! `#{sentiment, confidence}` map, a strategy name) or `()` to change nothing.
+//! `state` is a [`Snapshot`] as a map. Scripts have no file or network access,
+//! `print` goes to the log, and a script that runs too long is stopped.
+
+use anyhow::{Context, Result};
+use rhai::{AST, Dynamic, Engine, FuncArgs, Scope};
+use serde::de::DeserializeOwned;
+use std::path::Path;
+use super::{Snapshot, StrategyContext, TurnHook};
+use crate::SentimentClassification;
+use crate::strategy::ResponseStrategy;
+
+/// Operations a hook call may run before it is stopped
+const MAX_OPERATIONS: u64 = 100_000;
+
+pub struct ScriptHook {
+    name: String,
+    engine: Engine,
+    ast: AST,
+}
+
+impl ScriptHook {
+    pub fn new(name: &str, source: &str) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(32)
+            .set_max_string_size(64 * 1024)
+            .set_max_array_size(1024)
+            .set_max_map_size(256);
+        let script = name.to_string();
+        engine.on_print(move |text| tracing::info!(script = %script, "{}", text));
+        let script = name.to_string();
+        engine.on_debug(move |text, _, _| tracing::debug!(script = %script, "{}", text));
+
+        let ast = engine.compile(source)?;
+        Ok(Self { name: name.to_string(), engine, ast })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path).with_context(|| format!("Failed to read script {}", path.display()))?;
+        let name = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+        Self::new(&name, &source).with_context(|| format!("Failed to compile script {}", path.display()))
+    }
+
+    /// `None` when the script does not define `function` or returned `()`
+    fn call(&self, function: &str, args: impl FuncArgs) -> Result<Option<Dynamic>> {
+        if !self.ast.iter_functions().any(|f| f.name == function) {
+            return Ok(None);
+        }
+        let result: Dynamic = self.engine.call_fn(&mut Scope::new(), &self.ast, function, args)?;
+        Ok((!result.is_unit()).then_some(result))
+    }
+}
+
+/// Through JSON, since rhai numbers are `f64` and `i64` whatever the field's type
+fn from_script<T: DeserializeOwned>(value: &Dynamic) -> Result<T> {
+    let json: serde_json::Value = rhai::serde::from_dynamic(value)?;
+    Ok(serde_json::from_value(json)?)
+}
+
+impl TurnHook for ScriptHook {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn preprocess(&self, input: &str, snapshot: &Snapshot) -> Result<Option<String>> {
+        let Some(result) = self.call("on_message", (input.to_string(), rhai::serde::to_dynamic(snapshot)?))? else {
+            return Ok(None);
+        };
+        let text = result.into_string().map_err(|kind| anyhow::anyhow!("on_message returned {} instead of a string", kind))?;
+        Ok(Some(text))
+    }
+
+    fn adjust_emotion(&self, emotion: &SentimentClassification, snapshot: &Snapshot) -> Result<Option<SentimentClassification>> {
+        let args = (rhai::serde::to_dynamic(emotion)?, rhai::serde::to_dynamic(snapshot)?);
+        let Some(result) = self.call("on_emotion", args)? else {
+            return Ok(None);
+        };
+        let emotion = from_script(&result).context("on_emotion returned an invalid emotion")?;
+        Ok(Some(emotion))
+    }
+
+    fn adjust_strategy(&self, context: &StrategyContext) -> Result<Option<ResponseStrategy>> {
+        let Some(result) = self.call("on_strategy_selected", (rhai::serde::to_dynamic(context)?,))? else {
+            return Ok(None);
+        };
+        let strategy = from_script(&result).with_context(|| format!("unknown strategy '{}'", result))?;
+        Ok(Some(strategy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sentiment;
+    use crate::state::{ConversationManager, EmotionTrend};
+
+    const SCRIPT: &str = r#"
+        fn on_message(text, state) {
+            if text.contains("darn") { text.replace("darn", "****"); text } else { () }
+        }
+
+        fn on_emotion(emotion, state) {
+            if state.turns == 0 && emotion.sentiment == "Negative" {
+                #{ sentiment: "Neutral", confidence: emotion.confidence }
+            }
+        }
+
+        fn on_strategy_selected(context) {
+            if context.trend == "Declining" { "Empathetic" }
+        }
+    "#;
+
+    #[test]
+    fn test_script_hooks() {
+        let hook = ScriptHook::new("test", SCRIPT).unwrap();
+        let snapshot = Snapshot::of(&ConversationManager::new());
+
+        assert_eq!(hook.preprocess("darn it", &snapshot).unwrap().as_deref(), Some("**** it"));
+        assert_eq!(hook.preprocess("fine", &snapshot).unwrap(), None);
+
+        let emotion = SentimentClassification { sentiment: Sentiment::Negative, confidence: 0.5, labels: Vec::new(), is_fallback: false };
+        let adjusted = hook.adjust_emotion(&emotion, &snapshot).unwrap().unwrap();
+        assert_eq!(adjusted.sentiment, Sentiment::Neutral);
+        assert_eq!(adjusted.confidence, 0.5);
+
+        let context = |trend| StrategyContext { emotion: &emotion, trend, strategy: ResponseStrategy::Cheerful, turns: 4 };
+        assert_eq!(hook.adjust_strategy(&context(EmotionTrend::Declining)).unwrap(), Some(ResponseStrategy::Empathetic));
+        assert_eq!(hook.adjust_strategy(&context(EmotionTrend::Stable)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_runaway_script_is_stopped() {
+        let hook = ScriptHook::new("spin", "fn on_message(text, state) { loop {} }").unwrap();
+        assert!(hook.preprocess("hi", &Snapshot::of(&ConversationManager::new())).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+//! Hooks compiled to WebAssembly, run in a sandbox with no imports.
+//!
+//! A plugin exports its `memory`, `alloc(len: i32) -> i32` for the host to copy
+//! text into, and any of `preprocess`, `adjust_strategy` and `postprocess`,
+//! each `(ptr: i32, len: i32) -> i64`. The result packs the returned UTF-8 text
+//! as `ptr << 32 | len`, or is 0 to leave the turn unchanged. `adjust_strategy`
+//! receives the strategy context as JSON and returns a strategy name such as
+//! `"Empathetic"`. The host never frees what it allocates, so a bump allocator
+//! that resets on each call is enough.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::sync::Mutex;
+use wasmi::{Engine, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+use super::{Snapshot, StrategyContext, TurnHook};
+use crate::strategy::ResponseStrategy;
+
+/// Instructions a plugin may run per call before it is stopped
+const FUEL_PER_CALL: u64 = 10_000_000;
+
+/// Bytes of linear memory a plugin may grow to
+const MEMORY_LIMIT: usize = 64 << 20;
+
+struct Loaded {
+    store: Store<StoreLimits>,
+    instance: Instance,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+}
+
+pub struct WasmPlugin {
+    name: String,
+    loaded: Mutex<Loaded>,
+}
+
+impl WasmPlugin {
+    pub fn new(name: &str, wasm: &[u8]) -> Result<Self> {
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm)?;
+        let mut store = Store::new(&engine, StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build());
+        store.limiter(|limits| limits);
+        let instance = Linker::<StoreLimits>::new(&engine).instantiate(&mut store, &module)?.start(&mut store)?;
+        let memory = instance.get_memory(&store, "memory").context("plugin does not export 'memory'")?;
+        let alloc = instance.get_typed_func(&store, "alloc").context("plugin does not export 'alloc'")?;
+        Ok(Self { name: name.to_string(), loaded: Mutex::new(Loaded { store, instance, memory, alloc }) })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let wasm = std::fs::read(path).with_context(|| format!("Failed to read plugin {}", path.display()))?;
+        let name = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+        Self::new(&name, &wasm).with_context(|| format!("Failed to load plugin {}", path.display()))
+    }
+
+    /// Calls `export` with `input`; `None` when the plugin lacks the export or
+    /// left the input unchanged
+    fn call(&self, export: &str, input: &[u8]) -> Result<Option<String>> {
+        let mut loaded = self.loaded.lock().map_err(|_| anyhow::anyhow!("plugin poisoned by an earlier panic"))?;
+        let Loaded { store, instance, memory, alloc } = &mut *loaded;
+        if instance.get_export(&*store, export).is_none() {
+            return Ok(None);
+        }
+        let hook: TypedFunc<(i32, i32), i64> = instance.get_typed_func(&*store, export)?;
+
+        store.set_fuel(FUEL_PER_CALL).map_err(wasmi::Error::from)?;
+        let len = i32::try_from(input.len()).context("input too large for a plugin")?;
+        let ptr = alloc.call(&mut *store, len)?;
+        memory.write(&mut *store, ptr as usize, input).map_err(wasmi::Error::from)?;
+        let packed = hook.call(&mut *store, (ptr, len))? as u64;
+        if packed == 0 {
+            return Ok(None);
+        }
+
+        let (start, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        let output = start.checked_add(len).and_then(|end| memory.data(&*store).get(start..end))
+            .context("plugin returned text outside its memory")?;
+        Ok(Some(std::str::from_utf8(output).context("plugin returned text that is not UTF-8")?.to_string()))
+    }
+}
+
+impl TurnHook for WasmPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn preprocess(&self, input: &str, _snapshot: &Snapshot) -> Result<Option<String>> {
+        self.call("preprocess", input.as_bytes())
+    }
+
+    fn adjust_strategy(&self, context: &StrategyContext) -> Result<Option<ResponseStrategy>> {
+        let Some(name) = self.call("adjust_strategy", &serde_json::to_vec(context)?)? else {
+            return Ok(None);
+        };
+        let strategy = serde_json::from_value(serde_json::Value::String(name.trim().to_string()))
+            .with_context(|| format!("unknown strategy '{}'", name.trim()))?;
+        Ok(Some(strategy))
+    }
+
+    fn postprocess(&self, response: &str) -> Result<Option<String>> {
+        self.call("postprocess", response.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hooks::Hooks;
+    use crate::state::{ConversationManager, EmotionTrend};
+    use std::sync::Arc;
+    use crate::{Sentiment, SentimentClassification};
+
+    /// Answers every turn encouragingly, cuts replies to five bytes, and spins
+    /// forever on input
+    const PLUGIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (data (i32.const 0) "Encouraging")
+          (func (export "alloc") (param i32) (result i32) i32.const 1024)
+          (func (export "adjust_strategy") (param i32 i32) (result i64)
+            i64.const 11)
+          (func (export "postprocess") (param $ptr i32) (param $len i32) (result i64)
+            (i64.or
+              (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+              (i64.const 5)))
+          (func (export "preprocess") (param i32 i32) (result i64)
+            (loop $spin (br $spin))
+            i64.const 0))
+    "#;
+
+    #[test]
+    fn test_plugin_hooks_run_in_the_sandbox() {
+        let plugin = WasmPlugin::new("test", &wat::parse_str(PLUGIN).unwrap()).unwrap();
+        let emotion = SentimentClassification { sentiment: Sentiment::Negative, confidence: 0.8, labels: Vec::new(), is_fallback: false };
+        let context = StrategyContext { emotion: &emotion, trend: EmotionTrend::Stable, strategy: ResponseStrategy::Empathetic, turns: 2 };
+
+        assert_eq!(plugin.adjust_strategy(&context).unwrap(), Some(ResponseStrategy::Encouraging));
+        assert_eq!(plugin.postprocess("Hello there").unwrap().as_deref(), Some("Hello"));
+        let snapshot = Snapshot::of(&ConversationManager::new());
+        assert!(plugin.preprocess("loop", &snapshot).is_err(), "fuel should stop a runaway plugin");
+
+        let hooks = Hooks::new(vec![Arc::new(plugin)]);
+        assert_eq!(hooks.preprocess("loop", &snapshot), "loop");
+    }
+
+    #[test]
+    fn test_plugins_stay_inside_their_memory() {
+        // Claims 4 GiB of output from one page, and grows past the limit
+        let wasm = wat::parse_str(r#"
+            (module
+              (memory (export "memory") 1)
+              (func (export "alloc") (param i32) (result i32) i32.const 0)
+              (func (export "postprocess") (param i32 i32) (result i64)
+                i64.const 0xffff_ffff)
+              (func (export "preprocess") (param i32 i32) (result i64)
+                ;; Echoes the input only when the growth was refused
+                (if (result i64) (i32.eq (memory.grow (i32.const 65535)) (i32.const -1))
+                  (then (i64.extend_i32_u (local.get 1)))
+                  (else (i64.const 0)))))
+        "#).unwrap();
+        let plugin = WasmPlugin::new("greedy", &wasm).unwrap();
+
+        assert!(plugin.postprocess("Hello").unwrap_err().to_string().contains("outside"));
+        let snapshot = Snapshot::of(&ConversationManager::new());
+        assert_eq!(plugin.preprocess("Hi", &snapshot).unwrap().as_deref(), Some("Hi"), "growth past the limit should fail");
+    }
+
+    #[test]
+    fn test_missing_exports_are_rejected() {
+        let wasm = wat::parse_str("(module (memory (export \"memory\") 1))").unwrap();
+        let error = WasmPlugin::new("bare", &wasm).err().unwrap();
+        assert!(error.to_string().contains("alloc"));
+    }
+}
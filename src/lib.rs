@@ -0,0 +1,159 @@
+//! Library side of the crate: the conversation state and strategy layers, which
+//! build without tokio or provider dependencies (including for wasm32), the chat
+//! pipeline behind `app`, and the feature-gated client and C ABI
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+pub mod events;
+pub mod injection;
+pub mod models;
+pub mod state;
+pub mod strategy;
+#[cfg(feature = "app")]
+pub mod agents;
+#[cfg(feature = "app")]
+pub mod anonymize;
+#[cfg(feature = "app")]
+pub mod handoff;
+#[cfg(feature = "app")]
+pub mod hooks;
+#[cfg(feature = "app")]
+pub mod minor_safe;
+#[cfg(feature = "app")]
+pub mod mock;
+#[cfg(feature = "app")]
+pub mod onboarding;
+#[cfg(feature = "app")]
+pub mod opener;
+#[cfg(feature = "app")]
+pub mod profanity;
+#[cfg(feature = "app")]
+pub mod report;
+#[cfg(feature = "app")]
+pub mod storage;
+#[cfg(feature = "app")]
+pub mod turn;
+#[cfg(feature = "app")]
+pub mod wellbeing;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "node")]
+mod node;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, JsonSchema, ToSchema)]
+pub enum Sentiment {
+    Positive,
+    Negative,
+    Neutral,
+}
+
+impl Sentiment {
+    pub fn score(self) -> i32 {
+        match self {
+            Sentiment::Positive => 1,
+            Sentiment::Neutral => 0,
+            Sentiment::Negative => -1,
+        }
+    }
+}
+
+/// Confidence a label needs before a message counts as mixed
+pub const MIXED_CONFIDENCE: f32 = 0.3;
+
+/// One of several emotions a message carries at once
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, JsonSchema, ToSchema)]
+pub struct EmotionLabel {
+    pub sentiment: Sentiment,
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub confidence: f32,
+}
+
+/// The sentiment a message expresses toward one thing it talks about, such
+/// as the manager in "the job is great but my manager is awful"
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema, ToSchema)]
+pub struct AspectSentiment {
+    /// A short noun phrase in the message's language, e.g. `manager`
+    pub aspect: String,
+    pub sentiment: Sentiment,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, ToSchema)]
+pub struct SentimentClassification {
+    pub sentiment: Sentiment,
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub confidence: f32,
+    /// Every emotion the text carries when there is more than one, as in
+    /// "excited but nervous", each with its own confidence
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<EmotionLabel>,
+    /// Not the model's answer but what the fallback policy put in its place
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[schemars(skip)]
+    pub is_fallback: bool,
+}
+
+impl SentimentClassification {
+    /// Sentiment score from -1 to 1: the labels averaged by confidence, or
+    /// the single sentiment's score when there are none
+    pub fn score(&self) -> f32 {
+        let weight: f32 = self.labels.iter().map(|l| l.confidence).sum();
+        if weight <= 0.0 {
+            return self.sentiment.score() as f32;
+        }
+        self.labels.iter().map(|l| l.sentiment.score() as f32 * l.confidence).sum::<f32>() / weight
+    }
+
+    /// The strongest label of the opposite polarity, e.g. the nerves in
+    /// "excited but nervous", when it is confident enough to answer to
+    pub fn mixed(&self) -> Option<EmotionLabel> {
+        let opposite = match self.sentiment {
+            Sentiment::Positive => Sentiment::Negative,
+            Sentiment::Negative => Sentiment::Positive,
+            Sentiment::Neutral => return None,
+        };
+        self.labels
+            .iter()
+            .filter(|l| l.sentiment == opposite && l.confidence >= MIXED_CONFIDENCE)
+            .max_by(|a, b| a.confidence.total_cmp(&b.confidence))
+            .copied()
+    }
+
+    /// e.g. `Positive 0.80 + Negative 0.60`, or just the sentiment without labels
+    pub fn describe(&self) -> String {
+        if self.labels.len() < 2 {
+            return format!("{:?}", self.sentiment);
+        }
+        let labels: Vec<String> = self.labels.iter().map(|l| format!("{:?} {:.2}", l.sentiment, l.confidence)).collect();
+        labels.join(" + ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixed_labels_score_and_describe() {
+        let label = |sentiment, confidence| EmotionLabel { sentiment, confidence };
+        let excited_but_nervous = SentimentClassification {
+            sentiment: Sentiment::Positive,
+            confidence: 0.8,
+            labels: vec![label(Sentiment::Positive, 0.8), label(Sentiment::Negative, 0.4)],
+            is_fallback: false,
+        };
+        assert!((excited_but_nervous.score() - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(excited_but_nervous.mixed(), Some(label(Sentiment::Negative, 0.4)));
+        assert_eq!(excited_but_nervous.describe(), "Positive 0.80 + Negative 0.40");
+
+        let faint = SentimentClassification { labels: vec![label(Sentiment::Positive, 0.9), label(Sentiment::Negative, 0.1)], ..excited_but_nervous.clone() };
+        assert_eq!(faint.mixed(), None);
+        let single = SentimentClassification { labels: Vec::new(), ..excited_but_nervous };
+        assert_eq!((single.score(), single.mixed(), single.describe()), (1.0, None, "Positive".to_string()));
+    }
+}
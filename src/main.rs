@@ -1,114 +1,556 @@
 use anyhow::Result;
-use rig::providers::openai;
-use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
-use std::io::{self, Write};
-
-mod models;
-mod agents;
-mod state;
-mod strategy;
-
-use agents::{ChatAgent, EmotionDetector};
-use models::MessageRole;
-use state::ConversationManager;
-use strategy::select_strategy;
-
-#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema)]
-pub enum Sentiment {
-    Positive,
-    Negative,
-    Neutral,
-}
+use clap::Parser;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
-#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
-pub struct SentimentClassification {
-    pub sentiment: Sentiment,
-    pub confidence: f32,
-}
+mod budget;
+mod cli;
+mod commands;
+#[cfg(feature = "keyring")]
+mod credentials;
+mod health;
+mod profiles;
+mod render;
+mod server;
+mod telemetry;
+#[cfg(feature = "grpc")]
+mod grpc;
+
+use cli::{Cli, Command};
+use text_classifier_extractor::{agents, anonymize, events, handoff, hooks, minor_safe, mock, models, onboarding, opener, profanity, report, state, storage, strategy, turn};
+pub use text_classifier_extractor::{EmotionLabel, Sentiment, SentimentClassification};
 
 struct Config {
     api_key: String,
     base_url: String,
     model: String,
+    /// The provider profile the key, endpoint and model came from, from `--profile` or `PROVIDER_PROFILE`
+    profile: Option<String>,
+    /// Endpoints and models to switch between, from `PROVIDERS_FILE`
+    profiles: Vec<profiles::ProviderProfile>,
+    embedding_model: String,
+    sessions_dir: PathBuf,
+    /// Chat sessions are checkpointed this often, in messages, from `AUTOSAVE_EVERY_MESSAGES`; 0 is off
+    autosave_every: usize,
+    database_url: Option<String>,
+    /// Classification results are cached on disk here when set
+    cache_dir: Option<PathBuf>,
+    cache_ttl: Duration,
+    /// Defaults for how turns choose a strategy; chat may change the mode
+    strategy: turn::StrategyPolicy,
+    /// How much of each server session is kept in memory, from `SESSION_MAX_MESSAGES`,
+    /// `SESSION_MAX_EMOTIONS` and `SESSION_EVICTION`
+    retention: state::RetentionPolicy,
+    /// Server sessions unused this long leave memory for storage, from `SESSION_IDLE_TTL_SECS`
+    session_idle_ttl: Option<Duration>,
+    /// Sessions written to `SESSIONS_DIR/live` and unused this long are deleted, from `LIVE_SESSION_EXPIRY_DAYS`
+    live_session_expiry: Duration,
+    /// How conversation trends are read, from `TREND_ANALYZER`
+    trend_analyzer: Arc<dyn state::TrendAnalyzer>,
+    /// Check-ins on quiet users whose mood keeps declining, when enabled
+    check_in: Option<state::CheckInPolicy>,
+    /// Escalations are POSTed here when set
+    handoff_webhook: Option<String>,
+    /// Or appended to this file as JSON lines
+    handoff_queue: Option<PathBuf>,
+    /// Priced into the chat recap, from `PROMPT_PRICE_PER_1K_TOKENS` and `COMPLETION_PRICE_PER_1K_TOKENS`
+    token_prices: Option<commands::recap::TokenPrices>,
+    /// Chat spend limits, from `SESSION_BUDGET_USD` and `DAILY_BUDGET_USD`, with
+    /// `BUDGET_FALLBACK_MODEL` or `BUDGET_FALLBACK_PROFILE` to answer with once reached
+    budget: Option<budget::BudgetPolicy>,
+    /// Topics the assistant declines, from `BLOCKED_TOPICS_FILE`
+    blocked_topics: Vec<agents::BlockedTopic>,
+    /// Temperature, length and verbosity by strategy and sentiment, from `GENERATION_FILE`
+    generation: agents::GenerationProfile,
+    /// Replies for these strategies go to their own model, from `STRATEGY_MODELS`
+    strategy_models: HashMap<strategy::ResponseStrategy, String>,
+    /// Reply length asked of the model and enforced on its replies, from `RESPONSE_LENGTH`; the generation profile decides when unset
+    response_length: Option<agents::Verbosity>,
+    /// Tokens the model reads per request, from `CONTEXT_WINDOW_TOKENS`; looked up from the model name when unset
+    context_window: Option<usize>,
+    /// Profanity masked or rephrased in replies, from `PROFANITY_FILTER` and `PROFANITY_ACTION`; off when unset
+    profanity: Option<profanity::ProfanityFilter>,
+    /// Near-duplicate messages are left out of long histories at this similarity, from `HISTORY_DEDUP_THRESHOLD`
+    history_dedup: Option<f64>,
+    /// Labelled texts shown to the classifier, from `EMOTION_EXAMPLES_FILE`; the curated set when unset
+    emotion_examples: Vec<agents::FewShotExample>,
+    /// Labels for `categorize` and `POST /categorize`, from `TAXONOMY_FILE`
+    taxonomy: Option<agents::Taxonomy>,
+    /// Extra samples for low-confidence classifications, from `SELF_CONSISTENCY_SAMPLES`; off when unset
+    self_consistency: Option<agents::SelfConsistency>,
+    /// What a classification becomes without a usable answer, from `CLASSIFICATION_FALLBACK`
+    fallback: agents::FallbackPolicy,
+    /// Corrective follow-ups after an invalid classification before it falls back, from `EXTRACTION_REPAIR_ATTEMPTS`
+    repair_attempts: usize,
+    /// Questions for fresh chat sessions, from `ONBOARDING_FILE`, or the standard ones with `--onboarding`
+    onboarding: Option<onboarding::OnboardingScript>,
+    /// The stages of each turn from `TURN_STAGES`, with plugins and scripts from `PLUGINS_DIR`
+    pipeline: turn::Pipeline,
+    dry_run: bool,
+    wellbeing_safe: bool,
+    /// Stricter prompts, blocked topics and reply screening for young users, from `--minor-safe` or `MINOR_SAFE`
+    minor_safe: bool,
+    /// Extract aspect-level sentiment for each message, from `--aspects` or `ASPECT_SENTIMENT`
+    aspect_sentiment: bool,
+    /// Extract the cause of negative emotions, from `--causes` or `EMOTION_CAUSES`
+    emotion_causes: bool,
+    /// Rate each reply for empathy and strategy adherence, from `--score-replies` or `SCORE_REPLIES`
+    score_replies: bool,
 }
 
 impl Config {
-    fn from_env() -> Result<Self> {
-        let api_key = std::env::var("OPENAI_API_KEY")
-            .map_err(|_| anyhow::anyhow!("OPENAI_API_KEY not set"))?;
-
-        let base_url = std::env::var("OPENAI_BASE_URL")
-            .unwrap_or_else(|_| "https://open.bigmodel.cn/api/paas/v4".to_string());
+    /// Without `needs_key`, a missing API key is left empty, as in a dry run
+    fn from_env(global: &cli::GlobalArgs, needs_key: bool) -> Result<Self> {
+        let dry_run = global.dry_run;
+        let keyless = dry_run || !needs_key;
+        let profiles = match std::env::var("PROVIDERS_FILE") {
+            Ok(path) => profiles::load_profiles(path.as_ref())?,
+            Err(_) => Vec::new(),
+        };
+        let (api_key, base_url, model) = match &global.profile {
+            Some(name) => {
+                let provider = profiles::find(&profiles, name)?.resolve(keyless)?;
+                (provider.api_key, provider.base_url, provider.model)
+            }
+            None => {
+                let base_url = base_url();
+                // A dry run never calls the provider, so it needs no key
+                let api_key = match std::env::var("OPENAI_API_KEY") {
+                    Ok(key) => key,
+                    Err(_) if keyless => String::new(),
+                    Err(_) => saved_key(&base_url)?,
+                };
+                let model = std::env::var("MODEL")
+                    .unwrap_or_else(|_| "glm-4.7".to_string());
+                (api_key, base_url, model)
+            }
+        };
 
-        let model = std::env::var("MODEL")
-            .unwrap_or_else(|_| "glm-4.7".to_string());
+        let embedding_model = std::env::var("EMBEDDING_MODEL")
+            .unwrap_or_else(|_| "embedding-3".to_string());
 
-        Ok(Self { api_key, base_url, model })
-    }
-}
+        let sessions_dir = std::env::var("SESSIONS_DIR")
+            .unwrap_or_else(|_| "sessions".to_string())
+            .into();
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    dotenv::dotenv().ok();
+        let autosave_every = match std::env::var("AUTOSAVE_EVERY_MESSAGES") {
+            Ok(messages) => messages.parse().map_err(|_| anyhow::anyhow!("AUTOSAVE_EVERY_MESSAGES must be a whole number"))?,
+            Err(_) => storage::autosave::DEFAULT_EVERY,
+        };
 
-    let config = Config::from_env()?;
+        let database_url = std::env::var("DATABASE_URL").ok();
 
-    println!("🤖 Emotional-Aware Chat System");
-    println!("📊 Model: {}", config.model);
-    println!("💬 Type 'quit' or 'exit' to end\n");
+        let cache_dir = std::env::var("CLASSIFICATION_CACHE_DIR").ok().map(PathBuf::from);
 
-    let client = openai::Client::from_url(&config.api_key, &config.base_url);
-    let emotion_detector = EmotionDetector::new(client.clone(), &config.model);
-    let chat_agent = ChatAgent::new(client, &config.model);
-    let mut state_manager = ConversationManager::new();
+        let cache_ttl_days: u64 = match std::env::var("CLASSIFICATION_CACHE_TTL_DAYS") {
+            Ok(days) => days.parse().map_err(|_| anyhow::anyhow!("CLASSIFICATION_CACHE_TTL_DAYS must be a whole number of days"))?,
+            Err(_) => 30,
+        };
+        let cache_ttl = cache_ttl_days
+            .checked_mul(24 * 60 * 60)
+            .map(Duration::from_secs)
+            .ok_or_else(|| anyhow::anyhow!("CLASSIFICATION_CACHE_TTL_DAYS is too large"))?;
 
-    loop {
-        print!("You: ");
-        io::stdout().flush()?;
+        let mut strategy = turn::StrategyPolicy::default();
+        if let Ok(turns) = std::env::var("STRATEGY_MIN_TURNS") {
+            strategy.hysteresis.min_turns = turns.parse().map_err(|_| anyhow::anyhow!("STRATEGY_MIN_TURNS must be a whole number"))?;
+        }
+        if let Ok(confidence) = std::env::var("STRATEGY_SWITCH_CONFIDENCE") {
+            strategy.hysteresis.switch_confidence = confidence
+                .parse()
+                .ok()
+                .filter(|c: &f32| (0.0..=1.0).contains(c))
+                .ok_or_else(|| anyhow::anyhow!("STRATEGY_SWITCH_CONFIDENCE must be between 0 and 1"))?;
+        }
+        if let Ok(preempt) = std::env::var("STRATEGY_PREEMPT") {
+            strategy.preempt = preempt.parse().map_err(|_| anyhow::anyhow!("STRATEGY_PREEMPT must be true or false"))?;
+        }
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let input = input.trim();
+        if let Ok(minutes) = std::env::var("SESSION_BREAK_AFTER_MINUTES") {
+            let minutes: u64 = minutes.parse().map_err(|_| anyhow::anyhow!("SESSION_BREAK_AFTER_MINUTES must be a whole number of minutes"))?;
+            strategy.limits.duration = Some(Duration::from_secs(minutes * 60));
+        }
+        if let Ok(turns) = std::env::var("SESSION_BREAK_AFTER_TURNS") {
+            strategy.limits.turns = Some(turns.parse().map_err(|_| anyhow::anyhow!("SESSION_BREAK_AFTER_TURNS must be a whole number"))?);
+        }
 
-        if input.is_empty() {
-            continue;
+        let mut retention = state::RetentionPolicy::default();
+        if let Ok(messages) = std::env::var("SESSION_MAX_MESSAGES") {
+            let messages: usize = messages.parse().map_err(|_| anyhow::anyhow!("SESSION_MAX_MESSAGES must be a whole number"))?;
+            // A message and its reply, so a turn never evicts its own message
+            if messages < 2 {
+                anyhow::bail!("SESSION_MAX_MESSAGES must be at least 2");
+            }
+            retention.max_messages = Some(messages);
         }
+        if let Ok(emotions) = std::env::var("SESSION_MAX_EMOTIONS") {
+            retention.max_emotions = Some(emotions.parse().map_err(|_| anyhow::anyhow!("SESSION_MAX_EMOTIONS must be a whole number"))?);
+        }
+        if let Ok(eviction) = std::env::var("SESSION_EVICTION") {
+            retention.eviction = eviction.parse()?;
+        }
+
+        let session_idle_ttl = match std::env::var("SESSION_IDLE_TTL_SECS") {
+            Ok(secs) => Some(Duration::from_secs(
+                secs.parse().map_err(|_| anyhow::anyhow!("SESSION_IDLE_TTL_SECS must be a whole number of seconds"))?,
+            )),
+            Err(_) => None,
+        };
+        let live_session_expiry_days: u64 = match std::env::var("LIVE_SESSION_EXPIRY_DAYS") {
+            Ok(days) => days.parse().map_err(|_| anyhow::anyhow!("LIVE_SESSION_EXPIRY_DAYS must be a whole number of days"))?,
+            Err(_) => 30,
+        };
+        let live_session_expiry = live_session_expiry_days
+            .checked_mul(24 * 60 * 60)
+            .map(Duration::from_secs)
+            .ok_or_else(|| anyhow::anyhow!("LIVE_SESSION_EXPIRY_DAYS is too large"))?;
 
-        if input.eq_ignore_ascii_case("quit") || input.eq_ignore_ascii_case("exit") {
-            println!("👋 Goodbye!");
-            break;
+        let trend_analyzer = match std::env::var("TREND_ANALYZER") {
+            Ok(name) => state::trend::by_name(&name)?,
+            Err(_) => Arc::new(state::trend::Heuristic),
+        };
+
+        let price = |var: &str| match std::env::var(var) {
+            Ok(price) => price.parse::<f64>().map(Some).map_err(|_| anyhow::anyhow!("{} must be a number of dollars", var)),
+            Err(_) => Ok(None),
+        };
+        let token_prices = match (price("PROMPT_PRICE_PER_1K_TOKENS")?, price("COMPLETION_PRICE_PER_1K_TOKENS")?) {
+            (None, None) => None,
+            (prompt, completion) => Some(commands::recap::TokenPrices { prompt: prompt.unwrap_or(0.0), completion: completion.unwrap_or(0.0) }),
+        };
+        let budget = match (price("SESSION_BUDGET_USD")?, price("DAILY_BUDGET_USD")?) {
+            (None, None) => None,
+            (session, daily) => {
+                let Some(prices) = token_prices else {
+                    anyhow::bail!("SESSION_BUDGET_USD and DAILY_BUDGET_USD need PROMPT_PRICE_PER_1K_TOKENS and COMPLETION_PRICE_PER_1K_TOKENS");
+                };
+                let fallback = match (std::env::var("BUDGET_FALLBACK_MODEL"), std::env::var("BUDGET_FALLBACK_PROFILE")) {
+                    (Ok(_), Ok(_)) => anyhow::bail!("set only one of BUDGET_FALLBACK_MODEL and BUDGET_FALLBACK_PROFILE"),
+                    (Ok(model), Err(_)) => budget::BudgetFallback::Model(model),
+                    (Err(_), Ok(name)) => budget::BudgetFallback::Profile(profiles::find(&profiles, &name)?.name.clone()),
+                    (Err(_), Err(_)) => budget::BudgetFallback::Stop,
+                };
+                Some(budget::BudgetPolicy { session, daily, prices, fallback })
+            }
+        };
+
+        let handoff_webhook = std::env::var("HANDOFF_WEBHOOK_URL").ok();
+        let handoff_queue = std::env::var("HANDOFF_QUEUE_FILE").ok().map(PathBuf::from);
+        if handoff_webhook.is_some() && handoff_queue.is_some() {
+            anyhow::bail!("set only one of HANDOFF_WEBHOOK_URL and HANDOFF_QUEUE_FILE");
+        }
+        if handoff_webhook.is_some() || handoff_queue.is_some() {
+            let negative_turns = match std::env::var("HANDOFF_NEGATIVE_TURNS") {
+                Ok(turns) => turns.parse().map_err(|_| anyhow::anyhow!("HANDOFF_NEGATIVE_TURNS must be a whole number"))?,
+                Err(_) => 3,
+            };
+            strategy.handoff = Some(handoff::HandoffPolicy { negative_turns });
         }
 
-        let emotion = match emotion_detector.analyze(input).await {
-            Ok(e) => e,
-            Err(e) => {
-                eprintln!("❌ Emotion detection failed: {}", e);
-                continue;
+        let blocked_topics = match std::env::var("BLOCKED_TOPICS_FILE") {
+            Ok(path) => agents::topics::load_topics(path.as_ref())?,
+            Err(_) => Vec::new(),
+        };
+        let generation = match std::env::var("GENERATION_FILE") {
+            Ok(path) => agents::generation::load_profile(path.as_ref())?,
+            Err(_) => agents::GenerationProfile::default(),
+        };
+        let strategy_models = match std::env::var("STRATEGY_MODELS") {
+            Ok(spec) => agents::generation::parse_strategy_models(&spec)?,
+            Err(_) => HashMap::new(),
+        };
+        let response_length = match std::env::var("RESPONSE_LENGTH") {
+            Ok(length) => Some(length.parse()?),
+            Err(_) => None,
+        };
+        let profanity = match std::env::var("PROFANITY_FILTER") {
+            Ok(severity) => Some(profanity::ProfanityFilter {
+                severity: severity.parse()?,
+                action: match std::env::var("PROFANITY_ACTION") {
+                    Ok(action) => action.parse()?,
+                    Err(_) => profanity::ProfanityAction::default(),
+                },
+            }),
+            Err(_) => None,
+        };
+        let context_window = match std::env::var("CONTEXT_WINDOW_TOKENS") {
+            Ok(tokens) => Some(tokens.parse().map_err(|_| anyhow::anyhow!("CONTEXT_WINDOW_TOKENS must be a whole number of tokens"))?),
+            Err(_) => None,
+        };
+        let history_dedup = match std::env::var("HISTORY_DEDUP_THRESHOLD") {
+            Ok(threshold) => {
+                let threshold: f64 = threshold.parse().map_err(|_| anyhow::anyhow!("HISTORY_DEDUP_THRESHOLD must be a number"))?;
+                if !(0.0..=1.0).contains(&threshold) {
+                    anyhow::bail!("HISTORY_DEDUP_THRESHOLD must be between 0 and 1");
+                }
+                Some(threshold)
+            }
+            Err(_) => None,
+        };
+
+        let emotion_examples = match std::env::var("EMOTION_EXAMPLES_FILE") {
+            Ok(path) => agents::examples::load_examples(path.as_ref())?,
+            Err(_) => agents::examples::default_examples(),
+        };
+        let taxonomy = match std::env::var("TAXONOMY_FILE") {
+            Ok(path) => Some(agents::taxonomy::load_taxonomy(path.as_ref())?),
+            Err(_) => None,
+        };
+        let self_consistency = match std::env::var("SELF_CONSISTENCY_SAMPLES") {
+            Ok(samples) => {
+                let samples: usize = samples.parse().map_err(|_| anyhow::anyhow!("SELF_CONSISTENCY_SAMPLES must be a whole number"))?;
+                if !agents::consistency::SAMPLES.contains(&samples) {
+                    anyhow::bail!("SELF_CONSISTENCY_SAMPLES must be between 3 and 5");
+                }
+                let below: f32 = match std::env::var("SELF_CONSISTENCY_BELOW") {
+                    Ok(below) => below.parse().map_err(|_| anyhow::anyhow!("SELF_CONSISTENCY_BELOW must be a number"))?,
+                    Err(_) => agents::consistency::DEFAULT_BELOW,
+                };
+                if !(0.0..=1.0).contains(&below) {
+                    anyhow::bail!("SELF_CONSISTENCY_BELOW must be between 0 and 1");
+                }
+                Some(agents::SelfConsistency { samples, below })
             }
+            Err(_) => None,
+        };
+        let fallback = match std::env::var("CLASSIFICATION_FALLBACK") {
+            Ok(policy) => policy.parse()?,
+            Err(_) => agents::FallbackPolicy::default(),
+        };
+        let repair_attempts = match std::env::var("EXTRACTION_REPAIR_ATTEMPTS") {
+            Ok(attempts) => attempts.parse().map_err(|_| anyhow::anyhow!("EXTRACTION_REPAIR_ATTEMPTS must be a whole number"))?,
+            Err(_) => agents::repair::DEFAULT_REPAIR_ATTEMPTS,
         };
 
-        state_manager.add_message(MessageRole::User, input);
-        state_manager.update_emotion(emotion.clone());
+        let onboarding = match std::env::var("ONBOARDING_FILE") {
+            Ok(path) => Some(onboarding::load_script(path.as_ref())?),
+            Err(_) => global.onboarding.then(onboarding::OnboardingScript::standard),
+        };
 
-        let trend = state_manager.get_recent_emotion_trend();
-        let strategy = select_strategy(&emotion, trend);
+        let hooks = match std::env::var("PLUGINS_DIR") {
+            Ok(dir) => hooks::load_dir(dir.as_ref())?,
+            Err(_) => hooks::Hooks::default(),
+        };
+        let pipeline = match std::env::var("TURN_STAGES") {
+            Ok(names) => turn::Pipeline::from_names(names.split(',').map(str::trim), hooks)?,
+            Err(_) => turn::Pipeline::new(hooks),
+        };
 
-        let response = match chat_agent.respond(input, strategy, state_manager.get_history()).await {
-            Ok(r) => r,
-            Err(e) => {
-                eprintln!("❌ Response generation failed: {}", e);
-                continue;
+        let check_in = match std::env::var("CHECK_IN_AFTER_TURNS") {
+            Ok(turns) => {
+                let declining_turns = turns.parse().map_err(|_| anyhow::anyhow!("CHECK_IN_AFTER_TURNS must be a whole number"))?;
+                let idle_secs: u64 = match std::env::var("CHECK_IN_IDLE_SECS") {
+                    Ok(secs) => secs.parse().map_err(|_| anyhow::anyhow!("CHECK_IN_IDLE_SECS must be a whole number of seconds"))?,
+                    Err(_) => 120,
+                };
+                Some(state::CheckInPolicy { declining_turns, idle: Duration::from_secs(idle_secs) })
             }
+            Err(_) => None,
         };
 
-        state_manager.add_message(MessageRole::Assistant, &response);
+        Ok(Self {
+            api_key,
+            base_url,
+            model,
+            profile: global.profile.clone(),
+            profiles,
+            embedding_model,
+            sessions_dir,
+            autosave_every,
+            database_url,
+            cache_dir,
+            cache_ttl,
+            strategy,
+            retention,
+            session_idle_ttl,
+            live_session_expiry,
+            trend_analyzer,
+            check_in,
+            handoff_webhook,
+            handoff_queue,
+            token_prices,
+            budget,
+            blocked_topics,
+            generation,
+            strategy_models,
+            response_length,
+            profanity,
+            context_window,
+            history_dedup,
+            emotion_examples,
+            taxonomy,
+            self_consistency,
+            fallback,
+            repair_attempts,
+            onboarding,
+            pipeline,
+            dry_run,
+            wellbeing_safe: global.wellbeing_safe,
+            minor_safe: global.minor_safe,
+            aspect_sentiment: global.aspects,
+            emotion_causes: global.causes,
+            score_replies: global.score_replies,
+        })
+    }
+
+    /// The key, endpoint and model in use
+    fn provider(&self) -> profiles::Provider {
+        profiles::Provider {
+            profile: self.profile.clone(),
+            api_key: self.api_key.clone(),
+            base_url: self.base_url.clone(),
+            model: self.model.clone(),
+        }
+    }
+
+    /// The strategy routing for replies from `provider`. Its models stand in
+    /// for the provider and model chosen at startup, so any other provider or
+    /// model answers every strategy itself.
+    fn strategy_models(&self, provider: &profiles::Provider) -> HashMap<strategy::ResponseStrategy, String> {
+        if provider.profile == self.profile && provider.model == self.model {
+            self.strategy_models.clone()
+        } else {
+            HashMap::new()
+        }
+    }
+
+    fn handoff_backend(&self) -> Option<Arc<dyn handoff::HandoffBackend>> {
+        if let Some(url) = &self.handoff_webhook {
+            return Some(Arc::new(handoff::WebhookBackend::new(url)));
+        }
+        let path = self.handoff_queue.as_ref()?;
+        Some(Arc::new(handoff::QueueFileBackend::new(path)))
+    }
+
+    fn history_dedup(&self, client: &rig::providers::openai::Client) -> Option<agents::HistoryDedup> {
+        let threshold = self.history_dedup?;
+        let embedder = agents::EmbeddingAgent::new(client.clone(), &self.embedding_model).with_dry_run(self.dry_run);
+        Some(agents::HistoryDedup::new(embedder, threshold))
+    }
+
+    /// `own` topics, or the operator's, with those for young users in minor-safe mode
+    fn blocked_topics(&self, own: Option<Vec<agents::BlockedTopic>>) -> Vec<agents::BlockedTopic> {
+        let mut topics = own.unwrap_or_else(|| self.blocked_topics.clone());
+        if self.minor_safe {
+            topics.extend(minor_safe::blocked_topics());
+        }
+        topics
+    }
 
-        println!("📊 Emotion: {:?} (confidence: {:.2})", emotion.sentiment, emotion.confidence);
-        println!("📈 Trend: {:?}", trend);
-        println!("🎯 Strategy: {:?}", strategy);
-        println!("🤖 Assistant: {}\n", response);
+    /// Shared only by detectors of the same tenant and provider
+    fn classification_cache(&self, tenant: Option<&str>, base_url: &str) -> Option<Arc<agents::ClassificationCache>> {
+        let dir = self.cache_dir.as_ref()?;
+        Some(Arc::new(agents::ClassificationCache::new(dir, self.cache_ttl, tenant, base_url)))
     }
+}
+
+#[cfg(test)]
+impl Config {
+    fn test() -> Self {
+        Self {
+            api_key: "test-key".to_string(),
+            base_url: "https://api.example.com".to_string(),
+            model: "test-model".to_string(),
+            profile: None,
+            profiles: Vec::new(),
+            embedding_model: "test-embedding".to_string(),
+            sessions_dir: std::env::temp_dir().join("tce-test-sessions"),
+            autosave_every: storage::autosave::DEFAULT_EVERY,
+            database_url: None,
+            cache_dir: None,
+            cache_ttl: Duration::from_secs(60),
+            strategy: turn::StrategyPolicy::default(),
+            retention: state::RetentionPolicy::default(),
+            session_idle_ttl: None,
+            live_session_expiry: Duration::from_secs(30 * 24 * 60 * 60),
+            trend_analyzer: Arc::new(state::trend::Heuristic),
+            check_in: None,
+            handoff_webhook: None,
+            handoff_queue: None,
+            token_prices: None,
+            budget: None,
+            blocked_topics: Vec::new(),
+            generation: agents::GenerationProfile::default(),
+            strategy_models: HashMap::new(),
+            response_length: None,
+            profanity: None,
+            context_window: None,
+            history_dedup: None,
+            emotion_examples: agents::examples::default_examples(),
+            taxonomy: None,
+            self_consistency: None,
+            fallback: agents::FallbackPolicy::default(),
+            repair_attempts: agents::repair::DEFAULT_REPAIR_ATTEMPTS,
+            onboarding: None,
+            pipeline: turn::Pipeline::default(),
+            dry_run: false,
+            wellbeing_safe: false,
+            minor_safe: false,
+            aspect_sentiment: false,
+            emotion_causes: false,
+            score_replies: false,
+        }
+    }
+}
+
+/// The provider API, from `OPENAI_BASE_URL`
+fn base_url() -> String {
+    std::env::var("OPENAI_BASE_URL").unwrap_or_else(|_| "https://open.bigmodel.cn/api/paas/v4".to_string())
+}
+
+/// The key `login` saved in the system keyring for `base_url`
+#[cfg(feature = "keyring")]
+fn saved_key(base_url: &str) -> Result<String> {
+    use anyhow::Context;
+    credentials::load(base_url)
+        .context("OPENAI_API_KEY not set")?
+        .ok_or_else(|| anyhow::anyhow!("OPENAI_API_KEY not set and no key saved for {} with `login`", base_url))
+}
+
+#[cfg(not(feature = "keyring"))]
+fn saved_key(_base_url: &str) -> Result<String> {
+    anyhow::bail!("OPENAI_API_KEY not set")
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv::dotenv().ok();
+
+    let cli = Cli::parse();
+    // Saving a key has to work before there is one to read the configuration with
+    #[cfg(feature = "keyring")]
+    if let Some(Command::Login(args)) = &cli.command {
+        return commands::login::run(&base_url(), args);
+    }
+    // The load test's server answers from a mock provider, or its target has a key of its own
+    let needs_key = !matches!(cli.command, Some(Command::Loadtest(_)));
+    let config = Config::from_env(&cli.global, needs_key)?;
+    let telemetry = telemetry::init()?;
+
+    let result = match cli.command {
+        None | Some(Command::Chat) => commands::chat::run(&config, &cli.global).await,
+        Some(Command::Analyze(args)) => commands::analyze::run(&config, &cli.global, &args).await,
+        Some(Command::Classify(args)) => commands::classify::run(&config, &cli.global, &args).await,
+        Some(Command::Extract(args)) => commands::extract::run(&config, &args).await,
+        Some(Command::Categorize(args)) => commands::categorize::run(&config, &args).await,
+        Some(Command::AnalyzeCorpus(args)) => commands::corpus::run(&config, &args).await,
+        Some(Command::Evaluate(args)) => commands::evaluate::run(&config, &cli.global, &args).await,
+        Some(Command::ComparePrompts(args)) => commands::compare::run(&config, &cli.global, &args).await,
+        Some(Command::Export(args)) => commands::export::run(&config, &args).await,
+        Some(Command::Import(args)) => commands::import::run(&config, &args).await,
+        Some(Command::Ipc(args)) => commands::ipc::run(&config, &cli.global, &args).await,
+        Some(Command::Loadtest(args)) => commands::loadtest::run(&config, &args).await,
+        #[cfg(feature = "keyring")]
+        Some(Command::Login(_)) => unreachable!("login runs before the configuration is read"),
+        Some(Command::Report(args)) => commands::report::run(&config, &args).await,
+        Some(Command::Serve(args)) => commands::serve::run(&config, &cli.global, &args).await,
+        Some(Command::Sessions(args)) => commands::sessions::run(&config, &args).await,
+        Some(Command::Simulate(args)) => commands::simulate::run(&config, &cli.global, &args).await,
+        Some(Command::Watch(args)) => commands::watch::run(&config, &cli.global, &args).await,
+    };
 
-    Ok(())
+    telemetry.shutdown();
+    result
 }
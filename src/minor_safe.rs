@@ -0,0 +1,156 @@
+//! Stricter prompts, blocked topics and reply screening for services used by
+//! children and teenagers (`--minor-safe`)
+
+use crate::agents::BlockedTopic;
+use crate::profanity::{ProfanityAction, ProfanityFilter, Severity};
+use crate::strategy::ResponseStrategy;
+
+/// Appended to every strategy prompt and the opener
+pub const FRAMING: &str = "Important: the user may be a child or a teenager. Keep every reply suitable for \
+     them: no swearing, nothing sexual, nothing about alcohol, drugs, gambling, weapons or dieting, and \
+     no graphic detail of violence or injury. Never ask for personal details such as their address, \
+     school or photos, and never suggest meeting anyone. When they describe something worrying, \
+     encourage them to tell a parent, teacher or another adult they trust.";
+
+/// Declines the topics blocked for young users
+pub const REFUSAL: &str = "That's not something I can talk about here. If it's on your mind, a parent, \
+     teacher or another adult you trust is a good person to ask. Is there something else you'd like to talk about?";
+
+/// Every grade of profanity, swapped for milder words
+pub const PROFANITY: ProfanityFilter = ProfanityFilter { severity: Severity::Mild, action: ProfanityAction::Rephrase };
+
+/// Used in place of the standard strategy prompts and any overrides
+pub fn strategy_prompt(strategy: ResponseStrategy) -> &'static str {
+    match strategy {
+        ResponseStrategy::Empathetic => {
+            "You are a kind, patient listener talking with a young person who is having a hard time.
+            Use simple, gentle words and short sentences. Acknowledge how they feel without
+            dramatizing it, and remind them that the adults around them can help."
+        }
+        ResponseStrategy::Encouraging => {
+            "You are a supportive guide talking with a young person who needs some encouragement.
+            Use simple words and short sentences. Point out what they are doing well and suggest
+            small, safe next steps, such as talking to a parent or teacher."
+        }
+        ResponseStrategy::Cheerful => {
+            "You are a friendly, upbeat companion talking with a young person in a good mood.
+            Share their happiness in simple words. Keep the fun clean and wholesome and avoid
+            sarcasm, teasing and innuendo."
+        }
+        ResponseStrategy::Neutral => {
+            "You are a polite, friendly assistant talking with a young person.
+            Use simple words and short sentences and keep to everyday, age-appropriate subjects."
+        }
+    }
+}
+
+/// Adds the mandatory framing to a strategy preamble
+pub fn frame(preamble: &str) -> String {
+    format!("{}\n\n{}", preamble, FRAMING)
+}
+
+/// Topics declined for young users, on top of the operator's. Keywords are
+/// shaped like requests, so a child telling of a parent's drinking or their
+/// own skipped meals is heard rather than refused; other requests are left to
+/// the classifier.
+pub fn blocked_topics() -> Vec<BlockedTopic> {
+    let topic = |name: &str, description: &str, keywords: Vec<String>| BlockedTopic {
+        name: name.to_string(),
+        description: description.to_string(),
+        keywords,
+        refusal: Some(REFUSAL.to_string()),
+    };
+    vec![
+        topic(
+            "sexual content",
+            "Sex, sexual acts, nudity or explicit material",
+            requests(&["send", "watch", "find"], &["nudes", "porn"]),
+        ),
+        topic(
+            "alcohol and drugs",
+            "Getting, using or hiding alcohol, drugs or vapes",
+            requests(&["buy", "get", "hide"], &["alcohol", "vodka", "beer", "weed", "cannabis", "cocaine", "a vape", "my vape"])
+                .into_iter()
+                .chain(["get drunk", "get high"].map(String::from))
+                .collect(),
+        ),
+        topic(
+            "gambling",
+            "Betting, casinos or gambling games played for money",
+            ["place a bet", "bet money", "gamble online", "play poker for money"].map(String::from).to_vec(),
+        ),
+        topic(
+            "weapons",
+            "Getting, making or using weapons",
+            requests(&["buy", "get", "make"], &["a gun", "a firearm", "a handgun", "ammunition", "a bomb"]),
+        ),
+        topic(
+            "dieting",
+            "Losing weight, restricting food or diet products",
+            requests(&["how do i", "how to", "how can i"], &["lose weight", "skip meals", "stop eating", "count calories"])
+                .into_iter()
+                .chain(["buy diet pills"].map(String::from))
+                .collect(),
+        ),
+    ]
+}
+
+/// Every `verb` followed by every `object`, such as "buy alcohol"
+fn requests(verbs: &[&str], objects: &[&str]) -> Vec<String> {
+    verbs.iter().flat_map(|verb| objects.iter().map(move |object| format!("{} {}", verb, object))).collect()
+}
+
+/// Rephrases profanity in a reply. A reply is never replaced for mentioning
+/// a blocked topic, since a supportive answer to a disclosure names it too;
+/// the framing keeps replies away from those topics.
+pub fn screen(reply: &str) -> String {
+    PROFANITY.apply(reply).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::TopicGuard;
+    use crate::agents::topics::match_keywords;
+
+    #[test]
+    fn test_replies_are_screened() {
+        assert_eq!(screen("Damn, that sounds like a bloody long day."), "Darn, that sounds like a really long day.");
+        let fine = "That sounds like a tough day at school. Do you want to tell me what happened?";
+        assert_eq!(screen(fine), fine);
+    }
+
+    #[test]
+    fn test_topics_refuse_with_a_trusted_adult() {
+        let topics = blocked_topics();
+        let topic = match_keywords(&topics, "How do I get VODKA without my parents knowing?").unwrap();
+        assert_eq!(topic.name, "alcohol and drugs");
+        assert_eq!(topic.refusal(), REFUSAL);
+    }
+
+    #[test]
+    fn test_identity_terms_are_not_blocked() {
+        let topics = blocked_topics();
+        assert!(match_keywords(&topics, "I think I might be bisexual, how do I tell my mum?").is_none());
+        let reply = "Thank you for telling me you're bisexual. That took courage.";
+        assert_eq!(screen(reply), reply);
+        assert!(match_keywords(&topics, "How do I send nudes without my parents seeing?").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_disclosures_are_heard() {
+        let client = rig::providers::openai::Client::from_url("test-key", "https://api.example.com");
+        let guard = TopicGuard::new(client, "test-model", blocked_topics()).with_dry_run(true);
+        for disclosure in [
+            "My mom drinks too much alcohol and it scares me",
+            "I skip meals because I feel bad about how I look",
+            "My brother got caught with weed at school",
+        ] {
+            assert_eq!(guard.check(disclosure).await, None, "{}", disclosure);
+        }
+        assert!(guard.check("Where can I buy alcohol without ID?").await.is_some());
+
+        let reply = "It sounds really frightening when your mom drinks too much alcohol. Is there an adult you trust you could tell?";
+        assert_eq!(screen(reply), reply);
+    }
+}
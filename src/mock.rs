@@ -0,0 +1,146 @@
+//! A canned OpenAI-compatible provider, so `loadtest` measures the server
+//! rather than a model and golden transcripts run without one
+
+use anyhow::Result;
+use axum::response::IntoResponse;
+use axum::{Json, Router, routing::post};
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub const MOCK_MODEL: &str = "mock-model";
+
+/// Reply to every chat request nothing was queued for
+const MOCK_REPLY: &str = "Thanks for telling me. How are you feeling about it now?";
+
+/// Answers each classification, a request offering the `submit` tool, and
+/// each chat request after `latency`: with the next answer queued by
+/// `classify_as` or `reply_with`, or once the queue is empty as Neutral and
+/// with the same reply
+#[derive(Debug, Clone, Default)]
+pub struct MockProvider {
+    latency: Duration,
+    classifications: Arc<Mutex<VecDeque<Value>>>,
+    replies: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl MockProvider {
+    /// `latency` stands in for the time a model takes to answer
+    pub fn new(latency: Duration) -> Self {
+        Self { latency, ..Self::default() }
+    }
+
+    /// Arguments of the `submit` call answering the next classification
+    pub fn classify_as(&self, classification: Value) {
+        self.classifications.lock().unwrap().push_back(classification);
+    }
+
+    pub fn reply_with(&self, reply: &str) {
+        self.replies.lock().unwrap().push_back(reply.to_string());
+    }
+
+    /// Queued classifications and replies nobody asked for
+    pub fn unused(&self) -> (usize, usize) {
+        (self.classifications.lock().unwrap().len(), self.replies.lock().unwrap().len())
+    }
+
+    /// Serves on a free local port and returns the base URL
+    pub async fn start(&self) -> Result<String> {
+        let provider = self.clone();
+        let app = Router::new().route("/chat/completions", post(move |Json(body): Json<Value>| async move {
+            tokio::time::sleep(provider.latency).await;
+            if body["stream"].as_bool().unwrap_or(false) {
+                return ([("content-type", "text/event-stream")], stream_answer(&provider.reply())).into_response();
+            }
+            Json(provider.answer(&body)).into_response()
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                tracing::error!(error = %e, "mock provider stopped");
+            }
+        });
+        Ok(url)
+    }
+
+    fn reply(&self) -> String {
+        self.replies.lock().unwrap().pop_front().unwrap_or_else(|| MOCK_REPLY.to_string())
+    }
+
+    fn answer(&self, body: &Value) -> Value {
+        let message = if body.get("tools").is_some_and(|tools| !tools.as_array().is_some_and(Vec::is_empty)) {
+            let arguments = self.classifications.lock().unwrap().pop_front()
+                .unwrap_or_else(|| json!({ "sentiment": "Neutral", "confidence": 0.8 }));
+            json!({ "role": "assistant", "tool_calls": [{
+                "id": "call", "type": "function", "function": { "name": "submit", "arguments": arguments.to_string() },
+            }]})
+        } else {
+            json!({ "role": "assistant", "content": self.reply() })
+        };
+        json!({
+            "id": "mock", "object": "chat.completion", "created": 0, "model": MOCK_MODEL,
+            "choices": [{ "index": 0, "finish_reason": "stop", "message": message }],
+            "usage": { "prompt_tokens": 10, "total_tokens": 15 },
+        })
+    }
+}
+
+/// `reply` a word at a time as server-sent events, then its usage
+fn stream_answer(reply: &str) -> String {
+    let chunk = |delta: Value, usage: Value| json!({
+        "id": "mock", "object": "chat.completion.chunk", "created": 0, "model": MOCK_MODEL,
+        "choices": if delta.is_null() { json!([]) } else { json!([{ "index": 0, "delta": delta }]) },
+        "usage": usage,
+    });
+    let words = reply.split_inclusive(' ').map(|word| chunk(json!({ "content": word }), Value::Null));
+    let usage = chunk(Value::Null, json!({ "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 }));
+    let mut body: String = words.chain([usage]).map(|chunk| format!("data: {}\n\n", chunk)).collect();
+    body.push_str("data: [DONE]\n\n");
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{ChatAgent, EmotionDetector, LengthPreference, ReplyChunk, TokenUsage, Verbosity};
+    use futures::StreamExt;
+    use crate::strategy::{ResponseStrategy, StrategyBlend};
+    use crate::Sentiment;
+
+    #[tokio::test]
+    async fn test_mock_provider_classifies_and_replies() {
+        let provider = MockProvider::default();
+        provider.classify_as(json!({ "sentiment": "Negative", "confidence": 0.9 }));
+        let url = provider.start().await.unwrap();
+        let client = rig::providers::openai::Client::from_url("mock", &url);
+
+        // Queued answers come first, then the canned ones
+        let emotion = EmotionDetector::new(client.clone(), MOCK_MODEL).analyze("I lost my keys").await.unwrap();
+        assert_eq!(emotion.sentiment, Sentiment::Negative);
+        assert_eq!(provider.unused(), (0, 0));
+        let emotion = EmotionDetector::new(client.clone(), MOCK_MODEL).analyze("I lost my keys").await.unwrap();
+        assert_eq!(emotion.sentiment, Sentiment::Neutral);
+        let blend = StrategyBlend::single(ResponseStrategy::Neutral);
+        let reply = ChatAgent::new(client.clone(), MOCK_MODEL).respond("I lost my keys", &blend, &[], None).await.unwrap();
+        assert_eq!(reply.text, MOCK_REPLY);
+
+        // Streamed replies end with what they cost
+        let agent = ChatAgent::new(client.clone(), MOCK_MODEL).with_endpoint("mock", &url);
+        let chunks: Vec<ReplyChunk> = agent.respond_stream("I lost my keys", &blend, &[], None).await.unwrap().map(Result::unwrap).collect().await;
+        let text: String = chunks.iter().filter_map(|chunk| match chunk {
+            ReplyChunk::Text(text) => Some(text.as_str()),
+            ReplyChunk::Usage(_) | ReplyChunk::Score(_) => None,
+        }).collect();
+        assert_eq!(text, MOCK_REPLY);
+        assert_eq!(chunks.last(), Some(&ReplyChunk::Usage(TokenUsage { prompt: 10, completion: 5 })));
+
+        // A reply cut to a length is generated whole, to be cut before it is sent
+        let length = LengthPreference::default();
+        length.set(Some(Verbosity::Brief));
+        let agent = ChatAgent::new(client, MOCK_MODEL).with_endpoint("mock", &url).with_length(length);
+        let chunks: Vec<ReplyChunk> = agent.respond_stream("I lost my keys", &blend, &[], None).await.unwrap().map(Result::unwrap).collect().await;
+        assert_eq!(chunks.first(), Some(&ReplyChunk::Text(MOCK_REPLY.to_string())));
+    }
+}
@@ -1,17 +1,42 @@
-use super::super::SentimentClassification;
+use super::super::{AspectSentiment, SentimentClassification};
+use crate::strategy::ResponseStrategy;
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 pub enum MessageRole {
     User,
     Assistant,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 pub struct Message {
     pub role: MessageRole,
     pub content: String,
     pub timestamp: i64,
     pub emotion: Option<SentimentClassification>,
+    #[serde(default)]
+    pub strategy: Option<ResponseStrategy>,
+    /// Who wrote it, in an imported transcript; `None` in chats with the assistant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+    /// Sentiment toward each thing the message talks about, when aspects are extracted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aspects: Vec<AspectSentiment>,
+    /// What the user's negative emotion seems to come from, e.g. "deadline
+    /// pressure", when causes are extracted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cause: Option<String>,
+    /// How the evaluator rated an assistant reply, when replies are scored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<ReplyScore>,
+}
+
+/// An evaluator's rating of one assistant reply
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct ReplyScore {
+    /// How closely the reply followed its strategy (0-1)
+    pub strategy_adherence: f32,
+    /// How well it acknowledged what the user felt (0-1)
+    pub empathy: f32,
 }
 
 #[cfg(test)]
@@ -25,6 +50,11 @@ mod tests {
             content: "Hello".to_string(),
             timestamp: 12345,
             emotion: None,
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
         };
 
         let json = serde_json::to_string(&msg).unwrap();
@@ -43,7 +73,14 @@ mod tests {
             emotion: Some(SentimentClassification {
                 sentiment: Sentiment::Positive,
                 confidence: 0.95,
+                labels: Vec::new(),
+                is_fallback: false,
             }),
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
         };
 
         assert!(msg.emotion.is_some());
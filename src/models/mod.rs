@@ -2,4 +2,4 @@
 
 pub mod message;
 
-pub use message::{Message, MessageRole};
+pub use message::{Message, MessageRole, ReplyScore};
@@ -0,0 +1,196 @@
+//! Node.js bindings through N-API, loaded as a native addon (see `npm/`)
+
+use futures::StreamExt;
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi_derive::napi;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use crate::SentimentClassification;
+use crate::agents::chat::Reply;
+use crate::agents::{self, ReplyChunk, TopicGuard};
+use crate::state::ConversationManager;
+use crate::turn::{Pipeline, Prepared, StrategyPolicy, TurnOutcome, prepare_turn};
+
+const DEFAULT_BASE_URL: &str = "https://open.bigmodel.cn/api/paas/v4";
+const DEFAULT_MODEL: &str = "glm-4.7";
+
+#[napi(object)]
+pub struct EngineOptions {
+    pub api_key: String,
+    pub base_url: Option<String>,
+    pub model: Option<String>,
+    /// Answer without calling the provider
+    pub dry_run: Option<bool>,
+}
+
+#[napi(object)]
+pub struct Classification {
+    /// `"Positive"`, `"Negative"` or `"Neutral"`
+    pub sentiment: String,
+    pub confidence: f64,
+    /// Not the model's answer but what the fallback policy put in its place
+    pub is_fallback: bool,
+}
+
+impl From<SentimentClassification> for Classification {
+    fn from(classification: SentimentClassification) -> Self {
+        Self {
+            sentiment: format!("{:?}", classification.sentiment),
+            confidence: classification.confidence.to_string().parse().unwrap_or_default(),
+            is_fallback: classification.is_fallback,
+        }
+    }
+}
+
+/// One answered message, with the same fields as the HTTP chat endpoint
+#[napi(object)]
+pub struct Turn {
+    pub emotion: Classification,
+    pub trend: String,
+    pub strategy: String,
+    pub response: String,
+    /// Set while the conversation waits for an operator; `response` is then a fixed notice
+    pub handoff: Option<String>,
+    pub blocked_topic: Option<String>,
+    pub break_suggested: bool,
+    /// What made the message read like instructions to the model
+    pub injection: Option<String>,
+}
+
+impl From<TurnOutcome> for Turn {
+    fn from(outcome: TurnOutcome) -> Self {
+        Self {
+            emotion: outcome.emotion.into(),
+            trend: format!("{:?}", outcome.trend),
+            strategy: format!("{:?}", outcome.strategy),
+            response: outcome.response,
+            handoff: outcome.handoff.map(|reason| format!("{:?}", reason)),
+            blocked_topic: outcome.blocked_topic,
+            break_suggested: outcome.break_suggested,
+            injection: outcome.injection,
+        }
+    }
+}
+
+struct Agents {
+    detector: agents::EmotionDetector,
+    chat_agent: agents::ChatAgent,
+    topics: TopicGuard,
+}
+
+fn failed(e: impl std::fmt::Display) -> Error {
+    Error::from_reason(e.to_string())
+}
+
+/// Provider clients shared by every session it starts
+#[napi]
+pub struct Engine {
+    agents: Arc<Agents>,
+}
+
+#[napi]
+impl Engine {
+    #[napi(constructor)]
+    pub fn new(options: EngineOptions) -> Self {
+        let base_url = options.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL);
+        let model = options.model.as_deref().unwrap_or(DEFAULT_MODEL);
+        let dry_run = options.dry_run.unwrap_or(false);
+        let client = rig::providers::openai::Client::from_url(&options.api_key, base_url);
+        let agents = Agents {
+            detector: agents::EmotionDetector::new(client.clone(), model).with_dry_run(dry_run),
+            chat_agent: agents::ChatAgent::new(client.clone(), model).with_endpoint(&options.api_key, base_url).with_dry_run(dry_run),
+            topics: TopicGuard::new(client, model, Vec::new()),
+        };
+        Self { agents: Arc::new(agents) }
+    }
+
+    #[napi]
+    pub async fn classify(&self, text: String) -> Result<Classification> {
+        let classification = self.agents.detector.analyze(&text).await.map_err(failed)?;
+        Ok(classification.into())
+    }
+
+    #[napi]
+    pub fn session(&self) -> Session {
+        Session { agents: self.agents.clone(), state: Arc::new(Mutex::new(ConversationManager::new())) }
+    }
+}
+
+/// One conversation; turns on the same session run one at a time
+#[napi]
+pub struct Session {
+    agents: Arc<Agents>,
+    state: Arc<Mutex<ConversationManager>>,
+}
+
+#[napi]
+impl Session {
+    #[napi]
+    pub async fn chat(&self, text: String) -> Result<Turn> {
+        self.turn(text, None).await
+    }
+
+    /// Like `chat`, calling `onToken` with each piece of the reply as it streams
+    #[napi(ts_args_type = "text: string, onToken: (token: string) => void")]
+    pub async fn chat_stream(&self, text: String, on_token: ThreadsafeFunction<String, ErrorStrategy::Fatal>) -> Result<Turn> {
+        self.turn(text, Some(on_token)).await
+    }
+
+    /// Every message so far, oldest first
+    #[napi(ts_return_type = "Promise<Array<{ role: string, content: string, timestamp: number }>>")]
+    pub async fn history(&self) -> Result<serde_json::Value> {
+        serde_json::to_value(self.state.lock().await.get_history()).map_err(failed)
+    }
+
+    async fn turn(&self, text: String, on_token: Option<ThreadsafeFunction<String, ErrorStrategy::Fatal>>) -> Result<Turn> {
+        let agents = &self.agents;
+        let mut state = self.state.lock().await;
+        let pipeline = Pipeline::default();
+        let prepared = prepare_turn(&agents.detector, &agents.topics, &mut state, &text, StrategyPolicy::default(), &pipeline)
+            .await
+            .map_err(|e| failed(format!("{:#}", e)))?;
+
+        let outcome = match prepared {
+            Prepared::Done(outcome) => {
+                if let Some(on_token) = &on_token {
+                    on_token.call(outcome.response.clone(), ThreadsafeFunctionCallMode::NonBlocking);
+                }
+                *outcome
+            }
+            Prepared::Reply(pending) => {
+                // Tokens hooks will rewrite are held back for the rewritten reply
+                let live = on_token.as_ref().filter(|_| !pending.rewrites_reply());
+                let mut stream = agents
+                    .chat_agent
+                    .respond_stream(pending.input(), pending.blend(), state.get_history(), pending.aside().as_deref())
+                    .await
+                    .map_err(failed)?;
+                let (mut response, mut usage, mut score) = (String::new(), None, None);
+                while let Some(chunk) = stream.next().await {
+                    match chunk.map_err(failed)? {
+                        ReplyChunk::Text(token) => {
+                            response.push_str(&token);
+                            if let Some(on_token) = live {
+                                on_token.call(token, ThreadsafeFunctionCallMode::NonBlocking);
+                            }
+                        }
+                        ReplyChunk::Usage(reported) => usage = Some(reported),
+                        ReplyChunk::Score(rated) => score = Some(rated),
+                    }
+                }
+                let outcome = pending
+                    .finish(&mut state, &agents.chat_agent, Reply { text: response, usage, alternatives: Vec::new(), score })
+                    .await
+                    .map_err(|e| failed(format!("{:#}", e)))?;
+                if live.is_none()
+                    && let Some(on_token) = &on_token
+                {
+                    on_token.call(outcome.response.clone(), ThreadsafeFunctionCallMode::NonBlocking);
+                }
+                outcome
+            }
+        };
+        Ok(outcome.into())
+    }
+}
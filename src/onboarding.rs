@@ -0,0 +1,174 @@
+//! The exchange that opens a fresh chat session: a greeting and a few
+//! questions whose answers go into the user profile
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use crate::state::UserProfile;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct OnboardingScript {
+    /// Said before the first question
+    pub greeting: String,
+    /// Said instead of `greeting` once the profile has a name, which replaces `{name}`
+    #[serde(default)]
+    pub returning_greeting: Option<String>,
+    pub questions: Vec<OnboardingQuestion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct OnboardingQuestion {
+    pub ask: String,
+    /// The answer is what the user wants to be called
+    #[serde(default)]
+    pub name: bool,
+    /// How the answer is introduced to the model, such as "What brings them
+    /// here"; needed unless the question asks for the name
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Asked only until the profile has an answer; other answers are asked
+    /// again each session
+    #[serde(default)]
+    pub once: bool,
+}
+
+impl OnboardingQuestion {
+    fn is_answered(&self, profile: &UserProfile) -> bool {
+        if self.name {
+            profile.name.is_some()
+        } else {
+            self.label.as_ref().is_some_and(|label| profile.answers.contains_key(label))
+        }
+    }
+
+    /// Keeps the answer in `profile`; a blank answer skips the question
+    pub fn record(&self, answer: &str, profile: &mut UserProfile) {
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return;
+        }
+        if self.name {
+            profile.name = Some(answer.to_string());
+        } else if let Some(label) = &self.label {
+            profile.answers.insert(label.clone(), answer.to_string());
+        }
+    }
+}
+
+impl OnboardingScript {
+    /// Asks for the user's name and what brings them here
+    pub fn standard() -> Self {
+        Self {
+            greeting: "Hi, I'm here to listen. Two quick questions before we start; press Enter to skip either."
+                .to_string(),
+            returning_greeting: Some("Welcome back, {name}.".to_string()),
+            questions: vec![
+                OnboardingQuestion { ask: "What would you like me to call you?".to_string(), name: true, label: None, once: true },
+                OnboardingQuestion {
+                    ask: "What brings you here today?".to_string(),
+                    name: false,
+                    label: Some("What brings them here".to_string()),
+                    once: false,
+                },
+            ],
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.questions.iter().filter(|q| q.name).count() > 1 {
+            bail!("only one onboarding question can ask for the name");
+        }
+        if let Some(question) = self.questions.iter().find(|q| !q.name && q.label.is_none()) {
+            bail!("onboarding question '{}' needs a label", question.ask);
+        }
+        Ok(())
+    }
+
+    /// The saved profile without the answers that are asked again each session
+    pub fn start(&self, mut saved: UserProfile) -> UserProfile {
+        for question in self.questions.iter().filter(|q| !q.once) {
+            if question.name {
+                saved.name = None;
+            } else if let Some(label) = &question.label {
+                saved.answers.remove(label);
+            }
+        }
+        saved
+    }
+
+    pub fn greeting(&self, profile: &UserProfile) -> String {
+        match (&self.returning_greeting, &profile.name) {
+            (Some(returning), Some(name)) => returning.replace("{name}", name),
+            _ => self.greeting.clone(),
+        }
+    }
+
+    /// The questions still to ask, in script order
+    pub fn pending<'a>(&'a self, profile: &'a UserProfile) -> impl Iterator<Item = &'a OnboardingQuestion> {
+        self.questions.iter().filter(|q| !q.is_answered(profile))
+    }
+}
+
+pub fn load_script(path: &Path) -> Result<OnboardingScript> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let script: OnboardingScript = serde_json::from_str(&json).with_context(|| format!("failed to parse {}", path.display()))?;
+    script.validate().with_context(|| format!("invalid onboarding script {}", path.display()))?;
+    Ok(script)
+}
+
+/// The profile saved by earlier sessions; empty on first run
+pub fn load_profile(path: &Path) -> Result<UserProfile> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).with_context(|| format!("failed to parse {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(UserProfile::default()),
+        Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+pub fn save_profile(path: &Path, profile: &UserProfile) -> Result<()> {
+    let json = serde_json::to_string_pretty(profile)?;
+    std::fs::write(path, json).with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_session_asks_everything() {
+        let script = OnboardingScript::standard();
+        let mut profile = script.start(UserProfile::default());
+        assert_eq!(script.greeting(&profile), script.greeting);
+
+        let questions: Vec<_> = script.pending(&profile).cloned().collect();
+        assert_eq!(questions.len(), 2);
+        questions[0].record("  Sam ", &mut profile);
+        questions[1].record("", &mut profile);
+        assert_eq!(profile.name.as_deref(), Some("Sam"));
+        assert!(profile.answers.is_empty());
+    }
+
+    #[test]
+    fn test_returning_user_is_asked_only_per_session_questions() {
+        let script = OnboardingScript::standard();
+        let mut saved = UserProfile { name: Some("Sam".to_string()), ..UserProfile::default() };
+        saved.answers.insert("What brings them here".to_string(), "exams".to_string());
+
+        let profile = script.start(saved);
+        assert_eq!(script.greeting(&profile), "Welcome back, Sam.");
+        assert!(profile.answers.is_empty());
+        let asks: Vec<_> = script.pending(&profile).map(|q| q.ask.as_str()).collect();
+        assert_eq!(asks, ["What brings you here today?"]);
+    }
+
+    #[test]
+    fn test_script_questions_need_labels() {
+        let script: OnboardingScript = serde_json::from_str(r#"{
+            "greeting": "Hello!",
+            "questions": [{ "ask": "How did you sleep?" }]
+        }"#).unwrap();
+        assert!(script.validate().is_err());
+    }
+}
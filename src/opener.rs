@@ -0,0 +1,151 @@
+//! What the assistant says first when it opens a session instead of waiting
+//! for the user
+
+use crate::Sentiment;
+use crate::models::MessageRole;
+use crate::state::UserProfile;
+use crate::storage::StoredSession;
+
+/// Causes from the last session brought up again, most recent last
+const MAX_FOLLOW_UPS: usize = 3;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// What the assistant knows of the user's previous session
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LastSession {
+    /// Whole days between its start and now
+    pub days_ago: i64,
+    /// How the user's last classified message in it read
+    pub mood: Option<Sentiment>,
+    /// What upset the user in it, when causes were extracted
+    pub follow_ups: Vec<String>,
+    /// A summary of it, when one could be written
+    pub summary: Option<String>,
+}
+
+impl LastSession {
+    pub fn of(session: &StoredSession, now: i64) -> Self {
+        let user = || session.messages.iter().filter(|m| matches!(m.role, MessageRole::User));
+        let mood = user().filter_map(|m| m.emotion.as_ref()).next_back().map(|emotion| emotion.sentiment);
+        let mut follow_ups: Vec<String> = Vec::new();
+        for cause in user().filter_map(|m| m.cause.as_ref()) {
+            follow_ups.retain(|c| !c.eq_ignore_ascii_case(cause));
+            follow_ups.push(cause.clone());
+        }
+        let excess = follow_ups.len().saturating_sub(MAX_FOLLOW_UPS);
+        follow_ups.drain(..excess);
+        Self {
+            days_ago: (now - session.started_at).max(0) / SECONDS_PER_DAY,
+            mood,
+            follow_ups,
+            summary: None,
+        }
+    }
+}
+
+/// Everything an opener can draw on
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpenerContext {
+    pub profile: Option<UserProfile>,
+    pub last_session: Option<LastSession>,
+}
+
+impl OpenerContext {
+    /// The prompt for the opener
+    pub fn describe(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(profile) = &self.profile {
+            lines.push(profile.describe());
+        }
+        match &self.last_session {
+            Some(last) => {
+                let when = match last.days_ago {
+                    0 => "earlier today".to_string(),
+                    1 => "yesterday".to_string(),
+                    days => format!("{} days ago", days),
+                };
+                lines.push(format!("You last talked with the user {}.", when));
+                if let Some(summary) = &last.summary {
+                    lines.push(format!("Summary of that conversation: {}", summary));
+                }
+                if let Some(mood) = last.mood {
+                    lines.push(format!("Their last message then read as {:?}.", mood));
+                }
+                if !last.follow_ups.is_empty() {
+                    lines.push(format!("Things that upset them then, worth asking about: {}.", last.follow_ups.join("; ")));
+                }
+            }
+            None => lines.push("This is your first conversation with the user.".to_string()),
+        }
+        lines.join("\n")
+    }
+
+    /// An opener built without the model, for `--dry-run` and when the call fails
+    pub fn fallback(&self) -> String {
+        let greeting = match self.profile.as_ref().and_then(|p| p.name.as_deref()) {
+            Some(name) => format!("Hi {}.", name),
+            None => "Hi.".to_string(),
+        };
+        let question = match &self.last_session {
+            Some(last) if !last.follow_ups.is_empty() => {
+                format!("Last time you mentioned {}. How is that going?", last.follow_ups[last.follow_ups.len() - 1])
+            }
+            Some(last) if last.mood == Some(Sentiment::Negative) => {
+                "Last time things sounded hard. How are you feeling today?".to_string()
+            }
+            Some(_) => "Good to see you again. How are you feeling today?".to_string(),
+            None => "How are you feeling today?".to_string(),
+        };
+        format!("{} {}", greeting, question)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SentimentClassification;
+    use crate::models::Message;
+
+    fn user(content: &str, sentiment: Sentiment, cause: Option<&str>) -> Message {
+        Message {
+            role: MessageRole::User,
+            content: content.to_string(),
+            timestamp: 1_000_000,
+            emotion: Some(SentimentClassification { sentiment, confidence: 0.8, labels: Vec::new(), is_fallback: false }),
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: cause.map(str::to_string),
+            score: None,
+        }
+    }
+
+    #[test]
+    fn test_last_session_follow_ups() {
+        let session = StoredSession::new(1_000_000, vec![
+            user("Work is a lot", Sentiment::Negative, Some("the deadline")),
+            user("And my sister is upset", Sentiment::Negative, Some("argument with sister")),
+            user("The deadline again", Sentiment::Negative, Some("The deadline")),
+            user("Talking helped", Sentiment::Positive, None),
+        ]);
+
+        let last = LastSession::of(&session, 1_000_000 + 3 * SECONDS_PER_DAY + 60);
+        assert_eq!(last.days_ago, 3);
+        assert_eq!(last.mood, Some(Sentiment::Positive));
+        assert_eq!(last.follow_ups, ["argument with sister", "The deadline"]);
+    }
+
+    #[test]
+    fn test_fallback_opener() {
+        assert_eq!(OpenerContext::default().fallback(), "Hi. How are you feeling today?");
+
+        let context = OpenerContext {
+            profile: Some(UserProfile { name: Some("Sam".to_string()), ..UserProfile::default() }),
+            last_session: Some(LastSession { days_ago: 1, follow_ups: vec!["the deadline".to_string()], ..LastSession::default() }),
+        };
+        assert_eq!(context.fallback(), "Hi Sam. Last time you mentioned the deadline. How is that going?");
+        assert!(context.describe().contains("You last talked with the user yesterday."));
+        assert!(context.describe().contains("worth asking about: the deadline."));
+    }
+}
@@ -0,0 +1,169 @@
+//! Masking or rephrasing profanity in assistant replies (`PROFANITY_FILTER`),
+//! for deployments aimed at minors or workplaces
+
+use anyhow::bail;
+use std::borrow::Cow;
+use std::str::FromStr;
+
+/// How strong a word is; a filter catches its own severity and everything
+/// stronger
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Mild,
+    Moderate,
+    Strong,
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mild" => Ok(Severity::Mild),
+            "moderate" => Ok(Severity::Moderate),
+            "strong" => Ok(Severity::Strong),
+            other => bail!("unknown profanity severity '{}', expected mild, moderate or strong", other),
+        }
+    }
+}
+
+/// What happens to a caught word
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProfanityAction {
+    /// Keeps the first letter and stars out the rest
+    #[default]
+    Mask,
+    /// Swaps in a milder word
+    Rephrase,
+}
+
+impl FromStr for ProfanityAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mask" => Ok(ProfanityAction::Mask),
+            "rephrase" => Ok(ProfanityAction::Rephrase),
+            other => bail!("unknown profanity action '{}', expected mask or rephrase", other),
+        }
+    }
+}
+
+/// Each word with its severity and the milder word it is rephrased as
+const WORDS: &[(&str, Severity, &str)] = &[
+    ("damn", Severity::Mild, "darn"),
+    ("damned", Severity::Mild, "darned"),
+    ("dammit", Severity::Mild, "darn it"),
+    ("goddamn", Severity::Mild, "darn"),
+    ("hell", Severity::Mild, "heck"),
+    ("crap", Severity::Mild, "rubbish"),
+    ("crappy", Severity::Mild, "lousy"),
+    ("bloody", Severity::Mild, "really"),
+    ("shit", Severity::Moderate, "rubbish"),
+    ("shitty", Severity::Moderate, "awful"),
+    ("bullshit", Severity::Moderate, "nonsense"),
+    ("ass", Severity::Moderate, "butt"),
+    ("arse", Severity::Moderate, "butt"),
+    ("asshole", Severity::Moderate, "jerk"),
+    ("arsehole", Severity::Moderate, "jerk"),
+    ("bastard", Severity::Moderate, "jerk"),
+    ("bitch", Severity::Moderate, "jerk"),
+    ("dick", Severity::Moderate, "jerk"),
+    ("fuck", Severity::Strong, "heck"),
+    ("fucking", Severity::Strong, "really"),
+    ("fucked", Severity::Strong, "messed up"),
+    ("fucker", Severity::Strong, "jerk"),
+    ("motherfucker", Severity::Strong, "jerk"),
+    ("cunt", Severity::Strong, "jerk"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfanityFilter {
+    /// The mildest words caught
+    pub severity: Severity,
+    pub action: ProfanityAction,
+}
+
+impl ProfanityFilter {
+    /// The reply with every caught word masked or rephrased. Only whole
+    /// words are matched, so "hello" and "class" stay as they are.
+    pub fn apply<'a>(&self, reply: &'a str) -> Cow<'a, str> {
+        let mut filtered = String::new();
+        let mut copied = 0;
+        let mut caught = 0;
+        for (start, word) in words(reply) {
+            let Some(replacement) = self.replace(word) else {
+                continue;
+            };
+            filtered.push_str(&reply[copied..start]);
+            filtered.push_str(&replacement);
+            copied = start + word.len();
+            caught += 1;
+        }
+        if caught == 0 {
+            return Cow::Borrowed(reply);
+        }
+        tracing::debug!(caught, "filtered profanity from the reply");
+        filtered.push_str(&reply[copied..]);
+        Cow::Owned(filtered)
+    }
+
+    fn replace(&self, word: &str) -> Option<String> {
+        let lowered = word.to_lowercase();
+        let &(_, _, milder) = WORDS.iter().find(|(w, severity, _)| *w == lowered && *severity >= self.severity)?;
+        Some(match self.action {
+            ProfanityAction::Mask => {
+                let mut chars = word.chars();
+                let first = chars.next().unwrap_or_default();
+                std::iter::once(first).chain(chars.map(|_| '*')).collect()
+            }
+            ProfanityAction::Rephrase => match_case(word, milder),
+        })
+    }
+}
+
+/// The alphabetic runs of `text` with their byte offsets
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+/// `replacement` shouting or capitalized like `word`
+fn match_case(word: &str, replacement: &str) -> String {
+    if word.chars().count() > 1 && word.chars().all(|c| c.is_uppercase()) {
+        return replacement.to_uppercase();
+    }
+    match word.chars().next() {
+        Some(first) if first.is_uppercase() => {
+            let mut chars = replacement.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+        }
+        _ => replacement.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_severity_decides_what_is_caught() {
+        let reply = "Damn, that shift sounds like hell. Your boss was a bastard and the whole week was fucked.";
+        let strong = ProfanityFilter { severity: Severity::Strong, action: ProfanityAction::Mask };
+        assert_eq!(strong.apply(reply), "Damn, that shift sounds like hell. Your boss was a bastard and the whole week was f*****.");
+
+        let mild = ProfanityFilter { severity: Severity::Mild, action: ProfanityAction::Mask };
+        assert_eq!(mild.apply(reply), "D***, that shift sounds like h***. Your boss was a b****** and the whole week was f*****.");
+
+        let clean = "Hello! That class sounds like a lot of pressure.";
+        assert!(matches!(mild.apply(clean), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_rephrase_keeps_case() {
+        let filter = ProfanityFilter { severity: Severity::Moderate, action: ProfanityAction::Rephrase };
+        assert_eq!(filter.apply("Shit. Such SHITTY luck, and damn unfair."), "Rubbish. Such AWFUL luck, and damn unfair.");
+        assert!("extreme".parse::<Severity>().is_err());
+    }
+}
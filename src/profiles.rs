@@ -0,0 +1,103 @@
+//! Named provider profiles (`PROVIDERS_FILE`), chosen with `--profile` or
+//! switched in chat with `/provider`
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::path::Path;
+
+/// An endpoint and model to talk to, such as `work-azure` or `local-ollama`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderProfile {
+    pub name: String,
+    pub base_url: String,
+    pub model: String,
+    /// Environment variable holding the key; without one the key `login`
+    /// saved for `base_url` is used, or none for local servers
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+}
+
+/// The endpoint, model and key requests go to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provider {
+    /// The profile it came from; `None` for `OPENAI_BASE_URL` and `MODEL`
+    pub profile: Option<String>,
+    pub api_key: String,
+    pub base_url: String,
+    pub model: String,
+}
+
+impl ProviderProfile {
+    /// Reads the profile's key; a dry run needs none
+    pub fn resolve(&self, dry_run: bool) -> Result<Provider> {
+        let api_key = match &self.api_key_env {
+            Some(_) if dry_run => String::new(),
+            Some(var) => std::env::var(var).with_context(|| format!("{} not set for provider profile '{}'", var, self.name))?,
+            None => crate::saved_key(&self.base_url).unwrap_or_default(),
+        };
+        Ok(Provider {
+            profile: Some(self.name.clone()),
+            api_key,
+            base_url: self.base_url.clone(),
+            model: self.model.clone(),
+        })
+    }
+}
+
+/// Reads a JSON list of provider profiles
+pub fn load_profiles(path: &Path) -> Result<Vec<ProviderProfile>> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let profiles: Vec<ProviderProfile> = serde_json::from_str(&json).with_context(|| format!("failed to parse {}", path.display()))?;
+    for (i, profile) in profiles.iter().enumerate() {
+        if profiles[..i].iter().any(|p| p.name == profile.name) {
+            bail!("provider profile '{}' is defined twice in {}", profile.name, path.display());
+        }
+    }
+    Ok(profiles)
+}
+
+pub fn find<'a>(profiles: &'a [ProviderProfile], name: &str) -> Result<&'a ProviderProfile> {
+    if let Some(profile) = profiles.iter().find(|p| p.name == name) {
+        return Ok(profile);
+    }
+    match profiles {
+        [] => bail!("unknown provider profile '{}', PROVIDERS_FILE defines none", name),
+        _ => bail!("unknown provider profile '{}', expected {}", name, names(profiles)),
+    }
+}
+
+/// e.g. `work-azure, personal-openai or local-ollama`
+pub fn names(profiles: &[ProviderProfile]) -> String {
+    let names: Vec<&str> = profiles.iter().map(|p| p.name.as_str()).collect();
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+        _ => names.concat(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_are_found_by_name() {
+        let json = r#"[
+            {"name": "work-azure", "base_url": "https://work.openai.azure.com/openai/v1", "model": "gpt-4o", "api_key_env": "WORK_AZURE_KEY"},
+            {"name": "personal-openai", "base_url": "https://api.openai.com/v1", "model": "gpt-4o-mini", "api_key_env": "OPENAI_PERSONAL_KEY"},
+            {"name": "local-ollama", "base_url": "http://localhost:11434/v1", "model": "llama3.1"}
+        ]"#;
+        let path = std::env::temp_dir().join(format!("tce-providers-{}.json", std::process::id()));
+        std::fs::write(&path, json).unwrap();
+        let profiles = load_profiles(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let local = find(&profiles, "local-ollama").unwrap().resolve(false).unwrap();
+        assert_eq!((local.profile.as_deref(), local.model.as_str()), (Some("local-ollama"), "llama3.1"));
+        assert_eq!(find(&profiles, "work-azure").unwrap().resolve(true).unwrap().api_key, "");
+        assert!(find(&profiles, "work-azure").unwrap().resolve(false).is_err());
+
+        let unknown = find(&profiles, "home").unwrap_err().to_string();
+        assert_eq!(unknown, "unknown provider profile 'home', expected work-azure, personal-openai or local-ollama");
+    }
+}
@@ -0,0 +1,245 @@
+//! Python bindings, built into the `text_classifier_extractor` extension module
+//! with maturin. Classifications, messages and state cross as plain dicts.
+//!
+//! Provider calls run on asyncio's default executor, blocking on a tokio runtime
+//! with the GIL released, so no thread Python does not know about ever takes the
+//! GIL (which crashes the interpreter if it is shutting down).
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use futures::future::BoxFuture;
+use std::sync::{Arc, Mutex, OnceLock};
+use crate::SentimentClassification;
+use crate::agents;
+use crate::models::MessageRole;
+use crate::state::{self, ConversationState};
+use crate::strategy::{ResponseStrategy, StrategyBlend, select_strategy};
+
+const DEFAULT_BASE_URL: &str = "https://open.bigmodel.cn/api/paas/v4";
+const DEFAULT_MODEL: &str = "glm-4.7";
+
+fn to_py<'py>(py: Python<'py>, value: &impl Serialize) -> PyResult<Bound<'py, PyAny>> {
+    let json = serde_json::to_string(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    py.import("json")?.call_method1("loads", (json,))
+}
+
+fn from_py<T: DeserializeOwned>(value: &Bound<'_, PyAny>) -> PyResult<T> {
+    let json: String = value.py().import("json")?.call_method1("dumps", (value,))?.extract()?;
+    serde_json::from_str(&json).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Enum variants such as `"Empathetic"` or `"User"` from their names
+fn parse<T: DeserializeOwned>(name: &str) -> PyResult<T> {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .map_err(|_| PyValueError::new_err(format!("unknown value '{}'", name)))
+}
+
+/// A classification as the dict `{"sentiment": ..., "confidence": ..., "is_fallback": ...}`
+#[derive(IntoPyObject)]
+struct Classification {
+    sentiment: String,
+    confidence: f64,
+    is_fallback: bool,
+}
+
+impl From<SentimentClassification> for Classification {
+    fn from(classification: SentimentClassification) -> Self {
+        Self {
+            sentiment: format!("{:?}", classification.sentiment),
+            // Through the shortest decimal form, so 0.7 does not become 0.699999988
+            confidence: classification.confidence.to_string().parse().unwrap_or_default(),
+            is_fallback: classification.is_fallback,
+        }
+    }
+}
+
+#[derive(IntoPyObject)]
+enum Output {
+    Classification(Classification),
+    Text(String),
+}
+
+fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| tokio::runtime::Runtime::new().expect("failed to start the tokio runtime"))
+}
+
+/// A provider call waiting for an executor thread to run it
+#[pyclass]
+struct Pending(Mutex<Option<BoxFuture<'static, PyResult<Output>>>>);
+
+#[pymethods]
+impl Pending {
+    fn __call__(&self, py: Python<'_>) -> PyResult<Output> {
+        let work = self.0.lock().ok().and_then(|mut work| work.take());
+        let work = work.ok_or_else(|| PyRuntimeError::new_err("call already ran"))?;
+        py.allow_threads(|| runtime().block_on(work))
+    }
+}
+
+/// An awaitable for `work` on the running event loop
+fn awaitable<'py>(
+    py: Python<'py>,
+    work: impl Future<Output = PyResult<Output>> + Send + 'static,
+) -> PyResult<Bound<'py, PyAny>> {
+    let pending = Bound::new(py, Pending(Mutex::new(Some(Box::pin(work)))))?;
+    py.import("asyncio")?
+        .call_method0("get_running_loop")?
+        .call_method1("run_in_executor", (py.None(), pending))
+}
+
+fn client(api_key: &str, base_url: Option<&str>) -> rig::providers::openai::Client {
+    rig::providers::openai::Client::from_url(api_key, base_url.unwrap_or(DEFAULT_BASE_URL))
+}
+
+#[pyclass]
+struct EmotionDetector {
+    inner: Arc<agents::EmotionDetector>,
+}
+
+#[pymethods]
+impl EmotionDetector {
+    #[new]
+    #[pyo3(signature = (api_key, base_url = None, model = DEFAULT_MODEL, dry_run = false))]
+    fn new(api_key: &str, base_url: Option<&str>, model: &str, dry_run: bool) -> Self {
+        let detector = agents::EmotionDetector::new(client(api_key, base_url), model).with_dry_run(dry_run);
+        Self { inner: Arc::new(detector) }
+    }
+
+    /// Awaitable; resolves to `{"sentiment": ..., "confidence": ..., "is_fallback": ...}`
+    fn analyze<'py>(&self, py: Python<'py>, text: String) -> PyResult<Bound<'py, PyAny>> {
+        let detector = self.inner.clone();
+        awaitable(py, async move {
+            let classification = detector.analyze(&text).await.map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+            Ok(Output::Classification(classification.into()))
+        })
+    }
+}
+
+#[pyclass]
+struct ChatAgent {
+    inner: Arc<agents::ChatAgent>,
+}
+
+#[pymethods]
+impl ChatAgent {
+    #[new]
+    #[pyo3(signature = (api_key, base_url = None, model = DEFAULT_MODEL, dry_run = false))]
+    fn new(api_key: &str, base_url: Option<&str>, model: &str, dry_run: bool) -> Self {
+        let agent = agents::ChatAgent::new(client(api_key, base_url), model).with_dry_run(dry_run);
+        Self { inner: Arc::new(agent) }
+    }
+
+    /// Awaitable; resolves to the reply text. The conversation is not changed.
+    fn respond<'py>(
+        &self,
+        py: Python<'py>,
+        text: String,
+        conversation: &ConversationManager,
+        strategy: &str,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let agent = self.inner.clone();
+        let blend = StrategyBlend::single(parse::<ResponseStrategy>(strategy)?);
+        let history = conversation.inner.get_history().to_vec();
+        awaitable(py, async move {
+            let reply = agent
+                .respond(&text, &blend, &history, None)
+                .await
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+            Ok(Output::Text(reply.text))
+        })
+    }
+}
+
+#[pyclass]
+struct ConversationManager {
+    inner: state::ConversationManager,
+}
+
+#[pymethods]
+impl ConversationManager {
+    #[new]
+    fn new() -> Self {
+        Self { inner: state::ConversationManager::new() }
+    }
+
+    /// Restores a conversation saved with `to_dict`
+    #[staticmethod]
+    fn from_dict(value: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let state: ConversationState = from_py(value)?;
+        Ok(Self { inner: state::ConversationManager::from_state(state) })
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_py(py, self.inner.state())
+    }
+
+    /// `role` is `"User"` or `"Assistant"`
+    fn add_message(&mut self, role: &str, content: &str) -> PyResult<()> {
+        self.inner.add_message(parse::<MessageRole>(role)?, content);
+        Ok(())
+    }
+
+    /// Records a classification from `EmotionDetector.analyze` against the last message
+    fn update_emotion(&mut self, classification: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.inner.update_emotion(from_py(classification)?);
+        Ok(())
+    }
+
+    fn update_strategy(&mut self, strategy: &str) -> PyResult<()> {
+        self.inner.update_strategy(parse(strategy)?);
+        Ok(())
+    }
+
+    /// `"Improving"`, `"Declining"` or `"Stable"`
+    fn trend(&self) -> String {
+        format!("{:?}", self.inner.get_recent_emotion_trend().direction)
+    }
+
+    /// The strategy for answering the latest classified message, or `"Neutral"` before any
+    fn select_strategy(&self) -> String {
+        let strategy = self
+            .inner
+            .state()
+            .emotion_history
+            .last()
+            .map_or(ResponseStrategy::Neutral, |emotion| select_strategy(emotion, self.inner.get_recent_emotion_trend().direction));
+        format!("{:?}", strategy)
+    }
+
+    fn history<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_py(py, &self.inner.get_history())
+    }
+}
+
+#[pymodule]
+fn text_classifier_extractor(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<EmotionDetector>()?;
+    m.add_class::<ChatAgent>()?;
+    m.add_class::<ConversationManager>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversation_round_trips_through_dicts() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut conversation = ConversationManager::new();
+            conversation.add_message("User", "I failed my exam").unwrap();
+            let emotion = to_py(py, &serde_json::json!({"sentiment": "Negative", "confidence": 0.9})).unwrap();
+            conversation.update_emotion(&emotion).unwrap();
+            assert_eq!(conversation.select_strategy(), "Encouraging");
+            assert!(conversation.add_message("Narrator", "...").is_err());
+
+            let restored = ConversationManager::from_dict(&conversation.to_dict(py).unwrap()).unwrap();
+            assert_eq!(restored.inner.get_history().len(), 1);
+            assert_eq!(restored.trend(), "Stable");
+        });
+    }
+}
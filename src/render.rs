@@ -0,0 +1,477 @@
+//! How the chat REPL shows each turn
+
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::time::Duration;
+use crate::cli::Verbosity;
+use crate::commands::recap::SessionRecap;
+use crate::strategy::StrategyBlend;
+use crate::turn::TurnOutcome;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Emoji-labelled lines
+    Plain,
+    /// Plain output with ANSI colors
+    Colored,
+    /// One JSON object per turn on stdout; notices go to stderr
+    Json,
+    /// Only the assistant's reply
+    Minimal,
+}
+
+pub trait OutputRenderer {
+    /// Text shown before reading the user's input
+    fn prompt(&self) -> &str;
+
+    fn banner(&mut self, model: &str, dry_run: bool) -> io::Result<()>;
+
+    fn turn(&mut self, turn: &TurnOutcome, elapsed: Duration) -> io::Result<()>;
+
+    /// An unprompted assistant message, printed above the prompt while the user is typing
+    fn check_in(&self, message: &str) -> String;
+
+    /// The assistant's first message, when it opens the session
+    fn opener(&mut self, message: &str) -> io::Result<()>;
+
+    /// Status lines such as "session saved" that are not part of a turn
+    fn notice(&mut self, message: &str) -> io::Result<()>;
+
+    fn error(&mut self, error: &anyhow::Error) -> io::Result<()>;
+
+    /// The summary shown when a chat ends
+    fn recap(&mut self, recap: &SessionRecap) -> io::Result<()> {
+        self.notice(&recap.render_text())
+    }
+}
+
+pub fn renderer(format: OutputFormat, verbosity: Verbosity) -> Box<dyn OutputRenderer> {
+    match format {
+        OutputFormat::Plain => Box::new(PlainRenderer::new(io::stdout(), verbosity, false)),
+        OutputFormat::Colored => Box::new(PlainRenderer::new(io::stdout(), verbosity, true)),
+        OutputFormat::Json => Box::new(JsonRenderer::new(io::stdout())),
+        OutputFormat::Minimal => Box::new(MinimalRenderer::new(io::stdout())),
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+
+pub struct PlainRenderer<W> {
+    out: W,
+    verbosity: Verbosity,
+    colored: bool,
+}
+
+impl<W: Write> PlainRenderer<W> {
+    pub fn new(out: W, verbosity: Verbosity, colored: bool) -> Self {
+        Self { out, verbosity, colored }
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.colored {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+impl<W: Write> OutputRenderer for PlainRenderer<W> {
+    fn prompt(&self) -> &str {
+        if self.colored { "\x1b[1mYou:\x1b[0m " } else { "You: " }
+    }
+
+    fn banner(&mut self, model: &str, dry_run: bool) -> io::Result<()> {
+        writeln!(self.out, "🤖 Emotional-Aware Chat System")?;
+        writeln!(self.out, "📊 Model: {}", model)?;
+        if dry_run {
+            writeln!(self.out, "🧪 Dry run: classifications are local and nothing is sent to the provider")?;
+        }
+        writeln!(self.out, "💬 Type 'quit' or 'exit' to end, '/help' for commands\n")
+    }
+
+    fn turn(&mut self, turn: &TurnOutcome, elapsed: Duration) -> io::Result<()> {
+        if self.verbosity >= Verbosity::Normal {
+            let sentiment_color = match turn.emotion.sentiment {
+                crate::Sentiment::Positive => GREEN,
+                crate::Sentiment::Negative => RED,
+                crate::Sentiment::Neutral => YELLOW,
+            };
+            let sentiment = self.paint(sentiment_color, &format!("{:?}", turn.emotion.sentiment));
+            let mut fallback = if turn.emotion.is_fallback { ", fallback" } else { "" }.to_string();
+            if turn.emotion.labels.len() > 1 {
+                fallback.push_str(&format!(", mixed: {}", turn.emotion.describe()));
+            }
+            match &turn.calibrated {
+                Some(calibrated) => writeln!(
+                    self.out,
+                    "📊 Emotion: {} (confidence: {:.2}{}), usual for you, treated as {:?}",
+                    sentiment, turn.emotion.confidence, fallback, calibrated.sentiment
+                )?,
+                None => writeln!(self.out, "📊 Emotion: {} (confidence: {:.2}{})", sentiment, turn.emotion.confidence, fallback)?,
+            }
+            let trend = self.paint(CYAN, &format!("{:?}", turn.trend));
+            match turn.forecast {
+                Some(forecast) => writeln!(
+                    self.out,
+                    "📈 Trend: {}, next message likely {:?} ({:.2})",
+                    trend, forecast.sentiment, forecast.confidence
+                )?,
+                None => writeln!(self.out, "📈 Trend: {}", trend)?,
+            }
+            let strategy = self.paint(CYAN, &turn.blend.to_string());
+            if let Some(reason) = turn.handoff {
+                writeln!(self.out, "🙋 Waiting for an operator ({:?}), the assistant is paused", reason)?;
+            }
+            if let Some(topic) = &turn.blocked_topic {
+                writeln!(self.out, "🚫 Declined: {} is a blocked topic", topic)?;
+            }
+            if turn.break_suggested {
+                writeln!(self.out, "☕ Long session, the reply suggests a break")?;
+            }
+            if let Some(signal) = &turn.injection {
+                writeln!(self.out, "🛡️  Message reads like instructions to the model ({}), treated as conversation", signal)?;
+            }
+            match turn.suppressed {
+                Some(suppressed) => writeln!(self.out, "🎯 Strategy: {} (held, not switching to {:?} yet)", strategy, suppressed)?,
+                None => writeln!(self.out, "🎯 Strategy: {}", strategy)?,
+            }
+        }
+        if self.verbosity == Verbosity::Verbose {
+            let tokens = match turn.usage {
+                Some(usage) => format!("{} prompt + {} completion tokens", usage.prompt, usage.completion),
+                None => "token usage not reported".to_string(),
+            };
+            let stats = self.paint(DIM, &format!("{:.2}s, {}", elapsed.as_secs_f64(), tokens));
+            writeln!(self.out, "⏱️  {}", stats)?;
+            if let Some(score) = turn.score {
+                writeln!(self.out, "⭐ Reply: empathy {:.2}, strategy adherence {:.2}", score.empathy, score.strategy_adherence)?;
+            }
+        }
+        writeln!(self.out, "🤖 {} {}\n", self.paint(BOLD, "Assistant:"), turn.response)
+    }
+
+    fn check_in(&self, message: &str) -> String {
+        format!("🤖 {} {}\n", self.paint(BOLD, "Assistant:"), message)
+    }
+
+    fn opener(&mut self, message: &str) -> io::Result<()> {
+        let line = self.check_in(message);
+        writeln!(self.out, "{}", line)
+    }
+
+    fn notice(&mut self, message: &str) -> io::Result<()> {
+        writeln!(self.out, "{}", message)
+    }
+
+    fn error(&mut self, error: &anyhow::Error) -> io::Result<()> {
+        let message = self.paint(RED, &format!("{:#}", error));
+        writeln!(io::stderr(), "❌ {}", message)
+    }
+}
+
+#[derive(Serialize)]
+struct JsonTurn<'a> {
+    sentiment: crate::Sentiment,
+    confidence: f32,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    labels: &'a [crate::EmotionLabel],
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    is_fallback: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    calibrated_sentiment: Option<crate::Sentiment>,
+    trend: crate::state::EmotionTrend,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    forecast: Option<crate::state::EmotionForecast>,
+    strategy: crate::strategy::ResponseStrategy,
+    #[serde(skip_serializing_if = "StrategyBlend::is_single")]
+    blend: &'a StrategyBlend,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suppressed_strategy: Option<crate::strategy::ResponseStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    handoff: Option<crate::handoff::HandoffReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blocked_topic: Option<&'a str>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    break_suggested: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    injection: Option<&'a str>,
+    response: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_score: Option<crate::models::ReplyScore>,
+    latency_ms: u128,
+    prompt_tokens: Option<usize>,
+    completion_tokens: Option<usize>,
+}
+
+pub struct JsonRenderer<W> {
+    out: W,
+}
+
+impl<W: Write> JsonRenderer<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl<W: Write> OutputRenderer for JsonRenderer<W> {
+    fn prompt(&self) -> &str {
+        ""
+    }
+
+    fn banner(&mut self, _model: &str, _dry_run: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn turn(&mut self, turn: &TurnOutcome, elapsed: Duration) -> io::Result<()> {
+        let json = JsonTurn {
+            sentiment: turn.emotion.sentiment,
+            confidence: turn.emotion.confidence,
+            labels: &turn.emotion.labels,
+            is_fallback: turn.emotion.is_fallback,
+            calibrated_sentiment: turn.calibrated.as_ref().map(|c| c.sentiment),
+            trend: turn.trend,
+            forecast: turn.forecast,
+            strategy: turn.strategy,
+            blend: &turn.blend,
+            suppressed_strategy: turn.suppressed,
+            handoff: turn.handoff,
+            blocked_topic: turn.blocked_topic.as_deref(),
+            break_suggested: turn.break_suggested,
+            injection: turn.injection.as_deref(),
+            response: &turn.response,
+            reply_score: turn.score,
+            latency_ms: elapsed.as_millis(),
+            prompt_tokens: turn.usage.map(|usage| usage.prompt),
+            completion_tokens: turn.usage.map(|usage| usage.completion),
+        };
+        serde_json::to_writer(&mut self.out, &json)?;
+        writeln!(self.out)?;
+        self.out.flush()
+    }
+
+    fn check_in(&self, message: &str) -> String {
+        serde_json::json!({ "check_in": message }).to_string()
+    }
+
+    fn opener(&mut self, message: &str) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, &serde_json::json!({ "opener": message }))?;
+        writeln!(self.out)?;
+        self.out.flush()
+    }
+
+    fn notice(&mut self, message: &str) -> io::Result<()> {
+        writeln!(io::stderr(), "{}", message)
+    }
+
+    fn error(&mut self, error: &anyhow::Error) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, &serde_json::json!({ "error": format!("{:#}", error) }))?;
+        writeln!(self.out)?;
+        self.out.flush()
+    }
+
+    fn recap(&mut self, recap: &SessionRecap) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, &serde_json::json!({ "recap": recap }))?;
+        writeln!(self.out)?;
+        self.out.flush()
+    }
+}
+
+pub struct MinimalRenderer<W> {
+    out: W,
+}
+
+impl<W: Write> MinimalRenderer<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl<W: Write> OutputRenderer for MinimalRenderer<W> {
+    fn prompt(&self) -> &str {
+        "> "
+    }
+
+    fn banner(&mut self, _model: &str, _dry_run: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn turn(&mut self, turn: &TurnOutcome, _elapsed: Duration) -> io::Result<()> {
+        writeln!(self.out, "{}\n", turn.response)
+    }
+
+    fn check_in(&self, message: &str) -> String {
+        format!("{}\n", message)
+    }
+
+    fn opener(&mut self, message: &str) -> io::Result<()> {
+        writeln!(self.out, "{}\n", message)
+    }
+
+    fn notice(&mut self, message: &str) -> io::Result<()> {
+        writeln!(self.out, "{}", message)
+    }
+
+    fn error(&mut self, error: &anyhow::Error) -> io::Result<()> {
+        writeln!(io::stderr(), "error: {:#}", error)
+    }
+
+    fn recap(&mut self, _recap: &SessionRecap) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::TokenUsage;
+    use crate::state::EmotionTrend;
+    use crate::strategy::ResponseStrategy;
+    use crate::{Sentiment, SentimentClassification};
+
+    fn turn() -> TurnOutcome {
+        TurnOutcome {
+            emotion: SentimentClassification { sentiment: Sentiment::Negative, confidence: 0.8, labels: Vec::new(), is_fallback: false },
+            calibrated: None,
+            trend: EmotionTrend::Declining,
+            forecast: None,
+            strategy: ResponseStrategy::Empathetic,
+            blend: StrategyBlend::single(ResponseStrategy::Empathetic),
+            suppressed: None,
+            response: "That sounds hard.".to_string(),
+            usage: Some(TokenUsage { prompt: 120, completion: 8 }),
+            alternatives: Vec::new(),
+            score: None,
+            assessment: None,
+            handoff: None,
+            escalated: false,
+            blocked_topic: None,
+            break_suggested: false,
+            injection: None,
+        }
+    }
+
+    fn render(renderer: &mut dyn OutputRenderer) {
+        renderer.turn(&turn(), Duration::from_millis(1500)).unwrap();
+    }
+
+    #[test]
+    fn test_plain_respects_verbosity() {
+        let mut out = Vec::new();
+        render(&mut PlainRenderer::new(&mut out, Verbosity::Quiet, false));
+        assert_eq!(String::from_utf8(out).unwrap(), "🤖 Assistant: That sounds hard.\n\n");
+
+        let mut out = Vec::new();
+        render(&mut PlainRenderer::new(&mut out, Verbosity::Verbose, false));
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("📊 Emotion: Negative (confidence: 0.80)"));
+        assert!(out.contains("1.50s, 120 prompt + 8 completion tokens"));
+    }
+
+    #[test]
+    fn test_opener_is_written_like_a_reply() {
+        let mut out = Vec::new();
+        PlainRenderer::new(&mut out, Verbosity::Normal, false).opener("Hi Sam.").unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "🤖 Assistant: Hi Sam.\n\n");
+
+        let mut out = Vec::new();
+        JsonRenderer::new(&mut out).opener("Hi Sam.").unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["opener"], "Hi Sam.");
+    }
+
+    #[test]
+    fn test_colored_wraps_labels() {
+        let mut out = Vec::new();
+        render(&mut PlainRenderer::new(&mut out, Verbosity::Normal, true));
+        assert!(String::from_utf8(out).unwrap().contains("\x1b[31mNegative\x1b[0m"));
+    }
+
+    #[test]
+    fn test_json_writes_one_object_per_turn() {
+        let mut out = Vec::new();
+        render(&mut JsonRenderer::new(&mut out));
+        render(&mut JsonRenderer::new(&mut out));
+
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<serde_json::Value> = out.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["strategy"], "Empathetic");
+        assert_eq!(lines[0]["latency_ms"], 1500);
+        assert_eq!(lines[0]["prompt_tokens"], 120);
+        assert!(lines[0].get("blend").is_none());
+        assert!(lines[0].get("suppressed_strategy").is_none());
+    }
+
+    #[test]
+    fn test_blended_strategy_is_shown() {
+        let mut turn = turn();
+        turn.blend = StrategyBlend::new(&[(ResponseStrategy::Empathetic, 0.7), (ResponseStrategy::Encouraging, 0.3)]);
+
+        let mut out = Vec::new();
+        PlainRenderer::new(&mut out, Verbosity::Normal, false).turn(&turn, Duration::ZERO).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("🎯 Strategy: Empathetic 70% + Encouraging 30%"));
+
+        let mut out = Vec::new();
+        JsonRenderer::new(&mut out).turn(&turn, Duration::ZERO).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["blend"][1]["strategy"], "Encouraging");
+    }
+
+    #[test]
+    fn test_mixed_emotion_is_shown() {
+        let mut turn = turn();
+        turn.emotion.labels = vec![
+            crate::EmotionLabel { sentiment: Sentiment::Negative, confidence: 0.8 },
+            crate::EmotionLabel { sentiment: Sentiment::Positive, confidence: 0.5 },
+        ];
+
+        let mut out = Vec::new();
+        PlainRenderer::new(&mut out, Verbosity::Normal, false).turn(&turn, Duration::ZERO).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("mixed: Negative 0.80 + Positive 0.50"));
+
+        let mut out = Vec::new();
+        JsonRenderer::new(&mut out).turn(&turn, Duration::ZERO).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["labels"][1]["sentiment"], "Positive");
+    }
+
+    #[test]
+    fn test_calibrated_emotion_is_shown() {
+        let mut turn = turn();
+        turn.calibrated = Some(SentimentClassification { sentiment: Sentiment::Neutral, confidence: 0.8, labels: Vec::new(), is_fallback: false });
+
+        let mut out = Vec::new();
+        PlainRenderer::new(&mut out, Verbosity::Normal, false).turn(&turn, Duration::ZERO).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("usual for you, treated as Neutral"));
+
+        let mut out = Vec::new();
+        JsonRenderer::new(&mut out).turn(&turn, Duration::ZERO).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["calibrated_sentiment"], "Neutral");
+    }
+
+    #[test]
+    fn test_suppressed_switch_is_shown() {
+        let mut turn = turn();
+        turn.suppressed = Some(ResponseStrategy::Cheerful);
+
+        let mut out = Vec::new();
+        PlainRenderer::new(&mut out, Verbosity::Normal, false).turn(&turn, Duration::ZERO).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("🎯 Strategy: Empathetic (held, not switching to Cheerful yet)"));
+    }
+
+    #[test]
+    fn test_minimal_prints_reply_only() {
+        let mut out = Vec::new();
+        render(&mut MinimalRenderer::new(&mut out));
+        assert_eq!(String::from_utf8(out).unwrap(), "That sounds hard.\n\n");
+    }
+}
@@ -0,0 +1,306 @@
+use crate::Sentiment;
+use crate::models::MessageRole;
+use crate::storage::StoredSession;
+use super::weekly::{WeeklyReport, messages_in_range};
+
+const CHART_WIDTH: f32 = 720.0;
+const CHART_HEIGHT: f32 = 200.0;
+const CHART_PADDING: f32 = 20.0;
+
+const STYLE: &str = "\
+body { font-family: -apple-system, 'Segoe UI', sans-serif; max-width: 800px; margin: 2em auto; color: #222; }
+h1 { font-size: 1.6em; } h2 { font-size: 1.2em; margin-top: 2em; border-bottom: 1px solid #ddd; }
+table { border-collapse: collapse; width: 100%; } td, th { padding: 4px 8px; text-align: right; }
+td:first-child, th:first-child { text-align: left; }
+.bar { background: #6c8ebf; height: 14px; display: inline-block; vertical-align: middle; }
+.msg { margin: 6px 0; padding: 6px 10px; border-radius: 6px; }
+.user { background: #eef3fb; } .assistant { background: #f4f4f4; }
+.meta { color: #777; font-size: 0.8em; }
+.positive { color: #2e7d32; } .negative { color: #c62828; } .neutral { color: #757575; }";
+
+pub fn render_html(report: &WeeklyReport, sessions: &[StoredSession]) -> String {
+    let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>Emotion report {} – {}</title>\n", report.from, report.to));
+    html.push_str(&format!("<style>\n{}\n</style>\n</head>\n<body>\n", STYLE));
+
+    html.push_str(&format!("<h1>Emotion report {} – {}</h1>\n", report.from, report.to));
+    html.push_str(&format!(
+        "<p>{} sessions, {} messages</p>\n",
+        report.session_count, report.message_count
+    ));
+
+    if let Some(mood) = &report.mood {
+        html.push_str(&format!(
+            "<p><strong>Mood by the end:</strong> <span class=\"{}\">{:?}</span> ({:+.2})</p>\n",
+            format!("{:?}", mood.sentiment).to_lowercase(),
+            mood.sentiment,
+            mood.score
+        ));
+    }
+    if let Some(summary) = &report.summary {
+        html.push_str(&format!("<h2>Summary</h2>\n<p>{}</p>\n", escape(summary)));
+    }
+    if !report.topics.is_empty() {
+        let topics: Vec<String> = report.topics.iter().map(|t| escape(t)).collect();
+        html.push_str(&format!("<p><strong>Topics:</strong> {}</p>\n", topics.join(", ")));
+    }
+    for session in &report.session_keyphrases {
+        let phrases: Vec<String> = session.phrases.iter().map(|p| escape(p)).collect();
+        html.push_str(&format!(
+            "<p><strong>Key phrases ({}):</strong> {}</p>\n",
+            escape(&session.session_id),
+            phrases.join(", ")
+        ));
+    }
+    if !report.low_mood_keyphrases.is_empty() {
+        let phrases: Vec<String> = report.low_mood_keyphrases.iter().map(|p| escape(p)).collect();
+        html.push_str(&format!("<p><strong>When the mood was low:</strong> {}</p>\n", phrases.join(", ")));
+    }
+
+    let points: Vec<(Sentiment, f32)> = messages_in_range(sessions, report.from, report.to)
+        .filter_map(|(_, msg)| msg.emotion.as_ref())
+        .map(|e| (e.sentiment, e.confidence))
+        .collect();
+    html.push_str("<h2>Emotion timeline</h2>\n");
+    html.push_str(&emotion_timeline_svg(&points));
+
+    if !report.speakers.is_empty() {
+        html.push_str("<h2>By speaker</h2>\n");
+        for timeline in &report.speakers {
+            let points: Vec<(Sentiment, f32)> = messages_in_range(sessions, report.from, report.to)
+                .filter(|(_, msg)| msg.speaker.as_ref() == Some(&timeline.speaker))
+                .filter_map(|(_, msg)| msg.emotion.as_ref())
+                .map(|e| (e.sentiment, e.confidence))
+                .collect();
+            html.push_str(&format!("<h3>{}</h3>\n", escape(&timeline.speaker)));
+            html.push_str(&emotion_timeline_svg(&points));
+        }
+    }
+
+    html.push_str("<h2>Daily distribution</h2>\n<table>\n");
+    html.push_str("<tr><th>Date</th><th>Positive</th><th>Neutral</th><th>Negative</th></tr>\n");
+    for day in &report.daily {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            day.date, day.counts.positive, day.counts.neutral, day.counts.negative
+        ));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Strategies</h2>\n");
+    if report.strategies.is_empty() {
+        html.push_str("<p>No strategies recorded.</p>\n");
+    } else {
+        let max = report.strategies.iter().map(|u| u.count).max().unwrap_or(1);
+        html.push_str("<table>\n");
+        for usage in &report.strategies {
+            let width = usage.count * 300 / max;
+            html.push_str(&format!(
+                "<tr><td>{:?}</td><td><span class=\"bar\" style=\"width: {}px\"></span> {}</td></tr>\n",
+                usage.strategy, width, usage.count
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    if !report.aspects.is_empty() {
+        html.push_str("<h2>Aspects</h2>\n<table>\n<tr><th>Aspect</th><th>Positive</th><th>Neutral</th><th>Negative</th></tr>\n");
+        for aspect in &report.aspects {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape(&aspect.aspect), aspect.counts.positive, aspect.counts.neutral, aspect.counts.negative
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("<h2>Transcript</h2>\n");
+    for (date, msg) in messages_in_range(sessions, report.from, report.to) {
+        let (class, role) = match msg.role {
+            MessageRole::User => ("user", "User"),
+            MessageRole::Assistant => ("assistant", "Assistant"),
+        };
+        let role = msg.speaker.as_deref().map_or_else(|| role.to_string(), escape);
+
+        let mut meta = date.to_string();
+        if let Some(emotion) = &msg.emotion {
+            meta.push_str(&format!(
+                " · <span class=\"{}\">{:?} ({:.2})</span>",
+                sentiment_class(emotion.sentiment), emotion.sentiment, emotion.confidence
+            ));
+        }
+        if let Some(strategy) = msg.strategy {
+            meta.push_str(&format!(" · {:?}", strategy));
+        }
+        for aspect in &msg.aspects {
+            meta.push_str(&format!(
+                " · {}: <span class=\"{}\">{:?}</span>",
+                escape(&aspect.aspect), sentiment_class(aspect.sentiment), aspect.sentiment
+            ));
+        }
+
+        html.push_str(&format!(
+            "<div class=\"msg {}\"><div class=\"meta\">{} · {}</div>{}</div>\n",
+            class, role, meta, escape(&msg.content)
+        ));
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn emotion_timeline_svg(points: &[(Sentiment, f32)]) -> String {
+    if points.is_empty() {
+        return "<p>No classified messages.</p>\n".to_string();
+    }
+
+    let step = if points.len() > 1 {
+        (CHART_WIDTH - 2.0 * CHART_PADDING) / (points.len() - 1) as f32
+    } else {
+        0.0
+    };
+    let mid = CHART_HEIGHT / 2.0;
+    let amplitude = mid - CHART_PADDING;
+
+    let coords: Vec<(f32, f32)> = points
+        .iter()
+        .enumerate()
+        .map(|(i, (sentiment, confidence))| {
+            let x = CHART_PADDING + step * i as f32;
+            let y = mid - sentiment.score() as f32 * confidence * amplitude;
+            (x, y)
+        })
+        .collect();
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+        w = CHART_WIDTH,
+        h = CHART_HEIGHT
+    );
+    svg.push_str(&format!(
+        "<line x1=\"0\" y1=\"{mid}\" x2=\"{w}\" y2=\"{mid}\" stroke=\"#ccc\" stroke-dasharray=\"4\"/>\n",
+        mid = mid,
+        w = CHART_WIDTH
+    ));
+
+    let polyline: Vec<String> = coords.iter().map(|(x, y)| format!("{:.1},{:.1}", x, y)).collect();
+    svg.push_str(&format!(
+        "<polyline fill=\"none\" stroke=\"#6c8ebf\" stroke-width=\"2\" points=\"{}\"/>\n",
+        polyline.join(" ")
+    ));
+
+    for ((x, y), (sentiment, _)) in coords.iter().zip(points) {
+        svg.push_str(&format!(
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"4\" fill=\"{}\"/>\n",
+            x, y, sentiment_color(*sentiment)
+        ));
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn sentiment_class(sentiment: Sentiment) -> &'static str {
+    match sentiment {
+        Sentiment::Positive => "positive",
+        Sentiment::Negative => "negative",
+        Sentiment::Neutral => "neutral",
+    }
+}
+
+fn sentiment_color(sentiment: Sentiment) -> &'static str {
+    match sentiment {
+        Sentiment::Positive => "#2e7d32",
+        Sentiment::Negative => "#c62828",
+        Sentiment::Neutral => "#757575",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SentimentClassification;
+    use crate::models::Message;
+    use crate::strategy::ResponseStrategy;
+
+    fn session() -> StoredSession {
+        let timestamp = 1_770_000_000;
+        StoredSession::new(timestamp, vec![
+            Message {
+                role: MessageRole::User,
+                content: "I <hate> Mondays".to_string(),
+                timestamp,
+                emotion: Some(SentimentClassification {
+                    sentiment: Sentiment::Negative,
+                    confidence: 0.9,
+                    labels: Vec::new(),
+                    is_fallback: false,
+                }),
+                strategy: None,
+                speaker: None,
+                aspects: Vec::new(),
+                cause: None,
+                score: None,
+            },
+            Message {
+                role: MessageRole::Assistant,
+                content: "That sounds rough.".to_string(),
+                timestamp,
+                emotion: None,
+                strategy: Some(ResponseStrategy::Encouraging),
+                speaker: None,
+                aspects: Vec::new(),
+                cause: None,
+                score: None,
+            },
+        ])
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("<b>\"a\" & b</b>"), "&lt;b&gt;&quot;a&quot; &amp; b&lt;/b&gt;");
+    }
+
+    #[test]
+    fn test_timeline_svg_points() {
+        let svg = emotion_timeline_svg(&[
+            (Sentiment::Positive, 1.0),
+            (Sentiment::Neutral, 0.5),
+            (Sentiment::Negative, 1.0),
+        ]);
+
+        assert!(svg.contains("<polyline"));
+        assert_eq!(svg.matches("<circle").count(), 3);
+        // Positive at full confidence sits at the top padding, negative at the bottom
+        assert!(svg.contains("cy=\"20.0\""));
+        assert!(svg.contains("cy=\"180.0\""));
+    }
+
+    #[test]
+    fn test_timeline_svg_empty() {
+        assert!(!emotion_timeline_svg(&[]).contains("<svg"));
+    }
+
+    #[test]
+    fn test_render_html_sections() {
+        let sessions = vec![session()];
+        let date = sessions[0].messages[0].timestamp;
+        let date = chrono::DateTime::from_timestamp(date, 0).unwrap().date_naive();
+        let report = WeeklyReport::new(&sessions, date, date);
+
+        let html = render_html(&report, &sessions);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<svg"));
+        assert!(html.contains("Encouraging"));
+        assert!(html.contains("I &lt;hate&gt; Mondays"));
+        assert!(!html.contains("<hate>"));
+    }
+}
@@ -0,0 +1,7 @@
+//! Emotion reports aggregated over stored sessions
+
+pub mod weekly;
+pub mod html;
+
+pub use weekly::{EmotionCounts, SessionKeyphrases, SpeakerTimeline, WeeklyReport, build_transcript, last_week, messages_in_range, user_text};
+pub use html::render_html;
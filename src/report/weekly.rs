@@ -0,0 +1,447 @@
+use chrono::{DateTime, Days, NaiveDate};
+use serde::Serialize;
+use crate::{Sentiment, SentimentClassification};
+use crate::models::{Message, MessageRole};
+pub use crate::state::stats::{AspectCounts, ConfidenceBySentiment, EmotionCounts, StrategyUsage};
+use crate::state::{ConversationMood, mood, stats};
+use crate::storage::StoredSession;
+
+const MAX_TRANSCRIPT_CHARS: usize = 12_000;
+
+/// Most-mentioned aspects listed in a report
+const MAX_ASPECTS: usize = 8;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyEmotion {
+    pub date: NaiveDate,
+    pub counts: EmotionCounts,
+}
+
+/// One speaker's classified messages by day, in transcripts with named speakers
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeakerTimeline {
+    pub speaker: String,
+    pub daily: Vec<DailyEmotion>,
+    pub mood: Option<ConversationMood>,
+}
+
+impl SpeakerTimeline {
+    /// One character per day: `+` mostly positive, `·` neutral, `-` negative, blank for none
+    pub fn glyphs(&self) -> String {
+        self.daily
+            .iter()
+            .map(|day| match day.counts.dominant() {
+                Some(Sentiment::Positive) => '+',
+                Some(Sentiment::Neutral) => '·',
+                Some(Sentiment::Negative) => '-',
+                None => ' ',
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionKeyphrases {
+    pub session_id: String,
+    pub phrases: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WeeklyReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub session_count: usize,
+    pub message_count: usize,
+    pub daily: Vec<DailyEmotion>,
+    pub strategies: Vec<StrategyUsage>,
+    /// What the user felt about the things they mentioned, most mentioned
+    /// first; empty unless aspects were extracted
+    pub aspects: Vec<AspectCounts>,
+    /// How sure the classifier was of each sentiment over the period
+    pub confidence: ConfidenceBySentiment,
+    pub topics: Vec<String>,
+    pub session_keyphrases: Vec<SessionKeyphrases>,
+    /// Key phrases of the user messages classified Negative
+    pub low_mood_keyphrases: Vec<String>,
+    /// The mood over the period's last classified messages
+    pub mood: Option<ConversationMood>,
+    /// Per speaker, most messages first; empty unless messages name their speaker
+    pub speakers: Vec<SpeakerTimeline>,
+    pub summary: Option<String>,
+}
+
+impl WeeklyReport {
+    pub fn new(sessions: &[StoredSession], from: NaiveDate, to: NaiveDate) -> Self {
+        let mut daily: Vec<DailyEmotion> = from
+            .iter_days()
+            .take_while(|date| *date <= to)
+            .map(|date| DailyEmotion { date, counts: EmotionCounts::default() })
+            .collect();
+
+        let mut emotions = Vec::new();
+        let mut speakers: Vec<(SpeakerTimeline, Vec<(i64, SentimentClassification)>)> = Vec::new();
+        let mut message_count = 0;
+
+        for (date, msg) in messages_in_range(sessions, from, to) {
+            message_count += 1;
+
+            if let Some(emotion) = &msg.emotion {
+                let index = (date - from).num_days() as usize;
+                daily[index].counts.record(emotion.sentiment);
+                emotions.push((msg.timestamp, emotion.clone()));
+
+                if let Some(speaker) = &msg.speaker {
+                    let position = match speakers.iter().position(|(timeline, _)| &timeline.speaker == speaker) {
+                        Some(position) => position,
+                        None => {
+                            let days = daily.iter().map(|day| DailyEmotion { date: day.date, counts: EmotionCounts::default() }).collect();
+                            speakers.push((SpeakerTimeline { speaker: speaker.clone(), daily: days, mood: None }, Vec::new()));
+                            speakers.len() - 1
+                        }
+                    };
+                    let (timeline, emotions) = &mut speakers[position];
+                    timeline.daily[index].counts.record(emotion.sentiment);
+                    emotions.push((msg.timestamp, emotion.clone()));
+                }
+            }
+        }
+
+        let strategies = stats::strategy_usage(messages_in_range(sessions, from, to).map(|(_, msg)| msg));
+        let mut aspects = stats::aspect_sentiment(messages_in_range(sessions, from, to).map(|(_, msg)| msg));
+        aspects.truncate(MAX_ASPECTS);
+        let emotions = by_time(emotions);
+        let mut speakers: Vec<SpeakerTimeline> = speakers
+            .into_iter()
+            .map(|(timeline, emotions)| SpeakerTimeline { mood: mood::overall_mood(&by_time(emotions)), ..timeline })
+            .collect();
+        speakers.sort_by_key(|timeline| std::cmp::Reverse(timeline.daily.iter().map(|day| day.counts.total()).sum::<usize>()));
+
+        let session_count = sessions
+            .iter()
+            .filter(|s| {
+                s.messages
+                    .iter()
+                    .filter_map(message_date)
+                    .any(|date| date >= from && date <= to)
+            })
+            .count();
+
+        Self {
+            from,
+            to,
+            session_count,
+            message_count,
+            daily,
+            strategies,
+            aspects,
+            confidence: stats::average_confidence_by_sentiment(&emotions),
+            topics: Vec::new(),
+            session_keyphrases: Vec::new(),
+            low_mood_keyphrases: Vec::new(),
+            mood: mood::overall_mood(&emotions),
+            speakers,
+            summary: None,
+        }
+    }
+
+    pub fn totals(&self) -> EmotionCounts {
+        self.daily.iter().fold(EmotionCounts::default(), |mut acc, day| {
+            acc.positive += day.counts.positive;
+            acc.negative += day.counts.negative;
+            acc.neutral += day.counts.neutral;
+            acc
+        })
+    }
+
+    pub fn render_text(&self) -> String {
+        let mut out = format!("📅 Emotion report {} → {}\n", self.from, self.to);
+        out.push_str(&format!(
+            "💬 {} sessions, {} messages\n\n",
+            self.session_count, self.message_count
+        ));
+
+        out.push_str("Date        Positive  Neutral  Negative  Dominant\n");
+        for day in &self.daily {
+            let dominant = day
+                .counts
+                .dominant()
+                .map(|s| format!("{:?}", s))
+                .unwrap_or_else(|| "-".to_string());
+            out.push_str(&format!(
+                "{}  {:>8}  {:>7}  {:>8}  {}\n",
+                day.date, day.counts.positive, day.counts.neutral, day.counts.negative, dominant
+            ));
+        }
+
+        let totals = self.totals();
+        out.push_str(&format!(
+            "{:<10}  {:>8}  {:>7}  {:>8}\n",
+            "Total", totals.positive, totals.neutral, totals.negative
+        ));
+
+        if let Some(mood) = &self.mood {
+            out.push_str(&format!("\n🌡️  Mood by the end: {:?} ({:+.2})\n", mood.sentiment, mood.score));
+        }
+
+        if !self.speakers.is_empty() {
+            out.push_str("\n🗣️  By speaker, one mark per day (+ positive, · neutral, - negative):\n");
+            let width = self.speakers.iter().map(|timeline| timeline.speaker.chars().count()).max().unwrap_or(0);
+            for timeline in &self.speakers {
+                out.push_str(&format!("   {:<width$}  {}", timeline.speaker, timeline.glyphs(), width = width));
+                if let Some(mood) = &timeline.mood {
+                    out.push_str(&format!("  {:?} ({:+.2})", mood.sentiment, mood.score));
+                }
+                out.push('\n');
+            }
+        }
+
+        if !self.strategies.is_empty() {
+            let usage: Vec<String> = self.strategies.iter().map(StrategyUsage::describe).collect();
+            out.push_str(&format!("\n🎯 Strategies: {}\n", usage.join(", ")));
+            if let Some(lowest) = stats::lowest_scoring(&self.strategies) {
+                out.push_str(&format!("⚠️  Lowest-rated replies: {:?}, worth reviewing its prompt\n", lowest.strategy));
+            }
+        }
+
+        if !self.aspects.is_empty() {
+            let aspects: Vec<String> = self
+                .aspects
+                .iter()
+                .map(|a| {
+                    let counts = [(a.counts.negative, "negative"), (a.counts.positive, "positive"), (a.counts.neutral, "neutral")];
+                    let counts: Vec<String> = counts.iter().filter(|(n, _)| *n > 0).map(|(n, name)| format!("{} {}", n, name)).collect();
+                    format!("{} ({})", a.aspect, counts.join(", "))
+                })
+                .collect();
+            out.push_str(&format!("\n🔍 Aspects: {}\n", aspects.join(", ")));
+        }
+
+        if let Some(confidence) = self.confidence.describe() {
+            out.push_str(&format!("\n🎚️  Confidence: {}\n", confidence));
+        }
+
+        if !self.topics.is_empty() {
+            out.push_str(&format!("\n🏷️  Topics: {}\n", self.topics.join(", ")));
+        }
+
+        if !self.session_keyphrases.is_empty() {
+            out.push_str("\n🔑 Key phrases:\n");
+            for session in &self.session_keyphrases {
+                out.push_str(&format!("   {}: {}\n", session.session_id, session.phrases.join(", ")));
+            }
+        }
+        if !self.low_mood_keyphrases.is_empty() {
+            out.push_str(&format!("😟 When the mood was low: {}\n", self.low_mood_keyphrases.join(", ")));
+        }
+
+        if let Some(summary) = &self.summary {
+            out.push_str(&format!("\n📝 Summary:\n{}\n", summary));
+        }
+
+        out
+    }
+}
+
+/// Classifications in time order; sessions may overlap, so file order is not enough
+fn by_time(mut emotions: Vec<(i64, SentimentClassification)>) -> Vec<SentimentClassification> {
+    emotions.sort_by_key(|(timestamp, _)| *timestamp);
+    emotions.into_iter().map(|(_, emotion)| emotion).collect()
+}
+
+pub fn last_week(today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let from = today.checked_sub_days(Days::new(6)).unwrap_or(today);
+    (from, today)
+}
+
+pub fn messages_in_range(
+    sessions: &[StoredSession],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> impl Iterator<Item = (NaiveDate, &Message)> {
+    sessions
+        .iter()
+        .flat_map(|session| session.messages.iter())
+        .filter_map(|msg| message_date(msg).map(|date| (date, msg)))
+        .filter(move |(date, _)| *date >= from && *date <= to)
+}
+
+/// The user's side of `messages`, one message per line
+pub fn user_text<'a>(messages: impl IntoIterator<Item = &'a Message>) -> String {
+    messages
+        .into_iter()
+        .filter(|msg| matches!(msg.role, MessageRole::User))
+        .map(|msg| msg.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn build_transcript(sessions: &[StoredSession], from: NaiveDate, to: NaiveDate) -> String {
+    let lines: Vec<String> = messages_in_range(sessions, from, to)
+        .map(|(date, msg)| {
+            let role = match msg.role {
+                MessageRole::User => "User",
+                MessageRole::Assistant => "Assistant",
+            };
+            format!("[{}] {}: {}", date, msg.speaker.as_deref().unwrap_or(role), msg.content)
+        })
+        .collect();
+
+    // Keep the most recent lines when the range is too large for one prompt
+    let mut transcript = String::new();
+    for line in lines.iter().rev() {
+        if transcript.len() + line.len() + 1 > MAX_TRANSCRIPT_CHARS {
+            break;
+        }
+        transcript.insert_str(0, &format!("{}\n", line));
+    }
+
+    transcript
+}
+
+pub fn message_date(msg: &Message) -> Option<NaiveDate> {
+    DateTime::from_timestamp(msg.timestamp, 0).map(|dt| dt.date_naive())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AspectSentiment, SentimentClassification};
+    use crate::strategy::ResponseStrategy;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn timestamp(s: &str) -> i64 {
+        date(s).and_hms_opt(12, 0, 0).unwrap().and_utc().timestamp()
+    }
+
+    fn user_message(day: &str, sentiment: Sentiment) -> Message {
+        Message {
+            role: MessageRole::User,
+            content: format!("{:?} on {}", sentiment, day),
+            timestamp: timestamp(day),
+            emotion: Some(SentimentClassification { sentiment, confidence: 0.8, labels: Vec::new(), is_fallback: false }),
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
+        }
+    }
+
+    #[test]
+    fn test_last_week_covers_seven_days() {
+        let (from, to) = last_week(date("2026-02-10"));
+        assert_eq!(from, date("2026-02-04"));
+        assert_eq!(to, date("2026-02-10"));
+    }
+
+    #[test]
+    fn test_report_daily_distribution() {
+        let sessions = vec![
+            StoredSession::new(timestamp("2026-02-04"), vec![
+                user_message("2026-02-04", Sentiment::Negative),
+                user_message("2026-02-04", Sentiment::Neutral),
+            ]),
+            StoredSession::new(timestamp("2026-02-06"), vec![
+                user_message("2026-02-06", Sentiment::Positive),
+            ]),
+            StoredSession::new(timestamp("2026-01-01"), vec![
+                user_message("2026-01-01", Sentiment::Positive),
+            ]),
+        ];
+
+        let report = WeeklyReport::new(&sessions, date("2026-02-04"), date("2026-02-10"));
+
+        assert_eq!(report.daily.len(), 7);
+        assert_eq!(report.session_count, 2);
+        assert_eq!(report.message_count, 3);
+        assert_eq!(report.daily[0].counts.negative, 1);
+        assert_eq!(report.daily[0].counts.neutral, 1);
+        assert_eq!(report.daily[2].counts.positive, 1);
+        assert_eq!(report.totals().total(), 3);
+        assert!(report.strategies.is_empty());
+        assert_eq!(report.mood.map(|mood| mood.messages), Some(3));
+    }
+
+    #[test]
+    fn test_report_strategy_usage() {
+        let reply = |strategy| Message {
+            role: MessageRole::Assistant,
+            content: "reply".to_string(),
+            timestamp: timestamp("2026-02-05"),
+            emotion: None,
+            strategy: Some(strategy),
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
+        };
+        let sessions = vec![StoredSession::new(timestamp("2026-02-05"), vec![
+            reply(ResponseStrategy::Neutral),
+            reply(ResponseStrategy::Empathetic),
+            reply(ResponseStrategy::Empathetic),
+        ])];
+
+        let report = WeeklyReport::new(&sessions, date("2026-02-04"), date("2026-02-10"));
+
+        assert_eq!(report.strategies.len(), 2);
+        assert_eq!(report.strategies[0].strategy, ResponseStrategy::Empathetic);
+        assert_eq!(report.strategies[0].count, 2);
+    }
+
+    #[test]
+    fn test_report_speaker_timelines() {
+        let said = |speaker: &str, day, sentiment| Message { speaker: Some(speaker.to_string()), ..user_message(day, sentiment) };
+        let sessions = vec![StoredSession::new(timestamp("2026-02-04"), vec![
+            said("Sam", "2026-02-04", Sentiment::Negative),
+            said("Alex", "2026-02-04", Sentiment::Positive),
+            said("Sam", "2026-02-06", Sentiment::Negative),
+        ])];
+
+        let report = WeeklyReport::new(&sessions, date("2026-02-04"), date("2026-02-10"));
+
+        let speakers: Vec<&str> = report.speakers.iter().map(|timeline| timeline.speaker.as_str()).collect();
+        assert_eq!(speakers, ["Sam", "Alex"]);
+        assert_eq!(report.speakers[0].glyphs(), "- -    ");
+        assert_eq!(report.speakers[0].mood.map(|mood| mood.sentiment), Some(Sentiment::Negative));
+        assert!(report.render_text().contains("Alex  +"));
+        assert!(WeeklyReport::new(&[StoredSession::new(0, vec![user_message("2026-02-04", Sentiment::Neutral)])], date("2026-02-04"), date("2026-02-10")).speakers.is_empty());
+    }
+
+    #[test]
+    fn test_report_aspects() {
+        let about = |aspect: &str, sentiment| AspectSentiment { aspect: aspect.to_string(), sentiment };
+        let mut first = user_message("2026-02-04", Sentiment::Negative);
+        first.aspects = vec![about("job", Sentiment::Positive), about("manager", Sentiment::Negative)];
+        let mut second = user_message("2026-02-05", Sentiment::Negative);
+        second.aspects = vec![about("manager", Sentiment::Negative)];
+        let sessions = vec![StoredSession::new(timestamp("2026-02-04"), vec![first, second])];
+
+        let report = WeeklyReport::new(&sessions, date("2026-02-04"), date("2026-02-10"));
+
+        assert!(report.render_text().contains("Aspects: manager (2 negative), job (1 positive)"));
+    }
+
+    #[test]
+    fn test_user_text_skips_assistant() {
+        let mut reply = user_message("2026-02-04", Sentiment::Neutral);
+        reply.role = MessageRole::Assistant;
+        let messages = [user_message("2026-02-04", Sentiment::Negative), reply];
+
+        assert_eq!(user_text(&messages), "Negative on 2026-02-04");
+    }
+
+    #[test]
+    fn test_build_transcript_filters_range() {
+        let sessions = vec![StoredSession::new(timestamp("2026-02-04"), vec![
+            user_message("2026-02-04", Sentiment::Negative),
+            user_message("2026-03-01", Sentiment::Positive),
+        ])];
+
+        let transcript = build_transcript(&sessions, date("2026-02-01"), date("2026-02-07"));
+        assert!(transcript.contains("[2026-02-04] User: Negative"));
+        assert!(!transcript.contains("2026-03-01"));
+    }
+}
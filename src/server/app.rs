@@ -0,0 +1,58 @@
+use axum::Router;
+use axum::body::Body;
+use axum::middleware;
+use axum::routing::{get, post};
+use std::sync::Arc;
+use tower_http::trace::TraceLayer;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+use super::auth::{ApiKeys, require_api_key};
+use crate::telemetry::request_span;
+use super::docs::ApiDoc;
+use super::tenant::Tenants;
+use super::pacing::Pacing;
+use super::{chat, classify, live, metrics, stateless, stream};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub tenants: Arc<Tenants>,
+    pub max_batch: usize,
+    pub api_keys: Option<Arc<ApiKeys>>,
+    /// Long replies on live sessions are delivered in parts when set
+    pub pacing: Option<Pacing>,
+}
+
+pub fn router(state: AppState) -> Router {
+    let mut api = Router::new()
+        .route("/classify", post(classify::classify))
+        .route("/categorize", post(classify::categorize))
+        .route("/sessions/{session_id}", get(chat::get_session))
+        .route("/sessions/{session_id}/messages", post(chat::post_message))
+        .route("/sessions/{session_id}/messages/stream", post(stream::stream_message))
+        .route("/sessions/{session_id}/stats", get(chat::get_session_stats))
+        .route("/sessions/{session_id}/release", post(chat::release_session))
+        .route("/sessions/{session_id}/live", get(live::live_session))
+        .route("/turns", post(stateless::post_turn));
+
+    if let Some(keys) = state.api_keys.clone() {
+        api = api.layer(middleware::from_fn_with_state(keys, require_api_key));
+    }
+
+    // Added after the auth layer, so scrapers need no API key
+    api.route("/metrics", get(metrics::metrics))
+        .with_state(state)
+        .merge(SwaggerUi::new("/docs").url("/openapi.json", ApiDoc::openapi()))
+        .layer(TraceLayer::new_for_http().make_span_with(request_span::<Body>))
+}
+
+#[cfg(test)]
+pub fn test_state(max_batch: usize, api_keys: Option<Arc<ApiKeys>>) -> AppState {
+    use super::tenant::Tenant;
+
+    AppState {
+        tenants: Arc::new(Tenants::single(Tenant::default_for(&crate::Config::test()))),
+        max_batch,
+        api_keys,
+        pacing: None,
+    }
+}
@@ -0,0 +1,367 @@
+use anyhow::{Context, Result};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use super::error::ApiError;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKeyConfig {
+    pub key: String,
+    pub name: String,
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    #[serde(default)]
+    pub daily_quota: Option<u32>,
+    #[serde(default)]
+    pub tenant: Option<String>,
+    /// May hand conversations waiting for an operator back to the assistant
+    #[serde(default)]
+    pub operator: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthenticatedKey {
+    pub key: String,
+    pub tenant: Option<String>,
+    pub operator: bool,
+}
+
+impl From<&ApiKeyConfig> for AuthenticatedKey {
+    fn from(config: &ApiKeyConfig) -> Self {
+        Self { key: config.key.clone(), tenant: config.tenant.clone(), operator: config.operator }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    MissingKey,
+    UnknownKey,
+    RateLimited { retry_after_secs: i64 },
+    QuotaExceeded { retry_after_secs: i64 },
+}
+
+#[derive(Debug, Default)]
+struct KeyUsage {
+    minute: i64,
+    minute_count: u32,
+    day: Option<NaiveDate>,
+    day_count: u32,
+}
+
+pub struct ApiKeys {
+    keys: HashMap<String, ApiKeyConfig>,
+    usage: Mutex<HashMap<String, KeyUsage>>,
+}
+
+impl ApiKeys {
+    pub fn new(keys: Vec<ApiKeyConfig>) -> Self {
+        Self {
+            keys: keys.into_iter().map(|k| (k.key.clone(), k)).collect(),
+            usage: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let keys: Vec<ApiKeyConfig> = serde_json::from_str(&json)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+
+        if keys.is_empty() {
+            anyhow::bail!("{} defines no API keys", path.display());
+        }
+        Ok(Self::new(keys))
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.keys.values().map(|k| k.name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    pub fn bound_tenants(&self) -> impl Iterator<Item = &str> {
+        self.keys.values().filter_map(|k| k.tenant.as_deref())
+    }
+
+    /// Looks up the key and charges the request as one item
+    pub fn check(&self, key: Option<&str>, now: DateTime<Utc>) -> Result<&ApiKeyConfig, AuthError> {
+        let key = key.ok_or(AuthError::MissingKey)?;
+        let config = self.keys.get(key).ok_or(AuthError::UnknownKey)?;
+        self.charge(key, 1, now)?;
+        Ok(config)
+    }
+
+    /// Counts `items` against the key's rate limit and daily quota, or none
+    /// of them when they do not all fit
+    pub fn charge(&self, key: &str, items: u32, now: DateTime<Utc>) -> Result<(), AuthError> {
+        let config = self.keys.get(key).ok_or(AuthError::UnknownKey)?;
+
+        let mut usage = self.usage.lock().unwrap();
+        let usage = usage.entry(key.to_string()).or_default();
+
+        let minute = now.timestamp() / 60;
+        if usage.minute != minute {
+            usage.minute = minute;
+            usage.minute_count = 0;
+        }
+
+        let today = now.date_naive();
+        if usage.day != Some(today) {
+            usage.day = Some(today);
+            usage.day_count = 0;
+        }
+
+        if let Some(limit) = config.requests_per_minute
+            && usage.minute_count.saturating_add(items) > limit
+        {
+            return Err(AuthError::RateLimited {
+                retry_after_secs: 60 - now.timestamp() % 60,
+            });
+        }
+
+        if let Some(quota) = config.daily_quota
+            && usage.day_count.saturating_add(items) > quota
+        {
+            let tomorrow = today.succ_opt().and_then(|d| d.and_hms_opt(0, 0, 0));
+            let retry_after_secs = tomorrow
+                .map(|t| (t.and_utc() - now).num_seconds())
+                .unwrap_or(86_400);
+            return Err(AuthError::QuotaExceeded { retry_after_secs });
+        }
+
+        usage.minute_count += items;
+        usage.day_count += items;
+        Ok(())
+    }
+}
+
+pub async fn require_api_key(
+    State(keys): State<Arc<ApiKeys>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let authenticated = match keys.check(extract_key(request.headers()), Utc::now()) {
+        Ok(config) => AuthenticatedKey::from(config),
+        Err(e) => return auth_error_response(e),
+    };
+
+    request.extensions_mut().insert(authenticated);
+    next.run(request).await
+}
+
+/// The key from `x-api-key` or `Authorization: Bearer`, also used for gRPC metadata
+pub fn extract_key(headers: &HeaderMap) -> Option<&str> {
+    if let Some(key) = headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
+        return Some(key);
+    }
+
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+impl From<AuthError> for ApiError {
+    fn from(error: AuthError) -> Self {
+        match error {
+            AuthError::MissingKey => ApiError::new(StatusCode::UNAUTHORIZED, "missing API key"),
+            AuthError::UnknownKey => ApiError::new(StatusCode::UNAUTHORIZED, "invalid API key"),
+            AuthError::RateLimited { retry_after_secs } => {
+                ApiError::new(StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded").with_retry_after(retry_after_secs)
+            }
+            AuthError::QuotaExceeded { retry_after_secs } => {
+                ApiError::new(StatusCode::TOO_MANY_REQUESTS, "daily quota exceeded").with_retry_after(retry_after_secs)
+            }
+        }
+    }
+}
+
+fn auth_error_response(error: AuthError) -> Response {
+    let error = ApiError::from(error);
+    let unauthorized = error.status == StatusCode::UNAUTHORIZED;
+    let mut response = error.into_response();
+    if unauthorized {
+        response.headers_mut().insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> ApiKeys {
+        ApiKeys::new(vec![
+            ApiKeyConfig {
+                key: "limited".to_string(),
+                name: "limited".to_string(),
+                requests_per_minute: Some(2),
+                daily_quota: Some(3),
+                tenant: None,
+                operator: false,
+            },
+            ApiKeyConfig {
+                key: "open".to_string(),
+                name: "open".to_string(),
+                requests_per_minute: None,
+                daily_quota: None,
+                tenant: None,
+                operator: false,
+            },
+        ])
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_missing_and_unknown_keys() {
+        let keys = keys();
+        let now = at("2026-02-06T10:00:00Z");
+
+        assert_eq!(keys.check(None, now).unwrap_err(), AuthError::MissingKey);
+        assert_eq!(keys.check(Some("nope"), now).unwrap_err(), AuthError::UnknownKey);
+        assert_eq!(keys.check(Some("open"), now).unwrap().name, "open");
+    }
+
+    #[test]
+    fn test_rate_limit_resets_next_minute() {
+        let keys = keys();
+
+        assert!(keys.check(Some("limited"), at("2026-02-06T10:00:01Z")).is_ok());
+        assert!(keys.check(Some("limited"), at("2026-02-06T10:00:02Z")).is_ok());
+        assert_eq!(
+            keys.check(Some("limited"), at("2026-02-06T10:00:50Z")).unwrap_err(),
+            AuthError::RateLimited { retry_after_secs: 10 }
+        );
+
+        assert!(keys.check(Some("limited"), at("2026-02-06T10:01:00Z")).is_ok());
+    }
+
+    #[test]
+    fn test_daily_quota_resets_next_day() {
+        let keys = keys();
+
+        for minute in 0..3 {
+            let now = at(&format!("2026-02-06T10:0{}:00Z", minute));
+            assert!(keys.check(Some("limited"), now).is_ok());
+        }
+
+        let err = keys.check(Some("limited"), at("2026-02-06T23:00:00Z")).unwrap_err();
+        assert_eq!(err, AuthError::QuotaExceeded { retry_after_secs: 3600 });
+
+        assert!(keys.check(Some("limited"), at("2026-02-07T00:00:00Z")).is_ok());
+    }
+
+    #[test]
+    fn test_batch_items_are_charged() {
+        let keys = keys();
+        let now = at("2026-02-06T10:00:00Z");
+
+        // A batch that does not fit is charged nothing
+        assert_eq!(keys.charge("limited", 3, now).unwrap_err(), AuthError::RateLimited { retry_after_secs: 60 });
+        assert!(keys.charge("limited", 2, now).is_ok());
+        assert!(matches!(keys.check(Some("limited"), now), Err(AuthError::RateLimited { .. })));
+    }
+
+    #[test]
+    fn test_extract_key_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(extract_key(&headers), None);
+
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer abc"));
+        assert_eq!(extract_key(&headers), Some("abc"));
+
+        headers.insert("x-api-key", HeaderValue::from_static("xyz"));
+        assert_eq!(extract_key(&headers), Some("xyz"));
+    }
+
+    #[test]
+    fn test_rate_limited_response() {
+        let response = auth_error_response(AuthError::RateLimited { retry_after_secs: 7 });
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "7");
+    }
+
+    #[tokio::test]
+    async fn test_router_requires_key() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+        use crate::server::app::test_state;
+        use crate::server::router;
+
+        let app = router(test_state(4, Some(Arc::new(keys()))));
+
+        let request = |key: Option<&str>| {
+            let mut builder = Request::post("/classify").header("content-type", "application/json");
+            if let Some(key) = key {
+                builder = builder.header("x-api-key", key);
+            }
+            builder.body(Body::from(r#"{"text": ""}"#)).unwrap()
+        };
+
+        let response = app.clone().oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Each text of a batch counts against the limit of two a minute
+        let batch = Request::post("/classify")
+            .header("content-type", "application/json")
+            .header("x-api-key", "limited")
+            .body(Body::from(r#"{"texts": ["a", "b", "c"]}"#))
+            .unwrap();
+        let response = app.clone().oneshot(batch).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+
+        // A valid key reaches the handler, which rejects the empty text itself
+        let response = app.clone().oneshot(request(Some("open"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app.clone().oneshot(Request::get("/openapi.json").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.oneshot(Request::get("/metrics").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_release_needs_operator_key() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+        use crate::server::app::test_state;
+        use crate::server::router;
+
+        let mut configs = vec![ApiKeyConfig {
+            key: "ops".to_string(),
+            name: "operators".to_string(),
+            requests_per_minute: None,
+            daily_quota: None,
+            tenant: None,
+            operator: true,
+        }];
+        configs.extend(keys().keys.into_values());
+        let release = |key: Option<&str>| {
+            let mut builder = Request::post("/sessions/missing/release");
+            if let Some(key) = key {
+                builder = builder.header("x-api-key", key);
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+
+        let response = router(test_state(4, None)).oneshot(release(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let app = router(test_state(4, Some(Arc::new(ApiKeys::new(configs)))));
+        let response = app.clone().oneshot(release(Some("open"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.oneshot(release(Some("ops"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
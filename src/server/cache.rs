@@ -0,0 +1,55 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use redis::AsyncCommands;
+use redis::aio::ConnectionManager;
+use std::time::Duration;
+use crate::state::ConversationState;
+use crate::storage::schema;
+use super::sessions::SessionBackend;
+
+const KEY_PREFIX: &str = "session";
+
+/// Conversation state in Redis; each save restarts the idle TTL
+pub struct RedisSessions {
+    conn: ConnectionManager,
+    ttl: Duration,
+}
+
+impl RedisSessions {
+    pub async fn connect(url: &str, ttl: Duration) -> Result<Self> {
+        let client = redis::Client::open(url).context("invalid Redis URL")?;
+        let conn = ConnectionManager::new(client)
+            .await
+            .context("failed to connect to Redis")?;
+
+        Ok(Self { conn, ttl })
+    }
+}
+
+#[async_trait]
+impl SessionBackend for RedisSessions {
+    #[tracing::instrument(name = "storage.load", skip(self), fields(backend = "redis"))]
+    async fn load(&self, key: &str) -> Result<Option<ConversationState>> {
+        let mut conn = self.conn.clone();
+        let json: Option<String> = conn
+            .get(format!("{}:{}", KEY_PREFIX, key))
+            .await
+            .with_context(|| format!("failed to read session '{}' from Redis", key))?;
+
+        json.map(|json| schema::from_str(&json))
+            .transpose()
+            .with_context(|| format!("failed to parse session '{}'", key))
+    }
+
+    #[tracing::instrument(name = "storage.save", skip(self, state), fields(backend = "redis"))]
+    async fn save(&self, key: &str, state: &ConversationState) -> Result<()> {
+        let mut conn = self.conn.clone();
+        let json = schema::to_string(state)?;
+        let _: () = conn
+            .set_ex(format!("{}:{}", KEY_PREFIX, key), json, self.ttl.as_secs().max(1))
+            .await
+            .with_context(|| format!("failed to write session '{}' to Redis", key))?;
+
+        Ok(())
+    }
+}
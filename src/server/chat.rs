@@ -0,0 +1,270 @@
+use axum::{Extension, Json};
+use axum::extract::Path;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::SentimentClassification;
+use crate::agents::TokenUsage;
+use crate::handoff::HandoffReason;
+use crate::models::{Message, MessageRole, ReplyScore};
+use crate::state::{ConfidenceBySentiment, ConversationManager, ConversationMood, EmotionCounts, EmotionForecast, EmotionTrend, StrategyUsage};
+use crate::strategy::ResponseStrategy;
+use crate::turn::{TurnOutcome, run_turn};
+use super::auth::AuthenticatedKey;
+use super::error::{ApiError, ErrorBody};
+use super::tenant::{Tenant, TenantContext};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TurnRequest {
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TurnResponse {
+    pub emotion: SentimentClassification,
+    pub trend: EmotionTrend,
+    /// The user's likely sentiment on their next message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forecast: Option<EmotionForecast>,
+    pub strategy: ResponseStrategy,
+    /// Strategy the selection wanted while the previous one was still held
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppressed_strategy: Option<ResponseStrategy>,
+    /// Set while the session waits for an operator; `response` is then a fixed notice
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handoff: Option<HandoffReason>,
+    /// Set when the message asked about a blocked topic; `response` is then its refusal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked_topic: Option<String>,
+    /// The reply suggests a break because the session has run long
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub break_suggested: bool,
+    /// What made the message read like instructions to the model, which the reply treated as conversation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub injection: Option<String>,
+    pub response: String,
+    /// Tokens billed for the reply, when the provider reported them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+    /// How the evaluator rated the reply, when replies are scored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_score: Option<ReplyScore>,
+}
+
+impl From<TurnOutcome> for TurnResponse {
+    fn from(outcome: TurnOutcome) -> Self {
+        Self {
+            emotion: outcome.emotion,
+            trend: outcome.trend,
+            forecast: outcome.forecast,
+            strategy: outcome.strategy,
+            suppressed_strategy: outcome.suppressed,
+            handoff: outcome.handoff,
+            blocked_topic: outcome.blocked_topic,
+            break_suggested: outcome.break_suggested,
+            injection: outcome.injection,
+            response: outcome.response,
+            usage: outcome.usage,
+            reply_score: outcome.score,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionView {
+    pub session_id: String,
+    pub tenant: String,
+    pub messages: Vec<Message>,
+    pub trend: EmotionTrend,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mood: Option<ConversationMood>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forecast: Option<EmotionForecast>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handoff: Option<HandoffReason>,
+}
+
+/// Counts over a session, for `GET /sessions/{id}/stats` and the IPC `stats` op
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionStats {
+    pub session_id: String,
+    pub messages: usize,
+    pub user_messages: usize,
+    pub emotions: EmotionCounts,
+    /// Mean classifier confidence per sentiment
+    pub confidence: ConfidenceBySentiment,
+    /// Replies per strategy, most used first
+    pub strategies: Vec<StrategyUsage>,
+    pub trend: EmotionTrend,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mood: Option<ConversationMood>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forecast: Option<EmotionForecast>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handoff: Option<HandoffReason>,
+}
+
+impl SessionStats {
+    pub fn new(session_id: String, state: &ConversationManager) -> Self {
+        let history = state.get_history();
+        Self {
+            session_id,
+            messages: history.len(),
+            user_messages: history.iter().filter(|m| matches!(m.role, MessageRole::User)).count(),
+            emotions: state.emotion_distribution(),
+            confidence: state.average_confidence_by_sentiment(),
+            strategies: state.strategy_usage(),
+            trend: state.get_recent_emotion_trend().direction,
+            mood: state.overall_mood(),
+            forecast: state.forecast_next_emotion(),
+            handoff: state.handoff(),
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/sessions/{session_id}/messages",
+    params(("session_id" = String, Path, description = "Client-chosen session identifier")),
+    request_body = TurnRequest,
+    responses(
+        (status = 200, description = "Assistant reply with the detected emotion and chosen strategy", body = TurnResponse),
+        (status = 400, description = "Empty text", body = ErrorBody),
+        (status = 401, description = "Missing or invalid API key", body = ErrorBody),
+        (status = 403, description = "API key is bound to another tenant", body = ErrorBody),
+        (status = 404, description = "Unknown tenant", body = ErrorBody),
+        (status = 429, description = "Provider rate limit hit", body = ErrorBody),
+        (status = 502, description = "Provider call failed", body = ErrorBody),
+        (status = 503, description = "Session store unavailable", body = ErrorBody),
+        (status = 504, description = "Provider call timed out", body = ErrorBody),
+    ),
+    tag = "chat"
+)]
+pub async fn post_message(
+    TenantContext(tenant): TenantContext,
+    Path(session_id): Path<String>,
+    Json(request): Json<TurnRequest>,
+) -> Result<Json<TurnResponse>, ApiError> {
+    take_turn(&tenant, &session_id, &request.text).await.map(Json)
+}
+
+/// One turn of a stored session, shared by the JSON and live endpoints
+pub async fn take_turn(tenant: &Tenant, session_id: &str, text: &str) -> Result<TurnResponse, ApiError> {
+    if text.trim().is_empty() {
+        return Err(ApiError::bad_request("text must not be empty"));
+    }
+
+    let mut state = tenant.sessions.lock(session_id).await.map_err(session_store_error)?;
+    state.set_events(tenant.events.clone());
+
+    let outcome = run_turn(&tenant.detector, &tenant.chat_agent, &tenant.topics, &mut state, text, tenant.strategy, &tenant.pipeline)
+        .await
+        .map_err(ApiError::turn_failed)?;
+
+    tenant.sessions.persist(session_id, &state).await.map_err(session_store_error)?;
+    if outcome.escalated
+        && let Some(reason) = outcome.handoff
+    {
+        tenant.escalate(Some(session_id), reason, &state).await;
+    }
+
+    Ok(outcome.into())
+}
+
+#[utoipa::path(
+    get,
+    path = "/sessions/{session_id}",
+    params(("session_id" = String, Path, description = "Session identifier")),
+    responses(
+        (status = 200, description = "Session history and current trend", body = SessionView),
+        (status = 404, description = "Unknown session or tenant", body = ErrorBody),
+        (status = 503, description = "Session store unavailable", body = ErrorBody),
+    ),
+    tag = "chat"
+)]
+pub async fn get_session(
+    TenantContext(tenant): TenantContext,
+    Path(session_id): Path<String>,
+) -> Result<Json<SessionView>, ApiError> {
+    let state = tenant
+        .sessions
+        .find(&session_id)
+        .await
+        .map_err(session_store_error)?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("session '{}' not found", session_id)))?;
+
+    Ok(Json(SessionView {
+        session_id,
+        tenant: tenant.id.clone(),
+        messages: state.get_history().to_vec(),
+        trend: state.get_recent_emotion_trend().direction,
+        mood: state.overall_mood(),
+        forecast: state.forecast_next_emotion(),
+        handoff: state.handoff(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/sessions/{session_id}/stats",
+    params(("session_id" = String, Path, description = "Session identifier")),
+    responses(
+        (status = 200, description = "Emotion distribution, confidence and strategy usage", body = SessionStats),
+        (status = 404, description = "Unknown session or tenant", body = ErrorBody),
+        (status = 503, description = "Session store unavailable", body = ErrorBody),
+    ),
+    tag = "chat"
+)]
+pub async fn get_session_stats(
+    TenantContext(tenant): TenantContext,
+    Path(session_id): Path<String>,
+) -> Result<Json<SessionStats>, ApiError> {
+    let state = tenant
+        .sessions
+        .find(&session_id)
+        .await
+        .map_err(session_store_error)?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("session '{}' not found", session_id)))?;
+
+    Ok(Json(SessionStats::new(session_id, &state)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/sessions/{session_id}/release",
+    params(("session_id" = String, Path, description = "Session identifier")),
+    responses(
+        (status = 204, description = "The assistant answers the session again"),
+        (status = 403, description = "Needs an operator API key", body = ErrorBody),
+        (status = 404, description = "Unknown session or tenant", body = ErrorBody),
+        (status = 409, description = "The session is not waiting for an operator", body = ErrorBody),
+        (status = 503, description = "Session store unavailable", body = ErrorBody),
+    ),
+    tag = "chat"
+)]
+pub async fn release_session(
+    TenantContext(tenant): TenantContext,
+    key: Option<Extension<AuthenticatedKey>>,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    // Without --api-keys nobody can prove to be an operator
+    if !key.is_some_and(|key| key.operator) {
+        return Err(ApiError::new(StatusCode::FORBIDDEN, "releasing a handoff needs an operator API key"));
+    }
+    // Checked first, since locking creates sessions that do not exist yet
+    if tenant.sessions.find(&session_id).await.map_err(session_store_error)?.is_none() {
+        return Err(ApiError::new(StatusCode::NOT_FOUND, format!("session '{}' not found", session_id)));
+    }
+
+    let mut state = tenant.sessions.lock(&session_id).await.map_err(session_store_error)?;
+    if !state.release_handoff() {
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("session '{}' is not waiting for an operator", session_id)));
+    }
+    tenant.sessions.persist(&session_id, &state).await.map_err(session_store_error)?;
+    tracing::info!(tenant = %tenant.id, session = %session_id, "handoff released");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub fn session_store_error(e: anyhow::Error) -> ApiError {
+    ApiError::new(StatusCode::SERVICE_UNAVAILABLE, format!("{:#}", e))
+}
@@ -0,0 +1,290 @@
+use axum::{Extension, Json};
+use axum::extract::State;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::SentimentClassification;
+use crate::agents::{AgentError, TaxonomyClassification};
+use super::app::AppState;
+use super::auth::AuthenticatedKey;
+use super::error::{ApiError, ErrorBody};
+use super::tenant::TenantContext;
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum ClassifyRequest {
+    Single { text: String },
+    Batch { texts: Vec<String> },
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchItem {
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classification: Option<SentimentClassification>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum ClassifyResponse {
+    Single(SentimentClassification),
+    Batch { results: Vec<BatchItem> },
+}
+
+#[utoipa::path(
+    post,
+    path = "/classify",
+    request_body = ClassifyRequest,
+    responses(
+        (status = 200, description = "Classification, or per-item results for a batch", body = ClassifyResponse),
+        (status = 400, description = "Empty text or empty batch", body = ErrorBody),
+        (status = 401, description = "Missing or invalid API key", body = ErrorBody),
+        (status = 403, description = "API key is bound to another tenant", body = ErrorBody),
+        (status = 404, description = "Unknown tenant", body = ErrorBody),
+        (status = 413, description = "Batch exceeds the configured limit", body = ErrorBody),
+        (status = 429, description = "Rate limit, daily quota, or provider rate limit exceeded", body = ErrorBody),
+        (status = 502, description = "Provider call failed", body = ErrorBody),
+        (status = 504, description = "Provider call timed out", body = ErrorBody),
+    ),
+    tag = "classification"
+)]
+pub async fn classify(
+    State(state): State<AppState>,
+    TenantContext(tenant): TenantContext,
+    key: Option<Extension<AuthenticatedKey>>,
+    Json(request): Json<ClassifyRequest>,
+) -> Result<Json<ClassifyResponse>, ApiError> {
+    match request {
+        ClassifyRequest::Single { text } => {
+            validate_text(&text).map_err(ApiError::bad_request)?;
+
+            let classification = tenant
+                .detector
+                .analyze(&text)
+                .await
+                .map_err(ApiError::from)?;
+
+            Ok(Json(ClassifyResponse::Single(classification)))
+        }
+        ClassifyRequest::Batch { texts } => {
+            check_batch(&texts, state.max_batch)?;
+            charge_batch(&state, key.as_deref(), texts.len())?;
+
+            // Items fail independently, so one bad text never sinks the whole batch
+            let valid = valid_texts(&texts);
+            let outcomes = tenant.detector.analyze_batch(&valid).await;
+            let results = merge_outcomes(&texts, outcomes)
+                .map(|(index, outcome)| match outcome {
+                    Ok(c) => BatchItem { index, classification: Some(c), error: None },
+                    Err(e) => BatchItem { index, classification: None, error: Some(e) },
+                })
+                .collect();
+
+            Ok(Json(ClassifyResponse::Batch { results }))
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CategorizeItem {
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classification: Option<TaxonomyClassification>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum CategorizeResponse {
+    Single(TaxonomyClassification),
+    Batch { results: Vec<CategorizeItem> },
+}
+
+#[utoipa::path(
+    post,
+    path = "/categorize",
+    request_body = ClassifyRequest,
+    responses(
+        (status = 200, description = "Label from TAXONOMY_FILE, or per-item results for a batch", body = CategorizeResponse),
+        (status = 400, description = "Empty text or empty batch", body = ErrorBody),
+        (status = 401, description = "Missing or invalid API key", body = ErrorBody),
+        (status = 404, description = "Unknown tenant, or no taxonomy configured", body = ErrorBody),
+        (status = 413, description = "Batch exceeds the configured limit", body = ErrorBody),
+        (status = 429, description = "Rate limit, daily quota, or provider rate limit exceeded", body = ErrorBody),
+        (status = 502, description = "Provider call failed", body = ErrorBody),
+        (status = 504, description = "Provider call timed out", body = ErrorBody),
+    ),
+    tag = "classification"
+)]
+pub async fn categorize(
+    State(state): State<AppState>,
+    TenantContext(tenant): TenantContext,
+    key: Option<Extension<AuthenticatedKey>>,
+    Json(request): Json<ClassifyRequest>,
+) -> Result<Json<CategorizeResponse>, ApiError> {
+    let Some(categorizer) = &tenant.categorizer else {
+        return Err(ApiError::new(StatusCode::NOT_FOUND, "no taxonomy is configured (TAXONOMY_FILE)"));
+    };
+
+    match request {
+        ClassifyRequest::Single { text } => {
+            validate_text(&text).map_err(ApiError::bad_request)?;
+            let classification = categorizer.classify(&text).await.map_err(ApiError::from)?;
+            Ok(Json(CategorizeResponse::Single(classification)))
+        }
+        ClassifyRequest::Batch { texts } => {
+            check_batch(&texts, state.max_batch)?;
+            charge_batch(&state, key.as_deref(), texts.len())?;
+
+            let valid = valid_texts(&texts);
+            let outcomes = categorizer.classify_batch(&valid).await;
+            let results = merge_outcomes(&texts, outcomes)
+                .map(|(index, outcome)| match outcome {
+                    Ok(c) => CategorizeItem { index, classification: Some(c), error: None },
+                    Err(e) => CategorizeItem { index, classification: None, error: Some(e) },
+                })
+                .collect();
+
+            Ok(Json(CategorizeResponse::Batch { results }))
+        }
+    }
+}
+
+fn check_batch(texts: &[String], max_batch: usize) -> Result<(), ApiError> {
+    if texts.is_empty() {
+        return Err(ApiError::bad_request("texts must not be empty"));
+    }
+    if texts.len() > max_batch {
+        return Err(ApiError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("batch of {} exceeds the limit of {}", texts.len(), max_batch),
+        ));
+    }
+    Ok(())
+}
+
+/// The auth layer charged the request as one item; a batch pays for the rest
+fn charge_batch(state: &AppState, key: Option<&AuthenticatedKey>, items: usize) -> Result<(), ApiError> {
+    if let (Some(keys), Some(key)) = (&state.api_keys, key) {
+        let rest = u32::try_from(items.saturating_sub(1)).unwrap_or(u32::MAX);
+        keys.charge(&key.key, rest, chrono::Utc::now())?;
+    }
+    Ok(())
+}
+
+fn valid_texts(texts: &[String]) -> Vec<&str> {
+    texts.iter().map(String::as_str).filter(|t| validate_text(t).is_ok()).collect()
+}
+
+/// Each text's index and outcome, given the outcomes of its valid texts in order
+fn merge_outcomes<'a, T: 'a>(
+    texts: &'a [String],
+    outcomes: Vec<Result<T, AgentError>>,
+) -> impl Iterator<Item = (usize, Result<T, String>)> + 'a {
+    let mut outcomes = outcomes.into_iter();
+    texts.iter().enumerate().map(move |(index, text)| {
+        let outcome = match validate_text(text) {
+            Ok(()) => outcomes.next().expect("one outcome per valid text").map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        (index, outcome)
+    })
+}
+
+fn validate_text(text: &str) -> Result<(), &'static str> {
+    if text.trim().is_empty() {
+        return Err("text must not be empty");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+    use crate::server::app::test_state;
+    use crate::server::router;
+
+    fn test_router(max_batch: usize) -> axum::Router {
+        router(test_state(max_batch, None))
+    }
+
+    async fn post_classify(max_batch: usize, body: &str) -> StatusCode {
+        let request = Request::post("/classify")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+
+        test_router(max_batch).oneshot(request).await.unwrap().status()
+    }
+
+    #[test]
+    fn test_request_shapes() {
+        let single: ClassifyRequest = serde_json::from_str(r#"{"text": "hi"}"#).unwrap();
+        assert!(matches!(single, ClassifyRequest::Single { .. }));
+
+        let batch: ClassifyRequest = serde_json::from_str(r#"{"texts": ["a", "b"]}"#).unwrap();
+        assert!(matches!(batch, ClassifyRequest::Batch { texts } if texts.len() == 2));
+    }
+
+    #[test]
+    fn test_batch_item_serialization() {
+        let item = BatchItem { index: 1, classification: None, error: Some("boom".to_string()) };
+        let json = serde_json::to_string(&item).unwrap();
+        assert_eq!(json, r#"{"index":1,"error":"boom"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_classify_rejects_empty_text() {
+        assert_eq!(post_classify(4, r#"{"text": "   "}"#).await, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_classify_rejects_empty_batch() {
+        assert_eq!(post_classify(4, r#"{"texts": []}"#).await, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_classify_rejects_oversized_batch() {
+        let status = post_classify(2, r#"{"texts": ["a", "b", "c"]}"#).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_categorize_needs_a_taxonomy() {
+        use std::sync::Arc;
+        use crate::server::tenant::{Tenant, Tenants};
+
+        let request = |body: &str| {
+            Request::post("/categorize")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let response = test_router(4).oneshot(request(r#"{"text": "refund"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let mut config = crate::Config::test();
+        config.dry_run = true;
+        config.taxonomy = Some(serde_json::from_str(r#"{"name": "support", "labels": [{"name": "billing"}, {"name": "bug"}]}"#).unwrap());
+        let state = AppState { tenants: Arc::new(Tenants::single(Tenant::default_for(&config))), ..test_state(4, None) };
+        let response = router(state).oneshot(request(r#"{"texts": ["found a bug", " "]}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["results"][0]["classification"]["label"], "bug");
+        assert_eq!(json["results"][1]["error"], "text must not be empty");
+    }
+
+    #[tokio::test]
+    async fn test_classify_rejects_malformed_body() {
+        let status = post_classify(4, r#"{"message": "hi"}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
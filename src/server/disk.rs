@@ -0,0 +1,125 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use crate::state::ConversationState;
+use crate::storage::schema;
+use super::sessions::SessionBackend;
+
+/// Conversation state as JSON files, one per session, so a single instance
+/// can drop idle sessions from memory without Redis
+pub struct DiskSessions {
+    dir: PathBuf,
+    expiry: Option<Duration>,
+}
+
+impl DiskSessions {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into(), expiry: None }
+    }
+
+    /// Sessions not written for `expiry` are deleted by `expire`
+    pub fn with_expiry(mut self, expiry: Duration) -> Self {
+        self.expiry = Some(expiry);
+        self
+    }
+
+    /// Keys hold client-chosen session ids, so anything but letters, digits
+    /// and `-` is escaped to keep them inside `dir`
+    fn path(&self, key: &str) -> PathBuf {
+        let mut name = String::with_capacity(key.len());
+        for byte in key.bytes() {
+            if byte.is_ascii_alphanumeric() || byte == b'-' {
+                name.push(byte as char);
+            } else {
+                name.push_str(&format!("_{:02x}", byte));
+            }
+        }
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+#[async_trait]
+impl SessionBackend for DiskSessions {
+    #[tracing::instrument(name = "storage.load", skip(self), fields(backend = "disk"))]
+    async fn load(&self, key: &str) -> Result<Option<ConversationState>> {
+        let path = self.path(key);
+        let json = match fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+
+        schema::from_str(&json)
+            .map(Some)
+            .with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    #[tracing::instrument(name = "storage.save", skip(self, state), fields(backend = "disk"))]
+    async fn save(&self, key: &str, state: &ConversationState) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create {}", self.dir.display()))?;
+
+        let path = self.path(key);
+        let json = schema::to_string(state)?;
+        fs::write(&path, json)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+
+    async fn expire(&self) -> Result<usize> {
+        let Some(expiry) = self.expiry else {
+            return Ok(0);
+        };
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", self.dir.display())),
+        };
+        let now = SystemTime::now();
+        let mut expired = 0;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let written = fs::metadata(&path).and_then(|metadata| metadata.modified());
+            let stale = written.is_ok_and(|written| now.duration_since(written).is_ok_and(|age| age >= expiry));
+            if stale && fs::remove_file(&path).is_ok() {
+                expired += 1;
+            }
+        }
+        Ok(expired)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MessageRole;
+    use crate::state::ConversationManager;
+
+    #[tokio::test]
+    async fn test_round_trip_stays_inside_dir() {
+        let dir = std::env::temp_dir().join(format!("tce-disk-sessions-{}", std::process::id()));
+        let sessions = DiskSessions::new(&dir);
+        assert_eq!(sessions.path("acme:../x y"), dir.join("acme_3a_2e_2e_2fx_20y.json"));
+        assert_ne!(sessions.path("a_3a"), sessions.path("a:"));
+        assert!(sessions.load("acme:s").await.unwrap().is_none());
+
+        let mut conversation = ConversationManager::new();
+        conversation.add_message(MessageRole::User, "Hello");
+        sessions.save("acme:s", conversation.state()).await.unwrap();
+        assert_eq!(sessions.load("acme:s").await.unwrap().unwrap().messages.len(), 1);
+
+        assert_eq!(sessions.expire().await.unwrap(), 0);
+        assert_eq!(DiskSessions::new(&dir).with_expiry(Duration::from_secs(3600)).expire().await.unwrap(), 0);
+        assert_eq!(DiskSessions::new(&dir).with_expiry(Duration::ZERO).expire().await.unwrap(), 1);
+        assert!(sessions.load("acme:s").await.unwrap().is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
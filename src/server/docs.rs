@@ -0,0 +1,56 @@
+use utoipa::OpenApi;
+use super::{chat, classify, stateless, stream};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Text Classifier Extractor API",
+        description = "Emotion classification and chat over HTTP"
+    ),
+    paths(classify::classify, classify::categorize, chat::post_message, stream::stream_message, chat::get_session, chat::get_session_stats, chat::release_session, stateless::post_turn),
+    tags(
+        (name = "classification", description = "Sentiment classification"),
+        (name = "chat", description = "Emotion-aware chat sessions")
+    )
+)]
+pub struct ApiDoc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_lists_classify() {
+        let spec = ApiDoc::openapi();
+        assert!(spec.paths.paths.contains_key("/classify"));
+        assert!(spec.paths.paths.contains_key("/categorize"));
+        assert!(spec.paths.paths.contains_key("/sessions/{session_id}/messages"));
+        assert!(spec.paths.paths.contains_key("/sessions/{session_id}/messages/stream"));
+        assert!(spec.paths.paths.contains_key("/turns"));
+        assert!(spec.paths.paths.contains_key("/sessions/{session_id}/release"));
+        assert!(spec.paths.paths.contains_key("/sessions/{session_id}/stats"));
+
+        let json = spec.to_json().unwrap();
+        assert!(json.contains("SentimentClassification"));
+        assert!(json.contains("BatchItem"));
+
+        // Turn state carries the version it is saved with
+        let state = serde_json::to_value(&spec.components.unwrap().schemas["VersionedState"]).unwrap();
+        assert!(state.to_string().contains("\"version\""), "{}", state);
+    }
+
+    #[tokio::test]
+    async fn test_openapi_json_served() {
+        use axum::body::Body;
+        use axum::http::{Request, StatusCode};
+        use tower::ServiceExt;
+        use crate::server::app::test_state;
+        use crate::server::router;
+
+        let app = router(test_state(4, None));
+
+        let request = Request::get("/openapi.json").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,90 @@
+use axum::Json;
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::agents::AgentError;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: String,
+}
+
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+    /// Sent as `Retry-After`, in seconds
+    pub retry_after: Option<i64>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self { status, message: message.into(), retry_after: None }
+    }
+
+    pub fn with_retry_after(mut self, secs: i64) -> Self {
+        self.retry_after = Some(secs);
+        self
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    /// A failed chat turn, keeping the status of the model error behind it
+    pub fn turn_failed(error: anyhow::Error) -> Self {
+        let status = error
+            .downcast_ref::<AgentError>()
+            .map(upstream_status)
+            .unwrap_or(StatusCode::BAD_GATEWAY);
+        Self::new(status, format!("{:#}", error))
+    }
+}
+
+impl From<AgentError> for ApiError {
+    fn from(error: AgentError) -> Self {
+        Self::new(upstream_status(&error), error.to_string())
+    }
+}
+
+fn upstream_status(error: &AgentError) -> StatusCode {
+    match error {
+        AgentError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+        AgentError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+        AgentError::CircuitOpen => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::BAD_GATEWAY,
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut response = (self.status, Json(ErrorBody { error: self.message })).into_response();
+        if let Some(secs) = self.retry_after
+            && let Ok(value) = HeaderValue::from_str(&secs.to_string())
+        {
+            response.headers_mut().insert(header::RETRY_AFTER, value);
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_error_status() {
+        let response = ApiError::bad_request("nope").into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_turn_failed_keeps_agent_status() {
+        let error = anyhow::Error::new(AgentError::Timeout).context("Emotion detection failed");
+        assert_eq!(ApiError::turn_failed(error).status, StatusCode::GATEWAY_TIMEOUT);
+
+        let error = anyhow::anyhow!("something else");
+        assert_eq!(ApiError::turn_failed(error).status, StatusCode::BAD_GATEWAY);
+    }
+}
@@ -0,0 +1,154 @@
+use axum::extract::ws::{Message as WsMessage, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, State};
+use axum::response::Response;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use super::app::AppState;
+use super::chat::{TurnRequest, TurnResponse, take_turn};
+use super::pacing::Pacing;
+use super::tenant::{Tenant, TenantContext};
+
+/// How often `typing` is repeated while a reply is being generated
+pub const TYPING_INTERVAL: Duration = Duration::from_secs(3);
+
+/// What the server sends over a live session, one JSON text frame each.
+/// The streaming endpoint sends the same events as server-sent events.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+    /// The assistant is working on a reply
+    Typing,
+    /// One part of a long reply delivered at typing speed
+    ReplyPart { text: String },
+    /// A piece of the reply as the model streams it
+    Token { text: String },
+    /// The finished turn; `response` holds the whole reply
+    Turn(TurnResponse),
+    Error { status: u16, error: String },
+}
+
+impl LiveEvent {
+    /// The `type` the event is tagged with
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Typing => "typing",
+            Self::ReplyPart { .. } => "reply_part",
+            Self::Token { .. } => "token",
+            Self::Turn(_) => "turn",
+            Self::Error { .. } => "error",
+        }
+    }
+}
+
+/// Chat over a WebSocket: each `{"text": ...}` frame is one turn of the session
+pub async fn live_session(
+    State(app): State<AppState>,
+    TenantContext(tenant): TenantContext,
+    Path(session_id): Path<String>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade.on_upgrade(move |socket| serve_socket(socket, tenant, session_id, app.pacing))
+}
+
+async fn serve_socket(mut socket: WebSocket, tenant: Arc<Tenant>, session_id: String, pacing: Option<Pacing>) {
+    while let Some(Ok(frame)) = socket.recv().await {
+        let text = match frame {
+            WsMessage::Text(text) => text,
+            WsMessage::Close(_) => break,
+            _ => continue,
+        };
+        let sent = match serde_json::from_str::<TurnRequest>(text.as_str()) {
+            Ok(request) => turn(&mut socket, &tenant, &session_id, &request.text, pacing).await,
+            Err(e) => send(&mut socket, &LiveEvent::Error { status: 400, error: format!("invalid message: {}", e) }).await,
+        };
+        if sent.is_err() {
+            break;
+        }
+    }
+}
+
+async fn turn(
+    socket: &mut WebSocket,
+    tenant: &Tenant,
+    session_id: &str,
+    text: &str,
+    pacing: Option<Pacing>,
+) -> Result<(), axum::Error> {
+    send(socket, &LiveEvent::Typing).await?;
+    let result = {
+        let turn = take_turn(tenant, session_id, text);
+        tokio::pin!(turn);
+        let start = tokio::time::Instant::now() + TYPING_INTERVAL;
+        let mut typing = tokio::time::interval_at(start, TYPING_INTERVAL);
+        loop {
+            tokio::select! {
+                result = &mut turn => break result,
+                _ = typing.tick() => send(socket, &LiveEvent::Typing).await?,
+            }
+        }
+    };
+    let response = match result {
+        Ok(response) => response,
+        Err(e) => return send(socket, &LiveEvent::Error { status: e.status.as_u16(), error: e.message }).await,
+    };
+
+    if let Some(pacing) = pacing {
+        let parts = pacing.parts(&response.response);
+        if parts.len() > 1 {
+            for (i, part) in parts.into_iter().enumerate() {
+                if i > 0 {
+                    send(socket, &LiveEvent::Typing).await?;
+                    tokio::time::sleep(pacing.pause(&part)).await;
+                }
+                send(socket, &LiveEvent::ReplyPart { text: part }).await?;
+            }
+        }
+    }
+    send(socket, &LiveEvent::Turn(response)).await
+}
+
+async fn send(socket: &mut WebSocket, event: &LiveEvent) -> Result<(), axum::Error> {
+    let json = serde_json::to_string(event).expect("live events always serialize");
+    socket.send(WsMessage::Text(json.into())).await
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt};
+    use std::sync::Arc;
+    use tokio_tungstenite::tungstenite::Message;
+    use crate::Config;
+    use super::super::app::{AppState, router};
+    use super::super::tenant::{Tenant, Tenants};
+
+    #[tokio::test]
+    async fn test_live_session_sends_typing_then_turn() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let tenant = Tenant::default_for(&Config { dry_run: true, ..Config::test() });
+        let state = AppState { tenants: Arc::new(Tenants::single(tenant)), max_batch: 8, api_keys: None, pacing: None };
+        tokio::spawn(async move { axum::serve(listener, router(state)).await });
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/sessions/live-1/live", addr)).await.unwrap();
+        socket.send(Message::text(r#"{"text": "I had a good day"}"#)).await.unwrap();
+
+        let mut types = Vec::new();
+        while let Some(Ok(Message::Text(text))) = socket.next().await {
+            let event: serde_json::Value = serde_json::from_str(text.as_str()).unwrap();
+            types.push(event["type"].as_str().unwrap().to_string());
+            if event["type"] == "turn" {
+                assert!(event["response"].as_str().unwrap().starts_with("[dry run]"));
+                break;
+            }
+        }
+        assert_eq!(types.first().map(String::as_str), Some("typing"));
+        assert_eq!(types.last().map(String::as_str), Some("turn"));
+
+        socket.send(Message::text("not json")).await.unwrap();
+        let Some(Ok(Message::Text(text))) = socket.next().await else { panic!("expected an error event") };
+        let event: serde_json::Value = serde_json::from_str(text.as_str()).unwrap();
+        assert_eq!(event["type"], "error");
+        assert_eq!(event["status"], 400);
+    }
+}
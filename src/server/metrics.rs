@@ -0,0 +1,181 @@
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use crate::agents::CircuitState;
+use crate::events::Event;
+use super::app::AppState;
+
+/// Counts of one tenant's classifications and strategies, fed from conversation events
+#[derive(Debug, Default)]
+pub struct TurnMetrics {
+    sentiments: Mutex<BTreeMap<String, u64>>,
+    strategies: Mutex<BTreeMap<String, u64>>,
+}
+
+impl TurnMetrics {
+    pub fn record(&self, event: &Event) {
+        let (counts, key) = match event {
+            Event::EmotionUpdated { emotion } => (&self.sentiments, format!("{:?}", emotion.sentiment)),
+            Event::StrategySelected { strategy } => (&self.strategies, format!("{:?}", strategy)),
+            _ => return,
+        };
+        if let Ok(mut counts) = counts.lock() {
+            *counts.entry(key).or_default() += 1;
+        }
+    }
+
+    fn sentiments(&self) -> BTreeMap<String, u64> {
+        self.sentiments.lock().map(|counts| counts.clone()).unwrap_or_default()
+    }
+
+    fn strategies(&self) -> BTreeMap<String, u64> {
+        self.strategies.lock().map(|counts| counts.clone()).unwrap_or_default()
+    }
+}
+
+/// Prometheus text exposition of provider circuit breaker state per tenant
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let mut tenants: Vec<_> = state.tenants.all().collect();
+    tenants.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut body = String::new();
+    body.push_str("# HELP emotion_provider_circuit_state Provider circuit breaker state (0 closed, 1 half-open, 2 open)\n");
+    body.push_str("# TYPE emotion_provider_circuit_state gauge\n");
+    for tenant in &tenants {
+        let value = match tenant.breaker.state() {
+            CircuitState::Closed => 0,
+            CircuitState::HalfOpen => 1,
+            CircuitState::Open => 2,
+        };
+        let _ = writeln!(body, "emotion_provider_circuit_state{{tenant=\"{}\"}} {}", tenant.id, value);
+    }
+
+    body.push_str("# HELP emotion_provider_circuit_opened_total Times the provider circuit has opened\n");
+    body.push_str("# TYPE emotion_provider_circuit_opened_total counter\n");
+    for tenant in &tenants {
+        let _ = writeln!(
+            body,
+            "emotion_provider_circuit_opened_total{{tenant=\"{}\"}} {}",
+            tenant.id,
+            tenant.breaker.opened_count()
+        );
+    }
+
+    body.push_str("# HELP emotion_classification_extractions_total Classifications answered by the provider\n");
+    body.push_str("# TYPE emotion_classification_extractions_total counter\n");
+    for tenant in &tenants {
+        let _ = writeln!(
+            body,
+            "emotion_classification_extractions_total{{tenant=\"{}\"}} {}",
+            tenant.id,
+            tenant.detector.repairs().extractions()
+        );
+    }
+
+    body.push_str("# HELP emotion_classification_repairs_total Classifications whose answer was invalid, by whether a corrective follow-up fixed it\n");
+    body.push_str("# TYPE emotion_classification_repairs_total counter\n");
+    for tenant in &tenants {
+        let repairs = tenant.detector.repairs();
+        for (outcome, count) in [("repaired", repairs.repaired()), ("failed", repairs.failed())] {
+            let _ = writeln!(body, "emotion_classification_repairs_total{{tenant=\"{}\",outcome=\"{}\"}} {}", tenant.id, outcome, count);
+        }
+    }
+
+    body.push_str("# HELP emotion_classification_resampled_total Low-confidence classifications sampled again for self-consistency\n");
+    body.push_str("# TYPE emotion_classification_resampled_total counter\n");
+    for tenant in &tenants {
+        let _ = writeln!(body, "emotion_classification_resampled_total{{tenant=\"{}\"}} {}", tenant.id, tenant.detector.resampled());
+    }
+
+    body.push_str("# HELP emotion_classification_fallbacks_total Classifications answered by the fallback policy instead of the model\n");
+    body.push_str("# TYPE emotion_classification_fallbacks_total counter\n");
+    for tenant in &tenants {
+        let _ = writeln!(body, "emotion_classification_fallbacks_total{{tenant=\"{}\"}} {}", tenant.id, tenant.degradation.fallbacks());
+    }
+
+    body.push_str("# HELP emotion_reply_failures_total Replies that failed or were replaced by the holding message\n");
+    body.push_str("# TYPE emotion_reply_failures_total counter\n");
+    for tenant in &tenants {
+        let _ = writeln!(body, "emotion_reply_failures_total{{tenant=\"{}\"}} {}", tenant.id, tenant.degradation.failed_replies());
+    }
+
+    body.push_str("# HELP emotion_provider_retries_total Provider calls retried after a transient error\n");
+    body.push_str("# TYPE emotion_provider_retries_total counter\n");
+    for tenant in &tenants {
+        let _ = writeln!(body, "emotion_provider_retries_total{{tenant=\"{}\"}} {}", tenant.id, tenant.degradation.retries());
+    }
+
+    body.push_str("# HELP emotion_messages_total Classified user messages by sentiment\n");
+    body.push_str("# TYPE emotion_messages_total counter\n");
+    for tenant in &tenants {
+        for (sentiment, count) in tenant.metrics.sentiments() {
+            let _ = writeln!(body, "emotion_messages_total{{tenant=\"{}\",sentiment=\"{}\"}} {}", tenant.id, sentiment, count);
+        }
+    }
+
+    body.push_str("# HELP emotion_replies_total Replies by the strategy they were answered with\n");
+    body.push_str("# TYPE emotion_replies_total counter\n");
+    for tenant in &tenants {
+        for (strategy, count) in tenant.metrics.strategies() {
+            let _ = writeln!(body, "emotion_replies_total{{tenant=\"{}\",strategy=\"{}\"}} {}", tenant.id, strategy, count);
+        }
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{Body, to_bytes};
+    use axum::http::{Request, StatusCode};
+    use std::time::Instant;
+    use tower::ServiceExt;
+    use std::sync::Arc;
+    use crate::Config;
+    use crate::server::app::{AppState, test_state};
+    use crate::server::router;
+    use crate::server::tenant::{Tenant, Tenants};
+
+    #[tokio::test]
+    async fn test_metrics_report_circuit_state() {
+        let state = test_state(4, None);
+        let tenant = state.tenants.resolve(None).unwrap();
+        for _ in 0..5 {
+            tenant.breaker.record_failure(Instant::now());
+        }
+        tenant.degradation.record_fallback();
+
+        let response = router(state)
+            .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("emotion_provider_circuit_state{tenant=\"default\"} 2"));
+        assert!(body.contains("emotion_provider_circuit_opened_total{tenant=\"default\"} 1"));
+        assert!(body.contains("emotion_classification_repairs_total{tenant=\"default\",outcome=\"failed\"} 0"));
+        assert!(body.contains("emotion_classification_fallbacks_total{tenant=\"default\"} 1"));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_count_turns() {
+        let tenant = Tenant::default_for(&Config { dry_run: true, ..Config::test() });
+        let app = router(AppState { tenants: Arc::new(Tenants::single(tenant)), max_batch: 8, api_keys: None, pacing: None });
+        let request = Request::post("/sessions/s1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"text":"I had a great day"}"#))
+            .unwrap();
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+
+        let response = app.oneshot(Request::get("/metrics").body(Body::empty()).unwrap()).await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("emotion_messages_total{tenant=\"default\",sentiment="));
+        assert!(body.contains("emotion_replies_total{tenant=\"default\",strategy="));
+    }
+}
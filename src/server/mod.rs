@@ -0,0 +1,20 @@
+//! HTTP server mode
+
+pub mod app;
+pub mod auth;
+#[cfg(feature = "redis")]
+pub mod cache;
+pub mod chat;
+pub mod classify;
+pub mod disk;
+pub mod docs;
+pub mod error;
+pub mod live;
+pub mod metrics;
+pub mod pacing;
+pub mod sessions;
+pub mod stateless;
+pub mod stream;
+pub mod tenant;
+
+pub use app::{AppState, router};
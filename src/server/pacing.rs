@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+/// Replies shorter than this are always sent whole
+const PACE_ABOVE: usize = 280;
+
+/// Longest part a paced reply is split into, unless one sentence is longer
+const PART_CHARS: usize = 200;
+
+/// Longest pause before a part, however long it is
+const MAX_PAUSE: Duration = Duration::from_secs(4);
+
+/// Delivers long replies in parts at roughly human typing speed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pacing {
+    pub chars_per_sec: u32,
+}
+
+impl Pacing {
+    /// Paragraphs, with long ones grouped into a few sentences each
+    pub fn parts(&self, reply: &str) -> Vec<String> {
+        if reply.chars().count() <= PACE_ABOVE {
+            return vec![reply.to_string()];
+        }
+
+        let mut parts = Vec::new();
+        for paragraph in reply.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+            let mut part = String::new();
+            for sentence in sentences(paragraph) {
+                if !part.is_empty() && part.chars().count() + sentence.chars().count() > PART_CHARS {
+                    parts.push(std::mem::take(&mut part));
+                }
+                if !part.is_empty() {
+                    part.push(' ');
+                }
+                part.push_str(sentence);
+            }
+            parts.push(part);
+        }
+        parts
+    }
+
+    /// How long "typing" a part takes before it is sent
+    pub fn pause(&self, part: &str) -> Duration {
+        let secs = part.chars().count() as f64 / self.chars_per_sec.max(1) as f64;
+        Duration::from_secs_f64(secs).min(MAX_PAUSE)
+    }
+}
+
+fn sentences(text: &str) -> impl Iterator<Item = &str> {
+    text.split_inclusive(['.', '!', '?']).map(str::trim).filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_replies_are_not_split() {
+        let pacing = Pacing { chars_per_sec: 40 };
+        assert_eq!(pacing.parts("That sounds hard. I'm here."), vec!["That sounds hard. I'm here."]);
+    }
+
+    #[test]
+    fn test_long_replies_split_by_paragraph_and_sentence() {
+        let pacing = Pacing { chars_per_sec: 40 };
+        let sentence = "This is a sentence of about sixty characters, give or take.";
+        let reply = format!("{0} {0} {0} {0} {0}\n\nShort closing paragraph.", sentence);
+
+        let parts = pacing.parts(&reply);
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], format!("{0} {0} {0}", sentence));
+        assert_eq!(parts[1], format!("{0} {0}", sentence));
+        assert_eq!(parts[2], "Short closing paragraph.");
+    }
+
+    #[test]
+    fn test_pause_follows_length_up_to_a_cap() {
+        let pacing = Pacing { chars_per_sec: 40 };
+        assert_eq!(pacing.pause(&"a".repeat(80)), Duration::from_secs(2));
+        assert_eq!(pacing.pause(&"a".repeat(1000)), MAX_PAUSE);
+    }
+}
@@ -0,0 +1,363 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+use crate::state::{CheckInPolicy, ConversationManager, ConversationState, RetentionPolicy, TrendAnalyzer};
+use crate::state::trend::Heuristic;
+
+pub type SharedConversation = Arc<AsyncMutex<ConversationManager>>;
+pub type SessionGuard = OwnedMutexGuard<ConversationManager>;
+
+/// Shared conversation state, so sessions outlive the process and span instances
+#[async_trait]
+pub trait SessionBackend: Send + Sync {
+    async fn load(&self, key: &str) -> Result<Option<ConversationState>>;
+
+    async fn save(&self, key: &str, state: &ConversationState) -> Result<()>;
+
+    /// Deletes sessions stored too long ago to come back; returns how many
+    /// went. Backends that expire entries themselves keep the default.
+    async fn expire(&self) -> Result<usize> {
+        Ok(0)
+    }
+}
+
+/// A session held in memory
+struct Slot {
+    conversation: SharedConversation,
+    last_used: Instant,
+}
+
+pub struct SessionManager {
+    sessions: Mutex<HashMap<String, Slot>>,
+    backend: Option<(Arc<dyn SessionBackend>, String)>,
+    retention: RetentionPolicy,
+    analyzer: Arc<dyn TrendAnalyzer>,
+    idle_ttl: Option<Duration>,
+    check_in: Option<CheckInPolicy>,
+}
+
+impl Default for SessionManager {
+    fn default() -> Self {
+        Self {
+            sessions: Mutex::default(),
+            backend: None,
+            retention: RetentionPolicy::default(),
+            analyzer: Arc::new(Heuristic),
+            idle_ttl: None,
+            check_in: None,
+        }
+    }
+}
+
+impl SessionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keys in the backend are prefixed with `namespace`, keeping tenants apart
+    pub fn with_backend(backend: Arc<dyn SessionBackend>, namespace: &str) -> Self {
+        Self { backend: Some((backend, namespace.to_string())), ..Self::default() }
+    }
+
+    /// Every session is kept within `retention`, so long-running ones stay bounded
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub fn retention(&self) -> RetentionPolicy {
+        self.retention
+    }
+
+    /// Every session reads its trend with `analyzer`
+    pub fn with_trend_analyzer(mut self, analyzer: Arc<dyn TrendAnalyzer>) -> Self {
+        self.analyzer = analyzer;
+        self
+    }
+
+    pub fn trend_analyzer(&self) -> Arc<dyn TrendAnalyzer> {
+        self.analyzer.clone()
+    }
+
+    /// Sessions unused for `ttl` are dropped from memory by `evict_idle`
+    pub fn with_idle_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.idle_ttl = ttl;
+        self
+    }
+
+    pub fn idle_ttl(&self) -> Option<Duration> {
+        self.idle_ttl
+    }
+
+    /// Sessions due a check-in under `policy` stay in memory until it is added
+    pub fn with_check_in(mut self, policy: Option<CheckInPolicy>) -> Self {
+        self.check_in = policy;
+        self
+    }
+
+    pub fn check_in(&self) -> Option<CheckInPolicy> {
+        self.check_in
+    }
+
+    /// Sessions held in memory
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    fn manager(&self, state: ConversationState) -> ConversationManager {
+        ConversationManager::from_state(state)
+            .with_retention(self.retention)
+            .with_trend_analyzer(self.analyzer.clone())
+    }
+
+    fn get_or_create(&self, id: &str) -> SharedConversation {
+        let mut sessions = self.sessions.lock().unwrap();
+        let slot = sessions.entry(id.to_string()).or_insert_with(|| Slot {
+            conversation: Arc::new(AsyncMutex::new(self.manager(ConversationState::default()))),
+            last_used: Instant::now(),
+        });
+        slot.last_used = Instant::now();
+        slot.conversation.clone()
+    }
+
+    /// Drops sessions unused for longer than the idle TTL from memory; returns
+    /// how many went. Every turn is already persisted to the backend, which
+    /// `lock` reads them back from, so this is a no-op without one. Sessions
+    /// mid-turn or waiting for a turn are kept, and so are those due a
+    /// check-in, which only reaches sessions in memory.
+    pub fn evict_idle(&self) -> usize {
+        let (Some(ttl), Some(_)) = (self.idle_ttl, &self.backend) else {
+            return 0;
+        };
+        let check_in_due = |slot: &Slot| {
+            self.check_in.is_some_and(|policy| slot.conversation.try_lock().is_ok_and(|conversation| conversation.check_in_due(&policy)))
+        };
+        let mut sessions = self.sessions.lock().unwrap();
+        let before = sessions.len();
+        sessions.retain(|_, slot| slot.last_used.elapsed() < ttl || Arc::strong_count(&slot.conversation) > 1 || check_in_due(slot));
+        before - sessions.len()
+    }
+
+    /// Has the backend delete sessions stored too long ago; returns how many went
+    pub async fn expire_stored(&self) -> Result<usize> {
+        match &self.backend {
+            Some((backend, _)) => backend.expire().await,
+            None => Ok(0),
+        }
+    }
+
+    /// Locks a session for a turn, creating it if needed. With a backend the
+    /// state is reloaded first, since another instance may have moved it on.
+    pub async fn lock(&self, id: &str) -> Result<SessionGuard> {
+        let mut guard = self.get_or_create(id).lock_owned().await;
+
+        if let Some((backend, namespace)) = &self.backend
+            && let Some(state) = backend.load(&key(namespace, id)).await?
+        {
+            guard.restore(state);
+        }
+
+        Ok(guard)
+    }
+
+    /// Writes a session back after a turn; a no-op without a backend
+    pub async fn persist(&self, id: &str, conversation: &ConversationManager) -> Result<()> {
+        if let Some((backend, namespace)) = &self.backend {
+            backend.save(&key(namespace, id), conversation.state()).await?;
+        }
+        Ok(())
+    }
+
+    /// Adds a check-in to every session that has been idle long enough after a
+    /// sustained decline; returns how many were added. Sessions mid-turn are skipped.
+    pub async fn check_in_idle(&self, policy: &CheckInPolicy) -> usize {
+        let sessions: Vec<(String, SharedConversation)> = self
+            .sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, slot)| (id.clone(), slot.conversation.clone()))
+            .collect();
+        let now = chrono::Utc::now().timestamp();
+
+        let mut added = 0;
+        for (id, session) in sessions {
+            let Ok(mut conversation) = session.try_lock_owned() else {
+                continue;
+            };
+            if let Some((backend, namespace)) = &self.backend {
+                match backend.load(&key(namespace, &id)).await {
+                    Ok(Some(state)) => conversation.restore(state),
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::warn!(session = %id, error = %e, "skipping check-in, session store unavailable");
+                        continue;
+                    }
+                }
+            }
+
+            let idle = conversation.get_history().last().is_some_and(|m| now - m.timestamp >= policy.idle.as_secs() as i64);
+            if !idle || !conversation.check_in_due(policy) {
+                continue;
+            }
+            conversation.add_check_in();
+            if let Err(e) = self.persist(&id, &conversation).await {
+                tracing::warn!(session = %id, error = %e, "failed to save check-in");
+            }
+            added += 1;
+        }
+        added
+    }
+
+    pub async fn find(&self, id: &str) -> Result<Option<ConversationManager>> {
+        if let Some((backend, namespace)) = &self.backend {
+            let state = backend.load(&key(namespace, id)).await?;
+            return Ok(state.map(|state| self.manager(state)));
+        }
+
+        let session = self.sessions.lock().unwrap().get(id).map(|slot| slot.conversation.clone());
+        match session {
+            Some(session) => {
+                let state = session.lock().await.state().clone();
+                Ok(Some(self.manager(state)))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+fn key(namespace: &str, id: &str) -> String {
+    format!("{}:{}", namespace, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MessageRole;
+
+    #[derive(Default)]
+    struct MemoryBackend {
+        states: Mutex<HashMap<String, ConversationState>>,
+    }
+
+    #[async_trait]
+    impl SessionBackend for MemoryBackend {
+        async fn load(&self, key: &str) -> Result<Option<ConversationState>> {
+            Ok(self.states.lock().unwrap().get(key).cloned())
+        }
+
+        async fn save(&self, key: &str, state: &ConversationState) -> Result<()> {
+            self.states.lock().unwrap().insert(key.to_string(), state.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lock_reuses_session() {
+        let manager = SessionManager::new();
+        assert!(manager.find("a").await.unwrap().is_none());
+
+        manager.lock("a").await.unwrap().add_message(MessageRole::User, "Hello");
+
+        let session = manager.find("a").await.unwrap().unwrap();
+        assert_eq!(session.get_history().len(), 1);
+        assert!(manager.find("b").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_check_in_idle_declining_sessions() {
+        use crate::{Sentiment, SentimentClassification};
+        let manager = SessionManager::new();
+        let policy = CheckInPolicy { declining_turns: 1, idle: std::time::Duration::ZERO };
+
+        let mut session = manager.lock("a").await.unwrap();
+        for sentiment in [Sentiment::Positive, Sentiment::Positive, Sentiment::Positive, Sentiment::Negative] {
+            session.add_message(MessageRole::User, "...");
+            session.update_emotion(SentimentClassification { sentiment, confidence: 0.8, labels: Vec::new(), is_fallback: false });
+            session.add_message(MessageRole::Assistant, "...");
+        }
+        manager.lock("b").await.unwrap().add_message(MessageRole::User, "Hello");
+
+        // Mid-turn sessions are left alone
+        assert_eq!(manager.check_in_idle(&policy).await, 0);
+        drop(session);
+
+        assert_eq!(manager.check_in_idle(&policy).await, 1);
+        assert_eq!(manager.check_in_idle(&policy).await, 0);
+        let history = manager.find("a").await.unwrap().unwrap().get_history().to_vec();
+        assert_eq!(history.last().unwrap().content, crate::state::CHECK_IN_MESSAGE);
+    }
+
+    #[tokio::test]
+    async fn test_backend_shares_sessions_between_instances() {
+        let backend: Arc<dyn SessionBackend> = Arc::new(MemoryBackend::default());
+        let first = SessionManager::with_backend(backend.clone(), "acme");
+        let second = SessionManager::with_backend(backend.clone(), "acme");
+        let other_tenant = SessionManager::with_backend(backend, "other");
+
+        let mut session = first.lock("s").await.unwrap();
+        session.add_message(MessageRole::User, "Hello");
+        first.persist("s", &session).await.unwrap();
+        drop(session);
+
+        // The second instance picks up the turn the first one recorded
+        let mut session = second.lock("s").await.unwrap();
+        assert_eq!(session.get_history().len(), 1);
+        session.add_message(MessageRole::Assistant, "Hi");
+        second.persist("s", &session).await.unwrap();
+        drop(session);
+
+        assert_eq!(first.lock("s").await.unwrap().get_history().len(), 2);
+        assert!(other_tenant.find("s").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_evict_idle_sessions_and_rehydrate() {
+        let ttl = Some(std::time::Duration::ZERO);
+        let in_memory = SessionManager::new().with_idle_ttl(ttl);
+        in_memory.lock("a").await.unwrap().add_message(MessageRole::User, "Hello");
+        // Nowhere to read it back from
+        assert_eq!(in_memory.evict_idle(), 0);
+
+        let manager = SessionManager::with_backend(Arc::new(MemoryBackend::default()), "acme").with_idle_ttl(ttl);
+        let mut session = manager.lock("a").await.unwrap();
+        session.add_message(MessageRole::User, "Hello");
+        manager.persist("a", &session).await.unwrap();
+        manager.lock("b").await.unwrap();
+
+        // "a" is mid-turn
+        assert_eq!(manager.evict_idle(), 1);
+        drop(session);
+        assert_eq!(manager.evict_idle(), 1);
+        assert_eq!(manager.len(), 0);
+
+        assert_eq!(manager.lock("a").await.unwrap().get_history().len(), 1);
+        assert_eq!(manager.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sessions_due_a_check_in_stay_in_memory() {
+        use crate::{Sentiment, SentimentClassification};
+        let policy = CheckInPolicy { declining_turns: 1, idle: std::time::Duration::ZERO };
+        let manager = SessionManager::with_backend(Arc::new(MemoryBackend::default()), "acme")
+            .with_idle_ttl(Some(std::time::Duration::ZERO))
+            .with_check_in(Some(policy));
+
+        let mut session = manager.lock("a").await.unwrap();
+        for sentiment in [Sentiment::Positive, Sentiment::Positive, Sentiment::Positive, Sentiment::Negative] {
+            session.add_message(MessageRole::User, "...");
+            session.update_emotion(SentimentClassification { sentiment, confidence: 0.8, labels: Vec::new(), is_fallback: false });
+            session.add_message(MessageRole::Assistant, "...");
+        }
+        manager.persist("a", &session).await.unwrap();
+        drop(session);
+
+        assert_eq!(manager.evict_idle(), 0);
+        assert_eq!(manager.check_in_idle(&policy).await, 1);
+        // Checked in on, it can go
+        assert_eq!(manager.evict_idle(), 1);
+    }
+}
@@ -0,0 +1,171 @@
+use axum::Json;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::SentimentClassification;
+use crate::handoff::{self, HandoffReason};
+use crate::state::{ConversationManager, ConversationState, EmotionForecast, EmotionTrend};
+use crate::strategy::ResponseStrategy;
+use crate::turn::run_turn;
+use super::error::{ApiError, ErrorBody};
+use super::tenant::TenantContext;
+
+/// Longest history a client may send back in one request
+const MAX_HISTORY_MESSAGES: usize = 500;
+
+/// How `ConversationState` travels over the API: tagged with the format
+/// version it was written in, so older states are upgraded on arrival.
+/// Only describes the JSON for the OpenAPI document.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+struct VersionedState {
+    version: u64,
+    #[serde(flatten)]
+    state: ConversationState,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct StatelessTurnRequest {
+    /// State returned by the previous turn; omit to start a conversation
+    #[serde(default, with = "crate::storage::schema")]
+    #[schema(value_type = VersionedState)]
+    pub state: ConversationState,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StatelessTurnResponse {
+    pub emotion: SentimentClassification,
+    pub trend: EmotionTrend,
+    /// The user's likely sentiment on their next message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forecast: Option<EmotionForecast>,
+    pub strategy: ResponseStrategy,
+    /// Strategy the selection wanted while the previous one was still held
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppressed_strategy: Option<ResponseStrategy>,
+    /// Set while the conversation waits for an operator; `response` is then a fixed notice
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handoff: Option<HandoffReason>,
+    /// Set when the message asked about a blocked topic; `response` is then its refusal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked_topic: Option<String>,
+    /// The reply suggests a break because the session has run long
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub break_suggested: bool,
+    /// What made the message read like instructions to the model, which the reply treated as conversation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub injection: Option<String>,
+    pub response: String,
+    /// Send this back with the next turn
+    #[serde(with = "crate::storage::schema")]
+    #[schema(value_type = VersionedState)]
+    pub state: ConversationState,
+}
+
+#[utoipa::path(
+    post,
+    path = "/turns",
+    request_body = StatelessTurnRequest,
+    responses(
+        (status = 200, description = "Assistant reply plus the updated conversation state", body = StatelessTurnResponse),
+        (status = 400, description = "Empty text", body = ErrorBody),
+        (status = 401, description = "Missing or invalid API key", body = ErrorBody),
+        (status = 403, description = "API key is bound to another tenant", body = ErrorBody),
+        (status = 404, description = "Unknown tenant", body = ErrorBody),
+        (status = 413, description = "History exceeds the limit", body = ErrorBody),
+        (status = 429, description = "Provider rate limit hit", body = ErrorBody),
+        (status = 502, description = "Provider call failed", body = ErrorBody),
+        (status = 504, description = "Provider call timed out", body = ErrorBody),
+    ),
+    tag = "chat"
+)]
+pub async fn post_turn(
+    TenantContext(tenant): TenantContext,
+    Json(request): Json<StatelessTurnRequest>,
+) -> Result<Json<StatelessTurnResponse>, ApiError> {
+    if request.text.trim().is_empty() {
+        return Err(ApiError::bad_request("text must not be empty"));
+    }
+    if request.state.messages.len() > MAX_HISTORY_MESSAGES {
+        return Err(ApiError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("history of {} messages exceeds the limit of {}", request.state.messages.len(), MAX_HISTORY_MESSAGES),
+        ));
+    }
+
+    let mut conversation = ConversationManager::from_state(request.state);
+    if conversation.handoff().is_none()
+        && let Some(reason) = handoff::from_history(conversation.get_history())
+    {
+        conversation.pause_for_handoff(reason);
+    }
+    conversation.set_events(tenant.events.clone());
+    let outcome = run_turn(&tenant.detector, &tenant.chat_agent, &tenant.topics, &mut conversation, &request.text, tenant.strategy, &tenant.pipeline)
+        .await
+        .map_err(ApiError::turn_failed)?;
+    if outcome.escalated
+        && let Some(reason) = outcome.handoff
+    {
+        tenant.escalate(None, reason, &conversation).await;
+    }
+
+    Ok(Json(StatelessTurnResponse {
+        emotion: outcome.emotion,
+        trend: outcome.trend,
+        forecast: outcome.forecast,
+        strategy: outcome.strategy,
+        suppressed_strategy: outcome.suppressed,
+        handoff: outcome.handoff,
+        blocked_topic: outcome.blocked_topic,
+        break_suggested: outcome.break_suggested,
+        injection: outcome.injection,
+        response: outcome.response,
+        state: conversation.into_state(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+    use crate::server::app::test_state;
+    use crate::server::router;
+
+    async fn post_turn(body: String) -> StatusCode {
+        let request = Request::post("/turns")
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap();
+
+        router(test_state(4, None)).oneshot(request).await.unwrap().status()
+    }
+
+    #[test]
+    fn test_state_is_optional() {
+        let request: StatelessTurnRequest = serde_json::from_str(r#"{"text": "hi"}"#).unwrap();
+        assert!(request.state.messages.is_empty());
+
+        let request: StatelessTurnRequest = serde_json::from_str(
+            r#"{"text": "hi", "state": {"messages": [{"role": "User", "content": "Hello", "timestamp": 1}]}}"#,
+        ).unwrap();
+        assert_eq!(request.state.messages.len(), 1);
+        assert!(request.state.emotion_history.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_empty_text() {
+        assert_eq!(post_turn(r#"{"text": " "}"#.to_string()).await, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_oversized_history() {
+        let message = r#"{"role": "User", "content": "hi", "timestamp": 1}"#;
+        let messages = vec![message; MAX_HISTORY_MESSAGES + 1].join(",");
+        let body = format!(r#"{{"text": "hi", "state": {{"messages": [{}]}}}}"#, messages);
+
+        assert_eq!(post_turn(body).await, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,198 @@
+use axum::Json;
+use axum::extract::Path;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::{Stream, StreamExt};
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use crate::agents::ReplyChunk;
+use crate::agents::chat::Reply;
+use crate::turn::{Prepared, TurnOutcome, prepare_turn};
+use super::chat::{TurnRequest, TurnResponse, session_store_error};
+use super::error::{ApiError, ErrorBody};
+use super::live::{LiveEvent, TYPING_INTERVAL};
+use super::sessions::SessionGuard;
+use super::tenant::{Tenant, TenantContext};
+
+#[utoipa::path(
+    post,
+    path = "/sessions/{session_id}/messages/stream",
+    params(("session_id" = String, Path, description = "Client-chosen session identifier")),
+    request_body = TurnRequest,
+    responses(
+        (status = 200, description = "Server-sent events: `typing` until the first `token`, the reply as `token` events, \
+            then a `turn` event with the emotion, strategy and usage. Failures after the stream starts arrive as an `error` event.",
+            content_type = "text/event-stream", body = TurnResponse),
+        (status = 400, description = "Empty text", body = ErrorBody),
+        (status = 401, description = "Missing or invalid API key", body = ErrorBody),
+        (status = 403, description = "API key is bound to another tenant", body = ErrorBody),
+        (status = 404, description = "Unknown tenant", body = ErrorBody),
+        (status = 503, description = "Session store unavailable", body = ErrorBody),
+    ),
+    tag = "chat"
+)]
+pub async fn stream_message(
+    TenantContext(tenant): TenantContext,
+    Path(session_id): Path<String>,
+    Json(request): Json<TurnRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    if request.text.trim().is_empty() {
+        return Err(ApiError::bad_request("text must not be empty"));
+    }
+    let mut state = tenant.sessions.lock(&session_id).await.map_err(session_store_error)?;
+    state.set_events(tenant.events.clone());
+
+    let (tx, rx) = mpsc::channel(32);
+    // Holding the session lock for the whole turn keeps turns within a session ordered
+    tokio::spawn(async move {
+        if let Err(e) = turn(&tx, &tenant, &session_id, state, &request.text).await {
+            send(&tx, LiveEvent::Error { status: e.status.as_u16(), error: e.message }).await;
+        }
+    });
+
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event: LiveEvent| (Ok(sse_event(&event)), rx))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn turn(
+    tx: &mpsc::Sender<LiveEvent>,
+    tenant: &Arc<Tenant>,
+    session_id: &str,
+    mut state: SessionGuard,
+    text: &str,
+) -> Result<(), ApiError> {
+    send(tx, LiveEvent::Typing).await;
+    let prepared = typing(tx, prepare_turn(&tenant.detector, &tenant.topics, &mut state, text, tenant.strategy, &tenant.pipeline))
+        .await
+        .map_err(ApiError::turn_failed)?;
+
+    let outcome = match prepared {
+        Prepared::Done(outcome) => {
+            send(tx, LiveEvent::Token { text: outcome.response.clone() }).await;
+            *outcome
+        }
+        Prepared::Reply(pending) => {
+            let live = !pending.rewrites_reply();
+            let mut stream = typing(tx, tenant.chat_agent.respond_stream(pending.input(), pending.blend(), state.get_history(), pending.aside().as_deref())).await?;
+            let (mut response, mut usage, mut score) = (String::new(), None, None);
+            loop {
+                // Typing continues until the first token the user sees
+                let chunk = if !live || response.is_empty() { typing(tx, stream.next()).await } else { stream.next().await };
+                match chunk {
+                    Some(Ok(ReplyChunk::Text(token))) => {
+                        response.push_str(&token);
+                        if live {
+                            send(tx, LiveEvent::Token { text: token }).await;
+                        }
+                    }
+                    Some(Ok(ReplyChunk::Usage(reported))) => usage = Some(reported),
+                    Some(Ok(ReplyChunk::Score(rated))) => score = Some(rated),
+                    Some(Err(e)) => return Err(e.into()),
+                    None => break,
+                }
+            }
+            let outcome = pending.finish(&mut state, &tenant.chat_agent, Reply { text: response, usage, alternatives: Vec::new(), score })
+                .await
+                .map_err(ApiError::turn_failed)?;
+            if !live {
+                send(tx, LiveEvent::Token { text: outcome.response.clone() }).await;
+            }
+            outcome
+        }
+    };
+
+    finish(tx, tenant, session_id, &state, outcome).await
+}
+
+async fn finish(
+    tx: &mpsc::Sender<LiveEvent>,
+    tenant: &Tenant,
+    session_id: &str,
+    state: &SessionGuard,
+    outcome: TurnOutcome,
+) -> Result<(), ApiError> {
+    tenant.sessions.persist(session_id, state).await.map_err(session_store_error)?;
+    if outcome.escalated
+        && let Some(reason) = outcome.handoff
+    {
+        tenant.escalate(Some(session_id), reason, state).await;
+    }
+    send(tx, LiveEvent::Turn(TurnResponse::from(outcome))).await;
+    Ok(())
+}
+
+/// Awaits `work`, sending `typing` every few seconds until it is done
+async fn typing<F: Future>(tx: &mpsc::Sender<LiveEvent>, work: F) -> F::Output {
+    tokio::pin!(work);
+    let start = tokio::time::Instant::now() + TYPING_INTERVAL;
+    let mut ticks = tokio::time::interval_at(start, TYPING_INTERVAL);
+    loop {
+        tokio::select! {
+            output = &mut work => return output,
+            _ = ticks.tick() => send(tx, LiveEvent::Typing).await,
+        }
+    }
+}
+
+// A closed channel means the client went away; the turn still completes and is saved
+async fn send(tx: &mpsc::Sender<LiveEvent>, event: LiveEvent) {
+    let _ = tx.send(event).await;
+}
+
+fn sse_event(event: &LiveEvent) -> Event {
+    let json = serde_json::to_string(event).expect("live events always serialize");
+    Event::default().event(event.kind()).data(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::Router;
+    use axum::body::{Body, to_bytes};
+    use axum::http::{Request, StatusCode};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use crate::Config;
+    use super::super::app::{AppState, router};
+    use super::super::tenant::{Tenant, Tenants};
+
+    fn app() -> Router {
+        let tenant = Tenant::default_for(&Config { dry_run: true, ..Config::test() });
+        router(AppState { tenants: Arc::new(Tenants::single(tenant)), max_batch: 8, api_keys: None, pacing: None })
+    }
+
+    fn request(text: &str) -> Request<Body> {
+        Request::post("/sessions/sse-1/messages/stream")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::json!({ "text": text }).to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stream_sends_typing_tokens_then_turn() {
+        let response = app().oneshot(request("I had a good day")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let events: Vec<&str> = body.lines().filter_map(|line| line.strip_prefix("event: ")).collect();
+        assert_eq!(events.first(), Some(&"typing"));
+        assert!(events.contains(&"token"));
+        assert_eq!(events.last(), Some(&"turn"));
+
+        let turn = body.lines().filter_map(|line| line.strip_prefix("data: ")).next_back().unwrap();
+        let turn: serde_json::Value = serde_json::from_str(turn).unwrap();
+        assert!(turn["response"].as_str().unwrap().starts_with("[dry run]"));
+        assert!(turn["emotion"].is_object());
+        assert!(turn["strategy"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_stream_rejects_empty_text_before_streaming() {
+        let response = app().oneshot(request("  ")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
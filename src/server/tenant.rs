@@ -0,0 +1,433 @@
+use anyhow::{Context, Result};
+use axum::extract::FromRequestParts;
+use axum::http::StatusCode;
+use axum::http::request::Parts;
+use rig::providers::openai;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use crate::Config;
+use crate::agents::{BlockedTopic, ChatAgent, CircuitBreaker, Degradation, EmotionDetector, LengthPreference, TaxonomyClassifier, TopicGuard};
+use crate::handoff::{HandoffBackend, HandoffReason, HandoffRequest};
+use crate::events::{Event, EventBus};
+use crate::health::check_provider;
+use crate::state::ConversationManager;
+use crate::strategy::ResponseStrategy;
+use crate::turn::{Pipeline, StrategyPolicy};
+use super::app::AppState;
+use super::auth::AuthenticatedKey;
+use super::error::ApiError;
+use super::metrics::TurnMetrics;
+use super::disk::DiskSessions;
+use super::sessions::{SessionBackend, SessionManager};
+
+pub const TENANT_HEADER: &str = "x-tenant";
+pub const DEFAULT_TENANT: &str = "default";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TenantConfig {
+    pub id: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub strategy_prompts: HashMap<ResponseStrategy, String>,
+    /// Replaces `STRATEGY_MODELS` for this tenant
+    #[serde(default)]
+    pub strategy_models: Option<HashMap<ResponseStrategy, String>>,
+    /// Replaces the globally configured blocked topics for this tenant
+    #[serde(default)]
+    pub blocked_topics: Option<Vec<BlockedTopic>>,
+}
+
+pub struct Tenant {
+    pub id: String,
+    pub model: String,
+    pub detector: EmotionDetector,
+    pub chat_agent: ChatAgent,
+    pub topics: TopicGuard,
+    /// Classifies against `TAXONOMY_FILE` when one is configured
+    pub categorizer: Option<TaxonomyClassifier>,
+    pub sessions: SessionManager,
+    pub breaker: Arc<CircuitBreaker>,
+    /// Fallbacks, failed replies and retries across the tenant's agents
+    pub degradation: Arc<Degradation>,
+    pub strategy: StrategyPolicy,
+    pub handoff: Option<Arc<dyn HandoffBackend>>,
+    pub pipeline: Pipeline,
+    pub metrics: Arc<TurnMetrics>,
+    /// Attached to a session's conversation when it takes a turn
+    pub events: EventBus,
+    client: openai::Client,
+    base_url: String,
+}
+
+impl Tenant {
+    pub fn new(config: &Config, tenant: TenantConfig) -> Self {
+        let api_key = tenant.api_key.as_deref().unwrap_or(&config.api_key);
+        let base_url = tenant.base_url.as_deref().unwrap_or(&config.base_url);
+        let model = tenant.model.unwrap_or_else(|| config.model.clone());
+        // The global routing names models of the global provider
+        let strategy_models = match (tenant.strategy_models, &tenant.base_url) {
+            (Some(models), _) => models,
+            (None, None) => config.strategy_models.clone(),
+            (None, Some(_)) => HashMap::new(),
+        };
+
+        let client = openai::Client::from_url(api_key, base_url);
+        let breaker = Arc::new(CircuitBreaker::default());
+        let degradation = Arc::new(Degradation::default());
+        let metrics = Arc::new(TurnMetrics::default());
+        let recorder = metrics.clone();
+        let events = EventBus::default().with(move |event: &Event| recorder.record(event));
+        let sessions = idle_sessions(config, &tenant.id)
+            .with_retention(config.retention)
+            .with_trend_analyzer(config.trend_analyzer.clone())
+            .with_idle_ttl(config.session_idle_ttl)
+            .with_check_in(config.check_in);
+        let cache = config.classification_cache(Some(&tenant.id), base_url);
+        Self {
+            id: tenant.id,
+            detector: EmotionDetector::new(client.clone(), &model)
+                .with_breaker(breaker.clone())
+                .with_dry_run(config.dry_run)
+                .with_aspect_sentiment(config.aspect_sentiment)
+                .with_cause_extraction(config.emotion_causes)
+                .with_repair_attempts(config.repair_attempts)
+                .with_self_consistency(config.self_consistency)
+                .with_fallback(config.fallback)
+                .with_examples(config.emotion_examples.clone())
+                .with_cache(cache)
+                .with_degradation(degradation.clone()),
+            chat_agent: ChatAgent::new(client.clone(), &model)
+                .with_endpoint(api_key, base_url)
+                .with_strategy_prompts(tenant.strategy_prompts)
+                .with_breaker(breaker.clone())
+                .with_dry_run(config.dry_run)
+                .with_wellbeing_safe(config.wellbeing_safe)
+                .with_minor_safe(config.minor_safe)
+                .with_profanity_filter(config.profanity)
+                .with_reply_scoring(config.score_replies)
+                .with_generation(config.generation.clone())
+                .with_strategy_models(strategy_models)
+                .with_length(LengthPreference::new(config.response_length))
+                .with_context_window(config.context_window)
+                .with_history_dedup(config.history_dedup(&client))
+                .with_degradation(degradation.clone()),
+            topics: TopicGuard::new(client.clone(), &model, config.blocked_topics(tenant.blocked_topics))
+                .with_dry_run(config.dry_run),
+            categorizer: config.taxonomy.clone().map(|taxonomy| {
+                TaxonomyClassifier::new(client.clone(), &model, taxonomy)
+                    .with_dry_run(config.dry_run)
+                    .with_repair_attempts(config.repair_attempts)
+            }),
+            sessions,
+            breaker,
+            degradation,
+            strategy: config.strategy,
+            handoff: config.handoff_backend(),
+            pipeline: config.pipeline.clone(),
+            metrics,
+            events,
+            client,
+            base_url: base_url.to_string(),
+            model,
+        }
+    }
+
+    /// Notifies the operator; a failure is logged rather than failing the turn
+    pub async fn escalate(&self, session_id: Option<&str>, reason: HandoffReason, state: &ConversationManager) {
+        let Some(backend) = &self.handoff else {
+            return;
+        };
+        let request = HandoffRequest::new(session_id.map(str::to_string), reason, state).with_tenant(&self.id);
+        if let Err(e) = backend.escalate(&request).await {
+            tracing::error!(tenant = %self.id, session = ?session_id, error = %format!("{:#}", e), "handoff failed");
+        }
+    }
+
+    pub async fn check_health(&self) -> Result<()> {
+        check_provider(&self.client, &self.base_url, &self.model)
+            .await
+            .with_context(|| format!("tenant '{}'", self.id))
+    }
+
+    pub fn default_for(config: &Config) -> Self {
+        Self::new(config, TenantConfig {
+            id: DEFAULT_TENANT.to_string(),
+            api_key: None,
+            base_url: None,
+            model: None,
+            strategy_prompts: HashMap::new(),
+            strategy_models: None,
+            blocked_topics: None,
+        })
+    }
+
+    pub fn with_session_backend(mut self, backend: Arc<dyn SessionBackend>) -> Self {
+        self.sessions = SessionManager::with_backend(backend, &self.id)
+            .with_retention(self.sessions.retention())
+            .with_trend_analyzer(self.sessions.trend_analyzer())
+            .with_idle_ttl(self.sessions.idle_ttl())
+            .with_check_in(self.sessions.check_in());
+        self
+    }
+
+    /// Keeps sessions in memory only, even with `SESSION_IDLE_TTL` set
+    pub fn with_memory_sessions(mut self) -> Self {
+        self.sessions = SessionManager::new()
+            .with_retention(self.sessions.retention())
+            .with_trend_analyzer(self.sessions.trend_analyzer())
+            .with_check_in(self.sessions.check_in());
+        self
+    }
+}
+
+/// Sessions that can leave memory once idle are written to `SESSIONS_DIR/live`
+/// after each turn, unless a shared backend replaces it, and deleted from
+/// there once they have gone unused for the live session expiry
+fn idle_sessions(config: &Config, tenant: &str) -> SessionManager {
+    if config.session_idle_ttl.is_none() {
+        return SessionManager::new();
+    }
+    let live = DiskSessions::new(config.sessions_dir.join("live")).with_expiry(config.live_session_expiry);
+    SessionManager::with_backend(Arc::new(live), tenant)
+}
+
+pub struct Tenants {
+    default: Arc<Tenant>,
+    tenants: HashMap<String, Arc<Tenant>>,
+}
+
+impl Tenants {
+    pub fn single(default: Tenant) -> Self {
+        Self { default: Arc::new(default), tenants: HashMap::new() }
+    }
+
+    pub fn from_configs(
+        config: &Config,
+        configs: Vec<TenantConfig>,
+        backend: Option<Arc<dyn SessionBackend>>,
+    ) -> Result<Self> {
+        let with_backend = |tenant: Tenant| match &backend {
+            Some(backend) => tenant.with_session_backend(backend.clone()),
+            None => tenant,
+        };
+        let mut tenants = HashMap::new();
+
+        for tenant in configs {
+            if tenant.id == DEFAULT_TENANT {
+                anyhow::bail!("tenant id '{}' is reserved", DEFAULT_TENANT);
+            }
+            let id = tenant.id.clone();
+            let tenant = with_backend(Tenant::new(config, tenant));
+            if tenants.insert(id.clone(), Arc::new(tenant)).is_some() {
+                anyhow::bail!("duplicate tenant id '{}'", id);
+            }
+        }
+
+        let default = with_backend(Tenant::default_for(config));
+        Ok(Self { default: Arc::new(default), tenants })
+    }
+
+    pub fn load(path: &Path, config: &Config, backend: Option<Arc<dyn SessionBackend>>) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let configs: Vec<TenantConfig> = serde_json::from_str(&json)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+
+        Self::from_configs(config, configs, backend)
+    }
+
+    pub fn resolve(&self, id: Option<&str>) -> Option<Arc<Tenant>> {
+        match id {
+            None | Some(DEFAULT_TENANT) => Some(self.default.clone()),
+            Some(id) => self.tenants.get(id).cloned(),
+        }
+    }
+
+    /// The tenant a request reaches. With auth enabled that is always its
+    /// key's tenant, the default one for an unbound key, and `x-tenant` may only
+    /// repeat it; only without auth does `x-tenant` pick the tenant
+    pub fn for_request(&self, key: Option<&AuthenticatedKey>, requested: Option<&str>) -> Result<Arc<Tenant>, TenantRejection> {
+        let id = match key {
+            Some(key) => {
+                let bound = key.tenant.as_deref().unwrap_or(DEFAULT_TENANT);
+                if let Some(requested) = requested
+                    && requested != bound
+                {
+                    return Err(TenantRejection::Forbidden(requested.to_string()));
+                }
+                Some(bound)
+            }
+            None => requested,
+        };
+        self.resolve(id).ok_or_else(|| TenantRejection::Unknown(id.unwrap_or_default().to_string()))
+    }
+
+    /// The default tenant followed by the configured ones
+    pub fn all(&self) -> impl Iterator<Item = &Arc<Tenant>> {
+        std::iter::once(&self.default).chain(self.tenants.values())
+    }
+
+    pub fn ids(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = self.tenants.keys().map(String::as_str).collect();
+        ids.sort();
+        ids
+    }
+}
+
+/// Why a request was not given the tenant it named
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TenantRejection {
+    /// The API key belongs to another tenant
+    Forbidden(String),
+    Unknown(String),
+}
+
+pub struct TenantContext(pub Arc<Tenant>);
+
+impl FromRequestParts<AppState> for TenantContext {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, ApiError> {
+        let key = parts.extensions.get::<AuthenticatedKey>();
+        let requested = parts.headers.get(TENANT_HEADER).and_then(|v| v.to_str().ok());
+
+        state.tenants.for_request(key, requested).map(TenantContext).map_err(|e| match e {
+            TenantRejection::Forbidden(id) => {
+                ApiError::new(StatusCode::FORBIDDEN, format!("API key is not valid for tenant '{}'", id))
+            }
+            TenantRejection::Unknown(id) => ApiError::new(StatusCode::NOT_FOUND, format!("unknown tenant '{}'", id)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config { model: "global-model".to_string(), ..Config::test() }
+    }
+
+    #[test]
+    fn test_tenant_config_parsing() {
+        let configs: Vec<TenantConfig> = serde_json::from_str(r#"[
+            {"id": "acme", "model": "small", "strategy_prompts": {"Empathetic": "Be kind"}}
+        ]"#).unwrap();
+
+        assert_eq!(configs[0].model.as_deref(), Some("small"));
+        assert_eq!(configs[0].strategy_prompts[&ResponseStrategy::Empathetic], "Be kind");
+        assert_eq!(configs[0].blocked_topics, None);
+    }
+
+    #[test]
+    fn test_resolve_tenants() {
+        let tenants = Tenants::from_configs(&config(), vec![TenantConfig {
+            id: "acme".to_string(),
+            api_key: Some("acme-key".to_string()),
+            base_url: None,
+            model: Some("acme-model".to_string()),
+            strategy_prompts: HashMap::new(),
+            strategy_models: None,
+            blocked_topics: None,
+        }], None).unwrap();
+
+        assert_eq!(tenants.resolve(None).unwrap().model, "global-model");
+        assert_eq!(tenants.resolve(Some("acme")).unwrap().model, "acme-model");
+        assert!(tenants.resolve(Some("other")).is_none());
+        assert_eq!(tenants.ids(), vec!["acme"]);
+    }
+
+    #[test]
+    fn test_header_only_picks_the_tenant_without_auth() {
+        let tenants = Tenants::from_configs(&config(), vec![TenantConfig {
+            id: "acme".to_string(),
+            api_key: None,
+            base_url: None,
+            model: Some("acme-model".to_string()),
+            strategy_prompts: HashMap::new(),
+            strategy_models: None,
+            blocked_topics: None,
+        }], None).unwrap();
+        let unbound = AuthenticatedKey { key: "open".to_string(), tenant: None, operator: false };
+
+        assert_eq!(tenants.for_request(None, Some("acme")).unwrap().model, "acme-model");
+        assert_eq!(tenants.for_request(None, Some("other")).err(), Some(TenantRejection::Unknown("other".to_string())));
+        // An unbound key stays on the default tenant whatever the header says
+        assert_eq!(tenants.for_request(Some(&unbound), None).unwrap().model, "global-model");
+        assert_eq!(tenants.for_request(Some(&unbound), Some("acme")).err(), Some(TenantRejection::Forbidden("acme".to_string())));
+        assert_eq!(tenants.for_request(Some(&unbound), Some(DEFAULT_TENANT)).unwrap().model, "global-model");
+    }
+
+    #[test]
+    fn test_duplicate_and_reserved_ids_rejected() {
+        let tenant = |id: &str| TenantConfig {
+            id: id.to_string(),
+            api_key: None,
+            base_url: None,
+            model: None,
+            strategy_prompts: HashMap::new(),
+            strategy_models: None,
+            blocked_topics: None,
+        };
+
+        assert!(Tenants::from_configs(&config(), vec![tenant("a"), tenant("a")], None).is_err());
+        assert!(Tenants::from_configs(&config(), vec![tenant(DEFAULT_TENANT)], None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_router_tenant_resolution() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use tower::ServiceExt;
+        use crate::server::auth::{ApiKeyConfig, ApiKeys};
+        use crate::server::router;
+
+        let tenants = Tenants::from_configs(&config(), vec![TenantConfig {
+            id: "acme".to_string(),
+            api_key: None,
+            base_url: None,
+            model: None,
+            strategy_prompts: HashMap::new(),
+            strategy_models: None,
+            blocked_topics: None,
+        }], None).unwrap();
+        let keys = ApiKeys::new(vec![ApiKeyConfig {
+            key: "acme-key".to_string(),
+            name: "acme".to_string(),
+            requests_per_minute: None,
+            daily_quota: None,
+            tenant: Some("acme".to_string()),
+            operator: false,
+        }]);
+        let app = router(AppState {
+            tenants: Arc::new(tenants),
+            max_batch: 4,
+            api_keys: Some(Arc::new(keys)),
+            pacing: None,
+        });
+
+        let request = |tenant: &str| {
+            Request::get("/sessions/missing")
+                .header("x-api-key", "acme-key")
+                .header(TENANT_HEADER, tenant)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("other")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.clone().oneshot(request(DEFAULT_TENANT)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // The bound tenant resolves, and the missing session is reported as such
+        let response = app.oneshot(request("acme")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
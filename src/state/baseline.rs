@@ -0,0 +1,92 @@
+use crate::models::{Message, MessageRole};
+use crate::{Sentiment, SentimentClassification};
+
+/// Classified messages needed before a baseline is trusted
+const MIN_SAMPLES: usize = 20;
+
+/// How far a score must sit from the baseline to count as non-neutral
+const DEVIATION: f32 = 0.5;
+
+/// The sentiment a user usually writes with, from their past messages
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct EmotionBaseline {
+    /// Mean sentiment score, from -1 (always negative) to 1 (always positive)
+    pub mean: f32,
+    pub samples: usize,
+}
+
+impl EmotionBaseline {
+    /// `None` until the user has written enough classified messages
+    pub fn from_messages<'a>(messages: impl IntoIterator<Item = &'a Message>) -> Option<Self> {
+        let scores: Vec<f32> = messages
+            .into_iter()
+            .filter(|m| matches!(m.role, MessageRole::User))
+            .filter_map(|m| m.emotion.as_ref())
+            .map(SentimentClassification::score)
+            .collect();
+        if scores.len() < MIN_SAMPLES {
+            return None;
+        }
+        let mean = scores.iter().sum::<f32>() / scores.len() as f32;
+        Some(Self { mean, samples: scores.len() })
+    }
+
+    /// `emotion` read against the baseline: a sentiment that is ordinary for
+    /// this user counts as Neutral. Never flips Negative to Positive or back.
+    pub fn calibrate(&self, emotion: &SentimentClassification) -> SentimentClassification {
+        let relative = emotion.sentiment.score() as f32 - self.mean;
+        let usual = match emotion.sentiment {
+            Sentiment::Negative => relative > -DEVIATION,
+            Sentiment::Positive => relative < DEVIATION,
+            Sentiment::Neutral => false,
+        };
+        if !usual {
+            return emotion.clone();
+        }
+        SentimentClassification { sentiment: Sentiment::Neutral, confidence: emotion.confidence, labels: Vec::new(), is_fallback: false }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(negative: usize, neutral: usize) -> Vec<Message> {
+        let message = |sentiment| Message {
+            role: MessageRole::User,
+            content: String::new(),
+            timestamp: 0,
+            emotion: Some(SentimentClassification { sentiment, confidence: 0.8, labels: Vec::new(), is_fallback: false }),
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
+        };
+        let mut messages: Vec<Message> = (0..negative).map(|_| message(Sentiment::Negative)).collect();
+        messages.extend((0..neutral).map(|_| message(Sentiment::Neutral)));
+        messages
+    }
+
+    #[test]
+    fn test_needs_enough_samples() {
+        assert_eq!(EmotionBaseline::from_messages(&messages(5, 5)), None);
+
+        let baseline = EmotionBaseline::from_messages(&messages(15, 5)).unwrap();
+        assert_eq!(baseline.samples, 20);
+        assert!((baseline.mean + 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_habitual_negativity_reads_as_neutral() {
+        let negative = SentimentClassification { sentiment: Sentiment::Negative, confidence: 0.7, labels: Vec::new(), is_fallback: false };
+        let positive = SentimentClassification { sentiment: Sentiment::Positive, confidence: 0.7, labels: Vec::new(), is_fallback: false };
+
+        let grumpy = EmotionBaseline { mean: -0.75, samples: 20 };
+        assert_eq!(grumpy.calibrate(&negative).sentiment, Sentiment::Neutral);
+        assert_eq!(grumpy.calibrate(&positive).sentiment, Sentiment::Positive);
+
+        let balanced = EmotionBaseline { mean: -0.2, samples: 20 };
+        assert_eq!(balanced.calibrate(&negative).sentiment, Sentiment::Negative);
+    }
+}
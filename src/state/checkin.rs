@@ -0,0 +1,14 @@
+use std::time::Duration;
+
+/// Sent by the assistant, unprompted, when a declining user goes quiet
+pub const CHECK_IN_MESSAGE: &str = "Just checking in. The last few messages sounded like things have been getting \
+     harder. There's no rush, and I'm here whenever you want to talk.";
+
+/// When the assistant checks in on a user whose mood keeps declining
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckInPolicy {
+    /// Consecutive turns the trend must have been Declining
+    pub declining_turns: usize,
+    /// How long the user must have been quiet since the last reply
+    pub idle: Duration,
+}
@@ -1,13 +1,46 @@
-use crate::models::{Message, MessageRole};
-use crate::SentimentClassification;
-
-#[derive(Debug, Clone)]
+use crate::events::{Event, EventBus};
+use crate::models::{Message, MessageRole, ReplyScore};
+use crate::{AspectSentiment, SentimentClassification};
+use crate::strategy::ResponseStrategy;
+use std::sync::Arc;
+use std::time::Duration;
+use super::limits::{BREAK_GAP, REMIND_EVERY_TURNS};
+use super::{CHECK_IN_MESSAGE, CheckInPolicy, ConfidenceBySentiment, ConversationMood, EarlierMessages, EmotionBaseline, EmotionCounts, EmotionForecast, Eviction, RetentionPolicy, SessionLimits, StrategyUsage, TrendAnalyzer, TrendReading, UserProfile, forecast, mood, stats};
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 pub struct ConversationState {
+    #[serde(default)]
     pub messages: Vec<Message>,
+    #[serde(default)]
     pub emotion_history: Vec<SentimentClassification>,
+    /// Set while the conversation waits for a human operator
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handoff: Option<HandoffReason>,
+    /// Unix time of the first message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<i64>,
+    /// User turn count when the assistant last suggested a break
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub break_suggested_at: Option<usize>,
+    /// Messages evicted under a summarizing retention policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub earlier: Option<EarlierMessages>,
+    /// What the user shared during onboarding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<UserProfile>,
+}
+
+/// Why a conversation was handed to a human operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandoffReason {
+    /// The user wrote something suggesting they may be in danger
+    Crisis,
+    /// Several confidently negative turns in a row
+    SustainedNegativity,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 pub enum EmotionTrend {
     Improving,
     Declining,
@@ -16,79 +49,318 @@ pub enum EmotionTrend {
 
 pub struct ConversationManager {
     state: ConversationState,
+    baseline: Option<EmotionBaseline>,
+    events: EventBus,
+    retention: RetentionPolicy,
+    analyzer: Arc<dyn TrendAnalyzer>,
 }
 
 impl ConversationManager {
     pub fn new() -> Self {
+        Self::from_state(ConversationState::default())
+    }
+
+    pub fn from_state(state: ConversationState) -> Self {
         Self {
-            state: ConversationState {
-                messages: Vec::new(),
-                emotion_history: Vec::new(),
-            },
+            state,
+            baseline: None,
+            events: EventBus::default(),
+            retention: RetentionPolicy::default(),
+            analyzer: Arc::new(super::trend::Heuristic),
         }
     }
 
+    /// Trends are read by `analyzer` instead of the default heuristic
+    pub fn with_trend_analyzer(mut self, analyzer: Arc<dyn TrendAnalyzer>) -> Self {
+        self.analyzer = analyzer;
+        self
+    }
+
+    /// Keeps the conversation within `retention` from here on, evicting
+    /// whatever the state already holds beyond it
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self.enforce_retention();
+        self
+    }
+
+    pub fn retention(&self) -> RetentionPolicy {
+        self.retention
+    }
+
+    /// Changes from here on are published to `events`
+    pub fn set_events(&mut self, events: EventBus) {
+        self.events = events;
+    }
+
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Strategies are chosen from emotions read against `baseline`
+    pub fn set_baseline(&mut self, baseline: Option<EmotionBaseline>) {
+        self.baseline = baseline;
+    }
+
+    pub fn baseline(&self) -> Option<EmotionBaseline> {
+        self.baseline
+    }
+
+    pub fn state(&self) -> &ConversationState {
+        &self.state
+    }
+
+    pub fn into_state(self) -> ConversationState {
+        self.state
+    }
+
+    /// A copy of the conversation for a storage backend or API layer to keep
+    pub fn snapshot(&self) -> ConversationState {
+        self.state.clone()
+    }
+
+    /// Picks up a conversation from a snapshot, keeping this manager's
+    /// events, baseline, retention and trend analyzer
+    pub fn restore(&mut self, state: ConversationState) {
+        self.state = state;
+        self.enforce_retention();
+    }
+
     pub fn add_message(&mut self, role: MessageRole, content: &str) {
         let msg = Message {
-            role,
+            role: role.clone(),
             content: content.to_string(),
             timestamp: chrono::Utc::now().timestamp(),
             emotion: None,
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
         };
+        self.state.started_at.get_or_insert(msg.timestamp);
         self.state.messages.push(msg);
+        self.events.publish(Event::MessageAdded { role, content: content.to_string() });
+        self.enforce_retention();
     }
 
     pub fn update_emotion(&mut self, emotion: SentimentClassification) {
         // Attach emotion to last user message first
-        if let Some(msg) = self.state.messages.last_mut() {
-            if matches!(msg.role, MessageRole::User) {
-                msg.emotion = Some(emotion.clone());
-            }
+        if let Some(msg) = self.state.messages.last_mut()
+            && matches!(msg.role, MessageRole::User)
+        {
+            msg.emotion = Some(emotion.clone());
         }
 
         // Then add to history
-        self.state.emotion_history.push(emotion);
+        let before = self.get_recent_emotion_trend().direction;
+        self.state.emotion_history.push(emotion.clone());
+        self.enforce_retention();
+        self.events.publish(Event::EmotionUpdated { emotion });
+        let after = self.get_recent_emotion_trend().direction;
+        if after != before {
+            self.events.publish(Event::TrendChanged { from: before, to: after });
+        }
     }
 
-    pub fn get_recent_emotion_trend(&self) -> EmotionTrend {
-        use crate::Sentiment;
+    /// Attaches what the last user message felt about each thing it mentions
+    pub fn update_aspects(&mut self, aspects: Vec<AspectSentiment>) {
+        if let Some(msg) = self.state.messages.last_mut()
+            && matches!(msg.role, MessageRole::User)
+        {
+            msg.aspects = aspects;
+        }
+    }
 
-        let recent = self.state.emotion_history.iter().rev().take(5).collect::<Vec<_>>();
+    /// Attaches the cause of the last user message's negative emotion
+    pub fn update_cause(&mut self, cause: Option<String>) {
+        if let Some(msg) = self.state.messages.last_mut()
+            && matches!(msg.role, MessageRole::User)
+        {
+            msg.cause = cause;
+        }
+    }
 
-        if recent.len() < 2 {
-            return EmotionTrend::Stable;
+    pub fn update_strategy(&mut self, strategy: ResponseStrategy) {
+        // Record which strategy produced the last assistant reply
+        if let Some(msg) = self.state.messages.last_mut()
+            && matches!(msg.role, MessageRole::Assistant)
+        {
+            msg.strategy = Some(strategy);
+            self.events.publish(Event::StrategySelected { strategy });
         }
+    }
 
-        let scores: Vec<i32> = recent.iter()
-            .map(|e| match e.sentiment {
-                Sentiment::Positive => 1,
-                Sentiment::Neutral => 0,
-                Sentiment::Negative => -1,
-            })
-            .collect();
+    /// Records how the evaluator rated the last assistant reply
+    pub fn update_reply_score(&mut self, score: Option<ReplyScore>) {
+        if let Some(msg) = self.state.messages.last_mut()
+            && matches!(msg.role, MessageRole::Assistant)
+        {
+            msg.score = score;
+        }
+    }
 
-        let recent_count = scores.len().min(3);
-        let recent_avg: f32 = scores.iter().take(recent_count).sum::<i32>() as f32 / recent_count as f32;
+    /// Confidence-weighted mood over the latest classifications
+    pub fn overall_mood(&self) -> Option<ConversationMood> {
+        mood::overall_mood(&self.state.emotion_history)
+    }
 
-        let earlier_count = scores.len().saturating_sub(3);
-        let earlier_avg: f32 = if earlier_count > 0 {
-            scores.iter().skip(recent_count).sum::<i32>() as f32 / earlier_count as f32
-        } else {
-            recent_avg
+    /// Classifications of each sentiment still held
+    pub fn emotion_distribution(&self) -> EmotionCounts {
+        stats::emotion_distribution(&self.state.emotion_history)
+    }
+
+    pub fn average_confidence_by_sentiment(&self) -> ConfidenceBySentiment {
+        stats::average_confidence_by_sentiment(&self.state.emotion_history)
+    }
+
+    /// Replies per strategy among the messages still held, most used first
+    pub fn strategy_usage(&self) -> Vec<StrategyUsage> {
+        stats::strategy_usage(&self.state.messages)
+    }
+
+    pub fn get_recent_emotion_trend(&self) -> TrendReading {
+        self.analyzer.analyze(&self.state.emotion_history)
+    }
+
+    /// Turns in a row, up to now, whose trend was Declining
+    pub fn declining_streak(&self) -> usize {
+        let history = &self.state.emotion_history;
+        (1..=history.len())
+            .rev()
+            .take_while(|&turns| self.analyzer.analyze(&history[..turns]).direction == EmotionTrend::Declining)
+            .count()
+    }
+
+    /// The trend after each classification, with repeats collapsed, e.g.
+    /// Stable → Declining → Improving
+    pub fn trend_trajectory(&self) -> Vec<EmotionTrend> {
+        let history = &self.state.emotion_history;
+        let mut trajectory: Vec<EmotionTrend> = Vec::new();
+        for turns in 1..=history.len() {
+            let direction = self.analyzer.analyze(&history[..turns]).direction;
+            if trajectory.last() != Some(&direction) {
+                trajectory.push(direction);
+            }
+        }
+        trajectory
+    }
+
+    /// Whether the mood has declined long enough for a check-in, and the
+    /// assistant has the last word without having checked in already
+    pub fn check_in_due(&self, policy: &CheckInPolicy) -> bool {
+        let answered = self.state.messages.last().is_some_and(|m| {
+            matches!(m.role, MessageRole::Assistant) && m.content != CHECK_IN_MESSAGE
+        });
+        answered && self.declining_streak() >= policy.declining_turns
+    }
+
+    pub fn add_check_in(&mut self) {
+        self.add_message(MessageRole::Assistant, CHECK_IN_MESSAGE);
+        self.update_strategy(ResponseStrategy::Empathetic);
+    }
+
+    /// Why the conversation is waiting for an operator; no replies are generated meanwhile
+    pub fn handoff(&self) -> Option<HandoffReason> {
+        self.state.handoff
+    }
+
+    pub fn pause_for_handoff(&mut self, reason: HandoffReason) {
+        self.state.handoff = Some(reason);
+    }
+
+    /// Hands the conversation back to the assistant; false when it was not paused
+    pub fn release_handoff(&mut self) -> bool {
+        self.state.handoff.take().is_some()
+    }
+
+    /// Where the recent trend points for the user's next message
+    pub fn forecast_next_emotion(&self) -> Option<EmotionForecast> {
+        forecast::forecast(&self.state.emotion_history)
+    }
+
+    /// How long the current sitting has lasted at `now` and how many user turns
+    /// it holds. A quiet gap of `BREAK_GAP` starts a new sitting, so a resumed
+    /// session is not counted from its first day.
+    pub fn sitting(&self, now: i64) -> (Duration, usize) {
+        let messages = &self.state.messages;
+        let gap = BREAK_GAP.as_secs() as i64;
+        let mut first = messages.len();
+        let mut next = now;
+        while first > 0 && next - messages[first - 1].timestamp < gap {
+            first -= 1;
+            next = messages[first].timestamp;
+        }
+        let start = match (first, self.state.started_at) {
+            (0, Some(started_at)) => started_at.min(next),
+            _ => next,
         };
+        let turns = messages[first..].iter().filter(|m| matches!(m.role, MessageRole::User)).count();
+        (Duration::from_secs((now - start).max(0) as u64), turns)
+    }
 
-        if recent_avg > earlier_avg + 0.3 {
-            EmotionTrend::Improving
-        } else if recent_avg < earlier_avg - 0.3 {
-            EmotionTrend::Declining
-        } else {
-            EmotionTrend::Stable
+    pub fn user_turns(&self) -> usize {
+        self.state.messages.iter().filter(|m| matches!(m.role, MessageRole::User)).count()
+    }
+
+    /// Whether the sitting has passed a limit and no break was suggested in
+    /// it for the last `REMIND_EVERY_TURNS` turns
+    pub fn break_due(&self, limits: &SessionLimits, now: i64) -> bool {
+        let (elapsed, turns) = self.sitting(now);
+        if !limits.exceeded(elapsed, turns) {
+            return false;
+        }
+        let total = self.user_turns();
+        match self.state.break_suggested_at {
+            Some(at) if at > total - turns => total - at >= REMIND_EVERY_TURNS,
+            _ => true,
         }
     }
 
+    pub fn mark_break_suggested(&mut self) {
+        self.state.break_suggested_at = Some(self.user_turns());
+    }
+
     pub fn get_history(&self) -> &[Message] {
         &self.state.messages
     }
+
+    /// Digest of the messages evicted so far, under `Eviction::Summarize`
+    pub fn earlier(&self) -> Option<&EarlierMessages> {
+        self.state.earlier.as_ref()
+    }
+
+    pub fn profile(&self) -> Option<&UserProfile> {
+        self.state.profile.as_ref()
+    }
+
+    /// Seeds the conversation with what the user shared; an empty profile clears it
+    pub fn set_profile(&mut self, profile: UserProfile) {
+        self.state.profile = (!profile.is_empty()).then_some(profile);
+    }
+
+    fn enforce_retention(&mut self) {
+        if let Some(max) = self.retention.max_emotions {
+            let excess = self.state.emotion_history.len().saturating_sub(max);
+            self.state.emotion_history.drain(..excess);
+        }
+        let Some(max) = self.retention.max_messages else {
+            return;
+        };
+        let excess = self.state.messages.len().saturating_sub(max);
+        if excess == 0 {
+            return;
+        }
+        let evicted: Vec<Message> = self.state.messages.drain(..excess).collect();
+        // Break reminders are placed by user turn count, which just went down
+        let user_turns = evicted.iter().filter(|m| matches!(m.role, MessageRole::User)).count();
+        if let Some(at) = &mut self.state.break_suggested_at {
+            *at = at.saturating_sub(user_turns);
+        }
+        if self.retention.eviction == Eviction::Summarize {
+            self.state.earlier.get_or_insert_default().absorb(&evicted);
+        }
+    }
 }
 
 impl Default for ConversationManager {
@@ -115,6 +387,155 @@ mod tests {
         assert_eq!(manager.get_history()[0].content, "Hello");
     }
 
+    #[test]
+    fn test_changes_are_published() {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = events.clone();
+        let mut manager = ConversationManager::new();
+        manager.set_events(EventBus::default().with(move |event: &Event| {
+            seen.lock().unwrap().push(serde_json::to_value(event).unwrap()["type"].as_str().unwrap().to_string());
+        }));
+
+        use crate::Sentiment;
+        let emotion = |sentiment, confidence| SentimentClassification { sentiment, confidence, labels: Vec::new(), is_fallback: false };
+        for sentiment in [Sentiment::Positive, Sentiment::Positive, Sentiment::Positive, Sentiment::Negative, Sentiment::Negative] {
+            manager.add_message(MessageRole::User, "...");
+            manager.update_emotion(emotion(sentiment, 0.9));
+        }
+        manager.add_message(MessageRole::Assistant, "...");
+        manager.update_strategy(ResponseStrategy::Empathetic);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.iter().filter(|e| *e == "message_added").count(), 6);
+        assert_eq!(events.iter().filter(|e| *e == "emotion_updated").count(), 5);
+        assert!(events.contains(&"trend_changed".to_string()));
+        assert_eq!(events.last().map(String::as_str), Some("strategy_selected"));
+    }
+
+    #[test]
+    fn test_break_due_per_sitting() {
+        let message = |role, timestamp| Message { role, content: "...".to_string(), timestamp, emotion: None, strategy: None, speaker: None, aspects: Vec::new(), cause: None, score: None };
+        let minutes = |m: i64| 1_000_000 + m * 60;
+        // Yesterday's sitting, then one starting at minute 0 with a turn every ten minutes
+        let mut messages = vec![message(MessageRole::User, minutes(-24 * 60)), message(MessageRole::Assistant, minutes(-24 * 60))];
+        for m in (0..=50).step_by(10) {
+            messages.push(message(MessageRole::User, minutes(m)));
+            messages.push(message(MessageRole::Assistant, minutes(m)));
+        }
+        let mut manager = ConversationManager::from_state(ConversationState {
+            messages,
+            started_at: Some(minutes(-24 * 60)),
+            ..ConversationState::default()
+        });
+        let limits = SessionLimits { duration: Some(Duration::from_secs(3600)), turns: None };
+
+        assert_eq!(manager.sitting(minutes(55)), (Duration::from_secs(55 * 60), 6));
+        assert!(!manager.break_due(&limits, minutes(55)));
+        assert!(manager.break_due(&limits, minutes(60)));
+
+        manager.mark_break_suggested();
+        assert!(!manager.break_due(&limits, minutes(60)));
+        assert!(!manager.break_due(&SessionLimits { turns: Some(1), ..limits }, minutes(60)));
+        // A long pause is a break, so the clock starts again
+        assert!(!manager.break_due(&limits, minutes(120)));
+    }
+
+    #[test]
+    fn test_retention_evicts_oldest() {
+        use crate::Sentiment;
+        let emotion = |sentiment| SentimentClassification { sentiment, confidence: 0.8, labels: Vec::new(), is_fallback: false };
+        let retention = RetentionPolicy { max_messages: Some(4), max_emotions: Some(3), eviction: Eviction::DropOldest };
+        let mut manager = ConversationManager::new().with_retention(retention);
+        for i in 0..5 {
+            manager.add_message(MessageRole::User, &format!("message {}", i));
+            manager.update_emotion(emotion(Sentiment::Negative));
+            manager.add_message(MessageRole::Assistant, "...");
+        }
+        assert_eq!(manager.get_history().len(), 4);
+        assert_eq!(manager.get_history()[0].content, "message 3");
+        assert_eq!(manager.state().emotion_history.len(), 3);
+        assert!(manager.earlier().is_none());
+
+        let summarized = ConversationManager::from_state(manager.into_state())
+            .with_retention(RetentionPolicy { max_messages: Some(2), eviction: Eviction::Summarize, ..retention });
+        assert_eq!(summarized.get_history()[0].content, "message 4");
+        assert_eq!(summarized.earlier().map(|e| (e.messages, e.negative)), Some((2, 1)));
+    }
+
+    #[test]
+    fn test_snapshot_round_trips_through_json() {
+        use crate::Sentiment;
+        let mut manager = ConversationManager::new();
+        for sentiment in [Sentiment::Positive, Sentiment::Negative, Sentiment::Negative] {
+            manager.add_message(MessageRole::User, "...");
+            manager.update_emotion(SentimentClassification { sentiment, confidence: 0.9, labels: Vec::new(), is_fallback: false });
+            manager.add_message(MessageRole::Assistant, "...");
+            manager.update_strategy(ResponseStrategy::Empathetic);
+        }
+        manager.pause_for_handoff(HandoffReason::SustainedNegativity);
+        manager.mark_break_suggested();
+
+        let json = serde_json::to_string(&manager.snapshot()).unwrap();
+        let mut restored = ConversationManager::new().with_trend_analyzer(Arc::new(super::super::trend::Regression::default()));
+        restored.restore(serde_json::from_str(&json).unwrap());
+
+        assert_eq!(serde_json::to_string(&restored.snapshot()).unwrap(), json);
+        assert_eq!(restored.handoff(), Some(HandoffReason::SustainedNegativity));
+        assert_eq!(restored.get_recent_emotion_trend().direction, EmotionTrend::Declining);
+        let trend: EmotionTrend = serde_json::from_str(&serde_json::to_string(&EmotionTrend::Improving).unwrap()).unwrap();
+        assert_eq!(trend, EmotionTrend::Improving);
+    }
+
+    #[test]
+    fn test_update_strategy_only_tags_assistant() {
+        let mut manager = ConversationManager::new();
+
+        manager.add_message(MessageRole::User, "Hello");
+        manager.update_strategy(ResponseStrategy::Cheerful);
+        assert!(manager.get_history()[0].strategy.is_none());
+
+        manager.add_message(MessageRole::Assistant, "Hi!");
+        manager.update_strategy(ResponseStrategy::Cheerful);
+        assert_eq!(manager.get_history()[1].strategy, Some(ResponseStrategy::Cheerful));
+    }
+
+    #[test]
+    fn test_statistics_over_turns() {
+        let mut manager = ConversationManager::new();
+        assert_eq!(manager.emotion_distribution().total(), 0);
+        assert!(manager.strategy_usage().is_empty());
+
+        for (sentiment, confidence, strategy) in [
+            (crate::Sentiment::Negative, 0.9, ResponseStrategy::Empathetic),
+            (crate::Sentiment::Negative, 0.7, ResponseStrategy::Empathetic),
+            (crate::Sentiment::Positive, 0.6, ResponseStrategy::Cheerful),
+        ] {
+            manager.add_message(MessageRole::User, "...");
+            manager.update_emotion(SentimentClassification { sentiment, confidence, labels: Vec::new(), is_fallback: false });
+            manager.add_message(MessageRole::Assistant, "...");
+            manager.update_strategy(strategy);
+        }
+
+        let counts = manager.emotion_distribution();
+        assert_eq!((counts.negative, counts.neutral, counts.positive), (2, 0, 1));
+        let confidence = manager.average_confidence_by_sentiment();
+        assert!((confidence.negative.unwrap() - 0.8).abs() < 1e-6);
+        assert_eq!(confidence.neutral, None);
+        let usage: Vec<_> = manager.strategy_usage().into_iter().map(|u| (u.strategy, u.count)).collect();
+        assert_eq!(usage, [(ResponseStrategy::Empathetic, 2), (ResponseStrategy::Cheerful, 1)]);
+    }
+
+    #[test]
+    fn test_trend_trajectory_collapses_repeats() {
+        use crate::Sentiment::*;
+        let mut manager = ConversationManager::new();
+        assert!(manager.trend_trajectory().is_empty());
+        for sentiment in [Positive, Positive, Positive, Negative, Negative, Negative, Negative, Negative, Negative] {
+            manager.update_emotion(SentimentClassification { sentiment, confidence: 0.8, labels: Vec::new(), is_fallback: false });
+        }
+        assert_eq!(manager.trend_trajectory(), [EmotionTrend::Stable, EmotionTrend::Declining, EmotionTrend::Stable]);
+    }
+
     #[test]
     fn test_emotion_trend_stable() {
         let mut manager = ConversationManager::new();
@@ -124,13 +545,17 @@ mod tests {
         manager.update_emotion(SentimentClassification {
             sentiment: Sentiment::Neutral,
             confidence: 0.5,
+            labels: Vec::new(),
+            is_fallback: false,
         });
         manager.update_emotion(SentimentClassification {
             sentiment: Sentiment::Neutral,
             confidence: 0.5,
+            labels: Vec::new(),
+            is_fallback: false,
         });
 
-        assert_eq!(manager.get_recent_emotion_trend(), EmotionTrend::Stable);
+        assert_eq!(manager.get_recent_emotion_trend().direction, EmotionTrend::Stable);
     }
 
     #[test]
@@ -143,16 +568,20 @@ mod tests {
             manager.update_emotion(SentimentClassification {
                 sentiment: Sentiment::Negative,
                 confidence: 0.8,
+                labels: Vec::new(),
+                is_fallback: false,
             });
         }
         for _ in 0..3 {
             manager.update_emotion(SentimentClassification {
                 sentiment: Sentiment::Positive,
                 confidence: 0.8,
+                labels: Vec::new(),
+                is_fallback: false,
             });
         }
 
-        assert_eq!(manager.get_recent_emotion_trend(), EmotionTrend::Improving);
+        assert_eq!(manager.get_recent_emotion_trend().direction, EmotionTrend::Improving);
     }
 
     #[test]
@@ -165,16 +594,47 @@ mod tests {
             manager.update_emotion(SentimentClassification {
                 sentiment: Sentiment::Positive,
                 confidence: 0.8,
+                labels: Vec::new(),
+                is_fallback: false,
             });
         }
         for _ in 0..3 {
             manager.update_emotion(SentimentClassification {
                 sentiment: Sentiment::Negative,
                 confidence: 0.8,
+                labels: Vec::new(),
+                is_fallback: false,
             });
         }
 
-        assert_eq!(manager.get_recent_emotion_trend(), EmotionTrend::Declining);
+        let trend = manager.get_recent_emotion_trend();
+        assert_eq!((trend.direction, trend.magnitude), (EmotionTrend::Declining, 1.0));
+        assert!(trend.confidence > 0.0);
+    }
+
+    #[test]
+    fn test_check_in_after_sustained_decline() {
+        use crate::Sentiment::*;
+        let policy = CheckInPolicy { declining_turns: 2, idle: std::time::Duration::ZERO };
+        let mut manager = ConversationManager::new();
+
+        for sentiment in [Positive, Positive, Positive, Negative] {
+            manager.add_message(MessageRole::User, "...");
+            manager.update_emotion(SentimentClassification { sentiment, confidence: 0.8, labels: Vec::new(), is_fallback: false });
+            manager.add_message(MessageRole::Assistant, "...");
+        }
+        assert_eq!(manager.declining_streak(), 1);
+        assert!(!manager.check_in_due(&policy));
+
+        manager.add_message(MessageRole::User, "...");
+        manager.update_emotion(SentimentClassification { sentiment: Negative, confidence: 0.8, labels: Vec::new(), is_fallback: false });
+        assert!(!manager.check_in_due(&policy), "the user has the last word");
+        manager.add_message(MessageRole::Assistant, "...");
+        assert!(manager.check_in_due(&policy));
+
+        manager.add_check_in();
+        assert!(!manager.check_in_due(&policy));
+        assert_eq!(manager.get_history().last().unwrap().strategy, Some(ResponseStrategy::Empathetic));
     }
 
     #[test]
@@ -182,7 +642,7 @@ mod tests {
         let manager = ConversationManager::new();
 
         // Empty history should return Stable
-        assert_eq!(manager.get_recent_emotion_trend(), EmotionTrend::Stable);
+        assert_eq!(manager.get_recent_emotion_trend().direction, EmotionTrend::Stable);
     }
 
     #[test]
@@ -194,8 +654,10 @@ mod tests {
         manager.update_emotion(SentimentClassification {
             sentiment: Sentiment::Positive,
             confidence: 0.8,
+            labels: Vec::new(),
+            is_fallback: false,
         });
 
-        assert_eq!(manager.get_recent_emotion_trend(), EmotionTrend::Stable);
+        assert_eq!(manager.get_recent_emotion_trend().direction, EmotionTrend::Stable);
     }
 }
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::{Sentiment, SentimentClassification};
+use super::trend::Line;
+
+/// Most recent classifications the forecast extrapolates from
+const WINDOW: usize = 5;
+
+/// Fewest classifications worth drawing a line through
+const MIN_SAMPLES: usize = 3;
+
+/// The user's likely sentiment on their next message
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct EmotionForecast {
+    pub sentiment: Sentiment,
+    /// Predicted sentiment score, from -1 to 1
+    pub score: f32,
+    /// How well a straight line fits the recent scores, scaled down for short histories
+    pub confidence: f32,
+}
+
+/// Extrapolates a least-squares line through the last few sentiment scores
+pub fn forecast(history: &[SentimentClassification]) -> Option<EmotionForecast> {
+    let recent = &history[history.len().saturating_sub(WINDOW)..];
+    if recent.len() < MIN_SAMPLES {
+        return None;
+    }
+
+    let n = recent.len() as f32;
+    let ys: Vec<f32> = recent.iter().map(SentimentClassification::score).collect();
+    let line = Line::fit(&ys);
+    let rmse = line.rmse(&ys);
+    let score = line.at(n).clamp(-1.0, 1.0);
+    let sentiment = if score <= -1.0 / 3.0 {
+        Sentiment::Negative
+    } else if score >= 1.0 / 3.0 {
+        Sentiment::Positive
+    } else {
+        Sentiment::Neutral
+    };
+    let confidence = (1.0 - rmse).clamp(0.0, 1.0) * n / WINDOW as f32;
+
+    Some(EmotionForecast { sentiment, score, confidence })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(sentiments: &[Sentiment]) -> Vec<SentimentClassification> {
+        sentiments.iter().map(|&sentiment| SentimentClassification { sentiment, confidence: 0.8, labels: Vec::new(), is_fallback: false }).collect()
+    }
+
+    #[test]
+    fn test_extrapolates_a_slide() {
+        use Sentiment::*;
+        let forecast = forecast(&history(&[Positive, Positive, Positive, Neutral, Neutral])).unwrap();
+        assert_eq!(forecast.sentiment, Neutral);
+        assert!(forecast.score < 0.0);
+
+        let forecast = super::forecast(&history(&[Positive, Neutral, Negative])).unwrap();
+        assert_eq!(forecast.sentiment, Negative);
+        assert_eq!(forecast.score, -1.0);
+        assert!((forecast.confidence - 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_needs_a_few_turns() {
+        assert_eq!(forecast(&history(&[Sentiment::Negative, Sentiment::Negative])), None);
+    }
+}
@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+/// A quiet spell this long counts as a break and starts a new sitting
+pub const BREAK_GAP: Duration = Duration::from_secs(30 * 60);
+
+/// User turns between reminders once a limit has been passed
+pub const REMIND_EVERY_TURNS: usize = 20;
+
+/// Soft limits on one sitting, after which the assistant suggests a break
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SessionLimits {
+    pub duration: Option<Duration>,
+    pub turns: Option<usize>,
+}
+
+impl SessionLimits {
+    pub fn exceeded(&self, elapsed: Duration, turns: usize) -> bool {
+        self.duration.is_some_and(|limit| elapsed >= limit) || self.turns.is_some_and(|limit| turns >= limit)
+    }
+}
+
+/// Added to the strategy prompt on a turn that should suggest a break
+pub fn break_reminder(elapsed: Duration, turns: usize) -> String {
+    format!(
+        "The user has been talking with you for {} minutes ({} messages) without a pause. \
+         While following the guidance above, work a gentle suggestion to take a short break \
+         into your reply where it fits naturally, for example to stretch, get some water or \
+         step outside. Keep it warm and brief, and make clear they are welcome to come back.",
+        elapsed.as_secs() / 60,
+        turns
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_either_limit_is_enough() {
+        let limits = SessionLimits { duration: Some(Duration::from_secs(3600)), turns: Some(80) };
+        assert!(!limits.exceeded(Duration::from_secs(600), 10));
+        assert!(limits.exceeded(Duration::from_secs(3600), 10));
+        assert!(limits.exceeded(Duration::from_secs(600), 80));
+        assert!(!SessionLimits::default().exceeded(Duration::from_secs(86400), 1000));
+    }
+}
@@ -1,5 +1,33 @@
 //! Conversation state management
 
+pub mod baseline;
+pub mod checkin;
 pub mod conversation;
+pub mod forecast;
+pub mod limits;
+pub mod mood;
+pub mod profile;
+pub mod retention;
+pub mod stats;
+pub mod trend;
 
-pub use conversation::{ConversationManager, EmotionTrend};
+pub use baseline::EmotionBaseline;
+pub use checkin::{CHECK_IN_MESSAGE, CheckInPolicy};
+pub use conversation::{ConversationManager, ConversationState, EmotionTrend, HandoffReason};
+pub use forecast::EmotionForecast;
+pub use limits::SessionLimits;
+pub use mood::ConversationMood;
+pub use profile::UserProfile;
+pub use retention::{EarlierMessages, Eviction, RetentionPolicy};
+pub use stats::{AspectCounts, ConfidenceBySentiment, EmotionCounts, StrategyUsage};
+pub use trend::{TrendAnalyzer, TrendReading};
+
+/// A setting given a value this crate has no option for, e.g. `EVICTION_POLICY=lru`.
+/// Kept free of `anyhow`, which the library builds without.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown {setting} '{value}', expected {expected}")]
+pub struct UnknownSetting {
+    pub setting: &'static str,
+    pub value: String,
+    pub expected: &'static str,
+}
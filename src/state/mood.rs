@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::{Sentiment, SentimentClassification};
+
+/// Most recent classifications the mood aggregates
+const WINDOW: usize = 10;
+
+/// The conversation's mood over its latest messages, as opposed to the
+/// sentiment of any one of them
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ConversationMood {
+    pub sentiment: Sentiment,
+    /// Mean sentiment score weighted by classification confidence, from -1 to 1
+    pub score: f32,
+    /// Mean classification confidence over the window
+    pub confidence: f32,
+    /// Classifications the score was taken over
+    pub messages: usize,
+}
+
+/// Aggregates the last few classifications, so confident ones count for more
+pub fn overall_mood(history: &[SentimentClassification]) -> Option<ConversationMood> {
+    let recent = &history[history.len().saturating_sub(WINDOW)..];
+    if recent.is_empty() {
+        return None;
+    }
+
+    let weight: f32 = recent.iter().map(|e| e.confidence).sum();
+    let score = if weight > 0.0 {
+        recent.iter().map(|e| e.score() * e.confidence).sum::<f32>() / weight
+    } else {
+        0.0
+    };
+    let sentiment = if score <= -1.0 / 3.0 {
+        Sentiment::Negative
+    } else if score >= 1.0 / 3.0 {
+        Sentiment::Positive
+    } else {
+        Sentiment::Neutral
+    };
+
+    Some(ConversationMood { sentiment, score, confidence: weight / recent.len() as f32, messages: recent.len() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emotion(sentiment: Sentiment, confidence: f32) -> SentimentClassification {
+        SentimentClassification { sentiment, confidence, labels: Vec::new(), is_fallback: false }
+    }
+
+    #[test]
+    fn test_weights_by_confidence() {
+        let mood = overall_mood(&[emotion(Sentiment::Positive, 0.2), emotion(Sentiment::Negative, 0.9), emotion(Sentiment::Negative, 0.9)]).unwrap();
+        assert_eq!(mood.sentiment, Sentiment::Negative);
+        assert!((mood.score - -0.8).abs() < 1e-6);
+        assert_eq!(mood.messages, 3);
+
+        assert_eq!(overall_mood(&[]), None);
+    }
+
+    #[test]
+    fn test_only_the_window_counts() {
+        let mut history = vec![emotion(Sentiment::Negative, 1.0); 20];
+        history.extend(vec![emotion(Sentiment::Positive, 0.8); WINDOW]);
+        let mood = overall_mood(&history).unwrap();
+        assert_eq!(mood.sentiment, Sentiment::Positive);
+        assert_eq!(mood.messages, WINDOW);
+    }
+}
@@ -0,0 +1,144 @@
+use std::str::FromStr;
+use crate::Sentiment;
+use crate::models::{Message, MessageRole};
+use super::UnknownSetting;
+
+/// What happens to messages past `RetentionPolicy::max_messages`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Eviction {
+    /// They are dropped
+    #[default]
+    DropOldest,
+    /// They are dropped, but counted into a digest the assistant keeps seeing
+    Summarize,
+}
+
+impl FromStr for Eviction {
+    type Err = UnknownSetting;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "drop-oldest" | "drop" => Ok(Eviction::DropOldest),
+            "summarize" => Ok(Eviction::Summarize),
+            other => Err(UnknownSetting { setting: "eviction policy", value: other.to_string(), expected: "drop-oldest or summarize" }),
+        }
+    }
+}
+
+/// How much of a conversation is kept in memory; unlimited by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub max_messages: Option<usize>,
+    pub max_emotions: Option<usize>,
+    pub eviction: Eviction,
+}
+
+/// Messages evicted under `Eviction::Summarize`, reduced to what they said
+/// about the user's feelings
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct EarlierMessages {
+    pub messages: usize,
+    /// Unix time of the first evicted message
+    pub since: i64,
+    pub positive: usize,
+    pub neutral: usize,
+    pub negative: usize,
+    /// Causes given for negative emotions, most recent last
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub causes: Vec<String>,
+}
+
+/// Causes an `EarlierMessages` remembers
+const MAX_EARLIER_CAUSES: usize = 5;
+
+impl EarlierMessages {
+    pub fn absorb(&mut self, evicted: &[Message]) {
+        if self.messages == 0
+            && let Some(first) = evicted.first()
+        {
+            self.since = first.timestamp;
+        }
+        self.messages += evicted.len();
+        for message in evicted.iter().filter(|m| matches!(m.role, MessageRole::User)) {
+            match message.emotion.as_ref().map(|e| e.sentiment) {
+                Some(Sentiment::Positive) => self.positive += 1,
+                Some(Sentiment::Neutral) => self.neutral += 1,
+                Some(Sentiment::Negative) => self.negative += 1,
+                None => {}
+            }
+            if let Some(cause) = &message.cause {
+                self.causes.retain(|c| c != cause);
+                self.causes.push(cause.clone());
+            }
+        }
+        let excess = self.causes.len().saturating_sub(MAX_EARLIER_CAUSES);
+        self.causes.drain(..excess);
+    }
+
+    /// Guidance for the reply, so the assistant keeps the gist of what it can no longer see
+    pub fn describe(&self) -> String {
+        let mut line = format!("{} earlier messages of this conversation were condensed.", self.messages);
+        if self.positive + self.neutral + self.negative > 0 {
+            line.push_str(&format!(
+                " In them the user's messages were {} negative, {} neutral and {} positive.",
+                self.negative, self.neutral, self.positive
+            ));
+        }
+        if !self.causes.is_empty() {
+            line.push_str(&format!(" They were upset about: {}.", self.causes.join("; ")));
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SentimentClassification;
+
+    #[test]
+    fn test_absorb_counts_user_emotions() {
+        let message = |role, timestamp, sentiment: Option<Sentiment>| Message {
+            role,
+            content: "...".to_string(),
+            timestamp,
+            emotion: sentiment.map(|sentiment| SentimentClassification { sentiment, confidence: 0.8, labels: Vec::new(), is_fallback: false }),
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
+        };
+        let mut earlier = EarlierMessages::default();
+        earlier.absorb(&[message(MessageRole::User, 10, Some(Sentiment::Negative)), message(MessageRole::Assistant, 11, None)]);
+        earlier.absorb(&[message(MessageRole::User, 20, Some(Sentiment::Negative))]);
+
+        assert_eq!((earlier.messages, earlier.since, earlier.negative, earlier.positive), (3, 10, 2, 0));
+        assert_eq!(
+            earlier.describe(),
+            "3 earlier messages of this conversation were condensed. In them the user's messages were 2 negative, 0 neutral and 0 positive."
+        );
+        assert_eq!("Summarize".parse::<Eviction>().unwrap(), Eviction::Summarize);
+        assert_eq!("archive".parse::<Eviction>().unwrap_err().to_string(), "unknown eviction policy 'archive', expected drop-oldest or summarize");
+    }
+
+    #[test]
+    fn test_absorb_keeps_recent_causes() {
+        let upset = |cause: &str| Message {
+            role: MessageRole::User,
+            content: "...".to_string(),
+            timestamp: 0,
+            emotion: None,
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: Some(cause.to_string()),
+            score: None,
+        };
+        let mut earlier = EarlierMessages::default();
+        earlier.absorb(&["deadline", "commute", "deadline", "a", "b", "c", "d"].map(upset));
+
+        assert_eq!(earlier.causes, ["deadline", "a", "b", "c", "d"]);
+        assert!(earlier.describe().ends_with("They were upset about: deadline; a; b; c; d."));
+    }
+}
@@ -0,0 +1,298 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::{Sentiment, SentimentClassification};
+use crate::models::{Message, ReplyScore};
+use crate::strategy::ResponseStrategy;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, ToSchema)]
+pub struct EmotionCounts {
+    pub positive: usize,
+    pub negative: usize,
+    pub neutral: usize,
+}
+
+impl EmotionCounts {
+    pub fn record(&mut self, sentiment: Sentiment) {
+        match sentiment {
+            Sentiment::Positive => self.positive += 1,
+            Sentiment::Negative => self.negative += 1,
+            Sentiment::Neutral => self.neutral += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.positive + self.negative + self.neutral
+    }
+
+    pub fn dominant(&self) -> Option<Sentiment> {
+        if self.total() == 0 {
+            return None;
+        }
+
+        // Ties resolve towards Negative first, so a tie never hides negativity
+        let mut best = (self.negative, Sentiment::Negative);
+        for (count, sentiment) in [(self.neutral, Sentiment::Neutral), (self.positive, Sentiment::Positive)] {
+            if count > best.0 {
+                best = (count, sentiment);
+            }
+        }
+
+        Some(best.1)
+    }
+}
+
+/// Mean classifier confidence for each sentiment, `None` where it never came up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, ToSchema)]
+pub struct ConfidenceBySentiment {
+    pub positive: Option<f32>,
+    pub negative: Option<f32>,
+    pub neutral: Option<f32>,
+}
+
+impl ConfidenceBySentiment {
+    pub fn get(&self, sentiment: Sentiment) -> Option<f32> {
+        match sentiment {
+            Sentiment::Positive => self.positive,
+            Sentiment::Negative => self.negative,
+            Sentiment::Neutral => self.neutral,
+        }
+    }
+
+    /// e.g. `negative 0.82, positive 0.64`, or `None` before any classification
+    pub fn describe(&self) -> Option<String> {
+        let parts: Vec<String> = [Sentiment::Negative, Sentiment::Neutral, Sentiment::Positive]
+            .into_iter()
+            .filter_map(|sentiment| self.get(sentiment).map(|c| format!("{:?} {:.2}", sentiment, c).to_lowercase()))
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+pub struct StrategyUsage {
+    pub strategy: ResponseStrategy,
+    pub count: usize,
+    /// Mean rating of the strategy's scored replies; `None` when none were scored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<ReplyScore>,
+}
+
+impl StrategyUsage {
+    /// The count with the mean rating, e.g. `Empathetic ×3 (empathy 0.80, adherence 0.70)`
+    pub fn describe(&self) -> String {
+        match self.score {
+            Some(score) => format!(
+                "{:?} ×{} (empathy {:.2}, adherence {:.2})",
+                self.strategy, self.count, score.empathy, score.strategy_adherence
+            ),
+            None => format!("{:?} ×{}", self.strategy, self.count),
+        }
+    }
+}
+
+pub fn emotion_distribution(emotions: &[SentimentClassification]) -> EmotionCounts {
+    let mut counts = EmotionCounts::default();
+    for emotion in emotions {
+        counts.record(emotion.sentiment);
+    }
+    counts
+}
+
+/// Fallbacks are left out, since their confidence is not the classifier's
+pub fn average_confidence_by_sentiment(emotions: &[SentimentClassification]) -> ConfidenceBySentiment {
+    let average = |sentiment: Sentiment| {
+        let confidences: Vec<f32> = emotions
+            .iter()
+            .filter(|e| e.sentiment == sentiment && !e.is_fallback)
+            .map(|e| e.confidence)
+            .collect();
+        (!confidences.is_empty()).then(|| confidences.iter().sum::<f32>() / confidences.len() as f32)
+    };
+    ConfidenceBySentiment {
+        positive: average(Sentiment::Positive),
+        negative: average(Sentiment::Negative),
+        neutral: average(Sentiment::Neutral),
+    }
+}
+
+/// Replies per strategy, most used first, with the mean rating of those that
+/// were scored; ties keep the order of first use
+pub fn strategy_usage<'a>(messages: impl IntoIterator<Item = &'a Message>) -> Vec<StrategyUsage> {
+    let mut usage: Vec<(StrategyUsage, Vec<ReplyScore>)> = Vec::new();
+    for message in messages {
+        let Some(strategy) = message.strategy else { continue };
+        let position = match usage.iter().position(|(u, _)| u.strategy == strategy) {
+            Some(position) => position,
+            None => {
+                usage.push((StrategyUsage { strategy, count: 0, score: None }, Vec::new()));
+                usage.len() - 1
+            }
+        };
+        let (u, scores) = &mut usage[position];
+        u.count += 1;
+        scores.extend(message.score);
+    }
+
+    let mut usage: Vec<StrategyUsage> = usage
+        .into_iter()
+        .map(|(u, scores)| {
+            let n = scores.len() as f32;
+            let score = (!scores.is_empty()).then(|| ReplyScore {
+                strategy_adherence: scores.iter().map(|s| s.strategy_adherence).sum::<f32>() / n,
+                empathy: scores.iter().map(|s| s.empathy).sum::<f32>() / n,
+            });
+            StrategyUsage { score, ..u }
+        })
+        .collect();
+    usage.sort_by_key(|u| std::cmp::Reverse(u.count));
+    usage
+}
+
+/// The scored strategy whose replies rated lowest, empathy and adherence
+/// together, so its prompt can be reviewed; `None` unless at least two
+/// strategies were scored
+pub fn lowest_scoring(usage: &[StrategyUsage]) -> Option<&StrategyUsage> {
+    let scored: Vec<(&StrategyUsage, f32)> = usage.iter().filter_map(|u| u.score.map(|s| (u, s.empathy + s.strategy_adherence))).collect();
+    if scored.len() < 2 {
+        return None;
+    }
+    scored.into_iter().min_by(|a, b| a.1.total_cmp(&b.1)).map(|(u, _)| u)
+}
+
+/// The sentiments a conversation held toward one aspect, such as `manager`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct AspectCounts {
+    pub aspect: String,
+    pub counts: EmotionCounts,
+}
+
+/// Mentions per aspect, most mentioned first; aspects differing only in case
+/// count together and ties keep the order of first mention
+pub fn aspect_sentiment<'a>(messages: impl IntoIterator<Item = &'a Message>) -> Vec<AspectCounts> {
+    let mut aspects: Vec<AspectCounts> = Vec::new();
+    for pair in messages.into_iter().flat_map(|m| &m.aspects) {
+        let aspect = pair.aspect.trim().to_lowercase();
+        match aspects.iter_mut().find(|a| a.aspect == aspect) {
+            Some(a) => a.counts.record(pair.sentiment),
+            None => {
+                let mut counts = EmotionCounts::default();
+                counts.record(pair.sentiment);
+                aspects.push(AspectCounts { aspect, counts });
+            }
+        }
+    }
+    aspects.sort_by_key(|a| std::cmp::Reverse(a.counts.total()));
+    aspects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AspectSentiment;
+    use crate::models::MessageRole;
+
+    fn classification(sentiment: Sentiment, confidence: f32) -> SentimentClassification {
+        SentimentClassification { sentiment, confidence, labels: Vec::new(), is_fallback: false }
+    }
+
+    #[test]
+    fn test_dominant_prefers_negative_on_tie() {
+        let counts = EmotionCounts { positive: 2, negative: 2, neutral: 1 };
+        assert_eq!(counts.dominant(), Some(Sentiment::Negative));
+        assert_eq!(EmotionCounts::default().dominant(), None);
+    }
+
+    #[test]
+    fn test_distribution_and_confidence() {
+        let emotions = [
+            classification(Sentiment::Negative, 0.9),
+            classification(Sentiment::Negative, 0.5),
+            classification(Sentiment::Positive, 0.8),
+            SentimentClassification { sentiment: Sentiment::Neutral, confidence: 0.0, labels: Vec::new(), is_fallback: true },
+        ];
+        assert_eq!(emotion_distribution(&emotions), EmotionCounts { positive: 1, negative: 2, neutral: 1 });
+
+        let confidence = average_confidence_by_sentiment(&emotions);
+        assert!((confidence.get(Sentiment::Negative).unwrap() - 0.7).abs() < 1e-6);
+        assert_eq!(confidence.positive, Some(0.8));
+        assert_eq!(confidence.neutral, None);
+        assert_eq!(confidence.describe().unwrap(), "negative 0.70, positive 0.80");
+        assert_eq!(average_confidence_by_sentiment(&[]).describe(), None);
+    }
+
+    #[test]
+    fn test_strategy_usage_most_used_first() {
+        let reply = |strategy| Message {
+            role: MessageRole::Assistant,
+            content: "...".to_string(),
+            timestamp: 0,
+            emotion: None,
+            strategy,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
+        };
+        let messages = [
+            reply(Some(ResponseStrategy::Cheerful)),
+            reply(Some(ResponseStrategy::Empathetic)),
+            reply(None),
+            reply(Some(ResponseStrategy::Empathetic)),
+            reply(Some(ResponseStrategy::Neutral)),
+        ];
+        let usage: Vec<(ResponseStrategy, usize)> = strategy_usage(&messages).iter().map(|u| (u.strategy, u.count)).collect();
+        assert_eq!(usage, [(ResponseStrategy::Empathetic, 2), (ResponseStrategy::Cheerful, 1), (ResponseStrategy::Neutral, 1)]);
+        assert!(strategy_usage(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_strategy_usage_averages_scores() {
+        let reply = |strategy, empathy: Option<f32>| Message {
+            role: MessageRole::Assistant,
+            content: "...".to_string(),
+            timestamp: 0,
+            emotion: None,
+            strategy: Some(strategy),
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: empathy.map(|empathy| ReplyScore { strategy_adherence: 0.8, empathy }),
+        };
+        let messages = [
+            reply(ResponseStrategy::Empathetic, Some(0.9)),
+            reply(ResponseStrategy::Empathetic, Some(0.5)),
+            reply(ResponseStrategy::Empathetic, None),
+            reply(ResponseStrategy::Cheerful, Some(0.3)),
+            reply(ResponseStrategy::Neutral, None),
+        ];
+        let usage = strategy_usage(&messages);
+        assert_eq!(usage[0].describe(), "Empathetic ×3 (empathy 0.70, adherence 0.80)");
+        assert_eq!(usage[2].describe(), "Neutral ×1");
+        assert_eq!(lowest_scoring(&usage).map(|u| u.strategy), Some(ResponseStrategy::Cheerful));
+        assert!(lowest_scoring(&usage[..1]).is_none());
+    }
+
+    #[test]
+    fn test_aspect_sentiment_merges_case() {
+        let message = |aspects: &[(&str, Sentiment)]| Message {
+            role: MessageRole::User,
+            content: "...".to_string(),
+            timestamp: 0,
+            emotion: None,
+            strategy: None,
+            speaker: None,
+            aspects: aspects.iter().map(|(aspect, sentiment)| AspectSentiment { aspect: aspect.to_string(), sentiment: *sentiment }).collect(),
+            cause: None,
+            score: None,
+        };
+        let messages = [
+            message(&[("job", Sentiment::Positive), ("manager", Sentiment::Negative)]),
+            message(&[("Manager", Sentiment::Negative)]),
+            message(&[]),
+        ];
+        let aspects = aspect_sentiment(&messages);
+        assert_eq!(aspects.len(), 2);
+        assert_eq!((aspects[0].aspect.as_str(), aspects[0].counts.negative), ("manager", 2));
+        assert_eq!((aspects[1].aspect.as_str(), aspects[1].counts.positive), ("job", 1));
+    }
+}
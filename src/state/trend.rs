@@ -0,0 +1,285 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+use crate::SentimentClassification;
+use super::{EmotionTrend, UnknownSetting};
+
+/// Most recent scores the confidence of a reading is judged on
+const WINDOW: usize = 5;
+
+/// Where a conversation is heading, how fast, and how sure that is
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TrendReading {
+    pub direction: EmotionTrend,
+    /// How far the scores moved, from 0 (flat) to 1 (from one end of the scale to the other)
+    pub magnitude: f32,
+    /// How steadily the recent scores follow a line, scaled down for short histories
+    pub confidence: f32,
+}
+
+impl TrendReading {
+    /// A reading with nothing behind it, as for an empty conversation
+    pub fn stable() -> Self {
+        Self { direction: EmotionTrend::Stable, magnitude: 0.0, confidence: 0.0 }
+    }
+
+    /// How sharply and surely things are moving, from 0 to 1
+    pub fn sharpness(&self) -> f32 {
+        (self.magnitude * self.confidence).clamp(0.0, 1.0)
+    }
+}
+
+/// Reads the direction of a conversation from its classifications, oldest first
+pub trait TrendAnalyzer: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn analyze(&self, history: &[SentimentClassification]) -> TrendReading;
+}
+
+/// The analyzer named `name`, with its default settings
+pub fn by_name(name: &str) -> Result<Arc<dyn TrendAnalyzer>, UnknownSetting> {
+    match name.to_ascii_lowercase().as_str() {
+        "heuristic" => Ok(Arc::new(Heuristic)),
+        "ema" => Ok(Arc::new(Ema::default())),
+        "regression" => Ok(Arc::new(Regression::default())),
+        other => Err(UnknownSetting { setting: "trend analyzer", value: other.to_string(), expected: "heuristic, ema or regression" }),
+    }
+}
+
+fn scores(history: &[SentimentClassification]) -> impl DoubleEndedIterator<Item = f32> + '_ {
+    history.iter().map(SentimentClassification::score)
+}
+
+/// `change` is on the score scale, so at most 2 either way
+fn reading(history: &[SentimentClassification], change: f32, threshold: f32) -> TrendReading {
+    let direction = if change > threshold {
+        EmotionTrend::Improving
+    } else if change < -threshold {
+        EmotionTrend::Declining
+    } else {
+        EmotionTrend::Stable
+    };
+    let recent: Vec<f32> = scores(&history[history.len().saturating_sub(WINDOW)..]).collect();
+    let confidence = if recent.len() < 2 {
+        0.0
+    } else {
+        let line = Line::fit(&recent);
+        (1.0 - line.rmse(&recent)).clamp(0.0, 1.0) * recent.len() as f32 / WINDOW as f32
+    };
+    TrendReading { direction, magnitude: (change.abs() / 2.0).min(1.0), confidence }
+}
+
+/// Least-squares line through scores at x = 0, 1, 2...
+pub(super) struct Line {
+    mean_x: f32,
+    mean_y: f32,
+    /// Change per message
+    pub slope: f32,
+}
+
+impl Line {
+    pub fn fit(ys: &[f32]) -> Self {
+        let n = ys.len() as f32;
+        let mean_x = (n - 1.0) / 2.0;
+        let mean_y = ys.iter().sum::<f32>() / n;
+        let (mut covariance, mut variance) = (0.0, 0.0);
+        for (x, y) in ys.iter().enumerate() {
+            let dx = x as f32 - mean_x;
+            covariance += dx * (y - mean_y);
+            variance += dx * dx;
+        }
+        let slope = if variance > 0.0 { covariance / variance } else { 0.0 };
+        Self { mean_x, mean_y, slope }
+    }
+
+    pub fn at(&self, x: f32) -> f32 {
+        self.mean_y + self.slope * (x - self.mean_x)
+    }
+
+    /// Root mean square distance of `ys` from the line
+    pub fn rmse(&self, ys: &[f32]) -> f32 {
+        (ys.iter().enumerate().map(|(x, y)| (y - self.at(x as f32)).powi(2)).sum::<f32>() / ys.len() as f32).sqrt()
+    }
+}
+
+/// The last three scores against the two before them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Heuristic;
+
+impl TrendAnalyzer for Heuristic {
+    fn name(&self) -> &str {
+        "heuristic"
+    }
+
+    fn analyze(&self, history: &[SentimentClassification]) -> TrendReading {
+        let scores: Vec<f32> = scores(history).rev().take(5).collect();
+        if scores.len() < 2 {
+            return reading(history, 0.0, 0.3);
+        }
+
+        let recent_count = scores.len().min(3);
+        let recent_avg = scores.iter().take(recent_count).sum::<f32>() / recent_count as f32;
+
+        let earlier_count = scores.len().saturating_sub(3);
+        let earlier_avg = if earlier_count > 0 {
+            scores.iter().skip(recent_count).sum::<f32>() / earlier_count as f32
+        } else {
+            recent_avg
+        };
+
+        reading(history, recent_avg - earlier_avg, 0.3)
+    }
+}
+
+/// A fast exponential moving average of the scores against a slow one, so
+/// the whole conversation counts, recent messages most
+#[derive(Debug, Clone, Copy)]
+pub struct Ema {
+    pub fast: f32,
+    pub slow: f32,
+    pub threshold: f32,
+}
+
+impl Default for Ema {
+    fn default() -> Self {
+        Self { fast: 0.5, slow: 0.2, threshold: 0.3 }
+    }
+}
+
+impl TrendAnalyzer for Ema {
+    fn name(&self) -> &str {
+        "ema"
+    }
+
+    fn analyze(&self, history: &[SentimentClassification]) -> TrendReading {
+        let mut scores = scores(history);
+        let Some(first) = scores.next() else {
+            return TrendReading::stable();
+        };
+        let (fast, slow) = scores.fold((first, first), |(fast, slow), score| {
+            (fast + self.fast * (score - fast), slow + self.slow * (score - slow))
+        });
+        reading(history, fast - slow, self.threshold)
+    }
+}
+
+/// The slope of a least-squares line through the last `window` scores
+#[derive(Debug, Clone, Copy)]
+pub struct Regression {
+    pub window: usize,
+    /// Change per message that counts as a trend
+    pub threshold: f32,
+}
+
+impl Default for Regression {
+    fn default() -> Self {
+        Self { window: 5, threshold: 0.15 }
+    }
+}
+
+impl TrendAnalyzer for Regression {
+    fn name(&self) -> &str {
+        "regression"
+    }
+
+    fn analyze(&self, history: &[SentimentClassification]) -> TrendReading {
+        let recent = &history[history.len().saturating_sub(self.window)..];
+        // Two points always make a line; three make a trend
+        if recent.len() < 3 {
+            return reading(history, 0.0, self.threshold);
+        }
+        let ys: Vec<f32> = scores(recent).collect();
+        let slope = Line::fit(&ys).slope;
+        // Compared per message, reported over the window like the other analyzers
+        let threshold = self.threshold * (ys.len() - 1) as f32;
+        reading(history, slope * (ys.len() - 1) as f32, threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sentiment::{self, *};
+
+    fn history(sentiments: &[Sentiment]) -> Vec<SentimentClassification> {
+        sentiments.iter().map(|&sentiment| SentimentClassification { sentiment, confidence: 0.8, labels: Vec::new(), is_fallback: false }).collect()
+    }
+
+    /// Conversations every analyzer must read the same way
+    const FIXTURES: &[(&str, &[Sentiment], EmotionTrend)] = &[
+        ("empty", &[], EmotionTrend::Stable),
+        ("single", &[Negative], EmotionTrend::Stable),
+        ("flat", &[Neutral, Neutral, Neutral, Neutral, Neutral], EmotionTrend::Stable),
+        ("flat negative", &[Negative, Negative, Negative, Negative, Negative, Negative], EmotionTrend::Stable),
+        ("recovering", &[Negative, Negative, Negative, Positive, Positive, Positive], EmotionTrend::Improving),
+        ("collapsing", &[Positive, Positive, Positive, Negative, Negative, Negative], EmotionTrend::Declining),
+        ("climbing", &[Negative, Negative, Neutral, Positive, Positive], EmotionTrend::Improving),
+        ("sliding", &[Positive, Neutral, Neutral, Negative, Negative], EmotionTrend::Declining),
+    ];
+
+    fn analyzers() -> Vec<Arc<dyn TrendAnalyzer>> {
+        ["heuristic", "ema", "regression"].into_iter().map(|name| by_name(name).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_analyzers_agree_on_fixtures() {
+        for analyzer in analyzers() {
+            for (name, sentiments, expected) in FIXTURES {
+                assert_eq!(analyzer.analyze(&history(sentiments)).direction, *expected, "{} on {}", analyzer.name(), name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_heuristic_looks_at_the_last_five() {
+        let long_ago = history(&[Negative, Negative, Negative, Negative, Neutral, Neutral, Neutral, Neutral, Neutral]);
+        assert_eq!(Heuristic.analyze(&long_ago).direction, EmotionTrend::Stable);
+        assert_eq!(Heuristic.analyze(&history(&[Neutral, Positive])).direction, EmotionTrend::Stable);
+        assert_eq!(Heuristic.analyze(&history(&[Positive, Negative])).direction, EmotionTrend::Stable);
+    }
+
+    #[test]
+    fn test_ema_remembers_older_messages() {
+        let recovering = history(&[Negative, Negative, Negative, Negative, Neutral, Neutral, Neutral]);
+        assert_eq!(Ema::default().analyze(&recovering).direction, EmotionTrend::Improving);
+        // Once the slow average catches up the recovery is old news
+        let settled = history(&[Negative, Negative, Negative, Negative, Neutral, Neutral, Neutral, Neutral, Neutral, Neutral, Neutral, Neutral]);
+        assert_eq!(Ema::default().analyze(&settled).direction, EmotionTrend::Stable);
+        assert_eq!(Ema { threshold: 2.0, ..Ema::default() }.analyze(&recovering).direction, EmotionTrend::Stable);
+    }
+
+    #[test]
+    fn test_regression_needs_three_points_in_its_window() {
+        assert_eq!(Regression::default().analyze(&history(&[Negative, Positive])).direction, EmotionTrend::Stable);
+        assert_eq!(Regression::default().analyze(&history(&[Negative, Neutral, Positive])).direction, EmotionTrend::Improving);
+        // A window of three sees only the recovery from the dip
+        let dip = history(&[Positive, Negative, Negative, Neutral, Positive]);
+        assert_eq!(Regression { window: 3, ..Regression::default() }.analyze(&dip).direction, EmotionTrend::Improving);
+        assert_eq!(Regression::default().analyze(&dip).direction, EmotionTrend::Stable);
+    }
+
+    #[test]
+    fn test_readings_grow_with_sharper_and_steadier_moves() {
+        for analyzer in analyzers() {
+            let drift = analyzer.analyze(&history(&[Positive, Positive, Neutral, Neutral, Neutral, Neutral]));
+            let slide = analyzer.analyze(&history(&[Positive, Positive, Neutral, Neutral, Negative, Negative]));
+            let collapse = analyzer.analyze(&history(&[Positive, Positive, Positive, Negative, Negative, Negative]));
+            assert!(drift.magnitude < collapse.magnitude, "{}", analyzer.name());
+            assert!(slide.confidence > collapse.confidence, "{} trusts a steady slide over a jump", analyzer.name());
+            assert!((0.0..=1.0).contains(&collapse.magnitude) && (0.0..=1.0).contains(&collapse.confidence));
+            assert_eq!(analyzer.analyze(&[]), TrendReading::stable());
+        }
+        let short = Heuristic.analyze(&history(&[Positive, Negative]));
+        let long = Heuristic.analyze(&history(&[Positive, Positive, Negative, Negative, Negative]));
+        assert!(short.confidence < long.confidence);
+    }
+
+    #[test]
+    fn test_line() {
+        let line = Line::fit(&[-1.0, 0.0, 1.0]);
+        assert_eq!((line.at(1.0), line.slope, line.rmse(&[-1.0, 0.0, 1.0])), (0.0, 1.0, 0.0));
+        assert_eq!(Line::fit(&[0.5]).slope, 0.0);
+        assert!(by_name("Regression").is_ok());
+        assert_eq!(by_name("vibes").err().map(|e| e.to_string()).as_deref(), Some("unknown trend analyzer 'vibes', expected heuristic, ema or regression"));
+    }
+}
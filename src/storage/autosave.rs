@@ -0,0 +1,47 @@
+/// Messages between checkpoints when `AUTOSAVE_EVERY_MESSAGES` is unset: one
+/// message and its reply, so a crash loses at most the turn in progress
+pub const DEFAULT_EVERY: usize = 2;
+
+/// Decides when a live session is written to storage between the saves on quit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Autosave {
+    /// Messages added between checkpoints; 0 turns checkpoints off
+    every: usize,
+    saved_at: usize,
+}
+
+impl Autosave {
+    pub fn new(every: usize) -> Self {
+        Self { every, saved_at: 0 }
+    }
+
+    /// Whether a session now holding `messages` has grown enough since the last checkpoint
+    pub fn due(&self, messages: usize) -> bool {
+        self.every > 0 && messages >= self.saved_at + self.every
+    }
+
+    /// A checkpoint was written, or a session was loaded, at `messages`
+    pub fn saved(&mut self, messages: usize) {
+        self.saved_at = messages;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_due_every_n_messages() {
+        let mut autosave = Autosave::new(4);
+        assert!(!autosave.due(3));
+        assert!(autosave.due(4));
+        autosave.saved(4);
+        assert!(!autosave.due(6));
+        assert!(autosave.due(9));
+
+        // A resumed session counts from where it was loaded
+        autosave.saved(30);
+        assert!(!autosave.due(32));
+        assert!(!Autosave::new(0).due(100));
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::fs;
+use std::path::{Path, PathBuf};
+use super::{Storage, schema};
+use super::session::StoredSession;
+
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn load_file(path: &Path) -> Result<StoredSession> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        schema::from_str(&json)
+            .with_context(|| format!("failed to parse {}", path.display()))
+    }
+}
+
+#[async_trait]
+impl Storage for FileStore {
+    fn location(&self) -> String {
+        self.dir.display().to_string()
+    }
+
+    #[tracing::instrument(name = "storage.save", skip_all, fields(backend = "file", session = %session.id))]
+    async fn save(&self, session: &StoredSession) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create {}", self.dir.display()))?;
+
+        let path = self.dir.join(format!("{}.json", session.id));
+        let json = schema::to_string_pretty(session)?;
+        fs::write(&path, json)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "storage.load_all", skip_all, fields(backend = "file"))]
+    async fn load_all(&self) -> Result<Vec<StoredSession>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut sessions = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                sessions.push(Self::load_file(&path)?);
+            }
+        }
+
+        sessions.sort_by_key(|s| s.started_at);
+        Ok(sessions)
+    }
+
+    #[tracing::instrument(name = "storage.load", skip(self), fields(backend = "file"))]
+    async fn load(&self, id: &str) -> Result<StoredSession> {
+        let path = self.dir.join(format!("{}.json", id));
+        if !path.exists() {
+            anyhow::bail!("session '{}' not found in {}", id, self.dir.display());
+        }
+        Self::load_file(&path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Message, MessageRole};
+
+    fn temp_store(name: &str) -> FileStore {
+        let dir = std::env::temp_dir().join(format!("tce-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        FileStore::new(dir)
+    }
+
+    #[tokio::test]
+    async fn test_load_all_missing_dir() {
+        let store = temp_store("missing");
+        assert!(store.load_all().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load_roundtrip() {
+        let store = temp_store("roundtrip");
+
+        let later = StoredSession::new(200, vec![Message {
+            role: MessageRole::User,
+            content: "Hello".to_string(),
+            timestamp: 200,
+            emotion: None,
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
+        }]);
+        let earlier = StoredSession::new(100, Vec::new());
+
+        store.save(&later).await.unwrap();
+        store.save(&earlier).await.unwrap();
+
+        let sessions = store.load_all().await.unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].started_at, 100);
+        assert_eq!(sessions[1].messages[0].content, "Hello");
+
+        let loaded = store.load(&later.id).await.unwrap();
+        assert_eq!(loaded.date_span().unwrap().0.to_string(), "1970-01-01");
+        assert!(store.load("missing").await.is_err());
+
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+}
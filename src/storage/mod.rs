@@ -0,0 +1,44 @@
+//! Session persistence
+
+pub mod autosave;
+pub mod file;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod schema;
+pub mod session;
+pub mod wal;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::path::Path;
+
+pub use autosave::Autosave;
+pub use file::FileStore;
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresStore;
+pub use session::{IMPORTED_TAG, StoredSession, normalize_tag};
+pub use wal::TurnLog;
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Human-readable description of where sessions are kept
+    fn location(&self) -> String;
+
+    async fn save(&self, session: &StoredSession) -> Result<()>;
+
+    /// All sessions, oldest first
+    async fn load_all(&self) -> Result<Vec<StoredSession>>;
+
+    async fn load(&self, id: &str) -> Result<StoredSession>;
+}
+
+/// Postgres when `DATABASE_URL` is set, JSON files in `SESSIONS_DIR` otherwise
+pub async fn open(database_url: Option<&str>, sessions_dir: &Path) -> Result<Box<dyn Storage>> {
+    match database_url {
+        #[cfg(feature = "postgres")]
+        Some(url) => Ok(Box::new(PostgresStore::connect(url).await?)),
+        #[cfg(not(feature = "postgres"))]
+        Some(_) => anyhow::bail!("DATABASE_URL is set but this build lacks the 'postgres' feature"),
+        None => Ok(Box::new(FileStore::new(sessions_dir))),
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::types::Json;
+use super::session::StoredSession;
+use super::{Storage, schema};
+
+const MAX_CONNECTIONS: u32 = 5;
+
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+/// The row as one JSON document, so it goes through the same migrations as
+/// session files
+const SELECT_DOCUMENT: &str = "SELECT jsonb_build_object(
+    'id', id, 'started_at', started_at, 'title', title, 'messages', messages,
+    'tags', tags, 'bookmarks', bookmarks, 'version', version
+) FROM sessions";
+
+impl PostgresStore {
+    pub async fn connect(url: &str) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(MAX_CONNECTIONS)
+            .connect(url)
+            .await
+            .context("failed to connect to Postgres")?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS sessions (
+                id TEXT PRIMARY KEY,
+                started_at BIGINT NOT NULL,
+                messages JSONB NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await
+        .context("failed to create sessions table")?;
+
+        // Added after the first release; existing tables lack them. Rows
+        // written before versioning read as version 0.
+        for column in [
+            "tags JSONB NOT NULL DEFAULT '[]'::jsonb",
+            "bookmarks JSONB NOT NULL DEFAULT '[]'::jsonb",
+            "version BIGINT NOT NULL DEFAULT 0",
+            "title TEXT",
+        ] {
+            sqlx::query(&format!("ALTER TABLE sessions ADD COLUMN IF NOT EXISTS {}", column))
+                .execute(&pool)
+                .await
+                .with_context(|| format!("failed to add column {}", column))?;
+        }
+
+        Ok(Self { pool })
+    }
+}
+
+fn from_document((Json(document),): (Json<serde_json::Value>,)) -> Result<StoredSession> {
+    let id = document["id"].as_str().unwrap_or_default().to_string();
+    schema::from_value(document).with_context(|| format!("failed to parse session '{}'", id))
+}
+
+#[async_trait]
+impl Storage for PostgresStore {
+    fn location(&self) -> String {
+        "Postgres".to_string()
+    }
+
+    #[tracing::instrument(name = "storage.save", skip_all, fields(backend = "postgres", session = %session.id))]
+    async fn save(&self, session: &StoredSession) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO sessions (id, started_at, messages, tags, bookmarks, version, title) VALUES ($1, $2, $3, $4, $5, $6, $7)
+             ON CONFLICT (id) DO UPDATE SET started_at = $2, messages = $3, tags = $4, bookmarks = $5, version = $6, title = $7",
+        )
+        .bind(&session.id)
+        .bind(session.started_at)
+        .bind(Json(&session.messages))
+        .bind(Json(&session.tags))
+        .bind(Json(&session.bookmarks))
+        .bind(schema::VERSION as i64)
+        .bind(&session.title)
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("failed to save session '{}'", session.id))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "storage.load_all", skip_all, fields(backend = "postgres"))]
+    async fn load_all(&self) -> Result<Vec<StoredSession>> {
+        let rows = sqlx::query_as(&format!("{} ORDER BY started_at", SELECT_DOCUMENT))
+            .fetch_all(&self.pool)
+            .await
+            .context("failed to load sessions")?;
+
+        rows.into_iter().map(from_document).collect()
+    }
+
+    #[tracing::instrument(name = "storage.load", skip(self), fields(backend = "postgres"))]
+    async fn load(&self, id: &str) -> Result<StoredSession> {
+        let row = sqlx::query_as(&format!("{} WHERE id = $1", SELECT_DOCUMENT))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .with_context(|| format!("failed to load session '{}'", id))?;
+
+        row.map(from_document)
+            .ok_or_else(|| anyhow::anyhow!("session '{}' not found in Postgres", id))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore = "needs a live database in TEST_DATABASE_URL"]
+    async fn test_save_and_load_roundtrip() {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL, e.g. postgres://localhost/tce_test");
+        let store = PostgresStore::connect(&url).await.unwrap();
+
+        let mut session = StoredSession::new(4_000_000_000, Vec::new());
+        store.save(&session).await.unwrap();
+        session.messages.push(crate::models::Message {
+            role: crate::models::MessageRole::User,
+            content: "Hello".to_string(),
+            timestamp: 4_000_000_000,
+            emotion: None,
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
+        });
+        session.tags.push("work".to_string());
+        session.bookmarks.push(0);
+        session.title = Some("A hello".to_string());
+        store.save(&session).await.unwrap();
+
+        let loaded = store.load(&session.id).await.unwrap();
+        assert_eq!(loaded.messages[0].content, "Hello");
+        assert_eq!(loaded.tags, ["work"]);
+        assert_eq!(loaded.bookmarks, [0]);
+        assert_eq!(loaded.title.as_deref(), Some("A hello"));
+        assert!(store.load_all().await.unwrap().iter().any(|s| s.id == session.id));
+        assert!(store.load("missing").await.is_err());
+
+        sqlx::query("DELETE FROM sessions WHERE id = $1")
+            .bind(&session.id)
+            .execute(&store.pool)
+            .await
+            .unwrap();
+    }
+}
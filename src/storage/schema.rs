@@ -0,0 +1,128 @@
+//! Versioned JSON for saved conversations
+//!
+//! Every saved session and conversation state carries a `version` field.
+//! Older documents are upgraded one version at a time on load, so a change to
+//! `Message` or `SentimentClassification` only needs a new migration step here
+//! instead of breaking what users already saved.
+
+use serde::de::{self, DeserializeOwned};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, ser};
+use serde_json::{Map, Value};
+
+/// Version written by this build
+pub const VERSION: u64 = 1;
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// `MIGRATIONS[n]` upgrades a document from version `n` to `n + 1`
+const MIGRATIONS: &[Migration] = &[v0_to_v1];
+
+const _: () = assert!(VERSION as usize == MIGRATIONS.len(), "add a migration when bumping VERSION");
+
+/// Documents from before versioning have no `version` field; their layout is
+/// the same as version 1
+fn v0_to_v1(_document: &mut Map<String, Value>) {}
+
+fn upgrade_with(value: Value, migrations: &[Migration]) -> Result<Value, String> {
+    let Value::Object(mut document) = value else {
+        return Err("expected a JSON object".to_string());
+    };
+
+    let version = match document.get("version") {
+        None => 0,
+        Some(version) => version.as_u64().ok_or("version must be a number")?,
+    };
+    let latest = migrations.len() as u64;
+    if version > latest {
+        return Err(format!("written by a newer release (format version {}, this build reads up to {})", version, latest));
+    }
+
+    for migration in &migrations[version as usize..] {
+        migration(&mut document);
+    }
+    document.insert("version".to_string(), latest.into());
+    Ok(Value::Object(document))
+}
+
+/// Brings a saved document up to the current version
+pub fn upgrade(value: Value) -> Result<Value, String> {
+    upgrade_with(value, MIGRATIONS)
+}
+
+pub fn to_value<T: Serialize>(data: &T) -> serde_json::Result<Value> {
+    let mut value = serde_json::to_value(data)?;
+    if let Value::Object(document) = &mut value {
+        document.insert("version".to_string(), VERSION.into());
+    }
+    Ok(value)
+}
+
+pub fn from_value<T: DeserializeOwned>(value: Value) -> serde_json::Result<T> {
+    let value = upgrade(value).map_err(<serde_json::Error as de::Error>::custom)?;
+    serde_json::from_value(value)
+}
+
+pub fn to_string<T: Serialize>(data: &T) -> serde_json::Result<String> {
+    serde_json::to_string(&to_value(data)?)
+}
+
+pub fn to_string_pretty<T: Serialize>(data: &T) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&to_value(data)?)
+}
+
+pub fn from_str<T: DeserializeOwned>(json: &str) -> serde_json::Result<T> {
+    from_value(serde_json::from_str(json)?)
+}
+
+/// For `#[serde(with = "crate::storage::schema")]` on fields that travel
+/// through the API
+pub fn serialize<T: Serialize, S: Serializer>(data: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    to_value(data).map_err(<S::Error as ser::Error>::custom)?.serialize(serializer)
+}
+
+pub fn deserialize<'de, T: DeserializeOwned, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+    from_value(Value::deserialize(deserializer)?).map_err(<D::Error as de::Error>::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::state::ConversationState;
+
+    #[test]
+    fn test_unversioned_documents_load() {
+        let state: ConversationState = from_str(r#"{"messages": [], "emotion_history": []}"#).unwrap();
+        assert!(state.messages.is_empty());
+
+        let json = to_value(&state).unwrap().to_string();
+        assert!(json.contains(r#""version":1"#));
+        assert!(from_str::<ConversationState>(&json).is_ok());
+    }
+
+    #[test]
+    fn test_newer_versions_are_refused() {
+        let error = from_str::<ConversationState>(r#"{"version": 99, "messages": []}"#).unwrap_err();
+        assert!(error.to_string().contains("newer release"));
+    }
+
+    #[test]
+    fn test_migrations_run_in_order() {
+        fn rename_text(document: &mut Map<String, Value>) {
+            if let Some(text) = document.remove("text") {
+                document.insert("content".to_string(), text);
+            }
+        }
+        fn add_tags(document: &mut Map<String, Value>) {
+            document.entry("tags").or_insert(json!([]));
+        }
+        let migrations: &[Migration] = &[v0_to_v1, rename_text, add_tags];
+
+        let upgraded = upgrade_with(json!({"version": 1, "text": "hi"}), migrations).unwrap();
+        assert_eq!(upgraded, json!({"version": 3, "content": "hi", "tags": []}));
+
+        // Already current: nothing runs
+        let current = upgrade_with(json!({"version": 3, "text": "kept"}), migrations).unwrap();
+        assert_eq!(current["text"], "kept");
+    }
+}
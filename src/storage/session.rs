@@ -0,0 +1,174 @@
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use crate::Sentiment;
+use crate::models::{Message, MessageRole};
+use crate::state::EmotionCounts;
+
+/// Words of the first message shown for a session saved without a title
+const PREVIEW_WORDS: usize = 8;
+
+/// Tag every session `import` writes gets
+pub const IMPORTED_TAG: &str = "imported";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredSession {
+    pub id: String,
+    pub started_at: i64,
+    /// A few words on what the conversation was about, written from its first turns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub messages: Vec<Message>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Indices into `messages` marked as important
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bookmarks: Vec<usize>,
+}
+
+impl StoredSession {
+    pub fn new(started_at: i64, messages: Vec<Message>) -> Self {
+        let id = DateTime::from_timestamp(started_at, 0)
+            .map(|dt| dt.format("%Y%m%d-%H%M%S").to_string())
+            .unwrap_or_else(|| started_at.to_string());
+
+        Self { id, started_at, title: None, messages, tags: Vec::new(), bookmarks: Vec::new() }
+    }
+
+    /// The title, or the opening words of the first user message for a
+    /// session saved without one
+    pub fn display_title(&self) -> String {
+        if let Some(title) = &self.title {
+            return title.clone();
+        }
+        let Some(first) = self.messages.iter().find(|m| matches!(m.role, MessageRole::User)) else {
+            return String::new();
+        };
+        let words: Vec<&str> = first.content.split_whitespace().collect();
+        if words.len() > PREVIEW_WORDS {
+            format!("{}…", words[..PREVIEW_WORDS].join(" "))
+        } else {
+            words.join(" ")
+        }
+    }
+
+    /// Messages from the user
+    pub fn turns(&self) -> usize {
+        self.messages.iter().filter(|m| matches!(m.role, MessageRole::User)).count()
+    }
+
+    /// The most common sentiment of the user's classified messages
+    pub fn dominant_emotion(&self) -> Option<Sentiment> {
+        let mut counts = EmotionCounts::default();
+        for emotion in self.messages.iter().filter_map(|m| m.emotion.as_ref()) {
+            counts.record(emotion.sentiment);
+        }
+        counts.dominant()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Imported from another app, so its messages may be other people's
+    pub fn is_imported(&self) -> bool {
+        self.has_tag(IMPORTED_TAG)
+    }
+
+    /// Bookmarked messages with their indices, in conversation order; stale
+    /// indices past the end are skipped
+    pub fn bookmarked(&self) -> impl Iterator<Item = (usize, &Message)> {
+        let mut indices = self.bookmarks.clone();
+        indices.sort_unstable();
+        indices.dedup();
+        indices.into_iter().filter_map(|i| self.messages.get(i).map(|msg| (i, msg)))
+    }
+
+    pub fn date_span(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let first = self.messages.iter().map(|m| m.timestamp).min()?;
+        let last = self.messages.iter().map(|m| m.timestamp).max()?;
+
+        let from = DateTime::from_timestamp(first, 0)?.date_naive();
+        let to = DateTime::from_timestamp(last, 0)?.date_naive();
+        Some((from, to))
+    }
+}
+
+/// Lowercase, without a leading `#`; `None` unless only letters, digits, `-`
+/// and `_` remain
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#').to_lowercase();
+    let valid = !tag.is_empty() && tag.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    valid.then_some(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_id_from_timestamp() {
+        let session = StoredSession::new(0, Vec::new());
+        assert_eq!(session.id, "19700101-000000");
+    }
+
+    #[test]
+    fn test_bookmarked_in_order() {
+        let message = |content: &str| Message {
+            role: crate::models::MessageRole::User,
+            content: content.to_string(),
+            timestamp: 0,
+            emotion: None,
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
+        };
+        let mut session = StoredSession::new(0, vec![message("a"), message("b"), message("c")]);
+        session.bookmarks = vec![2, 0, 2, 7];
+
+        let marked: Vec<_> = session.bookmarked().map(|(i, msg)| (i, msg.content.as_str())).collect();
+        assert_eq!(marked, [(0, "a"), (2, "c")]);
+    }
+
+    #[test]
+    fn test_listing_details() {
+        let message = |role, content: &str, sentiment: Option<Sentiment>| Message {
+            role,
+            content: content.to_string(),
+            timestamp: 0,
+            emotion: sentiment.map(|sentiment| crate::SentimentClassification { sentiment, confidence: 0.9, labels: Vec::new(), is_fallback: false }),
+            strategy: None,
+            speaker: None,
+            aspects: Vec::new(),
+            cause: None,
+            score: None,
+        };
+        let mut session = StoredSession::new(0, vec![
+            message(MessageRole::Assistant, "Hi! How are you today?", None),
+            message(MessageRole::User, "My exam is tomorrow and I have not slept properly in days", Some(Sentiment::Negative)),
+            message(MessageRole::Assistant, "That sounds exhausting.", None),
+            message(MessageRole::User, "Thanks, talking helps", Some(Sentiment::Positive)),
+            message(MessageRole::User, "Still scared though", Some(Sentiment::Negative)),
+        ]);
+
+        assert_eq!(session.display_title(), "My exam is tomorrow and I have not…");
+        assert_eq!((session.turns(), session.dominant_emotion()), (3, Some(Sentiment::Negative)));
+        session.title = Some("Exam nerves and lost sleep".to_string());
+        assert_eq!(session.display_title(), "Exam nerves and lost sleep");
+    }
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(normalize_tag("#Work-Stress").as_deref(), Some("work-stress"));
+        assert_eq!(normalize_tag(" sleep_2 ").as_deref(), Some("sleep_2"));
+        assert_eq!(normalize_tag("two words"), None);
+        assert_eq!(normalize_tag("#"), None);
+    }
+
+    #[test]
+    fn test_untagged_sessions_still_parse() {
+        let session: StoredSession = serde_json::from_str(r#"{"id":"a","started_at":0,"messages":[]}"#).unwrap();
+        assert!(session.tags.is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use crate::models::Message;
+use super::session::StoredSession;
+
+/// One write to the log: the messages a turn added to the session started at `started_at`
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    started_at: i64,
+    /// The messages are the whole session, as when one is resumed from storage
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    reset: bool,
+    messages: Vec<Message>,
+}
+
+/// Append-only log of a chat's turns, written before each turn is shown and
+/// removed once the session is saved, so a crashed session can be rebuilt
+pub struct TurnLog {
+    path: PathBuf,
+}
+
+impl TurnLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, started_at: i64, reset: bool, messages: &[Message]) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        let mut line = serde_json::to_string(&Record { started_at, reset, messages: messages.to_vec() })?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("failed to open {}", self.path.display()))?;
+        // A line cut short by a crash is ended, so this record starts on its own
+        let len = file.metadata()?.len();
+        if len > 0 {
+            let mut last = [0u8];
+            file.seek(SeekFrom::Start(len - 1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                line.insert(0, '\n');
+            }
+        }
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// The session the log ends with, or `None` when it holds nothing. A line
+    /// cut short by the crash is skipped.
+    pub fn recover(&self) -> Result<Option<StoredSession>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", self.path.display())),
+        };
+
+        let mut session: Option<StoredSession> = None;
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let Ok(record) = serde_json::from_str::<Record>(line) else {
+                tracing::warn!(path = %self.path.display(), "skipping unreadable turn log line");
+                continue;
+            };
+            match &mut session {
+                Some(session) if !record.reset && session.started_at == record.started_at => {
+                    session.messages.extend(record.messages);
+                }
+                _ => session = Some(StoredSession::new(record.started_at, record.messages)),
+            }
+        }
+        Ok(session.filter(|session| !session.messages.is_empty()))
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("failed to remove {}", self.path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MessageRole;
+
+    fn message(content: &str) -> Message {
+        Message { role: MessageRole::User, content: content.to_string(), timestamp: 100, emotion: None, strategy: None, speaker: None, aspects: Vec::new(), cause: None, score: None }
+    }
+
+    #[test]
+    fn test_recovers_the_last_session() {
+        let log = TurnLog::new(std::env::temp_dir().join(format!("tce-wal-{}", std::process::id())).join("turns.wal"));
+        let _ = log.clear();
+        assert!(log.recover().unwrap().is_none());
+
+        log.append(100, false, &[message("first session")]).unwrap();
+        log.append(200, false, &[message("hello"), message("hi")]).unwrap();
+        log.append(200, false, &[message("how are you")]).unwrap();
+        // Killed halfway through writing a line
+        OpenOptions::new().append(true).open(log.path()).unwrap().write_all(b"{\"started_at\":200,\"mess").unwrap();
+
+        let session = log.recover().unwrap().unwrap();
+        assert_eq!(session.started_at, 200);
+        let contents: Vec<&str> = session.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["hello", "hi", "how are you"]);
+
+        log.append(200, true, &[message("resumed")]).unwrap();
+        assert_eq!(log.recover().unwrap().unwrap().messages.len(), 1);
+
+        log.clear().unwrap();
+        assert!(log.recover().unwrap().is_none());
+        log.clear().unwrap();
+    }
+}
@@ -0,0 +1,241 @@
+use serde::Serialize;
+use std::fmt;
+use crate::{Sentiment, SentimentClassification};
+use crate::state::{EmotionForecast, EmotionTrend, TrendReading};
+use super::ResponseStrategy;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct StrategyWeight {
+    pub strategy: ResponseStrategy,
+    pub weight: f32,
+}
+
+/// Several strategies mixed by weight, heaviest first; weights sum to 1
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct StrategyBlend(Vec<StrategyWeight>);
+
+impl StrategyBlend {
+    pub fn single(strategy: ResponseStrategy) -> Self {
+        Self(vec![StrategyWeight { strategy, weight: 1.0 }])
+    }
+
+    /// Merges repeated strategies, drops non-positive weights and normalizes
+    /// the rest; an empty mix falls back to Neutral
+    pub fn new(parts: &[(ResponseStrategy, f32)]) -> Self {
+        let mut weights: Vec<StrategyWeight> = Vec::new();
+        for &(strategy, weight) in parts.iter().filter(|(_, w)| *w > 0.0) {
+            match weights.iter_mut().find(|w| w.strategy == strategy) {
+                Some(existing) => existing.weight += weight,
+                None => weights.push(StrategyWeight { strategy, weight }),
+            }
+        }
+
+        let total: f32 = weights.iter().map(|w| w.weight).sum();
+        if total <= 0.0 {
+            return Self::single(ResponseStrategy::Neutral);
+        }
+        weights.iter_mut().for_each(|w| w.weight /= total);
+        // Stable, so equal weights keep the order given
+        weights.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+        Self(weights)
+    }
+
+    /// The heaviest strategy, recorded on the message and in reports
+    pub fn primary(&self) -> ResponseStrategy {
+        self.0[0].strategy
+    }
+
+    pub fn parts(&self) -> &[StrategyWeight] {
+        &self.0
+    }
+
+    pub fn is_single(&self) -> bool {
+        self.0.len() == 1
+    }
+
+    /// `strategy` mixed in at `weight`, the existing parts scaled to fill the rest
+    pub fn mix(&self, strategy: ResponseStrategy, weight: f32) -> Self {
+        let mut parts: Vec<_> = self.0.iter().map(|w| (w.strategy, w.weight * (1.0 - weight))).collect();
+        parts.push((strategy, weight));
+        Self::new(&parts)
+    }
+}
+
+impl fmt::Display for StrategyBlend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_single() {
+            return write!(f, "{:?}", self.primary());
+        }
+        let parts: Vec<String> = self.0.iter().map(|w| format!("{:?} {:.0}%", w.strategy, w.weight * 100.0)).collect();
+        write!(f, "{}", parts.join(" + "))
+    }
+}
+
+/// Share of Empathetic a decline can add on top of its base weight, when it
+/// is as sharp and steady as it gets
+const DECLINE_WEIGHT: f32 = 0.2;
+
+/// Share of the blend answering the weaker side of a mixed message, scaled
+/// by that label's confidence
+const MIXED_WEIGHT: f32 = 0.5;
+
+/// Like [`super::select_strategy`], but mixes in the neighbouring strategy
+/// when the trend points away from the current emotion, so the tone shifts
+/// over several turns instead of switching at once. The sharper a decline,
+/// the more Empathetic the mix. The weaker side of a mixed message is
+/// answered too: Empathetic for nerves under excitement, Encouraging for
+/// hope under sadness.
+pub fn select_blend(emotion: &SentimentClassification, trend: TrendReading) -> StrategyBlend {
+    use ResponseStrategy::*;
+
+    let empathy = |base: f32| base + DECLINE_WEIGHT * trend.sharpness();
+    let parts: Vec<(ResponseStrategy, f32)> = match (emotion.sentiment, trend.direction) {
+        (Sentiment::Negative, EmotionTrend::Declining) => vec![(Empathetic, 1.0)],
+        (Sentiment::Negative, EmotionTrend::Stable) => vec![(Encouraging, 0.7), (Empathetic, 0.3)],
+        (Sentiment::Negative, EmotionTrend::Improving) => vec![(Empathetic, 0.7), (Encouraging, 0.3)],
+        (Sentiment::Neutral, EmotionTrend::Declining) => vec![(Neutral, 0.7), (Empathetic, empathy(0.3))],
+        (Sentiment::Neutral, EmotionTrend::Stable) => vec![(Neutral, 1.0)],
+        (Sentiment::Neutral, EmotionTrend::Improving) => vec![(Neutral, 0.7), (Encouraging, 0.3)],
+        (Sentiment::Positive, EmotionTrend::Declining) => vec![(Cheerful, 0.6), (Empathetic, empathy(0.4))],
+        (Sentiment::Positive, _) => vec![(Cheerful, 1.0)],
+    };
+    let blend = StrategyBlend::new(&parts);
+    match emotion.mixed() {
+        Some(label) if label.sentiment == Sentiment::Negative => blend.mix(Empathetic, MIXED_WEIGHT * label.confidence),
+        Some(label) => blend.mix(Encouraging, MIXED_WEIGHT * label.confidence),
+        None => blend,
+    }
+}
+
+/// Forecast confidence needed before answering ahead of a turn for the worse
+const PREEMPT_CONFIDENCE: f32 = 0.5;
+
+/// Share of Encouraging mixed into a blend ahead of a turn for the worse
+const PREEMPT_WEIGHT: f32 = 0.3;
+
+/// Leans light-hearted strategies towards Encouraging when the forecast
+/// expects the user's next message to be negative: a single strategy is
+/// replaced, a blend gets some Encouraging mixed in
+pub fn preempt(blend: StrategyBlend, forecast: &EmotionForecast) -> StrategyBlend {
+    let expects_negative = forecast.sentiment == Sentiment::Negative && forecast.confidence >= PREEMPT_CONFIDENCE;
+    let light = matches!(blend.primary(), ResponseStrategy::Neutral | ResponseStrategy::Cheerful);
+    if !expects_negative || !light {
+        blend
+    } else if blend.is_single() {
+        StrategyBlend::single(ResponseStrategy::Encouraging)
+    } else {
+        blend.mix(ResponseStrategy::Encouraging, PREEMPT_WEIGHT)
+    }
+}
+
+/// One preamble combining each strategy's prompt with its share
+pub fn blend_preamble<'a>(blend: &StrategyBlend, prompt: impl Fn(ResponseStrategy) -> &'a str) -> String {
+    if blend.is_single() {
+        return prompt(blend.primary()).to_string();
+    }
+
+    let mut preamble = String::from(
+        "Blend the following response styles, giving each the share of your tone shown. \
+         Lead with the first and let the others soften or lift it, so the reply feels like one \
+         consistent voice rather than a switch between styles.\n",
+    );
+    for part in blend.parts() {
+        preamble.push_str(&format!("\n[{:.0}%] {}\n", part.weight * 100.0, prompt(part.strategy)));
+    }
+    preamble
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EmotionLabel;
+
+    fn emotion(sentiment: Sentiment) -> SentimentClassification {
+        SentimentClassification { sentiment, confidence: 0.8, labels: Vec::new(), is_fallback: false }
+    }
+
+    fn trend(direction: EmotionTrend) -> TrendReading {
+        TrendReading { direction, ..TrendReading::stable() }
+    }
+
+    #[test]
+    fn test_new_merges_and_normalizes() {
+        let blend = StrategyBlend::new(&[
+            (ResponseStrategy::Encouraging, 1.0),
+            (ResponseStrategy::Empathetic, 3.0),
+            (ResponseStrategy::Encouraging, 1.0),
+            (ResponseStrategy::Cheerful, 0.0),
+        ]);
+        assert_eq!(blend.primary(), ResponseStrategy::Empathetic);
+        assert_eq!(blend.parts().len(), 2);
+        assert!((blend.parts()[1].weight - 0.4).abs() < 1e-6);
+        assert_eq!(blend.to_string(), "Empathetic 60% + Encouraging 40%");
+
+        assert_eq!(StrategyBlend::new(&[]), StrategyBlend::single(ResponseStrategy::Neutral));
+    }
+
+    #[test]
+    fn test_negative_but_improving_leads_with_empathy() {
+        let blend = select_blend(&emotion(Sentiment::Negative), trend(EmotionTrend::Improving));
+        assert_eq!(blend.to_string(), "Empathetic 70% + Encouraging 30%");
+
+        let blend = select_blend(&emotion(Sentiment::Positive), trend(EmotionTrend::Stable));
+        assert!(blend.is_single());
+    }
+
+    #[test]
+    fn test_mixed_emotions_answer_both_sides() {
+        let label = |sentiment, confidence| EmotionLabel { sentiment, confidence };
+        let excited_but_nervous = SentimentClassification {
+            labels: vec![label(Sentiment::Positive, 0.8), label(Sentiment::Negative, 0.6)],
+            ..emotion(Sentiment::Positive)
+        };
+        let blend = select_blend(&excited_but_nervous, trend(EmotionTrend::Stable));
+        assert_eq!(blend.to_string(), "Cheerful 70% + Empathetic 30%");
+
+        let sad_but_hopeful = SentimentClassification {
+            labels: vec![label(Sentiment::Negative, 0.8), label(Sentiment::Positive, 0.4)],
+            ..emotion(Sentiment::Negative)
+        };
+        let blend = select_blend(&sad_but_hopeful, trend(EmotionTrend::Declining));
+        assert_eq!(blend.to_string(), "Empathetic 80% + Encouraging 20%");
+    }
+
+    #[test]
+    fn test_sharper_declines_mix_in_more_empathy() {
+        let gentle = select_blend(&emotion(Sentiment::Neutral), trend(EmotionTrend::Declining));
+        assert_eq!(gentle.to_string(), "Neutral 70% + Empathetic 30%");
+
+        let sharp = TrendReading { direction: EmotionTrend::Declining, magnitude: 1.0, confidence: 0.5 };
+        assert_eq!(select_blend(&emotion(Sentiment::Neutral), sharp).to_string(), "Neutral 64% + Empathetic 36%");
+        let steep = TrendReading { magnitude: 1.0, confidence: 1.0, ..sharp };
+        assert_eq!(select_blend(&emotion(Sentiment::Positive), steep).to_string(), "Cheerful 50% + Empathetic 50%");
+    }
+
+    #[test]
+    fn test_preempt_only_lightens_on_confident_negative_forecast() {
+        let forecast = |sentiment, confidence| EmotionForecast { sentiment, score: 0.0, confidence };
+        let cheerful = StrategyBlend::single(ResponseStrategy::Cheerful);
+
+        let blend = preempt(cheerful.clone(), &forecast(Sentiment::Negative, 0.8));
+        assert_eq!(blend, StrategyBlend::single(ResponseStrategy::Encouraging));
+        assert_eq!(preempt(cheerful.clone(), &forecast(Sentiment::Negative, 0.2)), cheerful);
+        assert_eq!(preempt(cheerful.clone(), &forecast(Sentiment::Neutral, 0.9)), cheerful);
+
+        let blend = select_blend(&emotion(Sentiment::Neutral), trend(EmotionTrend::Declining));
+        let blend = preempt(blend, &forecast(Sentiment::Negative, 0.8));
+        assert_eq!(blend.to_string(), "Neutral 49% + Encouraging 30% + Empathetic 21%");
+    }
+
+    #[test]
+    fn test_blend_preamble_lists_weighted_prompts() {
+        let blend = select_blend(&emotion(Sentiment::Negative), trend(EmotionTrend::Improving));
+        let preamble = blend_preamble(&blend, ResponseStrategy::to_prompt);
+        assert!(preamble.contains(&format!("[70%] {}", ResponseStrategy::Empathetic.to_prompt())));
+        assert!(preamble.contains(&format!("[30%] {}", ResponseStrategy::Encouraging.to_prompt())));
+
+        let single = StrategyBlend::single(ResponseStrategy::Cheerful);
+        assert_eq!(blend_preamble(&single, ResponseStrategy::to_prompt), ResponseStrategy::Cheerful.to_prompt());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::models::{Message, MessageRole};
+use super::ResponseStrategy;
+
+/// How readily the strategy may change between turns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hysteresis {
+    /// Replies a strategy is kept for before another may replace it
+    pub min_turns: usize,
+    /// A classification at least this confident switches straight away
+    pub switch_confidence: f32,
+}
+
+impl Default for Hysteresis {
+    /// Switches whenever the selection changes
+    fn default() -> Self {
+        Self { min_turns: 1, switch_confidence: 1.0 }
+    }
+}
+
+impl Hysteresis {
+    /// The strategy to keep instead of `proposed`, or `None` when switching is allowed
+    pub fn hold(&self, history: &[Message], proposed: ResponseStrategy, confidence: f32) -> Option<ResponseStrategy> {
+        let (current, turns) = current_run(history)?;
+        if current == proposed || turns >= self.min_turns || confidence >= self.switch_confidence {
+            return None;
+        }
+        Some(current)
+    }
+}
+
+/// The latest assistant strategy and how many replies in a row used it
+fn current_run(history: &[Message]) -> Option<(ResponseStrategy, usize)> {
+    let mut replies = history
+        .iter()
+        .rev()
+        .filter(|m| matches!(m.role, MessageRole::Assistant))
+        .map(|m| m.strategy);
+    let current = replies.next()??;
+    let turns = 1 + replies.take_while(|s| *s == Some(current)).count();
+    Some((current, turns))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(strategy: ResponseStrategy) -> [Message; 2] {
+        let message = |role| Message { role, content: String::new(), timestamp: 0, emotion: None, strategy: None, speaker: None, aspects: Vec::new(), cause: None, score: None };
+        let mut assistant = message(MessageRole::Assistant);
+        assistant.strategy = Some(strategy);
+        [message(MessageRole::User), assistant]
+    }
+
+    #[test]
+    fn test_holds_until_min_turns() {
+        let policy = Hysteresis { min_turns: 2, switch_confidence: 0.9 };
+        let mut history = reply(ResponseStrategy::Neutral).to_vec();
+        history.extend(reply(ResponseStrategy::Empathetic));
+
+        assert_eq!(policy.hold(&history, ResponseStrategy::Cheerful, 0.6), Some(ResponseStrategy::Empathetic));
+        assert_eq!(policy.hold(&history, ResponseStrategy::Empathetic, 0.6), None);
+
+        history.extend(reply(ResponseStrategy::Empathetic));
+        assert_eq!(policy.hold(&history, ResponseStrategy::Cheerful, 0.6), None);
+    }
+
+    #[test]
+    fn test_confident_classification_switches_early() {
+        let policy = Hysteresis { min_turns: 3, switch_confidence: 0.9 };
+        let history = reply(ResponseStrategy::Empathetic);
+
+        assert_eq!(policy.hold(&history, ResponseStrategy::Cheerful, 0.95), None);
+        assert_eq!(policy.hold(&[], ResponseStrategy::Cheerful, 0.1), None);
+        assert_eq!(Hysteresis::default().hold(&history, ResponseStrategy::Cheerful, 0.1), None);
+    }
+}
@@ -1,5 +1,9 @@
 //! Response strategy selection
 
+pub mod blend;
+pub mod hysteresis;
 pub mod response;
 
-pub use response::{ResponseStrategy, select_strategy};
+pub use blend::{StrategyBlend, blend_preamble, preempt, select_blend};
+pub use hysteresis::Hysteresis;
+pub use response::{ResponseStrategy, select_strategy, select_strategy_for_trend};
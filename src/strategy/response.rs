@@ -1,6 +1,9 @@
-use crate::{Sentiment, SentimentClassification, state::EmotionTrend};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::{Sentiment, SentimentClassification};
+use crate::state::{EmotionTrend, TrendReading};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
 pub enum ResponseStrategy {
     Empathetic,
     Encouraging,
@@ -9,7 +12,19 @@ pub enum ResponseStrategy {
 }
 
 impl ResponseStrategy {
-    pub fn to_prompt(&self) -> &'static str {
+    pub const ALL: [ResponseStrategy; 4] = [
+        ResponseStrategy::Empathetic,
+        ResponseStrategy::Encouraging,
+        ResponseStrategy::Neutral,
+        ResponseStrategy::Cheerful,
+    ];
+
+    /// Case-insensitive lookup by variant name, e.g. `empathetic`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| format!("{:?}", s).eq_ignore_ascii_case(name))
+    }
+
+    pub fn to_prompt(self) -> &'static str {
         match self {
             ResponseStrategy::Empathetic => {
                 "You are an empathetic listener. The user is going through a difficult time.
@@ -35,18 +50,38 @@ impl ResponseStrategy {
     }
 }
 
+/// A mixed message ("excited but nervous") gets Encouraging in place of
+/// Cheerful, and a recovering one in place of Neutral
 pub fn select_strategy(
     emotion: &SentimentClassification,
     trend: EmotionTrend,
 ) -> ResponseStrategy {
+    let mixed = emotion.mixed().is_some();
     match (emotion.sentiment, trend) {
         (Sentiment::Negative, EmotionTrend::Declining) => ResponseStrategy::Empathetic,
         (Sentiment::Negative, EmotionTrend::Stable) => ResponseStrategy::Encouraging,
+        (Sentiment::Negative, EmotionTrend::Improving) if mixed => ResponseStrategy::Encouraging,
+        (Sentiment::Positive, _) if mixed => ResponseStrategy::Encouraging,
         (Sentiment::Positive, _) => ResponseStrategy::Cheerful,
         _ => ResponseStrategy::Neutral,
     }
 }
 
+/// How sharply a conversation must be declining before a message that is not
+/// positive gets Empathetic, whatever it reads as on its own
+const SHARP_DECLINE: f32 = 0.5;
+
+/// `select_strategy` with the trend's strength counted as well as its
+/// direction, so a steep, steady slide is met with empathy even when the
+/// latest message alone reads as neutral
+pub fn select_strategy_for_trend(emotion: &SentimentClassification, trend: TrendReading) -> ResponseStrategy {
+    let sharp_decline = trend.direction == EmotionTrend::Declining && trend.sharpness() >= SHARP_DECLINE;
+    if sharp_decline && emotion.sentiment != Sentiment::Positive {
+        return ResponseStrategy::Empathetic;
+    }
+    select_strategy(emotion, trend.direction)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -58,6 +93,8 @@ mod tests {
         let emotion = SentimentClassification {
             sentiment: Sentiment::Negative,
             confidence: 0.8,
+            labels: Vec::new(),
+            is_fallback: false,
         };
 
         let strategy = select_strategy(&emotion, EmotionTrend::Declining);
@@ -71,6 +108,8 @@ mod tests {
         let emotion = SentimentClassification {
             sentiment: Sentiment::Negative,
             confidence: 0.8,
+            labels: Vec::new(),
+            is_fallback: false,
         };
 
         let strategy = select_strategy(&emotion, EmotionTrend::Stable);
@@ -84,6 +123,8 @@ mod tests {
         let emotion = SentimentClassification {
             sentiment: Sentiment::Positive,
             confidence: 0.8,
+            labels: Vec::new(),
+            is_fallback: false,
         };
 
         let strategy = select_strategy(&emotion, EmotionTrend::Improving);
@@ -97,12 +138,54 @@ mod tests {
         let emotion = SentimentClassification {
             sentiment: Sentiment::Neutral,
             confidence: 0.5,
+            labels: Vec::new(),
+            is_fallback: false,
         };
 
         let strategy = select_strategy(&emotion, EmotionTrend::Stable);
         assert_eq!(strategy, ResponseStrategy::Neutral);
     }
 
+    #[test]
+    fn test_select_strategy_mixed() {
+        use crate::{EmotionLabel, state::EmotionTrend};
+
+        let emotion = SentimentClassification {
+            sentiment: Sentiment::Positive,
+            confidence: 0.7,
+            labels: vec![
+                EmotionLabel { sentiment: Sentiment::Positive, confidence: 0.7 },
+                EmotionLabel { sentiment: Sentiment::Negative, confidence: 0.5 },
+            ],
+            is_fallback: false,
+        };
+
+        assert_eq!(select_strategy(&emotion, EmotionTrend::Stable), ResponseStrategy::Encouraging);
+        let emotion = SentimentClassification { sentiment: Sentiment::Negative, ..emotion };
+        assert_eq!(select_strategy(&emotion, EmotionTrend::Improving), ResponseStrategy::Encouraging);
+        assert_eq!(select_strategy(&emotion, EmotionTrend::Declining), ResponseStrategy::Empathetic);
+    }
+
+    #[test]
+    fn test_sharp_decline_escalates_to_empathetic() {
+        let emotion = SentimentClassification { sentiment: Sentiment::Neutral, confidence: 0.8, labels: Vec::new(), is_fallback: false };
+        let gentle = TrendReading { direction: EmotionTrend::Declining, magnitude: 0.3, confidence: 0.9 };
+        let sharp = TrendReading { magnitude: 0.8, ..gentle };
+        assert_eq!(select_strategy_for_trend(&emotion, gentle), ResponseStrategy::Neutral);
+        assert_eq!(select_strategy_for_trend(&emotion, sharp), ResponseStrategy::Empathetic);
+        // Unless the steepness is in doubt, or the message itself is positive
+        assert_eq!(select_strategy_for_trend(&emotion, TrendReading { confidence: 0.3, ..sharp }), ResponseStrategy::Neutral);
+        let positive = SentimentClassification { sentiment: Sentiment::Positive, ..emotion };
+        assert_eq!(select_strategy_for_trend(&positive, sharp), ResponseStrategy::Cheerful);
+    }
+
+    #[test]
+    fn test_strategy_from_name() {
+        assert_eq!(ResponseStrategy::from_name("cheerful"), Some(ResponseStrategy::Cheerful));
+        assert_eq!(ResponseStrategy::from_name("EMPATHETIC"), Some(ResponseStrategy::Empathetic));
+        assert_eq!(ResponseStrategy::from_name("grumpy"), None);
+    }
+
     #[test]
     fn test_strategy_prompts() {
         let prompts = vec![
@@ -0,0 +1,95 @@
+//! Tracing setup. Spans are always recorded through `tracing`; with the `otel`
+//! feature and `OTEL_EXPORTER_OTLP_ENDPOINT` set they are exported over OTLP.
+
+use anyhow::Result;
+use axum::http::Request;
+use tracing::Span;
+
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+#[cfg(feature = "otel")]
+pub fn init() -> Result<Telemetry> {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::SpanExporter;
+    use opentelemetry_sdk::Resource;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    if std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_none() {
+        return Ok(Telemetry { provider: None });
+    }
+
+    let service_name = std::env::var("OTEL_SERVICE_NAME")
+        .unwrap_or_else(|_| env!("CARGO_PKG_NAME").to_string());
+
+    let exporter = SpanExporter::builder().with_tonic().build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service_name).build())
+        .build();
+
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()?;
+
+    Ok(Telemetry { provider: Some(provider) })
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn init() -> Result<Telemetry> {
+    Ok(Telemetry {})
+}
+
+impl Telemetry {
+    /// Flushes spans still buffered for export
+    pub fn shutdown(self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("⚠️  Failed to flush traces: {}", e);
+        }
+    }
+}
+
+/// Root span for an incoming HTTP or gRPC request, continuing the caller's
+/// trace when it sent a `traceparent` header
+pub fn request_span<B>(request: &Request<B>) -> Span {
+    let span = tracing::info_span!(
+        "request",
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+
+    #[cfg(feature = "otel")]
+    {
+        use opentelemetry::propagation::Extractor;
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+        struct HeaderExtractor<'a>(&'a axum::http::HeaderMap);
+
+        impl Extractor for HeaderExtractor<'_> {
+            fn get(&self, key: &str) -> Option<&str> {
+                self.0.get(key).and_then(|v| v.to_str().ok())
+            }
+
+            fn keys(&self) -> Vec<&str> {
+                self.0.keys().map(|k| k.as_str()).collect()
+            }
+        }
+
+        let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(request.headers()))
+        });
+        let _ = span.set_parent(parent);
+    }
+
+    span
+}
@@ -0,0 +1,222 @@
+//! The built-in stages of a turn, in their default order
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use crate::{Sentiment, handoff, injection};
+use crate::agents::assessment::Decision;
+use crate::hooks::StrategyContext;
+use crate::state::{ConversationManager, limits};
+use crate::strategy::{StrategyBlend, preempt, select_blend, select_strategy_for_trend};
+use super::StrategyMode;
+use super::pipeline::{Stage, Turn};
+
+/// The stage that calls the chat model, which streaming transports replace
+pub const RESPOND: &str = "respond";
+
+/// Rewrites the message through the input hooks
+pub struct Normalize;
+
+#[async_trait]
+impl Stage for Normalize {
+    fn name(&self) -> &str {
+        "normalize"
+    }
+
+    async fn run(&self, turn: &mut Turn<'_>, _state: &mut ConversationManager) -> Result<()> {
+        turn.input = turn.hooks.preprocess(&turn.input, &turn.snapshot);
+        Ok(())
+    }
+}
+
+/// Strips role markers from the message and flags one that reads like
+/// instructions to the model, so the reply treats it as conversation
+pub struct Sanitize;
+
+#[async_trait]
+impl Stage for Sanitize {
+    fn name(&self) -> &str {
+        "sanitize"
+    }
+
+    async fn run(&self, turn: &mut Turn<'_>, _state: &mut ConversationManager) -> Result<()> {
+        if let Some(signal) = injection::detect(&turn.input) {
+            tracing::warn!(%signal, "message reads like instructions to the model");
+            turn.input = injection::neutralize(&turn.input);
+            turn.injection = Some(signal);
+        }
+        Ok(())
+    }
+}
+
+/// Classifies the message and records it with its emotion, unless the
+/// conversation is waiting for an operator
+pub struct Classify;
+
+#[async_trait]
+impl Stage for Classify {
+    fn name(&self) -> &str {
+        "classify"
+    }
+
+    async fn run(&self, turn: &mut Turn<'_>, state: &mut ConversationManager) -> Result<()> {
+        // Only an operator answers a paused conversation, so there is nothing to choose a strategy for
+        if state.handoff().is_some() {
+            return Ok(());
+        }
+        let previous = state.state().emotion_history.last().cloned();
+        let detector = turn.detector;
+        let aspects = async {
+            if !detector.extracts_aspects() {
+                return Vec::new();
+            }
+            // Aspects only enrich the record, so a failure costs nothing else
+            detector.aspects(&turn.input).await.unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "aspect extraction failed");
+                Vec::new()
+            })
+        };
+        let (emotion, aspects) = futures::join!(detector.analyze_after(&turn.input, previous.as_ref()), aspects);
+        let emotion = emotion.context("Emotion detection failed")?;
+        let emotion = turn.hooks.adjust_emotion(emotion, &turn.snapshot);
+        if detector.extracts_causes() && emotion.sentiment == Sentiment::Negative {
+            turn.cause = detector.cause(&turn.input).await.unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "cause extraction failed");
+                None
+            });
+        }
+
+        turn.record_input(state);
+        state.update_emotion(emotion.clone());
+        state.update_aspects(aspects);
+        state.update_cause(turn.cause.clone());
+        turn.trend = state.get_recent_emotion_trend();
+        turn.emotion = Some(emotion);
+        Ok(())
+    }
+}
+
+/// Answers without the chat model once the message is classified: a
+/// conversation waiting for an operator, a crisis or sustained negativity
+/// hands off, and blocked topics are declined
+pub struct Moderate;
+
+#[async_trait]
+impl Stage for Moderate {
+    fn name(&self) -> &str {
+        "moderate"
+    }
+
+    async fn run(&self, turn: &mut Turn<'_>, state: &mut ConversationManager) -> Result<()> {
+        if let Some((reason, escalated)) = handoff::update(state, &turn.input, turn.policy.handoff) {
+            turn.hand_off(state, reason, escalated);
+        } else if let Some(topic) = turn.topics.check(&turn.input).await {
+            tracing::info!(topic = %topic.name, "declining a blocked topic");
+            turn.refuse(state, &topic.name, topic.refusal());
+        }
+        Ok(())
+    }
+}
+
+/// Chooses the strategy, with a break reminder when the sitting has run long
+pub struct Strategize;
+
+#[async_trait]
+impl Stage for Strategize {
+    fn name(&self) -> &str {
+        "strategize"
+    }
+
+    async fn run(&self, turn: &mut Turn<'_>, state: &mut ConversationManager) -> Result<()> {
+        let emotion = turn.emotion();
+        let (policy, trend) = (turn.policy, turn.trend);
+        let calibrated = state
+            .baseline()
+            .map(|baseline| baseline.calibrate(&emotion))
+            .filter(|calibrated| calibrated.sentiment != emotion.sentiment);
+        let selected = calibrated.as_ref().unwrap_or(&emotion);
+        let forecast = state.forecast_next_emotion();
+        let (blend, suppressed) = tracing::info_span!("strategy", ?trend, ?forecast, mode = ?policy.mode).in_scope(|| {
+            let blend = match policy.mode {
+                StrategyMode::Single => StrategyBlend::single(select_strategy_for_trend(selected, trend)),
+                StrategyMode::Blended => select_blend(selected, trend),
+                StrategyMode::Pinned(strategy) => return (StrategyBlend::single(strategy), None),
+            };
+            let blend = match &forecast {
+                Some(forecast) if policy.preempt => preempt(blend, forecast),
+                _ => blend,
+            };
+            match policy.hysteresis.hold(state.get_history(), blend.primary(), emotion.confidence) {
+                Some(held) => {
+                    tracing::info!(?held, suppressed = ?blend.primary(), "strategy switch suppressed");
+                    (StrategyBlend::single(held), Some(blend.primary()))
+                }
+                None => (blend, None),
+            }
+        });
+        let context = StrategyContext { emotion: selected, trend: trend.direction, strategy: blend.primary(), turns: state.user_turns() };
+        turn.blend = match turn.hooks.adjust_strategy(context) {
+            Some(strategy) => {
+                tracing::info!(?strategy, replaced = ?blend.primary(), "strategy set by a hook");
+                StrategyBlend::single(strategy)
+            }
+            None => blend,
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        turn.reminder = state.break_due(&policy.limits, now).then(|| {
+            let (elapsed, turns) = state.sitting(now);
+            limits::break_reminder(elapsed, turns)
+        });
+        turn.calibrated = calibrated;
+        turn.forecast = forecast;
+        turn.suppressed = suppressed;
+        Ok(())
+    }
+}
+
+/// Generates the reply with the chat model
+pub struct Respond;
+
+#[async_trait]
+impl Stage for Respond {
+    fn name(&self) -> &str {
+        RESPOND
+    }
+
+    async fn run(&self, turn: &mut Turn<'_>, state: &mut ConversationManager) -> Result<()> {
+        let chat_agent = turn.chat_agent.context("No chat agent to generate the reply")?;
+        turn.record_input(state);
+        let emotion = turn.emotion();
+        let decision = Decision { emotion: &emotion, trend: turn.trend.direction, blend: &turn.blend, suppressed: turn.suppressed };
+        let assessment = async {
+            if !chat_agent.assesses() {
+                return None;
+            }
+            // The assessment is only shown on request, so a failure leaves the reply alone
+            chat_agent.assess(&turn.input, &decision).await.inspect_err(|e| tracing::warn!(error = %e, "assessment failed")).ok()
+        };
+        let aside = turn.aside();
+        let reply = chat_agent.respond(&turn.input, &turn.blend, state.get_history(), aside.as_deref());
+        let (reply, assessment) = futures::join!(reply, assessment);
+        turn.reply = Some(reply.context("Response generation failed")?);
+        turn.assessment = assessment;
+        Ok(())
+    }
+}
+
+/// Rewrites the reply through the output hooks
+pub struct Postprocess;
+
+#[async_trait]
+impl Stage for Postprocess {
+    fn name(&self) -> &str {
+        "postprocess"
+    }
+
+    async fn run(&self, turn: &mut Turn<'_>, _state: &mut ConversationManager) -> Result<()> {
+        if let Some(reply) = &mut turn.reply {
+            reply.text = turn.hooks.postprocess(std::mem::take(&mut reply.text));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+//! Framing and guard rails for public deployments (`--wellbeing-safe`)
+
+use crate::models::{Message, MessageRole};
+
+/// Appended to every strategy prompt
+pub const FRAMING: &str = "Important: you are an AI assistant, not a therapist, counsellor or doctor, \
+     and you must never present yourself as one. Do not diagnose the user or name a condition they \
+     might have, and do not suggest medication or treatment. When the user describes ongoing distress, \
+     health worries or thoughts of harming themselves, gently encourage them to talk to a qualified \
+     professional or someone they trust.";
+
+/// Added to the first reply and then every few replies
+pub const DISCLAIMER: &str = "(I'm an AI assistant, not a therapist. If things feel heavy, talking to a doctor, \
+     counsellor or someone you trust can really help.)";
+
+/// Replaces whatever a reply tried to diagnose
+pub const DIAGNOSIS_REDIRECT: &str = "I can't diagnose anything, but a doctor or mental health professional can \
+     help you make sense of what you're going through.";
+
+/// Replies between disclaimers
+const DISCLAIMER_EVERY: usize = 5;
+
+/// Phrases that attribute something to the user, or name what "it" is
+const DIAGNOSTIC_LEADS: &[&str] = &[
+    "you have",
+    "you've got",
+    "you might have",
+    "you may have",
+    "you could have",
+    "you probably have",
+    "you likely have",
+    "you suffer from",
+    "you are suffering from",
+    "you're suffering from",
+    "you are clinically",
+    "you're clinically",
+    "sounds like",
+    "seems like",
+    "signs of",
+    "symptoms of",
+];
+
+const CONDITIONS: &[&str] = &[
+    "depression",
+    "anxiety disorder",
+    "generalized anxiety",
+    "panic disorder",
+    "bipolar",
+    "ptsd",
+    "post-traumatic stress",
+    "adhd",
+    "ocd",
+    "obsessive-compulsive",
+    "personality disorder",
+    "schizophreni",
+    "autis",
+    "eating disorder",
+    "anorexia",
+    "bulimia",
+    "insomnia",
+    "burnout syndrome",
+    "clinically",
+];
+
+/// Adds the mandatory framing to a strategy preamble
+pub fn frame(preamble: &str) -> String {
+    format!("{}\n\n{}", preamble, FRAMING)
+}
+
+/// Strips diagnoses from a reply and adds the disclaimer when one is due.
+/// `history` holds the conversation before this reply.
+pub fn guard(reply: &str, history: &[Message]) -> String {
+    let mut guarded = strip_diagnoses(reply);
+    let replies = history.iter().filter(|m| matches!(m.role, MessageRole::Assistant)).count();
+    if replies % DISCLAIMER_EVERY == 0 {
+        guarded.push_str("\n\n");
+        guarded.push_str(DISCLAIMER);
+    }
+    guarded
+}
+
+/// Drops every sentence that names a condition the user might have, ending
+/// with a redirect to a professional instead
+pub fn strip_diagnoses(reply: &str) -> String {
+    let sentences = sentences(reply);
+    let kept: Vec<&str> = sentences.iter().copied().filter(|s| !is_diagnosis(s)).collect();
+    if kept.len() == sentences.len() {
+        return reply.to_string();
+    }
+    tracing::warn!(dropped = sentences.len() - kept.len(), "removed a diagnosis from the reply");
+
+    let mut stripped = kept.join(" ");
+    if !stripped.is_empty() {
+        stripped.push(' ');
+    }
+    stripped.push_str(DIAGNOSIS_REDIRECT);
+    stripped
+}
+
+fn is_diagnosis(sentence: &str) -> bool {
+    let lowered = sentence.to_lowercase();
+    DIAGNOSTIC_LEADS.iter().any(|lead| lowered.contains(lead)) && CONDITIONS.iter().any(|c| lowered.contains(c))
+}
+
+/// Splits after `.`, `!` and `?` followed by whitespace, trimming each sentence
+fn sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if matches!(c, '.' | '!' | '?') && chars.peek().is_some_and(|(_, next)| next.is_whitespace()) {
+            sentences.push(text[start..=i].trim());
+            start = i + 1;
+        }
+    }
+    sentences.push(text[start..].trim());
+    sentences.retain(|s| !s.is_empty());
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::ConversationManager;
+
+    fn replies(count: usize) -> Vec<Message> {
+        let mut state = ConversationManager::new();
+        for _ in 0..count {
+            state.add_message(MessageRole::User, "...");
+            state.add_message(MessageRole::Assistant, "...");
+        }
+        state.get_history().to_vec()
+    }
+
+    #[test]
+    fn test_diagnoses_are_replaced() {
+        let reply = "That sounds exhausting. It sounds like you have depression! Let's take it one step at a time.";
+        assert_eq!(
+            strip_diagnoses(reply),
+            format!("That sounds exhausting. Let's take it one step at a time. {}", DIAGNOSIS_REDIRECT)
+        );
+        assert_eq!(strip_diagnoses("You might have ADHD"), DIAGNOSIS_REDIRECT);
+
+        let fine = "It sounds like you're feeling low. Have you been sleeping?";
+        assert_eq!(strip_diagnoses(fine), fine);
+    }
+
+    #[test]
+    fn test_disclaimer_on_first_and_every_fifth_reply() {
+        assert!(guard("Hi!", &[]).ends_with(DISCLAIMER));
+        assert_eq!(guard("Hi!", &replies(1)), "Hi!");
+        assert_eq!(guard("Hi!", &replies(4)), "Hi!");
+        assert!(guard("Hi!", &replies(5)).ends_with(DISCLAIMER));
+    }
+}
@@ -0,0 +1,231 @@
+//! Golden transcripts: scripted conversations in `tests/golden/*.json` run
+//! through the whole turn pipeline against a mock provider. Each turn states
+//! what the provider answers and what the turn must decide; the serialized
+//! turns and final state must also match `<name>.snap.json`, which only
+//! `UPDATE_GOLDEN=1 cargo test --test golden` writes, a missing one failing.
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+use text_classifier_extractor::agents::{ChatAgent, EmotionDetector, TopicGuard};
+use text_classifier_extractor::handoff::HandoffPolicy;
+use text_classifier_extractor::mock::{MOCK_MODEL, MockProvider};
+use text_classifier_extractor::state::ConversationManager;
+use text_classifier_extractor::turn::{Pipeline, StrategyMode, StrategyPolicy, TurnOutcome, run_turn};
+use rig::providers::openai;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Transcript {
+    #[serde(default)]
+    policy: Policy,
+    turns: Vec<ScriptedTurn>,
+    #[serde(default)]
+    expect_state: Option<ExpectState>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Policy {
+    #[serde(default)]
+    blend: bool,
+    #[serde(default)]
+    preempt: bool,
+    handoff_negative_turns: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScriptedTurn {
+    user: String,
+    /// The `submit` arguments the provider classifies the message with
+    #[serde(default)]
+    emotion: Option<Value>,
+    /// The provider's reply, when the turn reaches the chat model
+    #[serde(default)]
+    reply: Option<String>,
+    #[serde(default)]
+    expect: ExpectTurn,
+}
+
+/// Whatever is given must match, `null` included; the rest is left to the snapshot
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExpectTurn {
+    #[serde(default, deserialize_with = "given")]
+    sentiment: Option<Value>,
+    #[serde(default, deserialize_with = "given")]
+    trend: Option<Value>,
+    #[serde(default, deserialize_with = "given")]
+    strategy: Option<Value>,
+    #[serde(default, deserialize_with = "given")]
+    handoff: Option<Value>,
+    response: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExpectState {
+    messages: Option<usize>,
+    emotions: Option<usize>,
+    #[serde(default, deserialize_with = "given")]
+    handoff: Option<Value>,
+}
+
+/// A field that is present, even as `null`, where a plain `Option` would read `null` as absent
+fn given<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+/// One turn as the snapshot records it
+fn turn_json(outcome: &TurnOutcome) -> Value {
+    json!({
+        "sentiment": outcome.emotion.sentiment,
+        "confidence": outcome.emotion.confidence,
+        "trend": outcome.trend,
+        "forecast": outcome.forecast,
+        "strategy": outcome.strategy,
+        "blend": outcome.blend,
+        "suppressed": outcome.suppressed,
+        "handoff": outcome.handoff,
+        "escalated": outcome.escalated,
+        "response": outcome.response,
+    })
+}
+
+/// The state with wall-clock times zeroed, so snapshots are stable
+fn state_json(state: &ConversationManager) -> Value {
+    let mut state = serde_json::to_value(state.state()).unwrap();
+    if let Some(messages) = state.get_mut("messages").and_then(Value::as_array_mut) {
+        for message in messages {
+            message["timestamp"] = json!(0);
+        }
+    }
+    if let Some(state) = state.as_object_mut() {
+        state.remove("started_at");
+    }
+    state
+}
+
+fn check(field: &str, expected: &Option<Value>, actual: Value, failures: &mut Vec<String>) {
+    if let Some(expected) = expected
+        && *expected != actual
+    {
+        failures.push(format!("{}: expected {}, got {}", field, expected, actual));
+    }
+}
+
+/// Runs one transcript and returns what did not match
+async fn run(path: &Path) -> Vec<String> {
+    let transcript: Transcript = serde_json::from_str(&std::fs::read_to_string(path).unwrap())
+        .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+
+    let provider = MockProvider::default();
+    for turn in &transcript.turns {
+        if let Some(emotion) = &turn.emotion {
+            provider.classify_as(emotion.clone());
+        }
+        if let Some(reply) = &turn.reply {
+            provider.reply_with(reply);
+        }
+    }
+    let client = openai::Client::from_url("test-key", &provider.start().await.unwrap());
+    let detector = EmotionDetector::new(client.clone(), MOCK_MODEL);
+    let chat_agent = ChatAgent::new(client.clone(), MOCK_MODEL);
+    let topics = TopicGuard::new(client, MOCK_MODEL, Vec::new());
+    let policy = StrategyPolicy {
+        mode: if transcript.policy.blend { StrategyMode::Blended } else { StrategyMode::Single },
+        preempt: transcript.policy.preempt,
+        handoff: transcript.policy.handoff_negative_turns.map(|negative_turns| HandoffPolicy { negative_turns }),
+        ..StrategyPolicy::default()
+    };
+    let pipeline = Pipeline::default();
+    let mut state = ConversationManager::new();
+
+    let mut failures = Vec::new();
+    let mut turns = Vec::new();
+    for (i, turn) in transcript.turns.iter().enumerate() {
+        let outcome = match run_turn(&detector, &chat_agent, &topics, &mut state, &turn.user, policy, &pipeline).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                failures.push(format!("turn {}: {:#}", i + 1, e));
+                return failures;
+            }
+        };
+        let mut problems = Vec::new();
+        let expect = &turn.expect;
+        check("sentiment", &expect.sentiment, json!(outcome.emotion.sentiment), &mut problems);
+        check("trend", &expect.trend, json!(outcome.trend), &mut problems);
+        check("strategy", &expect.strategy, json!(outcome.strategy), &mut problems);
+        check("handoff", &expect.handoff, json!(outcome.handoff), &mut problems);
+        check("response", &expect.response.clone().map(Value::from), json!(outcome.response), &mut problems);
+        failures.extend(problems.into_iter().map(|problem| format!("turn {}: {}", i + 1, problem)));
+        turns.push(turn_json(&outcome));
+    }
+
+    let (classifications, replies) = provider.unused();
+    if classifications + replies > 0 {
+        failures.push(format!(
+            "{} scripted emotions and {} replies were never asked for; the turns took another path",
+            classifications, replies
+        ));
+    }
+
+    if let Some(expect) = &transcript.expect_state {
+        let messages = state.state().messages.len();
+        let emotions = state.state().emotion_history.len();
+        check("state messages", &expect.messages.map(Value::from), json!(messages), &mut failures);
+        check("state emotions", &expect.emotions.map(Value::from), json!(emotions), &mut failures);
+        check("state handoff", &expect.handoff, json!(state.state().handoff), &mut failures);
+    }
+
+    let actual = json!({ "turns": turns, "state": state_json(&state) });
+    let snapshot = path.with_extension("snap.json");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    if update {
+        std::fs::write(&snapshot, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+        return failures;
+    }
+    match std::fs::read_to_string(&snapshot) {
+        Ok(expected) => {
+            let expected: Value = serde_json::from_str(&expected).unwrap();
+            if expected != actual {
+                failures.push(format!(
+                    "output differs from {} (rerun with UPDATE_GOLDEN=1 if the change is intended):\n{}",
+                    snapshot.display(),
+                    serde_json::to_string_pretty(&actual).unwrap()
+                ));
+            }
+        }
+        Err(e) => failures.push(format!(
+            "cannot read {} ({}); run with UPDATE_GOLDEN=1 to write it",
+            snapshot.display(),
+            e
+        )),
+    }
+    failures
+}
+
+#[tokio::test]
+async fn golden_transcripts() {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(golden_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json") && !path.to_string_lossy().ends_with(".snap.json"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no transcripts in {}", golden_dir().display());
+
+    let mut report = Vec::new();
+    for path in &paths {
+        let failures = run(path).await;
+        if !failures.is_empty() {
+            report.push(format!("{}:\n  {}", path.display(), failures.join("\n  ")));
+        }
+    }
+    assert!(report.is_empty(), "{}", report.join("\n\n"));
+}